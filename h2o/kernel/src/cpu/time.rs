@@ -7,7 +7,7 @@ use core::{
 };
 
 use self::chip::ClockChip;
-pub use self::timer::{tick as timer_tick, Timer};
+pub use self::timer::{next_deadline, tick as timer_tick, Timer};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(transparent)]
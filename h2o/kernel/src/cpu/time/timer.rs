@@ -167,6 +167,23 @@ impl Timer {
     }
 }
 
+/// Get the nearest deadline of the pending timers on the current CPU.
+pub fn next_deadline() -> Option<Instant> {
+    TIMER_QUEUE
+        .try_with_inner(|queue| loop {
+            match queue.peek() {
+                Some(TimerEntry(timer))
+                    if timer.callback.try_read().map_or(false, |r| r.is_none()) =>
+                {
+                    queue.pop();
+                }
+                Some(TimerEntry(timer)) => break Some(timer.deadline),
+                None => break None,
+            }
+        })
+        .flatten()
+}
+
 pub unsafe fn tick() {
     loop {
         let now = Instant::now();
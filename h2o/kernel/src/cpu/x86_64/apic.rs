@@ -386,7 +386,14 @@ pub unsafe fn error_handler() {
 pub unsafe fn init() {
     let mut lapic = Lapic::new();
     lapic.enable();
-    lapic.activate_timer(timer::TimerMode::Periodic, 7, 512);
-
     LAPIC = Some(lapic);
+
+    timer::init();
+    self::lapic(|lapic| {
+        lapic.activate_timer(
+            timer::TimerMode::Periodic,
+            timer::PERIODIC_DIV,
+            timer::PERIODIC_INIT,
+        )
+    });
 }
@@ -1,9 +1,11 @@
-use core::ops::Range;
+use core::{cell::Cell, ops::Range};
 
+use archop::{msr, Azy};
 use modular_bitfield::prelude::*;
+use raw_cpuid::CpuId;
 
-use super::LocalEntry;
-use crate::cpu::time::Instant;
+use super::{Lapic, LocalEntry};
+use crate::cpu::time::{chip, Instant};
 
 #[derive(Clone, Copy, PartialEq, Eq, BitfieldSpecifier)]
 #[repr(u32)]
@@ -16,6 +18,56 @@ pub enum TimerMode {
 
 pub const DIV: Range<u8> = 0..8;
 
+/// The divisor of the periodic tick.
+pub const PERIODIC_DIV: u8 = 7;
+/// The initial count of the periodic tick.
+pub const PERIODIC_INIT: u32 = 512;
+/// The divisor of the one-shot timer used when TSC-deadline mode is not
+/// supported.
+const ONESHOT_DIV: u8 = 0;
+
+static HAS_TSC_DEADLINE: Azy<bool> = Azy::new(|| {
+    CpuId::new()
+        .get_feature_info()
+        .map_or(false, |info| info.has_tsc_deadline())
+});
+
+/// The frequency of the LAPIC timer in kHz with the divisor [`ONESHOT_DIV`].
+///
+/// Only calibrated in [`init`] when TSC-deadline mode is not supported.
+static ONESHOT_KHZ: Azy<u64> = Azy::new(|| {
+    let read = || unsafe {
+        super::lapic(|lapic| u32::MAX - Lapic::read_reg_32(&mut lapic.ty, msr::X2APIC_CUR_COUNT))
+    };
+    let khz = chip::calibrate(
+        || unsafe {
+            super::lapic(|lapic| activate(lapic, TimerMode::OneShot, ONESHOT_DIV, u32::MAX))
+        },
+        || read() as u64,
+        || read() as u64,
+        || unsafe { super::lapic(|lapic| activate(lapic, TimerMode::OneShot, ONESHOT_DIV, 0)) },
+    );
+    log::info!("LAPIC timer frequency: {} KHz", khz);
+    khz
+});
+
+/// Whether the periodic tick of the current CPU is stopped.
+#[thread_local]
+static TICKLESS: Cell<bool> = Cell::new(false);
+
+/// Calibrate the one-shot timer of the current CPU's LAPIC if it will be used
+/// to stop the periodic tick.
+///
+/// # Safety
+///
+/// The caller must ensure that the LAPIC is initialized and its timer is not
+/// activated yet, since the calibration disarms it.
+pub unsafe fn init() {
+    if !*HAS_TSC_DEADLINE {
+        Azy::force(&ONESHOT_KHZ);
+    }
+}
+
 /// # Safety
 ///
 /// WARNING: This function modifies the architecture's basic registers. Be sure
@@ -38,12 +90,9 @@ pub unsafe fn activate(lapic: &mut super::Lapic, mode: TimerMode, div: u8, init_
     let encdiv = unsafe { encode_div(div) };
     let timer_val = LocalEntry::new().with_timer_mode(mode).with_vec(vec);
 
-    // SAFETY: Those MSRs are per-cpu and only 1 timer object is available in the
-    // context.
+    // SAFETY: Those MSRs are per-cpu and only 1 timer object is available in
+    // the context.
     unsafe {
-        use archop::msr;
-
-        use super::Lapic;
         Lapic::write_reg_32(&mut lapic.ty, msr::X2APIC_DIV_CONF, encdiv.into());
         Lapic::write_reg_32(&mut lapic.ty, msr::X2APIC_LVT_TIMER, timer_val.into());
         if matches!(mode, TimerMode::TscDeadline) {
@@ -65,3 +114,52 @@ pub unsafe fn timer_handler() {
     crate::cpu::time::timer_tick();
     crate::sched::SCHED.tick(Instant::now());
 }
+
+/// Stop the periodic tick of the current CPU and arm the timer to fire only at
+/// `deadline`, or never if it's `None`.
+///
+/// # Safety
+///
+/// The caller must ensure that this function is called with interrupts
+/// disabled.
+pub unsafe fn stop_tick(deadline: Option<Instant>) {
+    TICKLESS.set(true);
+
+    if *HAS_TSC_DEADLINE {
+        let vec = crate::cpu::intr::arch::def::ApicVec::Timer as u8;
+        let timer_val = LocalEntry::new()
+            .with_timer_mode(TimerMode::TscDeadline)
+            .with_vec(vec);
+        // Zero disarms the timer, so make sure an expired deadline still fires.
+        let tsc = deadline.map_or(0, |deadline| chip::CLOCK.ticks_at(deadline).max(1));
+
+        super::lapic(|lapic| {
+            Lapic::write_reg_32(&mut lapic.ty, msr::X2APIC_LVT_TIMER, timer_val.into());
+            // The write to the LVT must be completed before arming the
+            // deadline.
+            core::arch::asm!("mfence");
+            msr::write(msr::TSC_DEADLINE, tsc);
+        })
+    } else {
+        let count = deadline.map_or(0, |deadline| {
+            let ns = deadline
+                .saturating_duration_since(Instant::now())
+                .as_nanos();
+            let count = ns * (*ONESHOT_KHZ as u128) / 1_000_000;
+            count.clamp(1, u32::MAX as u128) as u32
+        });
+        super::lapic(|lapic| activate(lapic, TimerMode::OneShot, ONESHOT_DIV, count))
+    }
+}
+
+/// Resume the periodic tick of the current CPU if it's stopped.
+///
+/// # Safety
+///
+/// The caller must ensure that this function is called with interrupts
+/// disabled.
+pub unsafe fn resume_tick() {
+    if TICKLESS.replace(false) {
+        super::lapic(|lapic| activate(lapic, TimerMode::Periodic, PERIODIC_DIV, PERIODIC_INIT))
+    }
+}
@@ -10,14 +10,17 @@ pub struct TscClock {
     pub initial: u64,
     pub mul: u128,
     pub sft: u128,
+    /// Whether the TSC is invariant across P-, C- and T-states, and thus
+    /// reliable for computing the time in user mode.
+    pub stable: bool,
 }
 
 impl TscClock {
     pub fn new() -> Self {
-        if CpuId::new()
+        let stable = CpuId::new()
             .get_advanced_power_mgmt_info()
-            .map_or(true, |info| !info.has_invariant_tsc())
-        {
+            .map_or(false, |info| info.has_invariant_tsc());
+        if !stable {
             log::warn!("The TSC is not invariant. Ticks will be unreliable.");
         }
 
@@ -26,7 +29,20 @@ impl TscClock {
         let initial = rdtsc();
         let (mul, sft) = factor_from_freq(khz);
         log::info!("CPU Timestamp frequency: {} KHz", khz);
-        TscClock { initial, mul, sft }
+        TscClock {
+            initial,
+            mul,
+            sft,
+            stable,
+        }
+    }
+}
+
+impl TscClock {
+    /// Get the TSC value at `instant`.
+    pub fn ticks_at(&self, instant: Instant) -> u64 {
+        let ns = unsafe { instant.raw() };
+        ((ns << self.sft) / self.mul) as u64 + self.initial
     }
 }
 
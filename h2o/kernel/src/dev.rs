@@ -1,4 +1,5 @@
 pub mod acpi;
+mod power;
 mod res;
 
 cfg_if::cfg_if! {
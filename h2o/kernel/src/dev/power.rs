@@ -0,0 +1,286 @@
+use core::{slice, time::Duration};
+
+use acpi::{
+    address::{AddressSpace, GenericAddress},
+    fadt::Fadt,
+};
+use archop::io::{Io, Port};
+use paging::PAddr;
+use sv_call::*;
+
+use super::acpi::tables;
+use crate::cpu::time::{delay, Instant};
+
+const AML_NAME_OP: u8 = 0x08;
+const AML_PACKAGE_OP: u8 = 0x12;
+const AML_BYTE_PREFIX: u8 = 0x0a;
+
+const PM1_SLP_TYP_SHIFT: u64 = 10;
+const PM1_SLP_TYP_MASK: u64 = 0b111 << PM1_SLP_TYP_SHIFT;
+const PM1_SLP_EN: u64 = 1 << 13;
+const PM1_WAK_STS: u64 = 1 << 15;
+
+const KBD_STATUS_PORT: u16 = 0x64;
+const KBD_STATUS_INPUT_FULL: u8 = 1 << 1;
+const KBD_CMD_RESET: u8 = 0xfe;
+
+/// The time to wait for the hardware before assuming the operation failed.
+const POWER_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Get the `SLP_TYPa` and `SLP_TYPb` values of the sleep state object `name`
+/// (e.g. `\_S5`) in the DSDT.
+///
+/// We don't have an AML interpreter, so we simply search for the package
+/// definition, which is the same across virtually all the firmwares:
+///
+/// ```text
+/// NameOp ("\") "_S5_" PackageOp PkgLength NumElements SLP_TYPa SLP_TYPb ...
+/// ```
+fn sleep_types(name: &[u8; 4]) -> Option<(u64, u64)> {
+    let dsdt = tables().dsdt.as_ref()?;
+    let aml = unsafe {
+        let ptr = *PAddr::new(dsdt.address).to_laddr(minfo::ID_OFFSET);
+        slice::from_raw_parts(ptr, dsdt.length as usize)
+    };
+
+    let pos = aml.windows(4).position(|window| window == name)?;
+    let name_op = match aml[..pos] {
+        [.., op, b'\\'] | [.., op] => op,
+        [] => return None,
+    };
+    if name_op != AML_NAME_OP {
+        return None;
+    }
+
+    let mut data = &aml[(pos + 4)..];
+    if *data.first()? != AML_PACKAGE_OP {
+        return None;
+    }
+    // The bits 6-7 of the leading byte of `PkgLength` are the number of the
+    // following bytes.
+    let pkg_len = 1 + (*data.get(1)? >> 6) as usize;
+    data = data.get((1 + pkg_len + 1)..)?;
+
+    let mut next = || {
+        let (&op, rest) = data.split_first()?;
+        data = rest;
+        if op == AML_BYTE_PREFIX {
+            let (&value, rest) = data.split_first()?;
+            data = rest;
+            Some(value as u64)
+        } else {
+            // `ZeroOp`, `OneOp` or a raw byte data.
+            Some(op as u64)
+        }
+    };
+    let typ_a = next()?;
+    let typ_b = next().unwrap_or(typ_a);
+    Some((typ_a, typ_b))
+}
+
+unsafe fn read_reg(reg: &GenericAddress) -> Result<u64> {
+    match reg.address_space {
+        AddressSpace::SystemIo => {
+            let port = u16::try_from(reg.address)?;
+            Ok(match reg.bit_width {
+                8 => Port::<u8>::new(port).read() as u64,
+                16 => Port::<u16>::new(port).read() as u64,
+                32 => Port::<u32>::new(port).read() as u64,
+                _ => return Err(ESPRT),
+            })
+        }
+        AddressSpace::SystemMemory => {
+            let ptr = *PAddr::new(reg.address as usize).to_laddr(minfo::ID_OFFSET);
+            Ok(match reg.bit_width {
+                8 => ptr.read_volatile() as u64,
+                16 => ptr.cast::<u16>().read_volatile() as u64,
+                32 => ptr.cast::<u32>().read_volatile() as u64,
+                64 => ptr.cast::<u64>().read_volatile(),
+                _ => return Err(ESPRT),
+            })
+        }
+        _ => Err(ESPRT),
+    }
+}
+
+unsafe fn write_reg(reg: &GenericAddress, value: u64) -> Result {
+    match reg.address_space {
+        AddressSpace::SystemIo => {
+            let port = u16::try_from(reg.address)?;
+            match reg.bit_width {
+                8 => Port::<u8>::new(port).write(value as u8),
+                16 => Port::<u16>::new(port).write(value as u16),
+                32 => Port::<u32>::new(port).write(value as u32),
+                _ => return Err(ESPRT),
+            }
+        }
+        AddressSpace::SystemMemory => {
+            let ptr = *PAddr::new(reg.address as usize).to_laddr(minfo::ID_OFFSET);
+            match reg.bit_width {
+                8 => ptr.write_volatile(value as u8),
+                16 => ptr.cast::<u16>().write_volatile(value as u16),
+                32 => ptr.cast::<u32>().write_volatile(value as u32),
+                64 => ptr.cast::<u64>().write_volatile(value),
+                _ => return Err(ESPRT),
+            }
+        }
+        _ => return Err(ESPRT),
+    }
+    Ok(())
+}
+
+unsafe fn enter_sleep(reg: &GenericAddress, typ: u64) -> Result {
+    let value = read_reg(reg)? & !PM1_SLP_TYP_MASK;
+    write_reg(reg, value | (typ << PM1_SLP_TYP_SHIFT) | PM1_SLP_EN)
+}
+
+/// Power off the machine by entering the ACPI S5 state.
+///
+/// # Errors
+///
+/// Returns an error if the firmware doesn't support S5 or the machine is still
+/// alive after a timeout.
+pub fn shutdown() -> Result {
+    let fadt = tables().find_table::<Fadt>().map_err(|_| ESPRT)?;
+    let (typ_a, typ_b) = sleep_types(b"_S5_").ok_or(ESPRT)?;
+    let pm1a = fadt.pm1a_control_block().map_err(|_| ESPRT)?;
+    let pm1b = fadt.pm1b_control_block().map_err(|_| ESPRT)?;
+
+    log::info!("Shutting down the machine");
+    unsafe {
+        let flags = archop::pause_intr();
+        let ret = enter_sleep(&pm1a, typ_a)
+            .and_then(|_| pm1b.map_or(Ok(()), |pm1b| enter_sleep(&pm1b, typ_b)));
+        if ret.is_ok() {
+            delay(POWER_TIMEOUT);
+        }
+        archop::resume_intr(Some(flags));
+        ret?;
+    }
+    Err(ETIME)
+}
+
+/// The status register in the first half of a PM1 event block.
+fn pm1_status(evt: GenericAddress) -> GenericAddress {
+    GenericAddress {
+        bit_width: evt.bit_width / 2,
+        ..evt
+    }
+}
+
+/// Put the machine into the ACPI S1 state and return after it's woken up.
+///
+/// Unlike deeper sleep states, the hardware keeps the context of the CPUs in
+/// S1, so no resume path through the firmware is needed.
+///
+/// # Errors
+///
+/// Returns an error if the firmware doesn't support S1 or the machine doesn't
+/// enter it before a timeout.
+pub fn sleep() -> Result {
+    let fadt = tables().find_table::<Fadt>().map_err(|_| ESPRT)?;
+    let (typ_a, typ_b) = sleep_types(b"_S1_").ok_or(ESPRT)?;
+    let pm1a = fadt.pm1a_control_block().map_err(|_| ESPRT)?;
+    let pm1b = fadt.pm1b_control_block().map_err(|_| ESPRT)?;
+    let sts_a = pm1_status(fadt.pm1a_event_block().map_err(|_| ESPRT)?);
+    let sts_b = (fadt.pm1b_event_block().map_err(|_| ESPRT)?).map(pm1_status);
+
+    log::info!("Putting the machine to sleep");
+    unsafe {
+        let flags = archop::pause_intr();
+        let ret = (|| {
+            // The status bits are cleared by writing ones.
+            write_reg(&sts_a, PM1_WAK_STS)?;
+            if let Some(ref sts_b) = sts_b {
+                write_reg(sts_b, PM1_WAK_STS)?;
+            }
+
+            enter_sleep(&pm1a, typ_a)?;
+            if let Some(pm1b) = pm1b {
+                enter_sleep(&pm1b, typ_b)?;
+            }
+
+            // The hardware sets the wake status once the machine is woken up.
+            // The CPUs stop executing in S1, so if the status is still clear
+            // after a timeout, the machine never went to sleep.
+            let start = Instant::now();
+            while read_reg(&sts_a)? & PM1_WAK_STS == 0 {
+                if start.elapsed() >= POWER_TIMEOUT {
+                    return Err(ETIME);
+                }
+                core::hint::spin_loop();
+            }
+            Ok(())
+        })();
+        archop::resume_intr(Some(flags));
+        ret?;
+    }
+    log::info!("The machine is woken up");
+    Ok(())
+}
+
+/// Reset the machine via the ACPI reset register, falling back to the
+/// keyboard controller.
+///
+/// # Errors
+///
+/// Returns an error if the machine is still alive after a timeout.
+pub fn reboot() -> Result {
+    log::info!("Rebooting the machine");
+    unsafe {
+        let flags = archop::pause_intr();
+
+        if let Ok(fadt) = tables().find_table::<Fadt>() {
+            let value = fadt.reset_value;
+            match fadt.reset_register() {
+                Ok(reg) if reg.address != 0 => {
+                    if write_reg(&reg, value as u64).is_ok() {
+                        delay(POWER_TIMEOUT);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        log::warn!("ACPI reset failed, falling back to the keyboard controller");
+        let mut kbd = Port::<u8>::new(KBD_STATUS_PORT);
+        while kbd.read() & KBD_STATUS_INPUT_FULL != 0 {
+            core::hint::spin_loop();
+        }
+        kbd.write(KBD_CMD_RESET);
+        delay(POWER_TIMEOUT);
+
+        archop::resume_intr(Some(flags));
+    }
+    Err(ETIME)
+}
+
+mod syscall {
+    use sv_call::*;
+
+    use crate::{
+        dev::{pio_resource, Resource},
+        sched::SCHED,
+    };
+
+    #[syscall]
+    fn power(res: Handle, op: u32) -> Result {
+        SCHED.with_current(|cur| {
+            let res = cur.space().handles().get::<Resource<u16>>(res)?;
+            if !res.features().contains(Feature::WRITE)
+                || !res.magic_eq(pio_resource())
+                || res.range() != pio_resource().range()
+            {
+                return Err(EPERM);
+            }
+            Ok(())
+        })?;
+
+        match op {
+            res::POWER_SHUTDOWN => super::shutdown(),
+            res::POWER_REBOOT => super::reboot(),
+            res::POWER_SLEEP => super::sleep(),
+            _ => Err(EINVAL),
+        }
+    }
+}
@@ -1,10 +1,11 @@
 pub mod flags;
+mod ring;
 mod serial;
 
 use core::{
     fmt::*,
     mem::MaybeUninit,
-    sync::atomic::{AtomicBool, Ordering::*},
+    sync::atomic::{AtomicBool, AtomicU32, Ordering::*},
 };
 
 use spin::Mutex;
@@ -26,16 +27,49 @@ impl core::fmt::Display for OptionU32Display {
 
 pub static HAS_TIME: AtomicBool = AtomicBool::new(false);
 
+/// The current maximum level of the kernel log, stored as the raw value of
+/// `sv_call::klog::LOG_LEVEL_*`.
+static LEVEL: AtomicU32 = AtomicU32::new(0);
+
+fn level_to_raw(level: log::LevelFilter) -> u32 {
+    level as usize as u32
+}
+
+fn level_from_raw(raw: u32) -> Option<log::LevelFilter> {
+    use sv_call::klog::*;
+    Some(match raw {
+        LOG_LEVEL_ERROR => log::LevelFilter::Error,
+        LOG_LEVEL_WARN => log::LevelFilter::Warn,
+        LOG_LEVEL_INFO => log::LevelFilter::Info,
+        LOG_LEVEL_DEBUG => log::LevelFilter::Debug,
+        LOG_LEVEL_TRACE => log::LevelFilter::Trace,
+        _ => return None,
+    })
+}
+
+/// Set the maximum level of the kernel log at runtime, returning the previous
+/// one.
+pub fn set_level(level: log::LevelFilter) -> log::LevelFilter {
+    let old = LEVEL.swap(level_to_raw(level), AcqRel);
+    log::set_max_level(level);
+    level_from_raw(old).unwrap_or(log::LevelFilter::Off)
+}
+
+fn cur_time() -> Instant {
+    HAS_TIME
+        .load(Acquire)
+        .then(Instant::now)
+        .unwrap_or(unsafe { Instant::from_raw(0) })
+}
+
 struct Logger {
     output: Mutex<serial::Output>,
-    level: log::Level,
 }
 
 impl Logger {
-    pub fn new(level: log::Level) -> Logger {
+    pub fn new() -> Logger {
         Logger {
             output: Mutex::new(unsafe { serial::Output::new(COM_LOG) }),
-            level,
         }
     }
 }
@@ -43,7 +77,7 @@ impl Logger {
 impl log::Log for Logger {
     #[inline]
     fn enabled(&self, metadata: &log::Metadata) -> bool {
-        metadata.level() <= self.level
+        level_to_raw(metadata.level().to_level_filter()) <= LEVEL.load(Acquire)
     }
 
     fn log(&self, record: &log::Record) {
@@ -52,11 +86,18 @@ impl log::Log for Logger {
         }
 
         let _pree = PREEMPT.lock();
+        let cur_time = cur_time();
+        let cpu = unsafe { crate::cpu::id() };
+
+        ring::RING.lock().push(
+            cur_time,
+            cpu as u32,
+            level_to_raw(record.level().to_level_filter()),
+            record.module_path().unwrap_or("<NULL>"),
+            *record.args(),
+        );
+
         let mut os = self.output.lock();
-        let cur_time = HAS_TIME
-            .load(Acquire)
-            .then(Instant::now)
-            .unwrap_or(unsafe { Instant::from_raw(0) });
 
         let res = if record.level() < log::Level::Debug {
             writeln!(*os, "[{}] {}: {}", cur_time, record.level(), record.args(),)
@@ -68,7 +109,7 @@ impl log::Log for Logger {
                 "[{}] {}: [#{} {}:{}] {}",
                 cur_time,
                 record.level(),
-                unsafe { crate::cpu::id() },
+                cpu,
                 file,
                 line,
                 record.args(),
@@ -88,30 +129,93 @@ static mut LOGGER: MaybeUninit<Logger> = MaybeUninit::uninit();
 /// This function should only be called once before everything else is to be
 /// started up.
 pub unsafe fn init(max_level: log::Level) {
-    let logger = LOGGER.write(Logger::new(max_level));
+    let logger = LOGGER.write(Logger::new());
     log::set_logger(logger).expect("Failed to set the logger");
-    log::set_max_level(max_level.to_level_filter());
+    set_level(max_level.to_level_filter());
 }
 
 mod syscall {
     use core::fmt::Write;
 
-    use sv_call::*;
+    use sv_call::{klog::LogRecord, *};
 
-    use super::LOGGER;
+    use super::{ring::RING, LOGGER};
     use crate::{
-        sched::PREEMPT,
-        syscall::{In, UserPtr},
+        dev::{pio_resource, Resource},
+        sched::{PREEMPT, SCHED},
+        syscall::{In, Out, UserPtr},
     };
 
+    /// Reading the log and changing its level require the root port I/O
+    /// resource, which also owns the debug port the log is written to.
+    fn check_res(res: Handle, feature: Feature) -> Result {
+        SCHED.with_current(|cur| {
+            let res = cur.space().handles().get::<Resource<u16>>(res)?;
+            if !res.features().contains(feature)
+                || !res.magic_eq(pio_resource())
+                || res.range() != pio_resource().range()
+            {
+                return Err(EPERM);
+            }
+            Ok(())
+        })
+    }
+
     #[syscall]
     fn log(buffer: UserPtr<In>, len: usize) -> Result {
         buffer.check_slice(len)?;
         let string =
             core::str::from_utf8(unsafe { core::slice::from_raw_parts(buffer.as_ptr(), len) })?;
         let _pree = PREEMPT.lock();
+        RING.lock().push(
+            super::cur_time(),
+            unsafe { crate::cpu::id() } as u32,
+            klog::LOG_LEVEL_INFO,
+            klog::LOG_MODULE_USER,
+            format_args!("{string}"),
+        );
         let mut os = unsafe { LOGGER.assume_init_ref() }.output.lock();
         writeln!(os, "{string}").map_err(|_| EFAULT)?;
         Ok(())
     }
+
+    #[syscall]
+    fn log_read(
+        res: Handle,
+        seq: u64,
+        record: UserPtr<Out, LogRecord>,
+        buffer: UserPtr<Out>,
+        len: usize,
+    ) -> Result {
+        check_res(res, Feature::READ)?;
+        record.check()?;
+        buffer.check_slice(len)?;
+
+        let slot = PREEMPT.scope(|| RING.lock().get(seq)).ok_or(ENOENT)?;
+        record.write(slot.header())?;
+
+        let (module, msg) = (slot.module(), slot.msg());
+        if module.len() + msg.len() > len {
+            return Err(EBUFFER);
+        }
+        buffer.write_slice(module)?;
+        UserPtr::<Out>::new(unsafe { buffer.as_ptr().add(module.len()) }).write_slice(msg)
+    }
+
+    #[syscall]
+    fn log_level(res: Handle, level: u32) -> Result<u32> {
+        // Querying the level passes 0.
+        let feature = if level == 0 {
+            Feature::READ
+        } else {
+            Feature::WRITE
+        };
+        check_res(res, feature)?;
+        let old = match super::level_from_raw(level) {
+            Some(level) => super::set_level(level),
+            None if level == 0 => ::log::max_level(),
+            None => return Err(EINVAL),
+        };
+        Ok(super::level_to_raw(old))
+    }
 }
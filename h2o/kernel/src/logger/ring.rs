@@ -0,0 +1,133 @@
+//! The in-memory ring of structured log records.
+//!
+//! Every record logged by the kernel or by user tasks via `sv_log` is stored
+//! here besides being written to the serial port, so that user-space services
+//! can read it later with `sv_log_read`. The ring is statically allocated to
+//! keep logging free from heap allocations.
+
+use core::fmt::{self, Write};
+
+use spin::Mutex;
+use sv_call::klog::{LogRecord, LOG_MODULE_LEN, LOG_MSG_LEN};
+
+use crate::cpu::time::Instant;
+
+const RING_LEN: usize = 1024;
+
+#[derive(Copy, Clone)]
+pub struct Slot {
+    pub seq: u64,
+    pub time: Instant,
+    pub cpu: u32,
+    pub level: u32,
+    module: [u8; LOG_MODULE_LEN],
+    module_len: usize,
+    msg: [u8; LOG_MSG_LEN],
+    msg_len: usize,
+}
+
+impl Slot {
+    const EMPTY: Slot = Slot {
+        seq: 0,
+        time: unsafe { Instant::from_raw(0) },
+        cpu: 0,
+        level: 0,
+        module: [0; LOG_MODULE_LEN],
+        module_len: 0,
+        msg: [0; LOG_MSG_LEN],
+        msg_len: 0,
+    };
+
+    #[inline]
+    pub fn module(&self) -> &[u8] {
+        &self.module[..self.module_len]
+    }
+
+    #[inline]
+    pub fn msg(&self) -> &[u8] {
+        &self.msg[..self.msg_len]
+    }
+
+    pub fn header(&self) -> LogRecord {
+        LogRecord {
+            seq: self.seq,
+            time: unsafe { self.time.raw() },
+            cpu: self.cpu,
+            level: self.level,
+            module_len: self.module_len,
+            msg_len: self.msg_len,
+        }
+    }
+}
+
+/// A truncating writer into a fixed-size byte buffer.
+struct Truncate<'a> {
+    buf: &'a mut [u8],
+    len: &'a mut usize,
+}
+
+impl Write for Truncate<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let start = *self.len;
+        let end = (start + s.len()).min(self.buf.len());
+        self.buf[start..end].copy_from_slice(&s.as_bytes()[..(end - start)]);
+        *self.len = end;
+        Ok(())
+    }
+}
+
+pub struct Ring {
+    slots: [Slot; RING_LEN],
+    /// The sequence number of the next record to be pushed.
+    next: u64,
+}
+
+impl Ring {
+    const fn new() -> Self {
+        Ring {
+            slots: [Slot::EMPTY; RING_LEN],
+            next: 1,
+        }
+    }
+
+    pub fn push(
+        &mut self,
+        time: Instant,
+        cpu: u32,
+        level: u32,
+        module: &str,
+        args: fmt::Arguments,
+    ) {
+        let seq = self.next;
+        self.next += 1;
+
+        let slot = &mut self.slots[seq as usize % RING_LEN];
+        slot.seq = seq;
+        slot.time = time;
+        slot.cpu = cpu;
+        slot.level = level;
+
+        slot.module_len = 0;
+        let _ = Truncate {
+            buf: &mut slot.module,
+            len: &mut slot.module_len,
+        }
+        .write_str(module);
+
+        slot.msg_len = 0;
+        let _ = Truncate {
+            buf: &mut slot.msg,
+            len: &mut slot.msg_len,
+        }
+        .write_fmt(args);
+    }
+
+    /// Get the oldest record whose sequence number is not less than `seq`.
+    pub fn get(&self, seq: u64) -> Option<Slot> {
+        let oldest = self.next.saturating_sub(RING_LEN as u64).max(1);
+        let seq = seq.max(oldest);
+        (seq < self.next).then(|| self.slots[seq as usize % RING_LEN])
+    }
+}
+
+pub static RING: Mutex<Ring> = Mutex::new(Ring::new());
@@ -167,6 +167,88 @@ fn phys_writev(
     phys.write_vectored(offset, &bufs)
 }
 
+/// Pin the pages covering `offset..offset + len` of `phys`, returning the
+/// page-aligned start of the pinned range and the base of every page in it.
+fn pin_pages(
+    phys: &space::Phys,
+    offset: usize,
+    len: usize,
+    write: bool,
+) -> Result<(usize, Vec<paging::PAddr>)> {
+    let start = offset.round_down_bit(paging::PAGE_SHIFT);
+    let regions = phys.pin(start, offset + len - start, write)?;
+
+    let pages = regions
+        .into_iter()
+        .flat_map(|(base, len)| {
+            (0..len)
+                .step_by(paging::PAGE_SIZE)
+                .map(move |off| paging::PAddr::new(*base + off))
+        })
+        .collect();
+    Ok((start, pages))
+}
+
+#[syscall]
+fn phys_copy(
+    dst: Handle,
+    dst_offset: usize,
+    src: Handle,
+    src_offset: usize,
+    len: usize,
+) -> Result<usize> {
+    let (dst_feat, dst) = phys_check(dst, dst_offset, len)?;
+    if !dst_feat.contains(Feature::WRITE) {
+        return Err(EPERM);
+    }
+    let (src_feat, src) = phys_check(src, src_offset, len)?;
+    if !src_feat.contains(Feature::READ) {
+        return Err(EPERM);
+    }
+
+    let len = len
+        .min(dst.len().saturating_sub(dst_offset))
+        .min(src.len().saturating_sub(src_offset));
+    if len == 0 {
+        return Ok(0);
+    }
+
+    let (src_start, src_pages) = pin_pages(&src, src_offset, len, false)?;
+    let (dst_start, dst_pages) = match pin_pages(&dst, dst_offset, len, true) {
+        Ok(ret) => ret,
+        Err(err) => {
+            src.unpin(src_start, src_offset + len - src_start);
+            return Err(err);
+        }
+    };
+
+    let mut copied = 0;
+    while copied < len {
+        let src_pos = src_offset - src_start + copied;
+        let dst_pos = dst_offset - dst_start + copied;
+        let src_in_page = src_pos & paging::PAGE_MASK;
+        let dst_in_page = dst_pos & paging::PAGE_MASK;
+        let chunk = (paging::PAGE_SIZE - src_in_page)
+            .min(paging::PAGE_SIZE - dst_in_page)
+            .min(len - copied);
+
+        let src_page = src_pages[src_pos >> paging::PAGE_SHIFT];
+        let dst_page = dst_pages[dst_pos >> paging::PAGE_SHIFT];
+        // SAFETY: Both pages are pinned, and the objects may be the same, so
+        // the ranges can overlap.
+        unsafe {
+            let src = src_page.to_laddr(minfo::ID_OFFSET).add(src_in_page);
+            let dst = dst_page.to_laddr(minfo::ID_OFFSET).add(dst_in_page);
+            ptr::copy(src, dst, chunk);
+        }
+        copied += chunk;
+    }
+
+    dst.unpin(dst_start, dst_offset + len - dst_start);
+    src.unpin(src_start, src_offset + len - src_start);
+    Ok(len)
+}
+
 #[syscall]
 fn phys_sub(hdl: Handle, offset: usize, len: usize, copy: bool) -> Result<Handle> {
     let (feat, phys) = phys_check(hdl, offset, len)?;
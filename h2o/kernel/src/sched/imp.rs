@@ -81,6 +81,9 @@ impl Scheduler {
     }
 
     fn enqueue(&self, task: task::Ready, pree: PreemptStateGuard, preempt: bool) {
+        // SAFETY: We have `pree`, which means interrupts are disabled.
+        unsafe { crate::cpu::arch::apic::timer::resume_tick() };
+
         SCHED_INFO[self.cpu]
             .expected_runtime
             .fetch_add(task.time_slice.as_micros() as u64, Release);
@@ -102,6 +105,13 @@ impl Scheduler {
         }
     }
 
+    /// Check if there's no task to run on the current CPU other than the current
+    /// one.
+    #[inline]
+    pub fn is_idle(&self) -> bool {
+        self.run_queue.is_empty() && SCHED_INFO[self.cpu].migration_queue.is_empty()
+    }
+
     #[inline]
     pub fn with_current<F, R>(&self, func: F) -> sv_call::Result<R>
     where
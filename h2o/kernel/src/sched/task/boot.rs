@@ -1,5 +1,9 @@
 use alloc::{sync::Weak, vec::Vec};
-use core::mem;
+use core::{
+    mem,
+    ptr::addr_of_mut,
+    sync::atomic::{fence, Ordering::*},
+};
 
 use archop::Azy;
 use bitop_ex::BitOpEx;
@@ -8,7 +12,7 @@ use targs::Targs;
 
 use super::{hdl::DefaultFeature, *};
 use crate::{
-    cpu::time::chip::CLOCK,
+    cpu::{arch::tsc::TscClock, time::chip::CLOCK},
     mem::space::{self, Flags, Phys, PhysTrait, Virt},
     sched::SCHED,
 };
@@ -53,12 +57,36 @@ fn flags_to_feat(flags: Flags) -> Feature {
     feat
 }
 
+/// Publish the TSC scaling parameters of `clock` to the VDSO data page, so
+/// that user tasks can get the time without a syscall.
+fn publish_time(clock: &TscClock) {
+    #[allow(clippy::zero_prefixed_literal)]
+    let offset = include!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/target/time_data_offset.rs"
+    ));
+    unsafe {
+        let ptr = { VDSO.1.base().to_laddr(minfo::ID_OFFSET) }
+            .add(offset)
+            .cast::<sv_call::TimeData>();
+        let seq = &(*ptr).seq;
+
+        let old = seq.load(Relaxed);
+        seq.store(old.wrapping_add(1), Relaxed);
+        fence(Release);
+
+        addr_of_mut!((*ptr).stable).write_volatile(clock.stable);
+        addr_of_mut!((*ptr).ticks_offset).write_volatile(clock.initial);
+        addr_of_mut!((*ptr).ticks_multiplier).write_volatile(clock.mul);
+        addr_of_mut!((*ptr).ticks_shift).write_volatile(clock.sft);
+
+        seq.store(old.wrapping_add(2), Release);
+    }
+}
+
 pub fn setup() {
     unsafe {
         let constants = sv_call::Constants {
-            ticks_offset: CLOCK.initial,
-            ticks_multiplier: CLOCK.mul,
-            ticks_shift: CLOCK.sft,
             has_builtin_rand: archop::rand::has_builtin(),
             num_cpus: crate::cpu::count(),
         };
@@ -73,6 +101,7 @@ pub fn setup() {
             .cast::<sv_call::Constants>();
         ptr.write(constants);
     }
+    publish_time(&CLOCK);
 
     let mut objects = Vec::<hdl::Ref>::new();
 
@@ -63,6 +63,27 @@ fn idle(cpu: usize, fs_base: u64) -> ! {
             cur.running_state = RunningState::NEED_RESCHED;
             Ok(())
         });
-        unsafe { archop::resume_intr(None) };
+
+        // Stop the periodic tick if there's nothing to run, and only wake up
+        // for the nearest timer. The tick is resumed once a task
+        // becomes runnable.
+        //
+        // Interrupts stay disabled until halting, or a wakeup arriving in
+        // between would be missed until the next timer interrupt.
+        unsafe { archop::pause_intr() };
+        let tickless = PREEMPT.scope(|| {
+            let idle = CTX_DROPPER.is_empty() && crate::sched::SCHED.is_idle();
+            if idle {
+                let deadline = crate::cpu::time::next_deadline();
+                unsafe { crate::cpu::arch::apic::timer::stop_tick(deadline) };
+            }
+            idle
+        });
+
+        if tickless {
+            unsafe { archop::halt_intr() };
+        } else {
+            unsafe { archop::resume_intr(None) };
+        }
     }
 }
@@ -53,6 +53,12 @@ impl Space {
         let _ = self.main.compare_exchange(0, tid.raw(), AcqRel, Acquire);
     }
 
+    /// Get the raw ID of the main task, or 0 if the space is stopping.
+    #[inline]
+    pub fn main(&self) -> u64 {
+        self.main.load(Acquire)
+    }
+
     #[inline]
     pub fn try_stop(&self, tid: &Tid) {
         let _ = self.main.compare_exchange(tid.raw(), 0, AcqRel, Acquire);
@@ -192,6 +192,17 @@ fn task_join(hdl: Handle, retval: UserPtr<Out, usize>) -> Result {
     })
 }
 
+#[syscall]
+fn task_id(main: bool) -> Result<u64> {
+    SCHED.with_current(|cur| {
+        Ok(if main {
+            cur.space().main()
+        } else {
+            cur.tid.raw()
+        })
+    })
+}
+
 #[syscall]
 fn task_ctl(hdl: Handle, op: u32, data: UserPtr<InOut, Handle>) -> Result {
     hdl.check_null()?;
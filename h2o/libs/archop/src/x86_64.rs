@@ -41,6 +41,19 @@ pub unsafe fn halt() {
     asm!("hlt");
 }
 
+/// Enable interrupts and halt until the next one.
+///
+/// `sti` delays interrupts until the following instruction, so no interrupt
+/// can sneak in before `hlt` and leave the CPU halted without waking it up.
+///
+/// # Safety
+///
+/// Invalid use of this function can cause CPU's unrecoverable fault.
+#[inline]
+pub unsafe fn halt_intr() {
+    asm!("sti; hlt");
+}
+
 /// # Safety
 ///
 /// Invalid use of this function can cause CPU unrecoverable fault.
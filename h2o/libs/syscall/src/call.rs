@@ -2,7 +2,7 @@
 #![allow(clippy::missing_safety_doc)]
 
 pub(crate) mod hdl;
-#[cfg(all(not(feature = "stub"), feature = "call"))]
+#[cfg(any(feature = "host", all(not(feature = "stub"), feature = "call")))]
 mod raw;
 pub(crate) mod reg;
 
@@ -13,22 +13,46 @@ pub struct Syscall {
     pub args: [usize; 5],
 }
 
-#[cfg(all(not(feature = "stub"), feature = "call"))]
-use crate::{c_ty::*, ipc::RawPacket, mem::*, res::*, task::ExecInfo, Feature, Handle, SerdeReg};
+#[cfg(any(feature = "host", all(not(feature = "stub"), feature = "call")))]
+use crate::{
+    c_ty::*, ipc::RawPacket, klog::LogRecord, mem::*, res::*, task::ExecInfo, Feature, Handle,
+    SerdeReg,
+};
 
 #[cfg(feature = "vdso")]
 #[no_mangle]
 pub unsafe extern "C" fn sv_time_get(ptr: *mut ()) -> crate::c_ty::Status {
-    let ticks = {
-        let (eax, edx): (u32, u32);
-        core::arch::asm!("rdtsc", out("eax")eax, out("edx")edx);
-        ((edx as u64) << 32) | (eax as u64)
+    use core::{
+        ptr::addr_of,
+        sync::atomic::{fence, Ordering::*},
     };
 
-    let c = crate::constants();
+    let data = crate::time_data();
+    let ns = loop {
+        let seq = data.seq.load(Acquire);
+        if seq & 1 != 0 {
+            core::hint::spin_loop();
+            continue;
+        }
+        if !addr_of!(data.stable).read_volatile() {
+            let ret = raw::syscall(crate::SV_TIME_GET, ptr as usize, 0, 0, 0, 0);
+            return SerdeReg::decode(ret);
+        }
+
+        let ticks = {
+            let (eax, edx): (u32, u32);
+            core::arch::asm!("rdtsc", out("eax")eax, out("edx")edx);
+            ((edx as u64) << 32) | (eax as u64)
+        };
+        let offset = addr_of!(data.ticks_offset).read_volatile();
+        let mul = addr_of!(data.ticks_multiplier).read_volatile();
+        let sft = addr_of!(data.ticks_shift).read_volatile();
 
-    let val = ticks - c.ticks_offset;
-    let ns = (val as u128 * c.ticks_multiplier) >> c.ticks_shift;
+        fence(Acquire);
+        if data.seq.load(Relaxed) == seq {
+            break (ticks.wrapping_sub(offset) as u128 * mul) >> sft;
+        }
+    };
 
     ptr.cast::<u128>().write(ns);
 
@@ -75,5 +99,17 @@ pub extern "C" fn sv_cpu_num() -> crate::c_ty::StatusOrValue {
     crate::c_ty::StatusOrValue::from_res(Ok(crate::constants().num_cpus as u64))
 }
 
-#[cfg(all(not(feature = "stub"), feature = "call"))]
+#[cfg(feature = "host")]
+#[no_mangle]
+pub extern "C" fn sv_random() -> crate::c_ty::StatusOrValue {
+    crate::c_ty::StatusOrValue::from_res(Ok(crate::host::random()))
+}
+
+#[cfg(feature = "host")]
+#[no_mangle]
+pub extern "C" fn sv_cpu_num() -> crate::c_ty::StatusOrValue {
+    crate::c_ty::StatusOrValue::from_res(Ok(crate::host::cpu_num() as u64))
+}
+
+#[cfg(any(feature = "host", all(not(feature = "stub"), feature = "call")))]
 include!(concat!(env!("CARGO_MANIFEST_DIR"), "/target/call.rs"));
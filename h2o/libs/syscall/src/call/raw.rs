@@ -1,6 +1,7 @@
 /// # Safety
 ///
 /// The caller is responsible for the arguments and the results of the syscall.
+#[cfg(not(feature = "host"))]
 #[inline]
 pub unsafe fn syscall(
     num: usize,
@@ -26,6 +27,25 @@ pub unsafe fn syscall(
     ret
 }
 
+/// # Safety
+///
+/// The caller is responsible for the arguments and the results of the syscall.
+#[cfg(feature = "host")]
+#[inline]
+pub unsafe fn syscall(
+    num: usize,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> usize {
+    crate::host::handle(crate::Syscall {
+        num,
+        args: [arg1, arg2, arg3, arg4, arg5],
+    })
+}
+
 #[inline]
 pub fn pack_syscall(
     num: usize,
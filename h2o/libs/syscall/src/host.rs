@@ -0,0 +1,116 @@
+//! # In-process emulation of the kernel objects
+//!
+//! With the `host` feature enabled, every syscall is served by this module
+//! instead of the kernel, so that the user-space libraries (and the RPC
+//! protocols above them) can be tested with `cargo test` on the development
+//! host.
+//!
+//! The whole process is treated as one task space. Channels, events,
+//! dispatchers, timers, futexes, integers and (non-mappable) physical memory
+//! objects are emulated with the same semantics as in the kernel, while the
+//! syscalls that require a real address space or hardware return `ESPRT`.
+
+mod chan;
+mod disp;
+mod event;
+mod futex;
+mod mem;
+mod obj;
+mod time;
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    io::Write,
+    slice,
+    thread::{self, available_parallelism},
+    time::Duration,
+};
+
+use crate::*;
+
+#[inline]
+fn arg<T: SerdeReg>(reg: usize) -> T {
+    T::decode(reg)
+}
+
+pub(crate) fn handle(syscall: Syscall) -> usize {
+    let a = syscall.args;
+    match syscall.num {
+        SV_CHAN_NEW => chan::chan_new(arg(a[0]), arg(a[1])).encode(),
+        SV_CHAN_SEND => chan::chan_send(arg(a[0]), arg(a[1])).encode(),
+        SV_CHAN_RECV => chan::chan_recv(arg(a[0]), arg(a[1])).encode(),
+
+        SV_DISP_NEW => disp::disp_new(arg(a[0])).encode(),
+        SV_DISP_PUSH => {
+            disp::disp_push(arg(a[0]), arg(a[1]), arg(a[2]), arg(a[3]), arg(a[4])).encode()
+        }
+        SV_DISP_POP => disp::disp_pop(arg(a[0]), arg(a[1]), arg(a[2])).encode(),
+
+        SV_EVENT_NEW => event::event_new(arg(a[0])).encode(),
+        SV_EVENT_NOTIFY => event::event_notify(arg(a[0]), arg(a[1]), arg(a[2])).encode(),
+        SV_EVENT_CANCEL => event::event_cancel(arg(a[0])).encode(),
+
+        SV_FUTEX_WAIT => futex::futex_wait(arg(a[0]), arg(a[1]), arg(a[2])).encode(),
+        SV_FUTEX_WAKE => futex::futex_wake(arg(a[0]), arg(a[1])).encode(),
+        SV_FUTEX_REQUE => futex::futex_reque(arg(a[0]), arg(a[1]), arg(a[2]), arg(a[3])).encode(),
+
+        SV_INT_NEW => obj::int_new(arg(a[0])).encode(),
+        SV_INT_GET => obj::int_get(arg(a[0])).encode(),
+
+        SV_LOG => log(arg(a[0]), arg(a[1])).encode(),
+
+        SV_PHYS_ALLOC => mem::phys_alloc(arg(a[0]), arg(a[1])).encode(),
+        SV_PHYS_SIZE => mem::phys_size(arg(a[0])).encode(),
+        SV_PHYS_READ => mem::phys_read(arg(a[0]), arg(a[1]), arg(a[2]), arg(a[3])).encode(),
+        SV_PHYS_WRITE => mem::phys_write(arg(a[0]), arg(a[1]), arg(a[2]), arg(a[3])).encode(),
+        SV_PHYS_READV => mem::phys_readv(arg(a[0]), arg(a[1]), arg(a[2]), arg(a[3])).encode(),
+        SV_PHYS_WRITEV => mem::phys_writev(arg(a[0]), arg(a[1]), arg(a[2]), arg(a[3])).encode(),
+        SV_PHYS_COPY => {
+            mem::phys_copy(arg(a[0]), arg(a[1]), arg(a[2]), arg(a[3]), arg(a[4])).encode()
+        }
+        SV_PHYS_SUB => mem::phys_sub(arg(a[0]), arg(a[1]), arg(a[2]), arg(a[3])).encode(),
+        SV_PHYS_RESIZE => mem::phys_resize(arg(a[0]), arg(a[1]), arg(a[2])).encode(),
+
+        SV_OBJ_CLONE => obj::obj_clone(arg(a[0])).encode(),
+        SV_OBJ_FEAT => obj::obj_feat(arg(a[0]), arg(a[1])).encode(),
+        SV_OBJ_DROP => obj::obj_drop(arg(a[0])).encode(),
+        SV_OBJ_WAIT => {
+            event::obj_wait(arg(a[0]), arg(a[1]), arg(a[2]), arg(a[3]), arg(a[4])).encode()
+        }
+
+        SV_TASK_EXIT => task_exit(arg(a[0])),
+        SV_TASK_SLEEP => task_sleep(arg(a[0])).encode(),
+
+        SV_TIME_GET => time::time_get(arg(a[0])).encode(),
+        SV_TIMER_NEW => time::timer_new().encode(),
+        SV_TIMER_SET => time::timer_set(arg(a[0]), arg(a[1])).encode(),
+
+        _ => ESPRT.into_retval(),
+    }
+}
+
+pub(crate) fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+pub(crate) fn cpu_num() -> usize {
+    available_parallelism().map_or(1, |num| num.get())
+}
+
+fn log(buffer: *const u8, len: usize) -> Result {
+    if buffer.is_null() {
+        return Err(EINVAL);
+    }
+    let buffer = unsafe { slice::from_raw_parts(buffer, len) };
+    std::io::stderr().write_all(buffer).map_err(|_| EIO)
+}
+
+fn task_exit(retval: usize) -> ! {
+    std::process::exit(retval as i32)
+}
+
+fn task_sleep(ms: u32) -> Result {
+    thread::sleep(Duration::from_millis(ms as u64));
+    Ok(())
+}
@@ -0,0 +1,199 @@
+use std::{
+    collections::VecDeque,
+    slice,
+    sync::{
+        atomic::{AtomicU64, Ordering::SeqCst},
+        Arc, Mutex, Weak,
+    },
+    vec::Vec,
+};
+
+use super::{
+    event::Event,
+    obj::{self, Ref},
+};
+use crate::{ipc::*, *};
+
+const MAX_QUEUE_SIZE: usize = 2048;
+
+struct Packet {
+    id: usize,
+    objects: Vec<Ref>,
+    buffer: Vec<u8>,
+}
+
+#[derive(Default)]
+struct ChannelSide {
+    msgs: Mutex<VecDeque<Packet>>,
+    event: Arc<Event>,
+}
+
+pub struct Channel {
+    peer_id: u64,
+    me: Arc<ChannelSide>,
+    peer: Weak<ChannelSide>,
+    head: Mutex<Option<Packet>>,
+}
+
+impl Channel {
+    fn new() -> (Self, Self) {
+        static PEER_ID: AtomicU64 = AtomicU64::new(0);
+        let peer_id = PEER_ID.fetch_add(1, SeqCst);
+
+        let q1 = Arc::new(ChannelSide::default());
+        let q2 = Arc::new(ChannelSide::default());
+        let c1 = Channel {
+            peer_id,
+            me: Arc::clone(&q1),
+            peer: Arc::downgrade(&q2),
+            head: Mutex::new(None),
+        };
+        let c2 = Channel {
+            peer_id,
+            me: q2,
+            peer: Arc::downgrade(&q1),
+            head: Mutex::new(None),
+        };
+        (c1, c2)
+    }
+
+    fn send(&self, msg: Packet) -> Result {
+        let peer = self.peer.upgrade().ok_or(EPIPE)?;
+        let mut msgs = peer.msgs.lock().unwrap();
+        if msgs.len() >= MAX_QUEUE_SIZE {
+            Err(ENOSPC)
+        } else {
+            msgs.push_back(msg);
+            peer.event.notify(0, SIG_READ);
+            Ok(())
+        }
+    }
+
+    fn receive(&self, buffer_cap: &mut usize, handle_cap: &mut usize) -> Result<Packet> {
+        let mut head = self.head.lock().unwrap();
+
+        let packet = match head.take() {
+            Some(packet) => packet,
+            None => {
+                let err = if self.peer.strong_count() > 0 {
+                    ENOENT
+                } else {
+                    EPIPE
+                };
+                let mut msgs = self.me.msgs.lock().unwrap();
+                let packet = msgs.pop_front().ok_or(err)?;
+                // Unlike the kernel, keep the signal as long as there are more
+                // packets, so that level-triggered waiters won't miss any.
+                if msgs.is_empty() {
+                    self.me.event.notify(SIG_READ, 0);
+                }
+                packet
+            }
+        };
+
+        let buffer_size = packet.buffer.len();
+        let handle_count = packet.objects.len();
+        let ret = if buffer_size > *buffer_cap || handle_count > *handle_cap {
+            *head = Some(packet);
+            Err(EBUFFER)
+        } else {
+            Ok(packet)
+        };
+        *buffer_cap = buffer_size;
+        *handle_cap = handle_count;
+        ret
+    }
+}
+
+impl Drop for Channel {
+    fn drop(&mut self) {
+        if let Some(peer) = self.peer.upgrade() {
+            peer.event.cancel();
+        }
+    }
+}
+
+fn insert(channel: Channel) -> Handle {
+    let event = Arc::downgrade(&channel.me.event);
+    let feat = Feature::SEND | Feature::READ | Feature::WRITE | Feature::WAIT;
+    obj::insert(Ref::new(Arc::new(channel), Some(event), feat))
+}
+
+pub fn chan_new(p1: *mut Handle, p2: *mut Handle) -> Result {
+    if p1.is_null() || p2.is_null() {
+        return Err(EPERM);
+    }
+    let (c1, c2) = Channel::new();
+    unsafe {
+        p1.write(insert(c1));
+        p2.write(insert(c2));
+    }
+    Ok(())
+}
+
+pub fn chan_send(hdl: Handle, packet: *const RawPacket) -> Result {
+    hdl.check_null()?;
+    if packet.is_null() {
+        return Err(EPERM);
+    }
+
+    let packet = unsafe { packet.read() };
+    if packet.buffer_size > MAX_BUFFER_SIZE || packet.handle_count >= MAX_HANDLE_COUNT {
+        return Err(ENOMEM);
+    }
+    let handles = match packet.handle_count {
+        0 => &[][..],
+        count => unsafe { slice::from_raw_parts(packet.handles, count) },
+    };
+    if handles.contains(&hdl) {
+        return Err(EPERM);
+    }
+    let buffer = match packet.buffer_size {
+        0 => Vec::new(),
+        size => unsafe { slice::from_raw_parts(packet.buffer, size) }.to_vec(),
+    };
+
+    let (feat, channel) = obj::get::<Channel>(hdl)?;
+    if !feat.contains(Feature::WRITE) {
+        return Err(EPERM);
+    }
+
+    let objects = obj::split(handles, |obj| match obj.downcast::<Channel>() {
+        Ok(chan) if chan.peer_id == channel.peer_id => Err(EPERM),
+        _ if !obj.features().contains(Feature::SEND) => Err(EPERM),
+        _ => Ok(()),
+    })?;
+    channel.send(Packet {
+        id: packet.id,
+        objects,
+        buffer,
+    })
+}
+
+pub fn chan_recv(hdl: Handle, packet_ptr: *mut RawPacket) -> Result {
+    hdl.check_null()?;
+    if packet_ptr.is_null() {
+        return Err(EPERM);
+    }
+    let mut raw = unsafe { packet_ptr.read() };
+
+    let (feat, channel) = obj::get::<Channel>(hdl)?;
+    if !feat.contains(Feature::READ) {
+        return Err(EPERM);
+    }
+
+    raw.buffer_size = raw.buffer_cap;
+    raw.handle_count = raw.handle_cap;
+    let res = channel.receive(&mut raw.buffer_size, &mut raw.handle_count);
+    let ret = res.map(|packet| unsafe {
+        raw.id = packet.id;
+        for (index, obj) in packet.objects.into_iter().enumerate() {
+            raw.handles.add(index).write(obj::insert(obj));
+        }
+        raw.buffer
+            .copy_from_nonoverlapping(packet.buffer.as_ptr(), packet.buffer.len());
+    });
+
+    unsafe { packet_ptr.write(raw) };
+    ret
+}
@@ -0,0 +1,182 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering::*},
+        Arc, Mutex, Weak,
+    },
+    vec::Vec,
+};
+
+use super::{
+    event::{Event, Waiter, WaiterData},
+    obj::{self, Ref},
+};
+use crate::{ipc::*, *};
+
+struct Request {
+    key: usize,
+    event: Weak<Event>,
+    waiter_data: WaiterData,
+    syscall: Option<Syscall>,
+}
+
+struct Ready {
+    canceled: bool,
+    signal: usize,
+    request: Request,
+}
+
+pub struct Dispatcher {
+    next_key: AtomicUsize,
+    event: Arc<Event>,
+
+    capacity: usize,
+    pending: Mutex<Vec<Request>>,
+    ready: Mutex<VecDeque<Ready>>,
+}
+
+impl Dispatcher {
+    fn push(
+        self: &Arc<Self>,
+        event: &Arc<Event>,
+        waiter_data: WaiterData,
+        syscall: Option<Syscall>,
+    ) -> Result<usize> {
+        let key = self.next_key.fetch_add(1, AcqRel);
+        let req = Request {
+            key,
+            event: Arc::downgrade(event),
+            waiter_data,
+            syscall,
+        };
+        {
+            let mut pending = self.pending.lock().unwrap();
+            let ready = self.ready.lock().unwrap().len();
+            if pending.len() + ready >= self.capacity {
+                return Err(ENOSPC);
+            }
+            pending.push(req);
+        }
+
+        event.wait(Arc::clone(self) as _);
+        Ok(key)
+    }
+
+    fn pop(&self) -> Option<(usize, usize, bool, Option<Syscall>)> {
+        let Ready {
+            canceled,
+            signal,
+            request,
+        } = self.ready.lock().unwrap().pop_front()?;
+        let res = if !canceled { request.syscall } else { None };
+        self.event.notify(0, SIG_WRITE);
+        Some((request.key, signal, canceled, res))
+    }
+
+    fn fire(&self, event: *const Event, signal: usize, canceled: bool, on_wait: bool) -> bool {
+        let mut fired = false;
+        let empty = {
+            let mut pending = self.pending.lock().unwrap();
+            let mut ready = self.ready.lock().unwrap();
+            pending.retain_mut(|req| {
+                let fire = req.event.as_ptr() == event
+                    && req.waiter_data.can_signal(signal, on_wait)
+                    && (canceled || ready.len() < self.capacity);
+                if fire {
+                    let request = Request {
+                        event: Weak::new(),
+                        ..*req
+                    };
+                    ready.push_back(Ready {
+                        canceled,
+                        signal,
+                        request,
+                    });
+                    fired = true;
+                }
+                !fire
+            });
+            pending.is_empty()
+        };
+
+        if fired {
+            self.event.notify(0, SIG_READ);
+        }
+        empty
+    }
+}
+
+impl Waiter for Dispatcher {
+    fn on_cancel(&self, event: *const Event, signal: usize) {
+        self.fire(event, signal, true, false);
+    }
+
+    fn try_on_notify(&self, event: *const Event, signal: usize, on_wait: bool) -> bool {
+        self.fire(event, signal, false, on_wait)
+    }
+}
+
+pub fn disp_new(capacity: usize) -> Result<Handle> {
+    let event = Event::new(0);
+    let weak = Arc::downgrade(&event);
+    let disp = Arc::new(Dispatcher {
+        next_key: AtomicUsize::new(1),
+        event,
+        capacity,
+        pending: Mutex::new(Vec::new()),
+        ready: Mutex::new(VecDeque::new()),
+    });
+    let feat = Feature::SEND | Feature::SYNC | Feature::READ | Feature::WRITE | Feature::WAIT;
+    Ok(obj::insert(Ref::new(disp, Some(weak), feat)))
+}
+
+pub fn disp_push(
+    disp: Handle,
+    hdl: Handle,
+    level_triggered: bool,
+    signal: usize,
+    syscall: *const Syscall,
+) -> Result<usize> {
+    hdl.check_null()?;
+    disp.check_null()?;
+    let syscall = (!syscall.is_null())
+        .then(|| {
+            let syscall = unsafe { syscall.read() };
+            if matches!(syscall.num, SV_DISP_NEW | SV_DISP_PUSH | SV_DISP_POP) {
+                return Err(EPERM);
+            }
+            Ok(syscall)
+        })
+        .transpose()?;
+
+    let obj = obj::get_ref(hdl)?;
+    let (feat, disp) = obj::get::<Dispatcher>(disp)?;
+    if !obj.features().contains(Feature::WAIT) {
+        return Err(EPERM);
+    }
+    if !feat.contains(Feature::WRITE) {
+        return Err(EPERM);
+    }
+    let event = obj.event().ok_or(EPIPE)?;
+    drop(obj);
+
+    disp.push(&event, WaiterData::new(level_triggered, signal), syscall)
+}
+
+pub fn disp_pop(disp: Handle, signal_slot: *mut usize, result: *mut usize) -> Result<usize> {
+    let (feat, disp) = obj::get::<Dispatcher>(disp)?;
+    if !feat.contains(Feature::READ) {
+        return Err(EPERM);
+    }
+    let (key, signal, canceled, syscall) = disp.pop().ok_or(ENOENT)?;
+
+    if !signal_slot.is_null() {
+        unsafe { signal_slot.write(if canceled { 0 } else { signal }) };
+    }
+
+    let r = syscall.map_or(0, super::handle);
+    if !result.is_null() {
+        unsafe { result.write(r) };
+    }
+    Ok(key)
+}
@@ -0,0 +1,233 @@
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+    vec::Vec,
+};
+
+use super::obj::{self, Ref};
+use crate::*;
+
+#[derive(Debug, Clone, Copy)]
+pub struct WaiterData {
+    level_triggered: bool,
+    signal: usize,
+}
+
+impl WaiterData {
+    pub fn new(level_triggered: bool, signal: usize) -> Self {
+        WaiterData {
+            level_triggered,
+            signal,
+        }
+    }
+
+    #[inline]
+    pub fn signal(&self) -> usize {
+        self.signal
+    }
+
+    #[inline]
+    pub fn can_signal(&self, signal: usize, on_wait: bool) -> bool {
+        if on_wait && !self.level_triggered {
+            false
+        } else {
+            self.signal & !signal == 0
+        }
+    }
+}
+
+pub trait Waiter: Send + Sync {
+    fn on_cancel(&self, event: *const Event, signal: usize);
+
+    /// Returns whether the waiter should be removed from the event.
+    fn try_on_notify(&self, event: *const Event, signal: usize, on_wait: bool) -> bool;
+}
+
+#[derive(Default)]
+struct EventData {
+    signal: usize,
+    waiters: Vec<Arc<dyn Waiter>>,
+}
+
+/// The waitable part of every emulated kernel object.
+///
+/// Unlike the kernel, the waiters are called outside the lock of the event,
+/// so a waiter may fire more than once for concurrent notifications, which
+/// the waiters below tolerate.
+#[derive(Default)]
+pub struct Event {
+    data: Mutex<EventData>,
+}
+
+fn same(a: &Arc<dyn Waiter>, b: &Arc<dyn Waiter>) -> bool {
+    Arc::as_ptr(a).cast::<()>() == Arc::as_ptr(b).cast::<()>()
+}
+
+impl Event {
+    pub fn new(init_signal: usize) -> Arc<Self> {
+        Arc::new(Event {
+            data: Mutex::new(EventData {
+                signal: init_signal,
+                waiters: Vec::new(),
+            }),
+        })
+    }
+
+    pub fn wait(&self, waiter: Arc<dyn Waiter>) {
+        let signal = {
+            let mut data = self.data.lock().unwrap();
+            data.waiters.push(Arc::clone(&waiter));
+            data.signal
+        };
+        if waiter.try_on_notify(self, signal, true) {
+            self.remove(&waiter);
+        }
+    }
+
+    fn remove(&self, waiter: &Arc<dyn Waiter>) -> bool {
+        let mut data = self.data.lock().unwrap();
+        let len = data.waiters.len();
+        data.waiters.retain(|other| !same(other, waiter));
+        data.waiters.len() != len
+    }
+
+    #[inline]
+    pub fn unwait(&self, waiter: &Arc<dyn Waiter>) -> bool {
+        self.remove(waiter)
+    }
+
+    pub fn cancel(&self) {
+        let (signal, waiters) = {
+            let mut data = self.data.lock().unwrap();
+            (data.signal, std::mem::take(&mut data.waiters))
+        };
+        for waiter in waiters {
+            waiter.on_cancel(self, signal);
+        }
+    }
+
+    pub fn notify(&self, clear: usize, set: usize) -> usize {
+        let (signal, waiters) = {
+            let mut data = self.data.lock().unwrap();
+            let prev = data.signal;
+            let new = (prev & !clear) | set;
+            data.signal = new;
+            if prev == new || prev & new == new {
+                return new;
+            }
+            (new, data.waiters.clone())
+        };
+        for waiter in waiters {
+            if waiter.try_on_notify(self, signal, false) {
+                self.remove(&waiter);
+            }
+        }
+        signal
+    }
+}
+
+/// A task blocked in `obj_wait`.
+struct Blocker {
+    wake_all: bool,
+    waiter_data: WaiterData,
+    /// `None` until notified or canceled.
+    status: Mutex<Option<(bool, usize)>>,
+    cond: Condvar,
+}
+
+impl Blocker {
+    fn wait(&self, timeout: Duration) {
+        let deadline = Instant::now().checked_add(timeout);
+        let mut status = self.status.lock().unwrap();
+        while status.is_none() {
+            status = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    self.cond.wait_timeout(status, deadline - now).unwrap().0
+                }
+                None => self.cond.wait(status).unwrap(),
+            };
+        }
+    }
+
+    fn wake(&self, has_signal: bool, signal: usize) {
+        *self.status.lock().unwrap() = Some((has_signal, signal));
+        if self.wake_all {
+            self.cond.notify_all()
+        } else {
+            self.cond.notify_one()
+        }
+    }
+}
+
+impl Waiter for Blocker {
+    fn on_cancel(&self, _: *const Event, signal: usize) {
+        self.wake(false, signal)
+    }
+
+    fn try_on_notify(&self, _: *const Event, signal: usize, on_wait: bool) -> bool {
+        let ret = self.waiter_data.can_signal(signal, on_wait);
+        if ret {
+            self.wake(true, signal);
+        }
+        ret
+    }
+}
+
+pub fn event_new(init_signal: usize) -> Result<Handle> {
+    let event = Event::new(init_signal);
+    let weak = Arc::downgrade(&event);
+    let feat = Feature::SEND | Feature::SYNC | Feature::WAIT | Feature::EXECUTE;
+    Ok(obj::insert(Ref::new(event, Some(weak), feat)))
+}
+
+pub fn event_notify(hdl: Handle, clear: usize, set: usize) -> Result<usize> {
+    let (_, event) = obj::get::<Event>(hdl)?;
+    Ok(event.notify(clear, set))
+}
+
+pub fn event_cancel(hdl: Handle) -> Result {
+    let (_, event) = obj::get::<Event>(hdl)?;
+    event.cancel();
+    Ok(())
+}
+
+pub fn obj_wait(
+    hdl: Handle,
+    timeout_us: u64,
+    level_triggered: bool,
+    wake_all: bool,
+    signal: usize,
+) -> Result<usize> {
+    let obj = obj::get_ref(hdl)?;
+    if !obj.features().contains(Feature::WAIT) {
+        return Err(EPERM);
+    }
+    let event = obj.event().ok_or(EPIPE)?;
+    drop(obj);
+
+    let blocker = Arc::new(Blocker {
+        wake_all,
+        waiter_data: WaiterData::new(level_triggered, signal),
+        status: Mutex::new(None),
+        cond: Condvar::new(),
+    });
+    let waiter = Arc::clone(&blocker) as Arc<dyn Waiter>;
+    event.wait(Arc::clone(&waiter));
+    blocker.wait(Duration::from_micros(timeout_us));
+
+    event.unwait(&waiter);
+    let status = *blocker.status.lock().unwrap();
+    match status {
+        Some((true, signal)) => {
+            if !wake_all {
+                event.notify(blocker.waiter_data.signal(), 0);
+            }
+            Ok(signal)
+        }
+        _ => Err(ETIME),
+    }
+}
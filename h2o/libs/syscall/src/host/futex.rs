@@ -0,0 +1,129 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering::SeqCst},
+        Arc, Condvar, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::*;
+
+#[derive(Default)]
+struct Waiter {
+    woken: Mutex<bool>,
+    cond: Condvar,
+}
+
+impl Waiter {
+    fn wake(&self) {
+        *self.woken.lock().unwrap() = true;
+        self.cond.notify_one();
+    }
+}
+
+type Queue = VecDeque<Arc<Waiter>>;
+
+/// The wait queues of the futexes, keyed by their addresses.
+static FUTEXES: Mutex<BTreeMap<usize, Queue>> = Mutex::new(BTreeMap::new());
+
+fn wake(futexes: &mut BTreeMap<usize, Queue>, key: usize, num: usize) -> usize {
+    let Some(queue) = futexes.get_mut(&key) else {
+        return 0;
+    };
+    let num = num.min(queue.len());
+    queue.drain(..num).for_each(|waiter| waiter.wake());
+    if queue.is_empty() {
+        futexes.remove(&key);
+    }
+    num
+}
+
+pub fn futex_wait(ptr: *const u64, expected: u64, timeout_us: u64) -> Result {
+    if ptr.is_null() {
+        return Err(EPERM);
+    }
+    let waiter = Arc::new(Waiter::default());
+    {
+        let mut futexes = FUTEXES.lock().unwrap();
+        let value = unsafe { (*ptr.cast::<AtomicU64>()).load(SeqCst) };
+        if value != expected {
+            return Err(EINVAL);
+        }
+        let queue = futexes.entry(ptr as usize).or_default();
+        queue.push_back(Arc::clone(&waiter));
+    }
+
+    let deadline = Instant::now().checked_add(Duration::from_micros(timeout_us));
+    let mut woken = waiter.woken.lock().unwrap();
+    while !*woken {
+        woken = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    break;
+                }
+                waiter.cond.wait_timeout(woken, deadline - now).unwrap().0
+            }
+            None => waiter.cond.wait(woken).unwrap(),
+        };
+    }
+    if *woken {
+        return Ok(());
+    }
+    drop(woken);
+
+    // The waiter may have been requeued to another futex.
+    let mut futexes = FUTEXES.lock().unwrap();
+    if *waiter.woken.lock().unwrap() {
+        return Ok(());
+    }
+    futexes.retain(|_, queue| {
+        queue.retain(|other| !Arc::ptr_eq(other, &waiter));
+        !queue.is_empty()
+    });
+    Err(ETIME)
+}
+
+pub fn futex_wake(ptr: *const u64, num: usize) -> Result<usize> {
+    if ptr.is_null() {
+        return Err(EPERM);
+    }
+    Ok(wake(&mut FUTEXES.lock().unwrap(), ptr as usize, num))
+}
+
+pub fn futex_reque(
+    ptr: *const u64,
+    wake_num: *mut usize,
+    other: *const u64,
+    reque_num: *mut usize,
+) -> Result {
+    if ptr.is_null() || other.is_null() || wake_num.is_null() || reque_num.is_null() {
+        return Err(EPERM);
+    }
+    let (num, requeue) = unsafe { (wake_num.read(), reque_num.read()) };
+
+    let mut futexes = FUTEXES.lock().unwrap();
+    let woken = wake(&mut futexes, ptr as usize, num);
+    let moved = match futexes.get_mut(&(ptr as usize)) {
+        Some(queue) => {
+            let moved = queue.drain(..requeue.min(queue.len())).collect::<Queue>();
+            if queue.is_empty() {
+                futexes.remove(&(ptr as usize));
+            }
+            moved
+        }
+        None => Queue::new(),
+    };
+    let requeued = moved.len();
+    if requeued > 0 {
+        futexes.entry(other as usize).or_default().extend(moved);
+    }
+    drop(futexes);
+
+    unsafe {
+        wake_num.write(woken);
+        reque_num.write(requeued);
+    }
+    Ok(())
+}
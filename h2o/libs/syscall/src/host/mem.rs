@@ -0,0 +1,225 @@
+use std::{
+    slice,
+    sync::{Arc, Mutex},
+    vec,
+    vec::Vec,
+};
+
+use super::{
+    event::Event,
+    obj::{self, Ref},
+};
+use crate::{mem::*, *};
+
+/// A physical memory object backed by the heap of the host.
+///
+/// Sub-objects created without copying share the same storage with their
+/// parents, while the mapping syscalls are not supported at all.
+pub struct Phys {
+    data: Arc<Mutex<Vec<u8>>>,
+    offset: usize,
+    len: Mutex<usize>,
+    resizable: bool,
+    event: Arc<Event>,
+}
+
+impl Phys {
+    fn new(data: Arc<Mutex<Vec<u8>>>, offset: usize, len: usize, resizable: bool) -> Arc<Self> {
+        Arc::new(Phys {
+            data,
+            offset,
+            len: Mutex::new(len),
+            resizable,
+            event: Event::new(0),
+        })
+    }
+
+    fn insert(phys: Arc<Phys>, feat: Feature) -> Handle {
+        let event = Arc::downgrade(&phys.event);
+        obj::insert(Ref::new(phys, Some(event), feat))
+    }
+
+    fn access<R>(&self, offset: usize, len: usize, f: impl FnOnce(&mut [u8]) -> R) -> R {
+        let self_len = *self.len.lock().unwrap();
+        let pos = offset.min(self_len);
+        let len = (self_len - pos).min(len);
+
+        // The storage may be shrunk by the parent of a sub-object.
+        let mut data = self.data.lock().unwrap();
+        let end = (self.offset + pos + len).min(data.len());
+        let start = (self.offset + pos).min(end);
+        f(&mut data[start..end])
+    }
+
+    fn read(&self, offset: usize, len: usize, buffer: *mut u8) -> usize {
+        self.access(offset, len, |data| unsafe {
+            buffer.copy_from_nonoverlapping(data.as_ptr(), data.len());
+            data.len()
+        })
+    }
+
+    fn write(&self, offset: usize, len: usize, buffer: *const u8) -> usize {
+        self.access(offset, len, |data| unsafe {
+            data.as_mut_ptr()
+                .copy_from_nonoverlapping(buffer, data.len());
+            data.len()
+        })
+    }
+}
+
+const DEFAULT_FEATURES: Feature = Feature::SEND
+    .union(Feature::SYNC)
+    .union(Feature::READ)
+    .union(Feature::WRITE)
+    .union(Feature::EXECUTE)
+    .union(Feature::WAIT);
+
+fn phys_check(hdl: Handle, offset: usize, len: usize) -> Result<(Feature, Arc<Phys>)> {
+    if offset.checked_add(len).is_none() {
+        return Err(ERANGE);
+    }
+    obj::get::<Phys>(hdl)
+}
+
+unsafe fn io_vecs<'a>(bufs: *const IoVec, count: usize) -> Result<&'a [IoVec]> {
+    match count {
+        0 => Ok(&[]),
+        _ if bufs.is_null() => Err(EPERM),
+        count => Ok(slice::from_raw_parts(bufs, count)),
+    }
+}
+
+pub fn phys_alloc(size: usize, options: PhysOptions) -> Result<Handle> {
+    let data = Arc::new(Mutex::new(vec![0; size]));
+    let resizable = options.contains(PhysOptions::RESIZABLE);
+    let phys = Phys::new(data, 0, size, resizable);
+    Ok(Phys::insert(phys, DEFAULT_FEATURES))
+}
+
+pub fn phys_size(hdl: Handle) -> Result<usize> {
+    obj::get::<Phys>(hdl).map(|(_, phys)| *phys.len.lock().unwrap())
+}
+
+pub fn phys_read(hdl: Handle, offset: usize, len: usize, buffer: *mut u8) -> Result<usize> {
+    let (feat, phys) = phys_check(hdl, offset, len)?;
+    if !feat.contains(Feature::READ) {
+        return Err(EPERM);
+    }
+    if len > 0 {
+        if buffer.is_null() {
+            return Err(EPERM);
+        }
+        Ok(phys.read(offset, len, buffer))
+    } else {
+        Ok(0)
+    }
+}
+
+pub fn phys_write(hdl: Handle, offset: usize, len: usize, buffer: *const u8) -> Result<usize> {
+    let (feat, phys) = phys_check(hdl, offset, len)?;
+    if !feat.contains(Feature::WRITE) {
+        return Err(EPERM);
+    }
+    if len > 0 {
+        if buffer.is_null() {
+            return Err(EPERM);
+        }
+        Ok(phys.write(offset, len, buffer))
+    } else {
+        Ok(0)
+    }
+}
+
+pub fn phys_readv(hdl: Handle, offset: usize, bufs: *const IoVec, count: usize) -> Result<usize> {
+    let bufs = unsafe { io_vecs(bufs, count) }?;
+    let (feat, phys) = obj::get::<Phys>(hdl)?;
+    if !feat.contains(Feature::READ) {
+        return Err(EPERM);
+    }
+    let mut read_len = 0;
+    for buf in bufs {
+        let len = phys.read(offset + read_len, buf.len, buf.ptr);
+        read_len += len;
+        if len < buf.len {
+            break;
+        }
+    }
+    Ok(read_len)
+}
+
+pub fn phys_writev(hdl: Handle, offset: usize, bufs: *const IoVec, count: usize) -> Result<usize> {
+    let bufs = unsafe { io_vecs(bufs, count) }?;
+    let (feat, phys) = obj::get::<Phys>(hdl)?;
+    if !feat.contains(Feature::WRITE) {
+        return Err(EPERM);
+    }
+    let mut written_len = 0;
+    for buf in bufs {
+        let len = phys.write(offset + written_len, buf.len, buf.ptr);
+        written_len += len;
+        if len < buf.len {
+            break;
+        }
+    }
+    Ok(written_len)
+}
+
+/// The source is copied out first since both objects may share the same
+/// storage.
+pub fn phys_copy(
+    dst: Handle,
+    dst_offset: usize,
+    src: Handle,
+    src_offset: usize,
+    len: usize,
+) -> Result<usize> {
+    let (dst_feat, dst) = phys_check(dst, dst_offset, len)?;
+    if !dst_feat.contains(Feature::WRITE) {
+        return Err(EPERM);
+    }
+    let (src_feat, src) = phys_check(src, src_offset, len)?;
+    if !src_feat.contains(Feature::READ) {
+        return Err(EPERM);
+    }
+    let data = src.access(src_offset, len, |data| data.to_vec());
+    Ok(dst.write(dst_offset, data.len(), data.as_ptr()))
+}
+
+pub fn phys_sub(hdl: Handle, offset: usize, len: usize, copy: bool) -> Result<Handle> {
+    let (feat, phys) = phys_check(hdl, offset, len)?;
+    if !feat.contains(Feature::READ) {
+        return Err(EPERM);
+    }
+    if offset + len > *phys.len.lock().unwrap() {
+        return Err(ERANGE);
+    }
+
+    Ok(if copy {
+        let data = phys.access(offset, len, |data| data.to_vec());
+        let sub = Phys::new(Arc::new(Mutex::new(data)), 0, len, false);
+        Phys::insert(sub, DEFAULT_FEATURES)
+    } else {
+        let data = Arc::clone(&phys.data);
+        let sub = Phys::new(data, phys.offset + offset, len, false);
+        Phys::insert(sub, feat)
+    })
+}
+
+/// The extended memory is always zeroed.
+pub fn phys_resize(hdl: Handle, new_len: usize, _: bool) -> Result {
+    if new_len == 0 {
+        return Err(EINVAL);
+    }
+    let (feat, phys) = obj::get::<Phys>(hdl)?;
+    if !feat.contains(Feature::READ | Feature::WRITE | Feature::EXECUTE) {
+        return Err(EPERM);
+    }
+    if !phys.resizable {
+        return Err(EPERM);
+    }
+
+    let mut len = phys.len.lock().unwrap();
+    phys.data.lock().unwrap().resize(new_len, 0);
+    *len = new_len;
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU32, Ordering::SeqCst},
+        Arc, Mutex, Weak,
+    },
+    vec::Vec,
+};
+
+use super::event::Event;
+use crate::*;
+
+/// A reference to an emulated kernel object held by a handle.
+#[derive(Clone)]
+pub struct Ref {
+    obj: Arc<dyn Any + Send + Sync>,
+    event: Option<Weak<Event>>,
+    feat: Feature,
+}
+
+impl Ref {
+    pub fn new<T: Any + Send + Sync>(
+        obj: Arc<T>,
+        event: Option<Weak<Event>>,
+        feat: Feature,
+    ) -> Self {
+        Ref { obj, event, feat }
+    }
+
+    #[inline]
+    pub fn features(&self) -> Feature {
+        self.feat
+    }
+
+    #[inline]
+    pub fn event(&self) -> Option<Arc<Event>> {
+        self.event.as_ref().and_then(Weak::upgrade)
+    }
+
+    #[inline]
+    pub fn downcast<T: Any + Send + Sync>(&self) -> Result<Arc<T>> {
+        Arc::clone(&self.obj).downcast().map_err(|_| ETYPE)
+    }
+}
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+static HANDLES: Mutex<BTreeMap<u32, Ref>> = Mutex::new(BTreeMap::new());
+
+pub fn insert(obj: Ref) -> Handle {
+    let raw = NEXT_ID.fetch_add(1, SeqCst);
+    HANDLES.lock().unwrap().insert(raw, obj);
+    Handle::new(raw)
+}
+
+pub fn get_ref(hdl: Handle) -> Result<Ref> {
+    let hdl = hdl.check_null()?;
+    let handles = HANDLES.lock().unwrap();
+    handles.get(&hdl.raw()).cloned().ok_or(EINVAL)
+}
+
+pub fn get<T: Any + Send + Sync>(hdl: Handle) -> Result<(Feature, Arc<T>)> {
+    let obj = get_ref(hdl)?;
+    Ok((obj.features(), obj.downcast()?))
+}
+
+pub fn remove(hdl: Handle) -> Result<Ref> {
+    let hdl = hdl.check_null()?;
+    HANDLES.lock().unwrap().remove(&hdl.raw()).ok_or(EINVAL)
+}
+
+/// Remove the handles to be sent, checking with `f`, or restore them all on
+/// failure.
+pub fn split(handles: &[Handle], f: impl Fn(&Ref) -> Result) -> Result<Vec<Ref>> {
+    let mut map = HANDLES.lock().unwrap();
+    let mut ret = Vec::with_capacity(handles.len());
+    for hdl in handles {
+        let res = match map.get(&hdl.raw()) {
+            Some(obj) => f(obj).map(|_| map.remove(&hdl.raw()).unwrap()),
+            None => Err(EINVAL),
+        };
+        match res {
+            Ok(obj) => ret.push(obj),
+            Err(err) => {
+                map.extend(handles.iter().map(|hdl| hdl.raw()).zip(ret));
+                return Err(err);
+            }
+        }
+    }
+    Ok(ret)
+}
+
+pub fn obj_clone(hdl: Handle) -> Result<Handle> {
+    let obj = get_ref(hdl)?;
+    if !obj.features().contains(Feature::SEND | Feature::SYNC) {
+        return Err(EPERM);
+    }
+    Ok(insert(obj))
+}
+
+pub fn obj_feat(hdl_ptr: *mut Handle, feat: Feature) -> Result {
+    if hdl_ptr.is_null() {
+        return Err(EPERM);
+    }
+    let mut obj = remove(unsafe { hdl_ptr.read() })?;
+    let ret = if feat & !obj.feat == Feature::empty() {
+        obj.feat = feat;
+        Ok(())
+    } else {
+        Err(EPERM)
+    };
+    unsafe { hdl_ptr.write(insert(obj)) };
+    ret
+}
+
+pub fn obj_drop(hdl: Handle) -> Result {
+    // The object is dropped outside the lock of the handle table.
+    remove(hdl).map(drop)
+}
+
+pub fn int_new(value: u64) -> Result<Handle> {
+    let feat = Feature::SEND | Feature::SYNC | Feature::READ;
+    Ok(insert(Ref::new(Arc::new(value), None, feat)))
+}
+
+pub fn int_get(hdl: Handle) -> Result<u64> {
+    get::<u64>(hdl).map(|(_, value)| *value)
+}
@@ -0,0 +1,77 @@
+use std::{
+    sync::{Arc, Mutex, OnceLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{
+    event::Event,
+    obj::{self, Ref},
+};
+use crate::{ipc::SIG_TIMER, *};
+
+/// The time of the first call, which the emulated time is measured from.
+fn boot() -> Instant {
+    static BOOT: OnceLock<Instant> = OnceLock::new();
+    *BOOT.get_or_init(Instant::now)
+}
+
+pub fn time_get(ptr: *mut ()) -> Result {
+    if ptr.is_null() {
+        return Err(EPERM);
+    }
+    let ns = boot().elapsed().as_nanos();
+    unsafe { ptr.cast::<u128>().write(ns) };
+    Ok(())
+}
+
+/// A timer fired by a sleeping host thread.
+///
+/// Every setting increases the generation, so that the threads of the
+/// previous settings won't fire the event any longer.
+struct Timer {
+    event: Arc<Event>,
+    generation: Mutex<(u64, bool)>,
+}
+
+pub fn timer_new() -> Result<Handle> {
+    let timer = Arc::new(Timer {
+        event: Event::new(0),
+        generation: Mutex::new((0, false)),
+    });
+    let event = Arc::downgrade(&timer.event);
+    let feat = Feature::SEND | Feature::SYNC | Feature::WAIT | Feature::WRITE;
+    Ok(obj::insert(Ref::new(timer, Some(event), feat)))
+}
+
+pub fn timer_set(handle: Handle, duration_us: u64) -> Result {
+    let (feat, timer) = obj::get::<Timer>(handle)?;
+    if !feat.contains(Feature::WRITE) {
+        return Err(EPERM);
+    }
+
+    let generation = {
+        let mut generation = timer.generation.lock().unwrap();
+        let (ref mut value, ref mut active) = *generation;
+        if *active {
+            timer.event.cancel();
+        }
+        *value += 1;
+        *active = duration_us > 0;
+        *value
+    };
+    if duration_us > 0 {
+        let timer = Arc::downgrade(&timer);
+        thread::spawn(move || {
+            thread::sleep(Duration::from_micros(duration_us));
+            if let Some(timer) = timer.upgrade() {
+                let mut current = timer.generation.lock().unwrap();
+                if *current == (generation, true) {
+                    current.1 = false;
+                    timer.event.notify(0, SIG_TIMER);
+                }
+            }
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,30 @@
+/// The maximum length of the module path stored in a kernel log record.
+pub const LOG_MODULE_LEN: usize = 48;
+/// The maximum length of the message stored in a kernel log record.
+pub const LOG_MSG_LEN: usize = 200;
+/// The module path of records logged by user tasks via `sv_log`.
+pub const LOG_MODULE_USER: &str = "<user>";
+
+pub const LOG_LEVEL_ERROR: u32 = 1;
+pub const LOG_LEVEL_WARN: u32 = 2;
+pub const LOG_LEVEL_INFO: u32 = 3;
+pub const LOG_LEVEL_DEBUG: u32 = 4;
+pub const LOG_LEVEL_TRACE: u32 = 5;
+
+/// The header of a record in the kernel log ring, returned by `sv_log_read`.
+///
+/// The module path and the message are written to the user buffer in order,
+/// with their lengths recorded in `module_len` and `msg_len`.
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+pub struct LogRecord {
+    /// The sequence number of the record. Gaps between consecutive reads
+    /// indicate records overwritten before being read.
+    pub seq: u64,
+    /// The timestamp in nanoseconds.
+    pub time: u128,
+    pub cpu: u32,
+    pub level: u32,
+    pub module_len: usize,
+    pub msg_len: usize,
+}
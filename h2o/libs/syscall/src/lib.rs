@@ -7,19 +7,25 @@
 #![feature(linkage)]
 #![feature(macro_metavar_expr)]
 
+#[cfg(feature = "host")]
+extern crate std;
+
 pub mod call;
 pub mod error;
 pub mod feat;
+#[cfg(feature = "host")]
+mod host;
 pub mod ipc;
+pub mod klog;
 pub mod mem;
 pub mod res;
-#[cfg(feature = "stub")]
+#[cfg(all(feature = "stub", not(feature = "host")))]
 pub mod stub;
 pub mod task;
 
 pub use sv_gen::*;
 
-#[cfg(feature = "stub")]
+#[cfg(all(feature = "stub", not(feature = "host")))]
 pub use self::stub::*;
 pub use self::{
     call::{hdl::Handle, reg::*, Syscall, *},
@@ -30,9 +36,6 @@ pub use self::{
 #[derive(Debug, Copy, Clone)]
 #[repr(C)]
 pub struct Constants {
-    pub ticks_offset: u64,
-    pub ticks_multiplier: u128,
-    pub ticks_shift: u128,
     pub has_builtin_rand: bool,
     pub num_cpus: usize,
 }
@@ -40,9 +43,6 @@ pub struct Constants {
 impl Constants {
     pub const fn new() -> Constants {
         Constants {
-            ticks_offset: 0,
-            ticks_multiplier: 1,
-            ticks_shift: 0,
             has_builtin_rand: false,
             num_cpus: 1,
         }
@@ -74,6 +74,51 @@ fn constants() -> Constants {
     }
 }
 
+/// The TSC scaling parameters published by the kernel in the VDSO data page.
+///
+/// The kernel updates the fields under a seqlock: `seq` is odd while an update
+/// is in progress, and readers must retry if it changes across their reads.
+#[derive(Debug)]
+#[repr(C)]
+pub struct TimeData {
+    pub seq: core::sync::atomic::AtomicU32,
+    /// Whether the TSC can be used to compute the time in user mode. If not,
+    /// readers should fall back to the syscall.
+    pub stable: bool,
+    pub ticks_offset: u64,
+    pub ticks_multiplier: u128,
+    pub ticks_shift: u128,
+}
+
+#[cfg(feature = "vdso")]
+pub const TIME_DATA_SIZE: usize = core::mem::size_of::<TimeData>();
+// The data page is zeroed, so the time is read via the syscall until the kernel
+// publishes the parameters.
+#[cfg(feature = "vdso")]
+core::arch::global_asm!("
+    .section .rodata
+    .balign 4096
+    .global TIME_DATA
+    .type TIME_DATA, object
+TIME_DATA:
+    .fill {TIME_DATA_SIZE}, 1, 0
+    .balign 4096", 
+    TIME_DATA_SIZE = const TIME_DATA_SIZE
+);
+
+#[cfg(feature = "vdso")]
+fn time_data() -> &'static TimeData {
+    let mut addr: *const TimeData;
+
+    unsafe {
+        core::arch::asm!(
+            "lea {}, [rip + TIME_DATA]",
+            out(reg) addr
+        );
+        &*addr
+    }
+}
+
 #[cfg(all(not(feature = "call"), feature = "vdso"))]
 compile_error!("The VDSO feature is only supported with call feature");
 
@@ -2,6 +2,10 @@ pub const RES_MEM: u32 = 0;
 pub const RES_PIO: u32 = 1;
 pub const RES_INTR: u32 = 2;
 
+pub const POWER_SHUTDOWN: u32 = 0;
+pub const POWER_REBOOT: u32 = 1;
+pub const POWER_SLEEP: u32 = 2;
+
 #[derive(Debug, Clone, Copy, Default)]
 #[repr(C)]
 pub struct Msi {
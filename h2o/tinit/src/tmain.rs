@@ -138,6 +138,7 @@ extern "C" fn tmain(init_chan: sv_call::Handle) {
 
     let bootfs_phys =
         unsafe { Phys::from_raw(handles[HandleIndex::Bootfs as usize].assume_init()) };
+    let pio_res = unsafe { PioRes::from_raw(handles[HandleIndex::PioRes as usize].assume_init()) };
     let bootfs = map_bootfs(&bootfs_phys, root_virt);
 
     let bin = {
@@ -196,6 +197,7 @@ extern "C" fn tmain(init_chan: sv_call::Handle) {
                 HandleType::BootfsPhys.into(),
                 Phys::into_raw(bootfs_phys.clone()),
             ),
+            (HandleType::PioRes.into(), PioRes::into_raw(pio_res)),
         ]
         .into_iter()
         .collect(),
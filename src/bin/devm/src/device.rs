@@ -17,9 +17,9 @@ pub async fn handle_driver(server: DriverServer) {
 
         let res = match request {
             DriverRequest::CloseConnection { responder } => responder.send(()),
-            DriverRequest::Unknown(_) => {
+            DriverRequest::Unknown(req) => {
                 log::warn!("unknown request received");
-                continue;
+                req.unsupported()
             }
         };
 
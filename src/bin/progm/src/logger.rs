@@ -0,0 +1,335 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt::Write, mem, time::Duration};
+
+use futures_lite::StreamExt;
+use solvent::{
+    dev::PioRes,
+    error::{Error, EINVAL, ENOENT},
+    ipc::Channel,
+    klog,
+    time::{Instant, Timer},
+};
+use solvent_async::{ipc::Channel as AsyncChannel, time::Timer as AsyncTimer};
+use solvent_fs::{rpc::RpcNode, spawner, Spawner};
+use solvent_rpc::{
+    io::{self, entry::EntrySyncClient, file::FileClient, OpenOptions},
+    logger::{
+        Level, LogReader, LogReaderRequest, LogReaderServer, Logger, LoggerRequest, LoggerServer,
+        Record, Source, UserRecord,
+    },
+    sync::Client,
+    Protocol, Server,
+};
+use solvent_std::{
+    path::{Path, PathBuf},
+    sync::{Arsc, Mutex},
+};
+
+/// The kernel doesn't notify us of new records, so we poll the log ring in
+/// this interval when tailing.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+fn convert(record: &klog::Record) -> Record {
+    Record {
+        seq: record.seq(),
+        time: unsafe { record.time().raw() },
+        cpu: record.cpu(),
+        level: record.level().into(),
+        source: if record.module() == klog::LOG_MODULE_USER {
+            Source::User
+        } else {
+            Source::Kernel
+        },
+        module: record.module().to_string(),
+        message: record.message().to_string(),
+    }
+}
+
+fn read(res: &PioRes, start: u64, max: usize) -> (Vec<Record>, u64) {
+    let mut ret = Vec::new();
+    let mut seq = start;
+    while ret.len() < max {
+        match klog::read(res, seq) {
+            Ok(record) => {
+                seq = record.seq() + 1;
+                ret.push(convert(&record));
+            }
+            Err(ENOENT) => break,
+            Err(err) => {
+                log::warn!("failed to read the kernel log: {err}");
+                break;
+            }
+        }
+    }
+    (ret, seq)
+}
+
+async fn tail(res: &PioRes, timer: &AsyncTimer, start: u64, max: usize) -> (Vec<Record>, u64) {
+    loop {
+        let (records, seq) = read(res, start, max);
+        if !records.is_empty() || max == 0 {
+            break (records, seq);
+        }
+        if let Err(err) = timer.wait_after(POLL_INTERVAL).await {
+            log::warn!("failed to wait for the kernel log: {err}");
+            break (records, seq);
+        }
+    }
+}
+
+fn level(res: &PioRes) -> Result<Level, Error> {
+    let level = klog::level(res)?;
+    level.to_level().map(Level::from).ok_or(EINVAL)
+}
+
+fn set_level(res: &PioRes, level: Level) -> Result<Level, Error> {
+    let level = log::Level::from(level).to_level_filter();
+    let old = klog::set_level(res, level)?;
+    old.to_level().map(Level::from).ok_or(EINVAL)
+}
+
+fn spawn_log_reader(pio_res: Arsc<PioRes>, conn: Channel, spawner: &Spawner) {
+    let server = LogReaderServer::from(AsyncChannel::with_disp(conn, spawner.dispatch()));
+    spawner.spawn(handle_log_reader(pio_res, server, spawner.clone()))
+}
+
+async fn handle_log_reader(pio_res: Arsc<PioRes>, server: LogReaderServer, spawner: Spawner) {
+    let timer = AsyncTimer::with_disp(Timer::new(), spawner.dispatch());
+
+    let (mut stream, _) = server.serve();
+    while let Some(request) = stream.next().await {
+        let request = match request {
+            Ok(request) => request,
+            Err(err) => {
+                log::warn!("RPC receive error: {err}");
+                continue;
+            }
+        };
+
+        let res = match request {
+            LogReaderRequest::CloneConnection { conn, responder } => {
+                spawn_log_reader(pio_res.clone(), conn, &spawner);
+                responder.send(())
+            }
+            LogReaderRequest::CloseConnection { responder } => {
+                responder.close();
+                break;
+            }
+            LogReaderRequest::Read {
+                start,
+                max,
+                responder,
+            } => responder.send(read(&pio_res, start, max)),
+            LogReaderRequest::Tail {
+                start,
+                max,
+                responder,
+            } => responder.send(tail(&pio_res, &timer, start, max).await),
+            LogReaderRequest::Level { responder } => responder.send(level(&pio_res)),
+            LogReaderRequest::SetLevel { level, responder } => {
+                responder.send(set_level(&pio_res, level))
+            }
+            LogReaderRequest::Unknown(req) => {
+                log::warn!("unknown request received");
+                req.unsupported()
+            }
+        };
+
+        if let Err(err) = res {
+            log::warn!("RPC send error: {err}")
+        }
+    }
+}
+
+/// The number of buffered user records that triggers forwarding.
+const BUFFER_CAPACITY: usize = 64;
+/// The interval of forwarding buffered user records regardless of the number.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The system logger service, forwarding user records to the kernel log and
+/// optionally a log file.
+struct LogService {
+    buffer: Mutex<Vec<UserRecord>>,
+    file: Option<FileClient>,
+}
+
+fn format_record(record: &UserRecord) -> String {
+    let time = unsafe { Instant::from_raw(record.time) };
+    let level = log::Level::from(record.level);
+    let mut line = format!(
+        "[{time}] {level}: [{}:{} {}] ",
+        record.pid, record.tid, record.module
+    );
+    for tag in &record.tags {
+        let _ = write!(line, "[{tag}] ");
+    }
+    line += &record.message;
+    for (key, value) in &record.key_values {
+        let _ = write!(line, " {key}={value}");
+    }
+    line
+}
+
+/// Forward the line to the kernel log, split into records short enough not to
+/// be truncated by the log ring.
+fn forward(line: &str) -> Result<(), Error> {
+    let mut rest = line;
+    while !rest.is_empty() {
+        let mut len = rest.len().min(klog::LOG_MSG_LEN);
+        while !rest.is_char_boundary(len) {
+            len -= 1;
+        }
+        let (chunk, next) = rest.split_at(len);
+        klog::write(chunk)?;
+        rest = next;
+    }
+    Ok(())
+}
+
+impl LogService {
+    fn new(file: Option<FileClient>) -> Arsc<Self> {
+        Arsc::new(LogService {
+            buffer: Mutex::new(Vec::with_capacity(BUFFER_CAPACITY)),
+            file,
+        })
+    }
+
+    async fn log(&self, record: UserRecord) -> Result<(), io::Error> {
+        let full = {
+            let mut buffer = self.buffer.lock();
+            buffer.push(record);
+            buffer.len() >= BUFFER_CAPACITY
+        };
+        if full {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<(), io::Error> {
+        let records = mem::take(&mut *self.buffer.lock());
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let mut content = String::new();
+        for record in &records {
+            let line = format_record(record);
+            if let Err(err) = forward(&line) {
+                log::warn!("failed to forward the log to the kernel: {err}");
+            }
+            if self.file.is_some() {
+                content += &line;
+                content.push('\n');
+            }
+        }
+
+        if let Some(ref file) = self.file {
+            file.write(content.into_bytes()).await??;
+        }
+        Ok(())
+    }
+}
+
+fn spawn_logger(service: Arsc<LogService>, conn: Channel, spawner: &Spawner) {
+    let server = LoggerServer::from(AsyncChannel::with_disp(conn, spawner.dispatch()));
+    spawner.spawn(handle_logger(service, server, spawner.clone()))
+}
+
+async fn handle_logger(service: Arsc<LogService>, server: LoggerServer, spawner: Spawner) {
+    let (mut stream, _) = server.serve();
+    while let Some(request) = stream.next().await {
+        let request = match request {
+            Ok(request) => request,
+            Err(err) => {
+                log::warn!("RPC receive error: {err}");
+                continue;
+            }
+        };
+
+        let res = match request {
+            LoggerRequest::CloneConnection { conn, responder } => {
+                spawn_logger(service.clone(), conn, &spawner);
+                responder.send(())
+            }
+            LoggerRequest::CloseConnection { responder } => {
+                responder.close();
+                break;
+            }
+            LoggerRequest::Log { record, responder } => {
+                if let Err(err) = service.log(record).await {
+                    log::warn!("failed to forward the logs: {err}");
+                }
+                responder.send(())
+            }
+            LoggerRequest::Flush { responder } => responder.send(service.flush().await),
+            LoggerRequest::Unknown(req) => {
+                log::warn!("unknown request received");
+                req.unsupported()
+            }
+        };
+
+        if let Err(err) = res {
+            log::warn!("RPC send error: {err}")
+        }
+    }
+}
+
+/// Open the log file specified by the `LOG_FILE` environment variable, if any.
+fn log_file() -> Option<FileClient> {
+    let (_, path) = solvent_std::env::vars().find(|(key, _)| key == "LOG_FILE")?;
+    let options = OpenOptions::WRITE | OpenOptions::CREATE | OpenOptions::APPEND;
+    match solvent_fs::open(&path, options) {
+        Ok(file) => file.into_async().ok(),
+        Err(err) => {
+            log::warn!("failed to open the log file {path:?}: {err}");
+            None
+        }
+    }
+}
+
+/// Start the log services and export them to `vfs` for child processes.
+///
+/// The kernel log reader is exported only with the root port I/O resource
+/// `pio_res`, which grants the privilege of reading the kernel log.
+pub fn export(pio_res: Option<Arsc<PioRes>>, vfs: &mut impl Extend<(PathBuf, EntrySyncClient)>) {
+    match pio_res {
+        Some(pio_res) => {
+            let (log_reader, server) = Channel::new();
+            vfs.extend([(Path::new("use").join(LogReader::PATH), log_reader.into())]);
+            let node = RpcNode::new(move |server, spawner| {
+                handle_log_reader(pio_res.clone(), server, spawner)
+            });
+            node.open_conn(spawner(), Default::default(), server);
+        }
+        None => log::warn!("No privilege of reading the kernel log"),
+    }
+
+    let service = LogService::new(log_file());
+
+    let (logger, server) = Channel::new();
+    vfs.extend([(Path::new("use").join(Logger::PATH), logger.into())]);
+    {
+        let service = service.clone();
+        let node =
+            RpcNode::new(move |server, spawner| handle_logger(service.clone(), server, spawner));
+        node.open_conn(spawner(), Default::default(), server);
+    }
+
+    spawner().spawn(async move {
+        let timer = AsyncTimer::new(Timer::new());
+        loop {
+            if let Err(err) = timer.wait_after(FLUSH_INTERVAL).await {
+                log::warn!("failed to wait for flushing the logs: {err}");
+                break;
+            }
+            if let Err(err) = service.flush().await {
+                log::warn!("failed to flush the logs: {err}");
+            }
+        }
+    });
+}
@@ -4,6 +4,8 @@
 
 mod boot;
 mod com;
+mod logger;
+mod power;
 
 use alloc::vec;
 
@@ -37,6 +39,13 @@ async fn main() {
         .export(&mut vfs)
         .expect("Failed to export vfs");
 
+    let pio_res = power::resource();
+    logger::export(pio_res.clone(), &mut vfs);
+    match pio_res {
+        Some(ref pio_res) => power::export(pio_res.clone(), &mut vfs),
+        None => log::warn!("No power management privilege"),
+    }
+
     let mut task = Process::builder()
         .executable(devm, "devm")
         .expect("Failed to add executable")
@@ -49,9 +58,18 @@ async fn main() {
 
     log::debug!("Waiting for devm");
     let retval = task.ajoin().await.expect("Failed to wait for devm");
+    if retval == 0 {
+        log::debug!("Goodbye!");
+    } else {
+        log::error!("The process failed: {retval:#x}");
+    }
+
+    // Power off the machine whatever the status is so that the VM exits
+    // cleanly instead of hanging.
+    if let Some(pio_res) = pio_res {
+        let _ = power::shutdown(&pio_res);
+    }
     assert_eq!(retval, 0, "The process failed: {retval:#x}");
-
-    log::debug!("Goodbye!");
 }
 
 solvent_async::entry!(main, solvent_std, None);
@@ -0,0 +1,101 @@
+use futures_lite::StreamExt;
+use solvent::{
+    dev::{power, PioRes},
+    error::Error,
+    ipc::Channel,
+    obj::Object,
+};
+use solvent_async::ipc::Channel as AsyncChannel;
+use solvent_fs::{rpc::RpcNode, spawner, Spawner};
+use solvent_rpc::{
+    io::entry::EntrySyncClient,
+    power::{Power, PowerRequest, PowerServer},
+    Protocol, Server,
+};
+use solvent_std::{
+    path::{Path, PathBuf},
+    sync::Arsc,
+};
+use svrt::HandleType;
+
+fn spawn_power(pio_res: Arsc<PioRes>, conn: Channel, spawner: &Spawner) {
+    let server = PowerServer::from(AsyncChannel::with_disp(conn, spawner.dispatch()));
+    spawner.spawn(handle_power(pio_res, server, spawner.clone()))
+}
+
+async fn handle_power(pio_res: Arsc<PioRes>, server: PowerServer, spawner: Spawner) {
+    let (mut stream, _) = server.serve();
+    while let Some(request) = stream.next().await {
+        let request = match request {
+            Ok(request) => request,
+            Err(err) => {
+                log::warn!("RPC receive error: {err}");
+                continue;
+            }
+        };
+
+        let res = match request {
+            PowerRequest::CloneConnection { conn, responder } => {
+                spawn_power(pio_res.clone(), conn, &spawner);
+                responder.send(())
+            }
+            PowerRequest::CloseConnection { responder } => {
+                responder.close();
+                break;
+            }
+            PowerRequest::Shutdown { responder } => responder.send(shutdown(&pio_res)),
+            PowerRequest::Reboot { responder } => responder.send(reboot(&pio_res)),
+            PowerRequest::Sleep { responder } => responder.send(sleep(&pio_res)),
+            PowerRequest::Unknown(req) => {
+                log::warn!("unknown request received");
+                req.unsupported()
+            }
+        };
+
+        if let Err(err) = res {
+            log::warn!("RPC send error: {err}")
+        }
+    }
+}
+
+/// Power off the machine directly.
+pub fn shutdown(res: &PioRes) -> Result<(), Error> {
+    let ret = power::shutdown(res);
+    if let Err(err) = ret {
+        log::error!("failed to shut down: {err}");
+    }
+    ret
+}
+
+/// Reset the machine directly.
+pub fn reboot(res: &PioRes) -> Result<(), Error> {
+    let ret = power::reboot(res);
+    if let Err(err) = ret {
+        log::error!("failed to reboot: {err}");
+    }
+    ret
+}
+
+/// Put the machine to sleep directly.
+pub fn sleep(res: &PioRes) -> Result<(), Error> {
+    let ret = power::sleep(res);
+    if let Err(err) = ret {
+        log::error!("failed to sleep: {err}");
+    }
+    ret
+}
+
+/// Get the root port I/O resource passed from TINIT, which grants the
+/// privilege of power management.
+pub fn resource() -> Option<Arsc<PioRes>> {
+    let handle = svrt::try_take_startup_handle(HandleType::PioRes.into()).ok()?;
+    Some(Arsc::new(unsafe { PioRes::from_raw(handle) }))
+}
+
+/// Start the power service and export it to `vfs` for child processes.
+pub fn export(pio_res: Arsc<PioRes>, vfs: &mut impl Extend<(PathBuf, EntrySyncClient)>) {
+    let (power, server) = Channel::new();
+    vfs.extend([(Path::new("use").join(Power::PATH), power.into())]);
+    let node = RpcNode::new(move |server, spawner| handle_power(pio_res.clone(), server, spawner));
+    node.open_conn(spawner(), Default::default(), server);
+}
@@ -1,9 +1,12 @@
 #![no_std]
 
-use core::fmt::{self, Write};
+use core::{
+    fmt::{self, Write},
+    sync::atomic::{AtomicBool, Ordering::*},
+};
 
 use solvent::prelude::Instant;
-use spin::Mutex;
+use spin::Once;
 
 fn cur_cpu() -> usize {
     let mut ret;
@@ -11,27 +14,62 @@ fn cur_cpu() -> usize {
     ret
 }
 
+/// A log backend preferred over `sv_log`, typically the system logger service.
+pub trait Backend: Send + Sync {
+    /// Deliver the record to the backend.
+    ///
+    /// Returns `false` if the backend is unavailable, in which case the record
+    /// is logged through `sv_log` instead.
+    fn log(&self, record: &log::Record, time: Instant) -> bool;
+}
+
 static LOGGER: Logger = Logger;
 
 const BUFFER_SIZE: usize = 256;
 
-static BUFFER: Mutex<Buffer> = Mutex::new(Buffer([0; BUFFER_SIZE], 0));
-
+/// The buffer of the records logged through `sv_log`, allocated on the stack so
+/// that logging allocation errors doesn't allocate again. Longer records are
+/// truncated.
 struct Buffer([u8; BUFFER_SIZE], usize);
 
-struct Logger;
-
 impl Write for Buffer {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        let bytes = s.as_bytes();
         let start = self.1;
-        let end = (start + bytes.len()).min(BUFFER_SIZE);
-        self.0[start..end].copy_from_slice(&bytes[..(end - start)]);
-        self.1 = end;
+        // Keep the buffer valid UTF-8 for `sv_log`.
+        let mut len = s.len().min(BUFFER_SIZE - start);
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.0[start..(start + len)].copy_from_slice(&s.as_bytes()[..len]);
+        self.1 = start + len;
         Ok(())
     }
 }
 
+static BACKEND: Once<&'static dyn Backend> = Once::new();
+
+/// Set when the backend is delivering a record, so that the records logged by
+/// the backend itself fall back to `sv_log` instead of recursing. This includes
+/// the allocation errors raised by the backend.
+static IN_BACKEND: AtomicBool = AtomicBool::new(false);
+
+struct Logger;
+
+impl Logger {
+    fn log_backend(&self, record: &log::Record, time: Instant) -> bool {
+        let backend = match BACKEND.get() {
+            Some(backend) => backend,
+            None => return false,
+        };
+        if IN_BACKEND.swap(true, Acquire) {
+            return false;
+        }
+        let ret = backend.log(record, time);
+        IN_BACKEND.store(false, Release);
+        ret
+    }
+}
+
 impl log::Log for Logger {
     fn enabled(&self, _metadata: &log::Metadata) -> bool {
         true
@@ -39,10 +77,14 @@ impl log::Log for Logger {
 
     fn log(&self, record: &log::Record) {
         let cur_time = Instant::now();
-        let mut buffer = BUFFER.lock();
+        if self.log_backend(record, cur_time) {
+            return;
+        }
+
+        let mut buffer = Buffer([0; BUFFER_SIZE], 0);
         if record.level() < log::Level::Debug {
             write!(
-                &mut *buffer,
+                buffer,
                 "[{}] {}: {}",
                 cur_time,
                 record.level(),
@@ -52,7 +94,7 @@ impl log::Log for Logger {
             let file = record.file().unwrap_or("<NULL>");
             let line = record.line().unwrap_or(0);
             write!(
-                &mut *buffer,
+                buffer,
                 "[{}] {}: [#{} {}:{}] {}",
                 cur_time,
                 record.level(),
@@ -64,8 +106,6 @@ impl log::Log for Logger {
         }
         .expect("Failed to write str");
         let _ = unsafe { sv_call::sv_log(buffer.0.as_ptr(), buffer.1) };
-        *buffer = Buffer([0; BUFFER_SIZE], 0);
-        drop(buffer);
     }
 
     fn flush(&self) {}
@@ -75,3 +115,8 @@ pub fn init(max_level: log::Level) {
     log::set_logger(&LOGGER).expect("Failed to set the logger");
     log::set_max_level(max_level.to_level_filter());
 }
+
+/// Set the preferred backend of the logger. Only the first call takes effect.
+pub fn set_backend(backend: &'static dyn Backend) {
+    BACKEND.call_once(|| backend);
+}
@@ -1,7 +1,10 @@
 use alloc::vec::Vec;
 use core::fmt;
 
-use solvent::prelude::{IoSlice, IoSliceMut};
+use solvent::{
+    error::ERANGE,
+    prelude::{IoSlice, IoSliceMut},
+};
 use solvent_core::io::{RawStream, SeekFrom};
 
 use crate::{disp::DispSender, mem::Phys, sync::Mutex};
@@ -105,6 +108,53 @@ impl Stream {
         self.inner.lock().await.write_at_vectored(pos, bufs).await
     }
 
+    /// Copy at most `len` bytes from the current position into `phys` at
+    /// `offset` in the kernel, without bouncing them through user space.
+    ///
+    /// # Safety
+    ///
+    /// See [`solvent::mem::Phys::write`] for more information.
+    pub async unsafe fn read_into_phys(
+        &self,
+        phys: &solvent::mem::Phys,
+        offset: usize,
+        len: usize,
+    ) -> Result<usize, Error> {
+        let mut inner = self.inner.lock().await;
+        let src = inner.phys.as_ref();
+        let len = phys
+            .copy_from(offset, src, inner.seeker, len)
+            .map_err(Error::Other)?;
+        inner.seeker += len;
+        Ok(len)
+    }
+
+    /// Copy `len` bytes of `phys` at `offset` to the current position in the
+    /// kernel, growing the stream if needed.
+    pub async fn write_from_phys(
+        &self,
+        phys: &solvent::mem::Phys,
+        offset: usize,
+        len: usize,
+    ) -> Result<usize, Error> {
+        let mut inner = self.inner.lock().await;
+        let end = inner.seeker.checked_add(len).ok_or(Error::Other(ERANGE))?;
+        if end > inner.phys.len() {
+            inner.phys.resize(end, true).await.map_err(Error::Other)?;
+        }
+        // SAFETY: This struct holds the unique reference to the underlying
+        // `Phys`.
+        let len = unsafe {
+            inner
+                .phys
+                .as_ref()
+                .copy_from(inner.seeker, phys, offset, len)
+        }
+        .map_err(Error::Other)?;
+        inner.seeker += len;
+        Ok(len)
+    }
+
     pub async fn resize(&self, new_len: usize) -> Result<(), Error> {
         let inner = self.inner.lock().await;
         let res = inner.phys.resize(new_len, true).await;
@@ -226,7 +276,8 @@ impl Inner {
             cache.clear();
             let len = buf.len().min(len.saturating_sub(*seeker));
             cache.extend_from_slice(&buf[..len]);
-            // SAFETY: This struct holds the unique reference to the underlying `Phys`.
+            // SAFETY: This struct holds the unique reference to the underlying
+            // `Phys`.
             let len = unsafe { phys.write(*seeker, &mut cache) }
                 .await
                 .map_err(Error::Other)?;
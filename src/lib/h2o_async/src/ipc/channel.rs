@@ -63,6 +63,11 @@ impl Channel {
         this.inner
     }
 
+    #[inline]
+    pub fn disp(&self) -> DispSender {
+        self.disp.clone()
+    }
+
     #[inline]
     pub fn rebind(&mut self, disp: DispSender) {
         self.disp = disp
@@ -166,7 +171,7 @@ impl<'a> Receive<'a> {
                 // Not yet, continue waiting
                 Err(TryRecvError::Empty) => {
                     let Some(key) = self.key else {
-                        return ControlFlow::Break(Poll::Ready(Err(ENOENT)))
+                        return ControlFlow::Break(Poll::Ready(Err(ENOENT)));
                     };
                     if let Err(err) = self.channel.disp.update(key, cx.waker()) {
                         if let Ok(send_data) = rx.recv() {
@@ -0,0 +1,273 @@
+//! Read-only file systems built from archive images.
+//!
+//! Both the ustar (including the GNU and pax extensions for long names) and
+//! the cpio "newc" formats are supported. The files refer to the sub-ranges of
+//! the image directly if their contents are page-aligned, and are copied
+//! otherwise.
+
+mod cpio;
+mod tar;
+
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+
+use solvent::prelude::{Phys, PhysOptions, PAGE_MASK};
+use solvent_core::{
+    path::{Component, Path, PathBuf},
+    sync::Arsc,
+};
+use solvent_rpc::io::{Error, Permission};
+
+use crate::{
+    entry::Entry,
+    mem::{
+        dir::{Builder, MemDir},
+        file::MemFile,
+        symlink::MemSymlink,
+    },
+};
+
+/// The size of the buffer through which the unaligned contents are copied.
+const COPY_SIZE: usize = 64 * 1024;
+
+/// An entry parsed from the archive.
+struct Record {
+    path: PathBuf,
+    perm: Permission,
+    kind: Kind,
+}
+
+enum Kind {
+    File {
+        offset: usize,
+        len: usize,
+    },
+    Dir,
+    Symlink(PathBuf),
+    /// A hard link to the file at the path.
+    Link(PathBuf),
+}
+
+/// Build the directory tree of the ustar or cpio archive in `image`.
+///
+/// The permissions of the entries are the ones in the archive limited by
+/// `perm`, which never includes [`Permission::WRITE`].
+pub fn build(image: &Phys, perm: Permission) -> Result<Arsc<MemDir>, Error> {
+    let perm = perm - Permission::WRITE;
+    let records = if cpio::is_cpio(image)? {
+        cpio::parse(image)?
+    } else {
+        tar::parse(image)?
+    };
+
+    let mut builder = Builder::new();
+    let mut files = BTreeMap::<PathBuf, Arsc<dyn Entry>>::new();
+    for Record {
+        path,
+        perm: mode,
+        kind,
+    } in records
+    {
+        let perm = perm & mode;
+        match kind {
+            Kind::Dir => {
+                builder.empty_path(&path, perm)?;
+            }
+            Kind::File { offset, len } => {
+                let file = Arsc::new(MemFile::new(slice(image, offset, len)?, perm));
+                files.insert(path.clone(), file.clone());
+                builder.entry(&path, perm, file)?;
+            }
+            Kind::Symlink(target) => {
+                builder.entry(&path, perm, Arsc::new(MemSymlink::new(target)))?;
+            }
+            Kind::Link(target) => {
+                let file = files.get(&target).ok_or(Error::NotFound)?.clone();
+                if let Some(attrs) = file.attrs() {
+                    attrs.link();
+                }
+                builder.entry(&path, perm, file)?;
+            }
+        }
+    }
+    Ok(builder.build())
+}
+
+/// Check that `len` bytes from `offset` are within `image`, since the sizes
+/// come from the untrusted headers.
+fn check(image: &Phys, offset: usize, len: usize) -> Result<(), Error> {
+    match offset.checked_add(len) {
+        Some(end) if end <= image.len() => Ok(()),
+        _ => Err(Error::InvalidData("truncated archive".into())),
+    }
+}
+
+/// Get the content of `len` bytes from `offset` in `image`, sharing its pages
+/// if possible.
+fn slice(image: &Phys, offset: usize, len: usize) -> Result<Phys, Error> {
+    check(image, offset, len)?;
+    if len > 0 && offset & PAGE_MASK == 0 {
+        return image.create_sub(offset, len, false).map_err(Error::Other);
+    }
+
+    let phys = Phys::allocate(len, PhysOptions::ZEROED).map_err(Error::Other)?;
+    let mut buf = vec![0; len.min(COPY_SIZE)];
+    let mut copied = 0;
+    while copied < len {
+        let chunk = (len - copied).min(buf.len());
+        let read_len = image
+            .read_into(offset + copied, &mut buf[..chunk])
+            .map_err(Error::Other)?;
+        if read_len < chunk {
+            return Err(Error::InvalidData("truncated archive".into()));
+        }
+        // SAFETY: The object is freshly allocated and not shared.
+        unsafe { phys.write(copied, &buf[..chunk]) }.map_err(Error::Other)?;
+        copied += chunk;
+    }
+    Ok(phys)
+}
+
+/// Read exactly `len` bytes from `offset` in `image`.
+fn read(image: &Phys, offset: usize, len: usize) -> Result<Vec<u8>, Error> {
+    check(image, offset, len)?;
+    let buf = image.read(offset, len).map_err(Error::Other)?;
+    if buf.len() < len {
+        return Err(Error::InvalidData("truncated archive".into()));
+    }
+    Ok(buf)
+}
+
+/// Make the path in the archive relative to its root, or return `None` for
+/// the root itself.
+fn normalize(path: &str) -> Result<Option<PathBuf>, Error> {
+    let mut ret = PathBuf::new();
+    for comp in Path::new(path).components() {
+        match comp {
+            Component::Normal(name) => ret.push(name),
+            Component::RootDir | Component::CurDir => {}
+            _ => return Err(Error::InvalidPath(path.into())),
+        }
+    }
+    Ok((ret != Path::new("")).then_some(ret))
+}
+
+/// The permissions granted by the owner bits of the Unix `mode`.
+fn permission(mode: u32) -> Permission {
+    let mut perm = Permission::empty();
+    if mode & 0o400 != 0 {
+        perm |= Permission::READ;
+    }
+    if mode & 0o100 != 0 {
+        perm |= Permission::EXECUTE;
+    }
+    perm
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::{format, vec, vec::Vec};
+
+    use solvent_core::path::Path;
+    use solvent_rpc::io::{Error, Permission};
+
+    use super::{build, cpio, tar, Kind, Record};
+    use crate::fixture::phys;
+
+    fn tar_header(name: &str, typeflag: u8, size: usize, link: &str) -> Vec<u8> {
+        let mut header = vec![0; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..107].copy_from_slice(b"0000755");
+        header[124..135].copy_from_slice(format!("{size:011o}").as_bytes());
+        header[156] = typeflag;
+        header[157..157 + link.len()].copy_from_slice(link.as_bytes());
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        header[148..156].fill(b' ');
+        let sum = header.iter().map(|&b| usize::from(b)).sum::<usize>();
+        header[148..155].copy_from_slice(format!("{sum:06o}\0").as_bytes());
+        header
+    }
+
+    fn cpio_entry(out: &mut Vec<u8>, name: &str, mode: usize, data: &[u8]) {
+        let name_size = name.len() + 1;
+        let fields = [1, mode, 0, 0, 1, 0, data.len(), 0, 0, 0, 0, name_size, 0];
+        out.extend_from_slice(b"070701");
+        for field in fields {
+            out.extend_from_slice(format!("{field:08x}").as_bytes());
+        }
+        out.extend_from_slice(name.as_bytes());
+        out.push(0);
+        out.resize((out.len() + 3) & !3, 0);
+        out.extend_from_slice(data);
+        out.resize((out.len() + 3) & !3, 0);
+    }
+
+    fn assert_records(records: &[Record]) {
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].path, Path::new("dir"));
+        assert!(matches!(records[0].kind, Kind::Dir));
+        assert_eq!(records[1].path, Path::new("dir/hello"));
+        assert!(records[1].perm.contains(Permission::READ));
+        assert!(matches!(records[1].kind, Kind::File { len: 5, .. }));
+        assert_eq!(records[2].path, Path::new("link"));
+        assert!(
+            matches!(&records[2].kind, Kind::Symlink(target) if target == Path::new("dir/hello"))
+        );
+    }
+
+    #[test]
+    fn test_tar() {
+        let mut data = tar_header("dir/", b'5', 0, "");
+        data.extend(tar_header("dir/hello", b'0', 5, ""));
+        data.extend_from_slice(b"hello");
+        data.resize(1536, 0);
+        data.extend(tar_header("link", b'2', 0, "dir/hello"));
+        data.resize(data.len() + 1024, 0);
+        let image = phys(&data);
+
+        let records = tar::parse(&image).unwrap();
+        assert_records(&records);
+        assert!(matches!(records[1].kind, Kind::File { offset: 1024, .. }));
+        build(&image, Permission::all()).unwrap();
+    }
+
+    #[test]
+    fn test_tar_truncated() {
+        // A pax header whose data is far beyond the image.
+        let pax = phys(&tar_header("pax", b'x', 1 << 30, ""));
+        assert!(matches!(tar::parse(&pax), Err(Error::InvalidData(_))));
+
+        // A file whose content is beyond the image.
+        let mut data = tar_header("hello", b'0', 1 << 20, "");
+        data.resize(1024, 0);
+        let file = phys(&data);
+        assert!(matches!(
+            build(&file, Permission::all()),
+            Err(Error::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn test_cpio() {
+        let mut data = Vec::new();
+        cpio_entry(&mut data, "dir", 0o040755, b"");
+        cpio_entry(&mut data, "dir/hello", 0o100644, b"hello");
+        cpio_entry(&mut data, "link", 0o120777, b"dir/hello");
+        cpio_entry(&mut data, "TRAILER!!!", 0, b"");
+        let image = phys(&data);
+
+        assert!(cpio::is_cpio(&image).unwrap());
+        assert_records(&cpio::parse(&image).unwrap());
+        build(&image, Permission::all()).unwrap();
+    }
+
+    #[test]
+    fn test_cpio_truncated() {
+        let mut data = Vec::new();
+        cpio_entry(&mut data, "hello", 0o100644, b"hello");
+        // Claim a name far beyond the image.
+        data[6 + 11 * 8..6 + 12 * 8].copy_from_slice(b"ffffffff");
+        let image = phys(&data);
+        assert!(matches!(cpio::parse(&image), Err(Error::InvalidData(_))));
+    }
+}
@@ -0,0 +1,114 @@
+use alloc::{collections::BTreeMap, format, vec::Vec};
+use core::str;
+
+use solvent::prelude::Phys;
+use solvent_core::path::PathBuf;
+use solvent_rpc::io::Error;
+
+use super::{normalize, permission, read, Kind, Record};
+
+const HEADER_SIZE: usize = 110;
+const TRAILER: &str = "TRAILER!!!";
+
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+
+fn invalid(what: &str) -> Error {
+    Error::InvalidData(format!("invalid cpio {what}"))
+}
+
+/// Check whether `image` starts with the magic of the "newc" format, with or
+/// without checksums.
+pub(super) fn is_cpio(image: &Phys) -> Result<bool, Error> {
+    if image.len() < HEADER_SIZE {
+        return Ok(false);
+    }
+    let magic = read(image, 0, 6)?;
+    Ok(magic == b"070701" || magic == b"070702")
+}
+
+/// Parse the `index`-th hexadecimal field of the header.
+fn field(header: &[u8], index: usize) -> Result<usize, Error> {
+    let start = 6 + index * 8;
+    let digits = str::from_utf8(&header[start..start + 8]).map_err(|_| invalid("header"))?;
+    usize::from_str_radix(digits, 16).map_err(|_| invalid("header"))
+}
+
+#[inline]
+fn align4(offset: usize) -> Option<usize> {
+    offset.checked_add(3).map(|offset| offset & !3)
+}
+
+pub(super) fn parse(image: &Phys) -> Result<Vec<Record>, Error> {
+    let mut records = Vec::<Record>::new();
+    // The records of the files with multiple links by their inode numbers.
+    let mut inodes = BTreeMap::<usize, usize>::new();
+    let mut offset = 0;
+    loop {
+        let header = read(image, offset, HEADER_SIZE)?;
+        if &header[..5] != b"07070" {
+            return Err(invalid("magic"));
+        }
+        let (ino, mode, nlink) = (field(&header, 0)?, field(&header, 1)?, field(&header, 4)?);
+        let (size, name_size) = (field(&header, 6)?, field(&header, 11)?);
+
+        // The header is within the image, so this can't overflow.
+        let name = read(image, offset + HEADER_SIZE, name_size)?;
+        let name = name.strip_suffix(b"\0").ok_or_else(|| invalid("name"))?;
+        let name = str::from_utf8(name).map_err(|_| invalid("name"))?;
+        if name == TRAILER {
+            break;
+        }
+
+        // The name is within the image too.
+        let data = align4(offset + HEADER_SIZE + name_size).ok_or_else(|| invalid("size"))?;
+        let end = data.checked_add(size).and_then(align4);
+        offset = end.ok_or_else(|| invalid("size"))?;
+
+        let path = match normalize(name)? {
+            Some(path) => path,
+            None => continue,
+        };
+        let mode = mode as u32;
+        let perm = permission(mode);
+        let kind = match mode & S_IFMT {
+            S_IFREG if nlink > 1 => match inodes.get(&ino) {
+                // The content of hard links is stored in only one of them,
+                // usually the last.
+                Some(&index) => {
+                    let first = &mut records[index];
+                    if size > 0 {
+                        first.kind = Kind::File {
+                            offset: data,
+                            len: size,
+                        };
+                    }
+                    Kind::Link(first.path.clone())
+                }
+                None => {
+                    inodes.insert(ino, records.len());
+                    Kind::File {
+                        offset: data,
+                        len: size,
+                    }
+                }
+            },
+            S_IFREG => Kind::File {
+                offset: data,
+                len: size,
+            },
+            S_IFDIR => Kind::Dir,
+            S_IFLNK => {
+                let target = read(image, data, size)?;
+                let target = str::from_utf8(&target).map_err(|_| invalid("symlink"))?;
+                Kind::Symlink(PathBuf::from(target))
+            }
+            // Devices, FIFOs and sockets are not supported.
+            _ => continue,
+        };
+        records.push(Record { path, perm, kind });
+    }
+    Ok(records)
+}
@@ -0,0 +1,174 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::str;
+
+use solvent::prelude::Phys;
+use solvent_core::path::PathBuf;
+use solvent_rpc::io::Error;
+
+use super::{normalize, permission, read, Kind, Record};
+
+const BLOCK_SIZE: usize = 512;
+
+/// The extended attributes of the next entry from pax or GNU headers.
+#[derive(Default)]
+struct Extension {
+    path: Option<String>,
+    link: Option<String>,
+    size: Option<usize>,
+}
+
+fn invalid(what: &str) -> Error {
+    Error::InvalidData(format!("invalid tar {what}"))
+}
+
+/// Get the string in a field terminated by NUL or filling it up.
+fn field(raw: &[u8]) -> Result<&str, Error> {
+    let len = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+    str::from_utf8(&raw[..len]).map_err(|_| invalid("name"))
+}
+
+/// Parse an octal number field, or a base-256 one in the GNU extension.
+fn number(raw: &[u8]) -> Result<usize, Error> {
+    if raw.first().map_or(false, |&b| b & 0x80 != 0) {
+        let value = raw[1..]
+            .iter()
+            .try_fold(usize::from(raw[0] & 0x7f), |acc, &b| {
+                acc.checked_mul(256).map(|acc| acc + usize::from(b))
+            });
+        return value.ok_or_else(|| invalid("number"));
+    }
+    let digits = field(raw)?.trim_matches(' ');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    usize::from_str_radix(digits, 8).map_err(|_| invalid("number"))
+}
+
+fn checksum(header: &[u8]) -> Result<bool, Error> {
+    let expected = number(&header[148..156])?;
+    let sum = header.iter().enumerate().map(|(index, &b)| {
+        // The checksum field itself is counted as spaces.
+        if (148..156).contains(&index) {
+            usize::from(b' ')
+        } else {
+            usize::from(b)
+        }
+    });
+    Ok(sum.sum::<usize>() == expected)
+}
+
+/// Apply the records of a pax extended header, ignoring the unknown keys.
+fn pax(data: &[u8], ext: &mut Extension) -> Result<(), Error> {
+    let mut data = str::from_utf8(data).map_err(|_| invalid("pax header"))?;
+    while !data.is_empty() {
+        // Every record is "<len> <key>=<value>\n", where `len` counts the
+        // whole record.
+        let (len, _) = data.split_once(' ').ok_or_else(|| invalid("pax header"))?;
+        let len = len.parse::<usize>().map_err(|_| invalid("pax header"))?;
+        let record = data.get(..len).ok_or_else(|| invalid("pax header"))?;
+        data = &data[len..];
+
+        let (_, record) = record
+            .split_once(' ')
+            .ok_or_else(|| invalid("pax header"))?;
+        let record = record.strip_suffix('\n').unwrap_or(record);
+        let (key, value) = record
+            .split_once('=')
+            .ok_or_else(|| invalid("pax header"))?;
+        match key {
+            "path" => ext.path = Some(value.to_string()),
+            "linkpath" => ext.link = Some(value.to_string()),
+            "size" => ext.size = Some(value.parse().map_err(|_| invalid("pax header"))?),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn parse(image: &Phys) -> Result<Vec<Record>, Error> {
+    let mut records = Vec::new();
+    let mut ext = Extension::default();
+    let mut offset = 0;
+    while image.len().saturating_sub(offset) >= BLOCK_SIZE {
+        let header = read(image, offset, BLOCK_SIZE)?;
+        // The archive ends with zero blocks.
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        if &header[257..262] != b"ustar" {
+            return Err(invalid("magic"));
+        }
+        if !checksum(&header)? {
+            return Err(invalid("checksum"));
+        }
+
+        let size = match ext.size.take() {
+            Some(size) => size,
+            None => number(&header[124..136])?,
+        };
+        let data = offset + BLOCK_SIZE;
+        let end = size
+            .checked_add(BLOCK_SIZE - 1)
+            .and_then(|len| (len / BLOCK_SIZE * BLOCK_SIZE).checked_add(data));
+        offset = end.ok_or_else(|| invalid("size"))?;
+
+        let typeflag = header[156];
+        match typeflag {
+            b'x' => {
+                pax(&read(image, data, size)?, &mut ext)?;
+                continue;
+            }
+            // The global pax attributes are not used.
+            b'g' => continue,
+            b'L' | b'K' => {
+                let name = read(image, data, size)?;
+                let name = field(&name)?.to_string();
+                if typeflag == b'L' {
+                    ext.path = Some(name);
+                } else {
+                    ext.link = Some(name);
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        let path = match ext.path.take() {
+            Some(path) => path,
+            // Only POSIX archives split the long names into the prefix.
+            None if &header[257..263] == b"ustar\0" && header[345] != 0 => {
+                let (prefix, name) = (field(&header[345..500])?, field(&header[..100])?);
+                format!("{prefix}/{name}")
+            }
+            None => field(&header[..100])?.to_string(),
+        };
+        let link = match ext.link.take() {
+            Some(link) => link,
+            None => field(&header[157..257])?.to_string(),
+        };
+        let perm = permission(number(&header[100..108])? as u32);
+
+        let kind = match typeflag {
+            b'0' | b'\0' | b'7' => Kind::File {
+                offset: data,
+                len: size,
+            },
+            b'1' => match normalize(&link)? {
+                Some(target) => Kind::Link(target),
+                None => return Err(invalid("hard link")),
+            },
+            b'2' => Kind::Symlink(PathBuf::from(link)),
+            b'5' => Kind::Dir,
+            // Devices and FIFOs are not supported.
+            _ => continue,
+        };
+        if let Some(path) = normalize(&path)? {
+            records.push(Record { path, perm, kind });
+        }
+    }
+    Ok(records)
+}
@@ -0,0 +1,178 @@
+//! Block devices and the cache of their pages shared by the disk file systems.
+
+use alloc::{boxed::Box, collections::BTreeMap, format, vec};
+
+use solvent::prelude::{Phys, PAGE_SIZE};
+use solvent_core::sync::Mutex;
+use solvent_rpc::io::Error;
+
+/// A storage read in fixed-size blocks.
+///
+/// The reads are synchronous so that the file systems can walk their metadata
+/// while opening paths, which never waits.
+pub trait BlockDevice: Send + Sync + 'static {
+    /// The size of the blocks in bytes, which is a power of 2.
+    fn block_size(&self) -> usize;
+
+    fn block_count(&self) -> u64;
+
+    /// Read the blocks from `start` into `buf`, whose length is a multiple of
+    /// the block size.
+    fn read_blocks(&self, start: u64, buf: &mut [u8]) -> Result<(), Error>;
+}
+
+/// Disk images loaded in memory.
+impl BlockDevice for Phys {
+    #[inline]
+    fn block_size(&self) -> usize {
+        512
+    }
+
+    #[inline]
+    fn block_count(&self) -> u64 {
+        (self.len() / 512) as u64
+    }
+
+    fn read_blocks(&self, start: u64, buf: &mut [u8]) -> Result<(), Error> {
+        let offset = usize::try_from(start * 512).map_err(|_| Error::InvalidSeek)?;
+        let len = self.read_into(offset, buf).map_err(Error::Other)?;
+        if len < buf.len() {
+            return Err(Error::InvalidSeek);
+        }
+        Ok(())
+    }
+}
+
+/// The least recently used pages of a block device cached in memory.
+pub struct BlockCache {
+    device: Box<dyn BlockDevice>,
+    capacity: usize,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    pages: BTreeMap<u64, Page>,
+    /// The indices of the cached pages by their last uses.
+    lru: BTreeMap<u64, u64>,
+    tick: u64,
+}
+
+struct Page {
+    data: Box<[u8]>,
+    used: u64,
+}
+
+impl Inner {
+    /// Mark the page at `index` as the most recently used one.
+    fn touch(&mut self, index: u64) -> Option<&Page> {
+        self.tick += 1;
+        let page = self.pages.get_mut(&index)?;
+        self.lru.remove(&page.used);
+        page.used = self.tick;
+        self.lru.insert(self.tick, index);
+        Some(page)
+    }
+
+    fn insert(&mut self, index: u64, data: Box<[u8]>, capacity: usize) {
+        self.tick += 1;
+        let page = Page {
+            data,
+            used: self.tick,
+        };
+        if let Some(old) = self.pages.insert(index, page) {
+            self.lru.remove(&old.used);
+        }
+        self.lru.insert(self.tick, index);
+        while self.pages.len() > capacity {
+            let (_, index) = self.lru.pop_first().unwrap();
+            self.pages.remove(&index);
+        }
+    }
+}
+
+impl BlockCache {
+    /// Cache at most `capacity` pages of `device`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the blocks of the device are
+    /// larger than a page.
+    pub fn new<D: BlockDevice>(device: D, capacity: usize) -> Result<Self, Error> {
+        let block_size = device.block_size();
+        if !block_size.is_power_of_two() || block_size > PAGE_SIZE {
+            return Err(Error::InvalidData(format!(
+                "unsupported block size {block_size}"
+            )));
+        }
+        Ok(BlockCache {
+            device: Box::new(device),
+            capacity: capacity.max(1),
+            inner: Mutex::new(Inner {
+                pages: BTreeMap::new(),
+                lru: BTreeMap::new(),
+                tick: 0,
+            }),
+        })
+    }
+
+    /// The size of the device in bytes.
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.device.block_count() * self.device.block_size() as u64
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Read the page at `index` from the device, with the part beyond the end
+    /// filled with zeros.
+    fn load(&self, index: u64) -> Result<Box<[u8]>, Error> {
+        let block_size = self.device.block_size();
+        let per_page = (PAGE_SIZE / block_size) as u64;
+        let start = index * per_page;
+        let count = self
+            .device
+            .block_count()
+            .saturating_sub(start)
+            .min(per_page);
+
+        let mut data = vec![0; PAGE_SIZE].into_boxed_slice();
+        self.device
+            .read_blocks(start, &mut data[..count as usize * block_size])?;
+        Ok(data)
+    }
+
+    /// Read exactly `buf.len()` bytes from `offset` of the device.
+    pub fn read(&self, mut offset: u64, mut buf: &mut [u8]) -> Result<(), Error> {
+        let end = offset.checked_add(buf.len() as u64);
+        if end.map_or(true, |end| end > self.len()) {
+            return Err(Error::InvalidSeek);
+        }
+        while !buf.is_empty() {
+            let (index, start) = (offset / PAGE_SIZE as u64, offset as usize % PAGE_SIZE);
+            let len = buf.len().min(PAGE_SIZE - start);
+            let (chunk, rest) = buf.split_at_mut(len);
+
+            let hit = match self.inner.lock().touch(index) {
+                Some(page) => {
+                    chunk.copy_from_slice(&page.data[start..][..len]);
+                    true
+                }
+                None => false,
+            };
+            if !hit {
+                // The device is read without the lock, so concurrent misses of
+                // the same page may load it more than once.
+                let data = self.load(index)?;
+                chunk.copy_from_slice(&data[start..][..len]);
+                self.inner.lock().insert(index, data, self.capacity);
+            }
+
+            offset += len as u64;
+            buf = rest;
+        }
+        Ok(())
+    }
+}
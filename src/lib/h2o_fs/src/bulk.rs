@@ -0,0 +1,156 @@
+//! Bulk reads and writes of files through physical memory objects shared with
+//! the file servers, which bypass the size limit of packets.
+//!
+//! The helpers copy the buffers into and out of the objects once. Callers
+//! already holding the contents in a physical memory object should pass it to
+//! [`FileClient::read_into`] or [`FileClient::write_from`] directly instead,
+//! which moves them within the kernel only.
+
+use alloc::vec::Vec;
+use core::ops::Deref;
+
+use solvent::{mem::PhysOptions, obj::Object, prelude::Phys};
+use solvent_core::sync::Mutex;
+use solvent_rpc::io::{
+    file::{FileClient, FileSyncClient},
+    Error,
+};
+
+/// A pool of equally sized physical memory objects reused by bulk transfers.
+pub struct PhysPool {
+    size: usize,
+    cap: usize,
+    free: Mutex<Vec<Phys>>,
+}
+
+impl PhysPool {
+    /// Create a pool of objects sized `size`, at most `cap` of which are kept
+    /// for reuse.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `size` is 0.
+    pub const fn new(size: usize, cap: usize) -> Self {
+        assert!(size > 0, "The objects of the pool must not be empty");
+        PhysPool {
+            size,
+            cap,
+            free: Mutex::new(Vec::new()),
+        }
+    }
+
+    #[inline]
+    pub fn buf_size(&self) -> usize {
+        self.size
+    }
+
+    /// Take a free object from the pool, or allocate a new one.
+    pub fn get(&self) -> Result<PhysBuf<'_>, Error> {
+        let phys = self.free.lock().pop();
+        let phys = match phys {
+            Some(phys) => phys,
+            None => Phys::allocate(self.size, PhysOptions::ZEROED).map_err(Error::Other)?,
+        };
+        Ok(PhysBuf {
+            pool: self,
+            phys: Some(phys),
+        })
+    }
+}
+
+/// A physical memory object borrowed from a [`PhysPool`], returned on drop.
+pub struct PhysBuf<'a> {
+    pool: &'a PhysPool,
+    phys: Option<Phys>,
+}
+
+impl Deref for PhysBuf<'_> {
+    type Target = Phys;
+
+    #[inline]
+    fn deref(&self) -> &Phys {
+        self.phys.as_ref().unwrap()
+    }
+}
+
+impl Drop for PhysBuf<'_> {
+    fn drop(&mut self) {
+        if let Some(phys) = self.phys.take() {
+            let mut free = self.pool.free.lock();
+            if free.len() < self.pool.cap {
+                free.push(phys);
+            }
+        }
+    }
+}
+
+#[inline]
+fn share(phys: &Phys) -> Result<Phys, Error> {
+    Phys::try_clone(phys).map_err(Error::Other)
+}
+
+/// Read from the current position of `file` into `buf` through the objects of
+/// `pool`, returning the number of bytes read.
+pub async fn read(file: &FileClient, pool: &PhysPool, buf: &mut [u8]) -> Result<usize, Error> {
+    let phys = pool.get()?;
+    let mut read = 0;
+    for chunk in buf.chunks_mut(pool.size) {
+        let len = file.read_into(share(&phys)?, 0, chunk.len()).await??;
+        phys.read_into(0, &mut chunk[..len]).map_err(Error::Other)?;
+        read += len;
+        if len < chunk.len() {
+            break;
+        }
+    }
+    Ok(read)
+}
+
+/// Write `buf` to the current position of `file` through the objects of
+/// `pool`, returning the number of bytes written.
+pub async fn write(file: &FileClient, pool: &PhysPool, buf: &[u8]) -> Result<usize, Error> {
+    let phys = pool.get()?;
+    let mut written = 0;
+    for chunk in buf.chunks(pool.size) {
+        // SAFETY: The object is not mapped, and is shared only for this
+        // transfer.
+        unsafe { phys.write(0, chunk) }.map_err(Error::Other)?;
+        let len = file.write_from(share(&phys)?, 0, chunk.len()).await??;
+        written += len;
+        if len < chunk.len() {
+            break;
+        }
+    }
+    Ok(written)
+}
+
+/// The synchronous version of [`read`].
+pub fn read_sync(file: &FileSyncClient, pool: &PhysPool, buf: &mut [u8]) -> Result<usize, Error> {
+    let phys = pool.get()?;
+    let mut read = 0;
+    for chunk in buf.chunks_mut(pool.size) {
+        let len = file.read_into(share(&phys)?, 0, chunk.len())??;
+        phys.read_into(0, &mut chunk[..len]).map_err(Error::Other)?;
+        read += len;
+        if len < chunk.len() {
+            break;
+        }
+    }
+    Ok(read)
+}
+
+/// The synchronous version of [`write`].
+pub fn write_sync(file: &FileSyncClient, pool: &PhysPool, buf: &[u8]) -> Result<usize, Error> {
+    let phys = pool.get()?;
+    let mut written = 0;
+    for chunk in buf.chunks(pool.size) {
+        // SAFETY: The object is not mapped, and is shared only for this
+        // transfer.
+        unsafe { phys.write(0, chunk) }.map_err(Error::Other)?;
+        let len = file.write_from(share(&phys)?, 0, chunk.len())??;
+        written += len;
+        if len < chunk.len() {
+            break;
+        }
+    }
+    Ok(written)
+}
@@ -0,0 +1,616 @@
+//! The cache of the file contents shared by the disk file systems.
+
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
+    vec,
+    vec::Vec,
+};
+use core::ops::Range;
+
+use solvent::prelude::{Phys, PhysOptions as Flags, PAGE_MASK, PAGE_SHIFT, PAGE_SIZE};
+use solvent_core::sync::{Arsc, Mutex};
+use solvent_rpc::io::{file::PhysOptions, Error, Permission};
+
+/// The storage behind the cached files, which are identified by the numbers
+/// chosen by the file systems, such as the inode numbers.
+pub trait Backing: Send + Sync + 'static {
+    /// The length of `file` in the storage.
+    fn len(&self, file: u64) -> Result<usize, Error>;
+
+    /// Read the content of `file` from `pos` into `buf`, returning the number
+    /// of bytes read, which is less than `buf.len()` only at the end.
+    fn read_at(&self, file: u64, pos: usize, buf: &mut [u8]) -> Result<usize, Error>;
+
+    /// Write `buf` into `file` at `pos`, extending it if needed.
+    ///
+    /// The storage is read-only by default.
+    #[inline]
+    fn write_at(&self, file: u64, pos: usize, buf: &[u8]) -> Result<(), Error> {
+        let _ = (file, pos, buf);
+        Err(Error::PermissionDenied(Permission::WRITE))
+    }
+}
+
+/// The pages of the files read from or to be written back into a [`Backing`].
+///
+/// Every cached file is held in a physical object, which can be shared with
+/// the clients directly. The changes are written back in batches of contiguous
+/// dirty pages when the file is flushed or evicted.
+///
+/// When the cache grows beyond its capacity, the least recently used files are
+/// evicted as a whole. The files whose objects are shared are pinned instead,
+/// since their pages cannot be reclaimed while mapped by the clients. Their
+/// contents are also moved into contiguous objects, which cannot be resized,
+/// so the shared files cannot grow beyond their last pages or shrink.
+///
+/// Every file is locked on its own, and the storage is only accessed with the
+/// lock of the file held, so that a slow storage doesn't block the other
+/// files. The lock of a file is always taken before the one of the
+/// bookkeeping.
+pub struct PageCache {
+    backing: Box<dyn Backing>,
+    capacity: usize,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    files: BTreeMap<u64, Slot>,
+    /// The cached files by their last uses.
+    lru: BTreeMap<u64, u64>,
+    tick: u64,
+    /// The number of the pages loaded in all the files, with one more for
+    /// every file as its bookkeeping.
+    pages: usize,
+}
+
+struct Slot {
+    cached: Arsc<Mutex<Cached>>,
+    used: u64,
+}
+
+struct Cached {
+    phys: Phys,
+    /// The length of the file seen through the cache.
+    len: usize,
+    loaded: BTreeSet<usize>,
+    dirty: BTreeSet<usize>,
+    /// Whether the object is shared with the clients, which may also modify
+    /// it.
+    shared: bool,
+    /// Whether the file is dropped from the cache, so that the callers still
+    /// holding it must look it up again.
+    evicted: bool,
+}
+
+/// The indices of the pages covering `range`.
+#[inline]
+fn page_range(range: Range<usize>) -> Range<usize> {
+    if range.is_empty() {
+        return 0..0;
+    }
+    (range.start >> PAGE_SHIFT)..((range.end + PAGE_MASK) >> PAGE_SHIFT)
+}
+
+/// Split the sorted page indices into ranges of contiguous pages.
+fn runs(indices: impl IntoIterator<Item = usize>) -> impl Iterator<Item = Range<usize>> {
+    let mut indices = indices.into_iter().peekable();
+    core::iter::from_fn(move || {
+        let start = indices.next()?;
+        let mut end = start + 1;
+        while indices.next_if_eq(&end).is_some() {
+            end += 1;
+        }
+        Some(start..end)
+    })
+}
+
+impl Cached {
+    /// Make the object cover at least `len` bytes.
+    fn reserve(&mut self, len: usize) -> Result<(), Error> {
+        let len = (len + PAGE_MASK) & !PAGE_MASK;
+        if len > self.phys.len() {
+            self.phys.resize(len, true).map_err(Error::Other)?;
+        }
+        Ok(())
+    }
+
+    /// Load the pages in `range` not yet loaded.
+    ///
+    /// The pages loaded before a failure are kept.
+    fn load(&mut self, backing: &dyn Backing, file: u64, range: Range<usize>) -> Result<(), Error> {
+        let missing = range.filter(|index| !self.loaded.contains(index));
+        let missing = runs(missing).collect::<Vec<_>>();
+
+        for run in missing {
+            let mut buf = vec![0; run.len() << PAGE_SHIFT];
+            let len = backing.read_at(file, run.start << PAGE_SHIFT, &mut buf)?;
+            // The part beyond the end of the file is left as zeros.
+            buf[len..].fill(0);
+            // SAFETY: The pages not loaded yet hold nothing of the file, and
+            // the kernel guarantees the memory safety of writing them even if
+            // they're mapped.
+            unsafe { self.phys.write(run.start << PAGE_SHIFT, &buf) }.map_err(Error::Other)?;
+            self.loaded.extend(run);
+        }
+        Ok(())
+    }
+
+    /// Move the content into a contiguous object, since the resizable ones
+    /// can only be shared by copying.
+    fn share(&mut self) -> Result<(), Error> {
+        let size = self.phys.len();
+        let phys = Phys::allocate(size, Flags::ZEROED).map_err(Error::Other)?;
+        // SAFETY: The new object is not shared yet.
+        unsafe { phys.copy_from(0, &self.phys, 0, size) }.map_err(Error::Other)?;
+        self.phys = phys;
+        self.shared = true;
+        Ok(())
+    }
+
+    /// Write back the dirty pages, or all the loaded ones if the object is
+    /// shared.
+    fn write_back(&mut self, backing: &dyn Backing, file: u64) -> Result<(), Error> {
+        let pages = if self.shared {
+            self.loaded.clone()
+        } else {
+            self.dirty.clone()
+        };
+        for run in runs(pages) {
+            let start = run.start << PAGE_SHIFT;
+            let end = (run.end << PAGE_SHIFT).min(self.len);
+            if start >= end {
+                continue;
+            }
+            let buf = self.phys.read(start, end - start).map_err(Error::Other)?;
+            backing.write_at(file, start, &buf)?;
+            for index in run {
+                self.dirty.remove(&index);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Inner {
+    /// Get the cached `file` and mark it as the most recently used one.
+    fn touch(&mut self, file: u64) -> Option<Arsc<Mutex<Cached>>> {
+        self.tick += 1;
+        let slot = self.files.get_mut(&file)?;
+        self.lru.remove(&slot.used);
+        slot.used = self.tick;
+        self.lru.insert(self.tick, file);
+        Some(slot.cached.clone())
+    }
+
+    /// Drop the evicted `cached` of `file` with its pages.
+    fn detach(&mut self, file: u64, slot: &Arsc<Mutex<Cached>>, cached: &Cached) {
+        if let Some(old) = self.files.get(&file) {
+            if Arsc::ptr_eq(&old.cached, slot) {
+                self.lru.remove(&old.used);
+                self.files.remove(&file);
+            }
+        }
+        self.pages -= cached.loaded.len() + 1;
+    }
+}
+
+impl PageCache {
+    /// Cache at most `capacity` bytes of the files in `backing`, excluding the
+    /// pinned ones.
+    pub fn new<B: Backing>(backing: B, capacity: usize) -> Self {
+        PageCache {
+            backing: Box::new(backing),
+            capacity: (capacity >> PAGE_SHIFT).max(1),
+            inner: Mutex::new(Inner {
+                files: BTreeMap::new(),
+                lru: BTreeMap::new(),
+                tick: 0,
+                pages: 0,
+            }),
+        }
+    }
+
+    /// The memory used by the cache in bytes, counting a page of bookkeeping
+    /// for every file.
+    #[inline]
+    pub fn usage(&self) -> usize {
+        self.inner.lock().pages << PAGE_SHIFT
+    }
+
+    /// Get the cache of `file`, creating it if it's not cached.
+    fn get(&self, file: u64) -> Result<Arsc<Mutex<Cached>>, Error> {
+        if let Some(slot) = self.inner.lock().touch(file) {
+            return Ok(slot);
+        }
+        let len = self.backing.len(file)?;
+        // Empty objects cannot be resized later.
+        let size = ((len + PAGE_MASK) & !PAGE_MASK).max(PAGE_SIZE);
+        let phys = Phys::allocate(size, Flags::ZEROED | Flags::RESIZABLE).map_err(Error::Other)?;
+
+        let mut inner = self.inner.lock();
+        // The file may be cached by others meanwhile.
+        if let Some(slot) = inner.touch(file) {
+            return Ok(slot);
+        }
+        let cached = Arsc::new(Mutex::new(Cached {
+            phys,
+            len,
+            loaded: BTreeSet::new(),
+            dirty: BTreeSet::new(),
+            shared: false,
+            evicted: false,
+        }));
+        let slot = Slot {
+            cached: cached.clone(),
+            used: inner.tick,
+        };
+        inner.lru.insert(slot.used, file);
+        inner.files.insert(file, slot);
+        inner.pages += 1;
+        Ok(cached)
+    }
+
+    /// Get the cache of `file` if it's cached.
+    fn lookup(&self, file: u64) -> Option<Arsc<Mutex<Cached>>> {
+        let inner = self.inner.lock();
+        inner.files.get(&file).map(|slot| slot.cached.clone())
+    }
+
+    /// Call `f` with the locked cache of `file`, and evict the files beyond
+    /// the capacity afterwards.
+    fn with<T>(
+        &self,
+        file: u64,
+        f: impl FnOnce(&dyn Backing, &mut Cached) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let ret = loop {
+            let slot = self.get(file)?;
+            let mut cached = slot.lock();
+            if cached.evicted {
+                continue;
+            }
+            let old = cached.loaded.len();
+            let ret = f(&*self.backing, &mut cached);
+            // Count the pages changed even if `f` failed halfway.
+            let mut inner = self.inner.lock();
+            inner.pages = inner.pages + cached.loaded.len() - old;
+            break ret;
+        };
+        self.evict(self.capacity);
+        ret
+    }
+
+    /// Evict the least recently used files until at most `target` pages are
+    /// loaded.
+    fn evict(&self, target: usize) {
+        let candidates = {
+            let inner = self.inner.lock();
+            if inner.pages <= target {
+                return;
+            }
+            let files = inner.lru.values();
+            let files = files.map(|&file| (file, inner.files[&file].cached.clone()));
+            files.collect::<Vec<_>>()
+        };
+
+        let mut failed = Vec::new();
+        for (file, slot) in candidates {
+            if self.inner.lock().pages <= target {
+                break;
+            }
+            // The files in use are recently used anyway.
+            let mut cached = match slot.try_lock() {
+                Some(cached) => cached,
+                None => continue,
+            };
+            if cached.shared || cached.evicted {
+                continue;
+            }
+            if let Err(err) = cached.write_back(&*self.backing, file) {
+                failed.push((file, err));
+                continue;
+            }
+            cached.evicted = true;
+            self.inner.lock().detach(file, &slot, &cached);
+        }
+        // Logging may block on the log server, so no lock is held.
+        for (file, err) in failed {
+            log::warn!("failed to write back file {file}: {err:?}");
+        }
+    }
+
+    /// Get the length of `file` seen through the cache.
+    pub fn len(&self, file: u64) -> Result<usize, Error> {
+        if let Some(slot) = self.lookup(file) {
+            let cached = slot.lock();
+            if !cached.evicted {
+                return Ok(cached.len);
+            }
+        }
+        self.backing.len(file)
+    }
+
+    pub fn read(&self, file: u64, pos: usize, buf: &mut [u8]) -> Result<usize, Error> {
+        self.with(file, |backing, cached| {
+            let len = cached.len.saturating_sub(pos).min(buf.len());
+            if len == 0 {
+                return Ok(0);
+            }
+            cached.load(backing, file, page_range(pos..pos + len))?;
+            cached
+                .phys
+                .read_into(pos, &mut buf[..len])
+                .map_err(Error::Other)
+        })
+    }
+
+    /// Write `buf` into the cache of `file` at `pos`, which is written back on
+    /// the next flush or eviction.
+    pub fn write(&self, file: u64, pos: usize, buf: &[u8]) -> Result<usize, Error> {
+        let end = pos.checked_add(buf.len()).ok_or(Error::InvalidSeek)?;
+        self.with(file, |backing, cached| {
+            cached.reserve(end)?;
+
+            // Only the pages partially overwritten need their old contents.
+            let range = page_range(pos..end);
+            if !range.is_empty() {
+                let (first, last) = (range.start, range.end - 1);
+                if pos & PAGE_MASK != 0 {
+                    cached.load(backing, file, first..first + 1)?;
+                }
+                if end & PAGE_MASK != 0 && end < cached.len {
+                    cached.load(backing, file, last..last + 1)?;
+                }
+            }
+            // SAFETY: The clients sharing the object accept the concurrent
+            // changes, and the kernel guarantees the memory safety of writing
+            // it otherwise.
+            let written = unsafe { cached.phys.write(pos, buf) }.map_err(Error::Other)?;
+            for index in range {
+                cached.loaded.insert(index);
+                cached.dirty.insert(index);
+            }
+            cached.len = cached.len.max(end);
+            Ok(written)
+        })
+    }
+
+    /// Change the length of `file` seen through the cache, dropping the pages
+    /// beyond the new end.
+    ///
+    /// The storage itself is left to the caller to resize.
+    pub fn resize(&self, file: u64, new_len: usize) -> Result<(), Error> {
+        if self.lookup(file).is_none() {
+            return Ok(());
+        }
+        self.with(file, |_, cached| {
+            let size = ((new_len + PAGE_MASK) & !PAGE_MASK).max(PAGE_SIZE);
+            cached.phys.resize(size, true).map_err(Error::Other)?;
+            // Clear the rest of the last page for a later growth.
+            let tail = vec![0; size - new_len];
+            // SAFETY: The same as in `write`.
+            unsafe { cached.phys.write(new_len, &tail) }.map_err(Error::Other)?;
+
+            let end = (new_len + PAGE_MASK) >> PAGE_SHIFT;
+            cached.loaded.split_off(&end);
+            cached.dirty.split_off(&end);
+            cached.len = new_len;
+            Ok(())
+        })
+    }
+
+    /// Get the physical object of the whole `file`, loading all its pages.
+    ///
+    /// Sharing the object pins the file in the cache, whose contents may then
+    /// be changed by the clients at any time. An empty file has nothing to
+    /// share, so a fresh object of a zeroed page is returned instead, since
+    /// the objects cannot be empty.
+    pub fn phys(&self, file: u64, options: PhysOptions) -> Result<Phys, Error> {
+        let copy = options == PhysOptions::Copy;
+        self.with(file, |backing, cached| {
+            if cached.len == 0 {
+                let phys = Phys::allocate(PAGE_SIZE, Flags::ZEROED | Flags::RESIZABLE);
+                return phys.map_err(Error::Other);
+            }
+            cached.load(backing, file, page_range(0..cached.len))?;
+            if !copy && !cached.shared {
+                cached.share()?;
+            }
+            let phys = cached.phys.create_sub(0, cached.len, copy);
+            phys.map_err(Error::Other)
+        })
+    }
+
+    /// Write back the changes of `file` into the storage.
+    pub fn flush(&self, file: u64) -> Result<(), Error> {
+        if let Some(slot) = self.lookup(file) {
+            let mut cached = slot.lock();
+            // The evicted files are written back already.
+            if !cached.evicted {
+                return cached.write_back(&*self.backing, file);
+            }
+        }
+        Ok(())
+    }
+
+    /// Write back the changes of all the files, returning the first error if
+    /// any.
+    pub fn flush_all(&self) -> Result<(), Error> {
+        let files = self.inner.lock().files.keys().copied().collect::<Vec<_>>();
+        let mut res = Ok(());
+        for file in files {
+            let ret = self.flush(file);
+            res = res.and(ret);
+        }
+        res
+    }
+
+    /// Drop the cache of `file` without writing back the changes, usually
+    /// after the file is removed.
+    pub fn invalidate(&self, file: u64) {
+        if let Some(slot) = self.lookup(file) {
+            let mut cached = slot.lock();
+            if !cached.evicted {
+                cached.evicted = true;
+                self.inner.lock().detach(file, &slot, &cached);
+            }
+        }
+    }
+
+    /// Evict the files until at most `target` bytes are loaded, which is used
+    /// to release memory under pressure.
+    ///
+    /// The system has no notification of memory pressure yet, so the cache
+    /// never calls this by itself, and only keeps itself within its capacity.
+    /// The owners of the cache may call it when they see fit, such as after
+    /// failing to allocate memory.
+    ///
+    /// The pinned files and the ones failed to write back are kept.
+    pub fn reclaim(&self, target: usize) {
+        self.evict(target >> PAGE_SHIFT);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec;
+
+    use solvent::prelude::PAGE_SIZE;
+    use solvent_rpc::io::file::PhysOptions;
+
+    use super::PageCache;
+    use crate::fixture::MemFiles;
+
+    #[test]
+    fn test_write_back() {
+        let backing = MemFiles::new(&[(1, 3 * PAGE_SIZE)]);
+        let cache = PageCache::new(backing.clone(), 16 * PAGE_SIZE);
+        assert_eq!(cache.write(1, 1, b"ab").unwrap(), 2);
+        assert_eq!(cache.write(1, 2 * PAGE_SIZE, b"cd").unwrap(), 2);
+        assert!(backing.writes.lock().is_empty());
+
+        cache.flush(1).unwrap();
+        let writes = [(1, 0..PAGE_SIZE), (1, 2 * PAGE_SIZE..3 * PAGE_SIZE)];
+        assert_eq!(*backing.writes.lock(), writes);
+        {
+            let files = backing.files.lock();
+            assert_eq!(files[&1][..4], [1, b'a', b'b', 1]);
+            assert_eq!(files[&1][2 * PAGE_SIZE..][..3], [b'c', b'd', 1]);
+        }
+
+        // The contiguous dirty pages are written back in one batch.
+        backing.writes.lock().clear();
+        cache.write(1, PAGE_SIZE, b"e").unwrap();
+        cache.write(1, 2 * PAGE_SIZE, b"f").unwrap();
+        cache.write(1, 0, b"g").unwrap();
+        cache.flush(1).unwrap();
+        assert_eq!(*backing.writes.lock(), [(1, 0..3 * PAGE_SIZE)]);
+
+        // Nothing is left dirty.
+        backing.writes.lock().clear();
+        cache.flush_all().unwrap();
+        assert!(backing.writes.lock().is_empty());
+    }
+
+    #[test]
+    fn test_evict() {
+        let files = [(1, PAGE_SIZE), (2, PAGE_SIZE), (3, PAGE_SIZE)];
+        let backing = MemFiles::new(&files);
+        let cache = PageCache::new(backing.clone(), 4 * PAGE_SIZE);
+        let mut buf = [0; 16];
+        cache.read(1, 0, &mut buf).unwrap();
+        cache.read(2, 0, &mut buf).unwrap();
+        cache.write(1, 0, b"a").unwrap();
+        assert_eq!(cache.usage(), 4 * PAGE_SIZE);
+
+        // The least recently used file is evicted first.
+        cache.read(3, 0, &mut buf).unwrap();
+        assert!(cache.lookup(1).is_some());
+        assert!(cache.lookup(2).is_none());
+        assert!(cache.lookup(3).is_some());
+        assert!(backing.writes.lock().is_empty());
+
+        // The changes are written back on eviction.
+        cache.read(2, 0, &mut buf).unwrap();
+        assert!(cache.lookup(1).is_none());
+        assert_eq!(*backing.writes.lock(), [(1, 0..PAGE_SIZE)]);
+        assert_eq!(backing.files.lock()[&1][..2], [b'a', 1]);
+        assert_eq!(cache.usage(), 4 * PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_pin() {
+        let backing = MemFiles::new(&[(1, 10), (2, PAGE_SIZE)]);
+        let cache = PageCache::new(backing.clone(), 2 * PAGE_SIZE);
+        let phys = cache.phys(1, PhysOptions::Shared).unwrap();
+        assert_eq!(phys.len(), 10);
+
+        // The shared file is kept beyond the capacity.
+        let mut buf = [0; 16];
+        cache.read(2, 0, &mut buf).unwrap();
+        assert!(cache.lookup(1).is_some());
+        assert!(cache.lookup(2).is_none());
+
+        // The changes of the clients are seen through the cache.
+        // SAFETY: The object is not mapped.
+        unsafe { phys.write(0, b"xy") }.unwrap();
+        assert_eq!(cache.read(1, 0, &mut buf).unwrap(), 10);
+        assert_eq!(buf[..3], [b'x', b'y', 1]);
+        cache.flush(1).unwrap();
+        assert_eq!(backing.files.lock()[&1][..3], [b'x', b'y', 1]);
+
+        // The copies are not shared.
+        let copy = cache.phys(1, PhysOptions::Copy).unwrap();
+        // SAFETY: The same as above.
+        unsafe { copy.write(0, b"z") }.unwrap();
+        assert_eq!(cache.read(1, 0, &mut buf).unwrap(), 10);
+        assert_eq!(buf[0], b'x');
+    }
+
+    #[test]
+    fn test_empty() {
+        let cache = PageCache::new(MemFiles::new(&[(1, 0)]), 4 * PAGE_SIZE);
+        cache.phys(1, PhysOptions::Shared).unwrap();
+        cache.phys(1, PhysOptions::Copy).unwrap();
+        assert_eq!(cache.read(1, 0, &mut [0; 16]).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_resize() {
+        let cache = PageCache::new(MemFiles::new(&[(1, 2 * PAGE_SIZE + 10)]), 16 * PAGE_SIZE);
+        let mut buf = vec![0; 3 * PAGE_SIZE];
+        assert_eq!(cache.read(1, 0, &mut buf).unwrap(), 2 * PAGE_SIZE + 10);
+        assert_eq!(cache.usage(), 4 * PAGE_SIZE);
+
+        cache.resize(1, 5).unwrap();
+        assert_eq!(cache.usage(), 2 * PAGE_SIZE);
+        assert_eq!(cache.len(1).unwrap(), 5);
+        assert_eq!(cache.read(1, 0, &mut buf).unwrap(), 5);
+
+        // The bytes beyond the old end read as zeros after growing.
+        cache.write(1, PAGE_SIZE, b"z").unwrap();
+        assert_eq!(cache.len(1).unwrap(), PAGE_SIZE + 1);
+        assert_eq!(cache.read(1, 0, &mut buf).unwrap(), PAGE_SIZE + 1);
+        assert_eq!(buf[..6], [1, 1, 1, 1, 1, 0]);
+        assert!(buf[5..PAGE_SIZE].iter().all(|&b| b == 0));
+        assert_eq!(buf[PAGE_SIZE], b'z');
+    }
+
+    #[test]
+    fn test_load_failure() {
+        let backing = MemFiles::new(&[(1, 3 * PAGE_SIZE)]);
+        let cache = PageCache::new(backing.clone(), 16 * PAGE_SIZE);
+        cache.read(1, PAGE_SIZE, &mut [0; 1]).unwrap();
+        assert_eq!(cache.usage(), 2 * PAGE_SIZE);
+
+        // The pages loaded before the failure are still counted.
+        *backing.fail_at.lock() = Some(2 * PAGE_SIZE);
+        let mut buf = vec![0; 3 * PAGE_SIZE];
+        assert!(cache.read(1, 0, &mut buf).is_err());
+        assert_eq!(cache.usage(), 3 * PAGE_SIZE);
+
+        cache.invalidate(1);
+        assert_eq!(cache.usage(), 0);
+    }
+}
@@ -1,19 +1,28 @@
 mod event;
 
 mod handle;
+mod watch;
 
 use alloc::{boxed::Box, string::String};
 
 use async_trait::async_trait;
-use solvent_core::sync::Arsc;
+use solvent_core::{
+    path::{Component, Path, PathBuf},
+    sync::Arsc,
+};
 use solvent_rpc::io::{dir::DirEntry, Error};
 
-pub use self::{event::*, handle::*};
+pub use self::{event::*, handle::*, watch::*};
 use crate::entry::Entry;
 
+/// The maximum number of the symbolic links followed while opening a path.
+pub const MAX_SYMLINKS: usize = 40;
+
 #[async_trait]
 pub trait Directory: Entry {
     async fn next_dirent(&self, last: Option<String>) -> Result<DirEntry, Error>;
+
+    async fn readlink(&self, name: &str) -> Result<PathBuf, Error>;
 }
 
 #[async_trait]
@@ -33,21 +42,66 @@ pub trait DirectoryMut: Directory {
     ) -> Result<(), Error>;
 
     async fn unlink(&self, name: &str, expect_dir: bool) -> Result<(), Error>;
+
+    async fn symlink(&self, name: &str, target: &Path) -> Result<(), Error>;
+}
+
+/// Make the symbolic link found in the child entry `name` relative to its
+/// parent directory.
+pub fn redirect<T>(name: &str, res: Result<T, Error>) -> Result<T, Error> {
+    match res {
+        Err(Error::Symlink { path, conn }) if path.is_relative() => Err(Error::Symlink {
+            path: Path::new(name).join(path),
+            conn,
+        }),
+        res => res,
+    }
+}
+
+/// Resolve the `.` and `..` components of a relative path lexically, or return
+/// `None` if it's absolute or goes out of its base.
+pub fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut out = PathBuf::new();
+    for comp in path.components() {
+        match comp {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !out.pop() {
+                    return None;
+                }
+            }
+            Component::Normal(comp) => out.push(comp),
+            Component::Prefix(_) | Component::RootDir => return None,
+        }
+    }
+    Some(out)
 }
 
 pub mod sync {
     use alloc::string::String;
 
-    use solvent_rpc::io::{
-        dir::{DirEntry, DirectorySyncClient},
-        Error,
+    use solvent_rpc::{
+        io::{
+            dir::{DirEntry, DirectorySyncClient},
+            Error,
+        },
+        sync::StreamReceiver,
+        Error as RpcError,
     };
 
-    #[derive(Clone)]
+    enum State {
+        Init,
+        Stream(StreamReceiver<Result<DirEntry, Error>>),
+        /// The server doesn't support streaming, so fall back to fetching
+        /// entries one by one.
+        Dirent,
+        Stop,
+    }
+
     pub struct RemoteIter {
         inner: DirectorySyncClient,
         last: Option<String>,
-        stop: bool,
+        state: State,
     }
 
     impl From<DirectorySyncClient> for RemoteIter {
@@ -55,31 +109,68 @@ pub mod sync {
             RemoteIter {
                 inner: dir,
                 last: None,
-                stop: false,
+                state: State::Init,
             }
         }
     }
 
-    impl Iterator for RemoteIter {
-        type Item = Result<DirEntry, Error>;
-
-        #[inline]
-        fn next(&mut self) -> Option<Self::Item> {
-            if self.stop {
-                return None;
+    impl Clone for RemoteIter {
+        /// The cloned iterator continues from the last entry of this one.
+        fn clone(&self) -> Self {
+            RemoteIter {
+                inner: self.inner.clone(),
+                last: self.last.clone(),
+                state: match self.state {
+                    State::Stop => State::Stop,
+                    _ => State::Init,
+                },
             }
+        }
+    }
+
+    impl RemoteIter {
+        fn next_dirent(&mut self) -> Option<Result<DirEntry, Error>> {
             match self.inner.next_dirent(self.last.take()) {
                 Ok(Err(Error::IterEnd)) => None,
-                Ok(Ok(item)) => {
-                    self.last = Some(item.name.clone());
-                    Some(Ok(item))
-                }
+                Ok(Ok(item)) => Some(Ok(item)),
                 Ok(Err(err)) => Some(Err(err)),
                 Err(err) => {
-                    self.stop = true;
+                    self.state = State::Stop;
                     Some(Err(err.into()))
                 }
             }
         }
     }
+
+    impl Iterator for RemoteIter {
+        type Item = Result<DirEntry, Error>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            if let State::Init = self.state {
+                self.state = match self.inner.list(self.last.clone()) {
+                    Ok(stream) => State::Stream(stream),
+                    Err(RpcError::UnsupportedMethod(_)) => State::Dirent,
+                    Err(err) => {
+                        self.state = State::Stop;
+                        return Some(Err(err.into()));
+                    }
+                };
+            }
+            let res = match self.state {
+                State::Stream(ref mut stream) => match stream.next()? {
+                    Ok(res) => res,
+                    Err(err) => {
+                        self.state = State::Stop;
+                        return Some(Err(err.into()));
+                    }
+                },
+                State::Dirent => self.next_dirent()?,
+                _ => return None,
+            };
+            if let Ok(ref item) = res {
+                self.last = Some(item.name.clone());
+            }
+            Some(res)
+        }
+    }
 }
@@ -1,15 +1,20 @@
-use futures_lite::StreamExt;
-use solvent::prelude::Handle;
-use solvent_core::{path::Path, sync::Arsc};
+use alloc::string::String;
+
+use futures_lite::{future, StreamExt};
+use solvent::prelude::{Channel, Handle};
+use solvent_core::{
+    path::{Path, PathBuf},
+    sync::Arsc,
+};
 use solvent_rpc::{
     io::{
-        dir::{self as rpc, DirectoryEventSender, EventFlags},
+        dir::{self as rpc, DirEntry, DirectoryEventSender, EventFlags, WatchEvent, WatchMask},
         Error, OpenOptions, Permission,
     },
-    Error as RpcError, EventSender, Server,
+    Error as RpcError, EventSender, Server, StreamSender,
 };
 
-use super::{Directory, DirectoryMut, EventTokens};
+use super::{normalize, Directory, DirectoryMut, EventTokens, MAX_SYMLINKS};
 use crate::spawn::Spawner;
 
 pub async fn handle<D: Directory>(
@@ -19,7 +24,7 @@ pub async fn handle<D: Directory>(
     server: rpc::DirectoryServer,
     options: OpenOptions,
 ) {
-    let (mut requests, event) = server.serve();
+    let (mut requests, event) = server.with_limits(spawner.limits()).serve();
     while let Some(request) = requests.next().await {
         let request = match request {
             Ok(request) => request,
@@ -44,7 +49,7 @@ pub async fn handle_mut<D: DirectoryMut>(
     server: rpc::DirectoryServer,
     options: OpenOptions,
 ) {
-    let (mut requests, event) = server.serve();
+    let (mut requests, event) = server.with_limits(spawner.limits()).serve();
     let mut handle = None;
     while let Some(request) = requests.next().await {
         let request = match request {
@@ -108,6 +113,14 @@ async fn handle_request<D: Directory>(
             return HandleRequest::Break;
         }
         rpc::DirectoryRequest::Metadata { responder } => responder.send(dir.metadata()),
+        rpc::DirectoryRequest::SetMetadata { attr, responder } => responder.send({
+            if options.contains(OpenOptions::WRITE) {
+                dir.set_metadata(attr)
+            } else {
+                Err(Error::PermissionDenied(Permission::WRITE))
+            }
+        }),
+        rpc::DirectoryRequest::Statfs { responder } => responder.send(dir.statfs()),
         rpc::DirectoryRequest::NextDirent { last, responder } => responder.send({
             if options.contains(OpenOptions::READ) {
                 dir.next_dirent(last).await
@@ -115,25 +128,155 @@ async fn handle_request<D: Directory>(
                 Err(Error::PermissionDenied(Permission::READ))
             }
         }),
+        rpc::DirectoryRequest::List { last, responder } => match responder.accept() {
+            Ok(sender) => {
+                spawner.spawn(list(dir.clone(), last, options, sender));
+                Ok(())
+            }
+            Err(err) => Err(err),
+        },
+        rpc::DirectoryRequest::Watch {
+            mask,
+            recursive,
+            responder,
+        } => match responder.accept() {
+            Ok(sender) => {
+                spawner.spawn(watch(dir.clone(), mask, recursive, options, sender));
+                Ok(())
+            }
+            Err(err) => Err(err),
+        },
         rpc::DirectoryRequest::Open {
             path,
             options,
             conn,
             responder,
         } => responder.send({
-            dir.clone()
-                .open(spawner, tokens.clone(), &path, options, conn)
-                .map(|create| {
-                    if create {
-                        let _ = event.send(EventFlags::ADD);
-                    }
-                })
+            open(dir, spawner, tokens, path, options, conn).map(|create| {
+                if create {
+                    let _ = event.send(EventFlags::ADD);
+                }
+            })
+        }),
+        rpc::DirectoryRequest::Readlink { name, responder } => responder.send({
+            if options.contains(OpenOptions::READ) {
+                dir.readlink(&name).await
+            } else {
+                Err(Error::PermissionDenied(Permission::READ))
+            }
         }),
         request => return HandleRequest::Continue(request),
     };
     HandleRequest::Next(res)
 }
 
+/// Open `path` in the directory, following the symbolic links that stay
+/// inside it and returning the others to the client.
+fn open<D: Directory>(
+    dir: &Arsc<D>,
+    spawner: Spawner,
+    tokens: &EventTokens,
+    mut path: PathBuf,
+    options: OpenOptions,
+    mut conn: Channel,
+) -> Result<bool, Error> {
+    for _ in 0..MAX_SYMLINKS {
+        let res = dir
+            .clone()
+            .open(spawner.clone(), tokens.clone(), &path, options, conn);
+        match res {
+            Err(Error::Symlink {
+                path: target,
+                conn: c,
+            }) => match normalize(&target) {
+                Some(target) => {
+                    path = target;
+                    conn = c;
+                }
+                None => {
+                    return Err(Error::Symlink {
+                        path: target,
+                        conn: c,
+                    })
+                }
+            },
+            res => return res,
+        }
+    }
+    Err(Error::SymlinkLoop(path))
+}
+
+async fn list<D: Directory>(
+    dir: Arsc<D>,
+    mut last: Option<String>,
+    options: OpenOptions,
+    mut sender: StreamSender<Result<DirEntry, Error>>,
+) {
+    if !options.contains(OpenOptions::READ) {
+        let _ = sender
+            .send(Err(Error::PermissionDenied(Permission::READ)))
+            .await;
+        return;
+    }
+    loop {
+        let res = match dir.next_dirent(last.take()).await {
+            Err(Error::IterEnd) => break,
+            Ok(dirent) => {
+                last = Some(dirent.name.clone());
+                sender.send(Ok(dirent)).await
+            }
+            Err(err) => {
+                let _ = sender.send(Err(err)).await;
+                break;
+            }
+        };
+        // The client dropped the stream.
+        if res.is_err() {
+            break;
+        }
+    }
+}
+
+async fn watch<D: Directory>(
+    dir: Arsc<D>,
+    mask: WatchMask,
+    recursive: bool,
+    options: OpenOptions,
+    mut sender: StreamSender<Result<WatchEvent, Error>>,
+) {
+    let watch = match dir.attrs() {
+        _ if !options.contains(OpenOptions::READ) => Err(Error::PermissionDenied(Permission::READ)),
+        Some(attrs) => Ok(attrs.watch().clone()),
+        None => Err(Error::RpcError(RpcError::UnsupportedMethod(
+            rpc::directory::WATCH,
+        ))),
+    };
+    let watch = match watch {
+        Ok(watch) => watch,
+        Err(err) => {
+            let _ = sender.send(Err(err)).await;
+            return;
+        }
+    };
+    let watcher = watch.subscribe(mask, recursive);
+    loop {
+        let next = async { Some(watcher.next().await) };
+        let closed = async {
+            sender.closed().await;
+            None
+        };
+        let event = match future::or(next, closed).await {
+            Some(event) => event,
+            // The client dropped the stream.
+            None => break,
+        };
+        if sender.send(Ok(event)).await.is_err() {
+            break;
+        }
+    }
+    watch.unsubscribe(&watcher);
+}
+
 async fn handle_request_mut<D: DirectoryMut>(
     dir: &Arsc<D>,
     spawner: Spawner,
@@ -155,7 +298,8 @@ async fn handle_request_mut<D: DirectoryMut>(
             } else {
                 let raw = event.as_raw();
                 *handle = Some(raw);
-                // SAFETY: `raw` is the raw reference of a `DirectoryEventSender`.
+                // SAFETY: `raw` is the raw reference of a
+                // `DirectoryEventSender`.
                 unsafe { tokens.insert(dir.clone(), raw, options) }.await;
                 Ok(raw)
             }
@@ -228,6 +372,19 @@ async fn handle_request_mut<D: DirectoryMut>(
                 Err(Error::PermissionDenied(Permission::WRITE))
             }
         }),
+        rpc::DirectoryRequest::Symlink {
+            name,
+            target,
+            responder,
+        } => responder.send({
+            if options.contains(OpenOptions::WRITE) {
+                dir.symlink(&name, &target)
+                    .await
+                    .inspect(|_| drop(event.send(EventFlags::ADD)))
+            } else {
+                Err(Error::PermissionDenied(Permission::WRITE))
+            }
+        }),
         request => return HandleRequest::Continue(request),
     };
     HandleRequest::Next(res)
@@ -0,0 +1,189 @@
+use alloc::{collections::VecDeque, string::String, vec::Vec};
+use core::{
+    fmt,
+    task::{Poll, Waker},
+};
+
+use futures_lite::future;
+use solvent_core::{
+    path::{Path, PathBuf},
+    sync::{Arsc, Mutex},
+};
+use solvent_rpc::io::dir::{WatchEvent, WatchMask};
+
+/// The maximum number of the pending events of a watcher, including the final
+/// `Overflow`.
+pub const WATCH_CAPACITY: usize = 64;
+
+/// The watchers of a node, and the directory entries through which its events
+/// reach the watchers of the ancestors.
+pub struct Watch {
+    watchers: Mutex<Vec<Arsc<Watcher>>>,
+    parents: Mutex<Vec<(Arsc<Watch>, String)>>,
+}
+
+impl Watch {
+    #[inline]
+    pub fn new() -> Arsc<Self> {
+        Arsc::new(Watch {
+            watchers: Mutex::new(Vec::new()),
+            parents: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn subscribe(&self, mask: WatchMask, recursive: bool) -> Arsc<Watcher> {
+        let watcher = Arsc::new(Watcher {
+            mask,
+            recursive,
+            queue: Mutex::new(Queue {
+                events: VecDeque::new(),
+                waker: None,
+            }),
+        });
+        self.watchers.lock().push(watcher.clone());
+        watcher
+    }
+
+    pub fn unsubscribe(&self, watcher: &Arsc<Watcher>) {
+        self.watchers.lock().retain(|w| !Arsc::ptr_eq(w, watcher));
+    }
+
+    /// Record that the node is linked as `name` in the directory watched by
+    /// `parent`.
+    pub fn attach(&self, parent: &Arsc<Watch>, name: &str) {
+        self.parents.lock().push((parent.clone(), name.into()));
+    }
+
+    /// Record that the node is no longer linked as `name` in the directory
+    /// watched by `parent`.
+    pub fn detach(&self, parent: &Arsc<Watch>, name: &str) {
+        let mut parents = self.parents.lock();
+        if let Some(index) = parents
+            .iter()
+            .position(|(p, n)| Arsc::ptr_eq(p, parent) && n == name)
+        {
+            parents.swap_remove(index);
+        }
+    }
+
+    /// Report `event` to the watchers of the node and of its ancestors.
+    pub fn notify(&self, event: WatchEvent) {
+        let depth = depth(&event);
+        for watcher in self.watchers.lock().iter() {
+            if watcher.recursive || depth <= 1 {
+                watcher.push(&event);
+            }
+        }
+
+        let parents = self.parents.lock().clone();
+        for (parent, name) in parents {
+            parent.notify(prefix(&name, &event));
+        }
+    }
+}
+
+impl fmt::Debug for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watch")
+            .field("watchers", &self.watchers.lock().len())
+            .field("parents", &self.parents.lock().len())
+            .finish()
+    }
+}
+
+/// A subscription to the events of a directory.
+pub struct Watcher {
+    mask: WatchMask,
+    recursive: bool,
+    queue: Mutex<Queue>,
+}
+
+struct Queue {
+    events: VecDeque<WatchEvent>,
+    waker: Option<Waker>,
+}
+
+impl Watcher {
+    fn push(&self, event: &WatchEvent) {
+        if !self.mask.contains(kind(event)) {
+            return;
+        }
+        let mut queue = self.queue.lock();
+        // Drop the events following an overflow until the watcher catches up.
+        if matches!(queue.events.back(), Some(WatchEvent::Overflow)) {
+            return;
+        }
+        let event = if queue.events.len() + 1 >= WATCH_CAPACITY {
+            WatchEvent::Overflow
+        } else {
+            event.clone()
+        };
+        queue.events.push_back(event);
+        if let Some(waker) = queue.waker.take() {
+            waker.wake()
+        }
+    }
+
+    /// Wait for the next event.
+    pub async fn next(&self) -> WatchEvent {
+        future::poll_fn(|cx| {
+            let mut queue = self.queue.lock();
+            match queue.events.pop_front() {
+                Some(event) => Poll::Ready(event),
+                None => {
+                    queue.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+}
+
+fn kind(event: &WatchEvent) -> WatchMask {
+    match event {
+        WatchEvent::Create(_) => WatchMask::CREATE,
+        WatchEvent::Remove(_) => WatchMask::REMOVE,
+        WatchEvent::Rename { .. } => WatchMask::RENAME,
+        WatchEvent::Modify(_) => WatchMask::MODIFY,
+        WatchEvent::Metadata(_) => WatchMask::METADATA,
+        WatchEvent::Overflow => WatchMask::empty(),
+    }
+}
+
+/// The number of the components in the path of `event`, which is 1 for the
+/// direct entries of the watched directory.
+fn depth(event: &WatchEvent) -> usize {
+    let count = |path: &Path| path.components().count();
+    match event {
+        WatchEvent::Create(path)
+        | WatchEvent::Remove(path)
+        | WatchEvent::Modify(path)
+        | WatchEvent::Metadata(path) => count(path),
+        WatchEvent::Rename { old, new } => count(old).max(count(new)),
+        WatchEvent::Overflow => 0,
+    }
+}
+
+/// Make the path of `event` relative to the directory containing the node as
+/// `name`.
+fn prefix(name: &str, event: &WatchEvent) -> WatchEvent {
+    let join = |path: &PathBuf| {
+        if path == Path::new("") {
+            PathBuf::from(name)
+        } else {
+            Path::new(name).join(path)
+        }
+    };
+    match event {
+        WatchEvent::Create(path) => WatchEvent::Create(join(path)),
+        WatchEvent::Remove(path) => WatchEvent::Remove(join(path)),
+        WatchEvent::Rename { old, new } => WatchEvent::Rename {
+            old: join(old),
+            new: join(new),
+        },
+        WatchEvent::Modify(path) => WatchEvent::Modify(join(path)),
+        WatchEvent::Metadata(path) => WatchEvent::Metadata(join(path)),
+        WatchEvent::Overflow => WatchEvent::Overflow,
+    }
+}
@@ -1,10 +1,25 @@
-use core::any::Any;
+use core::{
+    any::Any,
+    sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering::*},
+};
 
-use solvent::prelude::Channel;
-use solvent_core::{path::Path, sync::Arsc};
-use solvent_rpc::io::{Error, Metadata, OpenOptions};
+use solvent::{prelude::Channel, time::Instant};
+use solvent_core::{
+    path::{Path, PathBuf},
+    sync::Arsc,
+};
+use solvent_rpc::{
+    io::{
+        dir::WatchEvent, fs as rpc, Error, FileTimes, FileType, FsStat, Metadata, OpenOptions,
+        Permission, SetMask, SetMetadata,
+    },
+    Error as RpcError,
+};
 
-use crate::{dir::EventTokens, spawn::Spawner};
+use crate::{
+    dir::{EventTokens, Watch},
+    spawn::Spawner,
+};
 
 pub trait Entry: IntoAny + Send + Sync + 'static {
     fn open(
@@ -17,6 +32,29 @@ pub trait Entry: IntoAny + Send + Sync + 'static {
     ) -> Result<bool, Error>;
 
     fn metadata(&self) -> Result<Metadata, Error>;
+
+    /// Set the attributes selected by `attr.mask`.
+    ///
+    /// The entry is read-only by default.
+    #[inline]
+    fn set_metadata(&self, attr: SetMetadata) -> Result<(), Error> {
+        let _ = attr;
+        Err(Error::PermissionDenied(Permission::WRITE))
+    }
+
+    /// Get the usage of the file system containing the entry, if known.
+    #[inline]
+    fn statfs(&self) -> Result<FsStat, Error> {
+        Err(Error::RpcError(RpcError::UnsupportedMethod(
+            rpc::filesystem::STATFS,
+        )))
+    }
+
+    /// The attributes of the entry maintained in memory, if any.
+    #[inline]
+    fn attrs(&self) -> Option<&Attrs> {
+        None
+    }
 }
 
 pub trait IntoAny: Any {
@@ -28,3 +66,126 @@ impl<T: Any + Send + Sync> IntoAny for T {
         self as _
     }
 }
+
+/// Get the timestamp of `instant` transferred in [`FileTimes`].
+#[inline]
+pub fn timestamp(instant: Instant) -> u64 {
+    // SAFETY: The raw value is the nanoseconds since the system boots, and is
+    // never used for measurements.
+    unsafe { instant.raw() as u64 }
+}
+
+/// The attributes of a node maintained in memory, shared by all its
+/// connections.
+#[derive(Debug)]
+pub struct Attrs {
+    node_id: u64,
+    mode: AtomicU32,
+    links: AtomicUsize,
+    created: AtomicU64,
+    modified: AtomicU64,
+    accessed: AtomicU64,
+    watch: Arsc<Watch>,
+}
+
+impl Attrs {
+    /// Create the attributes of a new node, allocating its ID.
+    pub fn new(mode: Permission) -> Self {
+        static NODE_ID: AtomicU64 = AtomicU64::new(1);
+        let now = timestamp(Instant::now());
+        Attrs {
+            node_id: NODE_ID.fetch_add(1, Relaxed),
+            mode: AtomicU32::new(mode.bits()),
+            links: AtomicUsize::new(1),
+            created: AtomicU64::new(now),
+            modified: AtomicU64::new(now),
+            accessed: AtomicU64::new(now),
+            watch: Watch::new(),
+        }
+    }
+
+    #[inline]
+    pub fn node_id(&self) -> u64 {
+        self.node_id
+    }
+
+    #[inline]
+    pub fn mode(&self) -> Permission {
+        Permission::from_bits_truncate(self.mode.load(Acquire))
+    }
+
+    #[inline]
+    pub fn link_count(&self) -> usize {
+        self.links.load(Acquire)
+    }
+
+    /// Record that another directory entry refers to the node.
+    #[inline]
+    pub fn link(&self) {
+        self.links.fetch_add(1, AcqRel);
+    }
+
+    /// Record that a directory entry referring to the node is removed.
+    #[inline]
+    pub fn unlink(&self) {
+        let _ = self
+            .links
+            .fetch_update(AcqRel, Acquire, |links| links.checked_sub(1));
+    }
+
+    /// The watchers of the node.
+    #[inline]
+    pub fn watch(&self) -> &Arsc<Watch> {
+        &self.watch
+    }
+
+    pub fn times(&self) -> FileTimes {
+        FileTimes {
+            created: self.created.load(Acquire),
+            modified: self.modified.load(Acquire),
+            accessed: self.accessed.load(Acquire),
+        }
+    }
+
+    /// Update the access time, and also the modification time if `modified`.
+    pub fn touch(&self, modified: bool) {
+        let now = timestamp(Instant::now());
+        self.accessed.store(now, Release);
+        if modified {
+            self.modified.store(now, Release);
+        }
+    }
+
+    /// Fill the attributes into the metadata of the node.
+    pub fn metadata(&self, file_type: FileType, perm: Permission, len: usize) -> Metadata {
+        Metadata {
+            node_id: self.node_id,
+            link_count: self.link_count(),
+            mode: self.mode(),
+            times: self.times(),
+            ..Metadata::new(file_type, perm, len)
+        }
+    }
+
+    /// Apply the mode and the timestamps selected by `attr.mask`, and notify
+    /// the watchers.
+    ///
+    /// The length is left to the caller.
+    pub fn set(&self, attr: &SetMetadata) {
+        if attr.mask.contains(SetMask::MODE) {
+            self.mode.store(attr.mode.bits(), Release);
+        }
+        if attr.mask.contains(SetMask::CREATED) {
+            self.created.store(attr.times.created, Release);
+        }
+        if attr.mask.contains(SetMask::MODIFIED) {
+            self.modified.store(attr.times.modified, Release);
+        }
+        if attr.mask.contains(SetMask::ACCESSED) {
+            self.accessed.store(attr.times.accessed, Release);
+        }
+        if !(attr.mask - SetMask::LEN).is_empty() {
+            self.watch.notify(WatchEvent::Metadata(PathBuf::new()));
+        }
+    }
+}
@@ -0,0 +1,517 @@
+//! A read-only driver of the ext2 file systems, also reading ext4 ones with
+//! extents.
+//!
+//! The journal of ext3/4 is never replayed, so the changes not yet written
+//! back into place by the last mount are invisible. The metadata is read
+//! through a [`BlockCache`] and the contents of the files through a
+//! [`PageCache`], and the nodes can be mounted into
+//! [`LocalFs`](crate::fs::LocalFs) by opening the root directory.
+
+mod dir;
+mod file;
+mod inode;
+mod symlink;
+
+use alloc::{collections::BTreeSet, format, vec, vec::Vec};
+use core::str;
+
+use solvent_core::sync::{Arsc, Mutex};
+use solvent_rpc::io::{Error, FileType, FsStat, Permission};
+
+use self::inode::Inode;
+pub use self::{dir::Ext2Dir, file::Ext2File, symlink::Ext2Symlink};
+use crate::{
+    block::BlockCache,
+    cache::{Backing, PageCache},
+    entry::Entry,
+};
+
+const SUPERBLOCK: u64 = 1024;
+const MAGIC: u16 = 0xef53;
+const ROOT_INO: u32 = 2;
+
+const INCOMPAT_FILETYPE: u32 = 0x2;
+const INCOMPAT_RECOVER: u32 = 0x4;
+const INCOMPAT_EXTENTS: u32 = 0x40;
+const INCOMPAT_64BIT: u32 = 0x80;
+const INCOMPAT_MMP: u32 = 0x100;
+const INCOMPAT_FLEX_BG: u32 = 0x200;
+const INCOMPAT_EA_INODE: u32 = 0x400;
+const INCOMPAT_CSUM_SEED: u32 = 0x2000;
+const INCOMPAT_LARGEDIR: u32 = 0x4000;
+/// The incompatible features that don't change the layout of what we read.
+const INCOMPAT_SUPPORTED: u32 = INCOMPAT_FILETYPE
+    | INCOMPAT_RECOVER
+    | INCOMPAT_EXTENTS
+    | INCOMPAT_64BIT
+    | INCOMPAT_MMP
+    | INCOMPAT_FLEX_BG
+    | INCOMPAT_EA_INODE
+    | INCOMPAT_CSUM_SEED
+    | INCOMPAT_LARGEDIR;
+
+fn invalid(what: &str) -> Error {
+    Error::InvalidData(format!("invalid ext2 {what}"))
+}
+
+#[inline]
+fn le16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(buf[offset..offset + 2].try_into().unwrap())
+}
+
+#[inline]
+fn le32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+/// A mounted ext2 file system.
+pub struct Ext2Fs {
+    volume: Arsc<Volume>,
+    pages: PageCache,
+    perm: Permission,
+    stat: FsStat,
+    /// The inodes of the files locked by their connections.
+    locked: Mutex<BTreeSet<u32>>,
+}
+
+/// The layout of the file system on the device.
+struct Volume {
+    cache: BlockCache,
+    block_size: usize,
+    inode_size: usize,
+    inodes_per_group: u32,
+    /// The first blocks of the inode tables of the groups.
+    inode_tables: Vec<u64>,
+    /// Whether the directory entries record the types of their nodes, which
+    /// also limits the names to 255 bytes.
+    filetype: bool,
+}
+
+impl Ext2Fs {
+    /// Mount the file system stored in the device of `cache`, caching at most
+    /// `capacity` bytes of the file contents.
+    ///
+    /// The permissions of the nodes are the owner's ones in their modes
+    /// limited by `perm`, which never includes [`Permission::WRITE`].
+    pub fn new(cache: BlockCache, capacity: usize, perm: Permission) -> Result<Arsc<Self>, Error> {
+        let mut sb = [0; 1024];
+        cache.read(SUPERBLOCK, &mut sb)?;
+        if le16(&sb, 0x38) != MAGIC {
+            return Err(invalid("magic"));
+        }
+
+        let incompat = le32(&sb, 0x60);
+        if incompat & !INCOMPAT_SUPPORTED != 0 {
+            return Err(Error::InvalidData(format!(
+                "unsupported ext2 features {:#x}",
+                incompat & !INCOMPAT_SUPPORTED
+            )));
+        }
+        if incompat & INCOMPAT_RECOVER != 0 {
+            log::warn!("ext2: the journal is not replayed, recent changes may be missing");
+        }
+        let is_64bit = incompat & INCOMPAT_64BIT != 0;
+
+        let log_block_size = le32(&sb, 0x18);
+        if log_block_size > 6 {
+            return Err(invalid("block size"));
+        }
+        let block_size = 1024 << log_block_size;
+        let inode_size = match le32(&sb, 0x4c) {
+            0 => 128,
+            _ => usize::from(le16(&sb, 0x58)),
+        };
+        if inode_size < 128 || !inode_size.is_power_of_two() || inode_size > block_size {
+            return Err(invalid("inode size"));
+        }
+
+        let inodes_count = le32(&sb, 0x0);
+        let blocks_per_group = le32(&sb, 0x20);
+        let inodes_per_group = le32(&sb, 0x28);
+        if blocks_per_group == 0 || inodes_per_group == 0 {
+            return Err(invalid("group"));
+        }
+        let hi = |offset| {
+            if is_64bit {
+                u64::from(le32(&sb, offset)) << 32
+            } else {
+                0
+            }
+        };
+        let blocks_count = u64::from(le32(&sb, 0x4)) | hi(0x150);
+        let free_blocks = u64::from(le32(&sb, 0xc)) | hi(0x158);
+        let free_inodes = le32(&sb, 0x10);
+
+        // The group descriptors start from the block following the superblock.
+        let desc_size = if is_64bit {
+            usize::from(le16(&sb, 0xfe))
+        } else {
+            32
+        };
+        if desc_size < 32 || desc_size > block_size {
+            return Err(invalid("group descriptor size"));
+        }
+        // The groups are counted by the blocks, and must hold all the inodes.
+        // The table of their descriptors must fit in the device before it's
+        // allocated.
+        let first_data = u64::from(le32(&sb, 0x14));
+        let blocks = blocks_count.saturating_sub(first_data);
+        let blocks_per_group = u64::from(blocks_per_group);
+        let groups = blocks / blocks_per_group + u64::from(blocks % blocks_per_group != 0);
+        if u64::from(inodes_count) > groups.saturating_mul(u64::from(inodes_per_group)) {
+            return Err(invalid("group"));
+        }
+        let descs_len = groups
+            .checked_mul(desc_size as u64)
+            .filter(|&len| len <= cache.len())
+            .ok_or_else(|| invalid("group"))?;
+        let mut descs = vec![0; descs_len as usize];
+        cache.read((first_data + 1) * block_size as u64, &mut descs)?;
+        let inode_tables = descs.chunks(desc_size).map(|desc| {
+            let hi = if desc_size >= 64 {
+                u64::from(le32(desc, 0x28)) << 32
+            } else {
+                0
+            };
+            u64::from(le32(desc, 0x8)) | hi
+        });
+
+        let block_bytes = |blocks: u64| usize::try_from(blocks * block_size as u64);
+        let stat = FsStat {
+            total_bytes: block_bytes(blocks_count).unwrap_or(usize::MAX),
+            used_bytes: block_bytes(blocks_count.saturating_sub(free_blocks)).unwrap_or(usize::MAX),
+            total_nodes: inodes_count as usize,
+            used_nodes: inodes_count.saturating_sub(free_inodes) as usize,
+        };
+
+        let volume = Arsc::new(Volume {
+            cache,
+            block_size,
+            inode_size,
+            inodes_per_group,
+            inode_tables: inode_tables.collect(),
+            filetype: incompat & INCOMPAT_FILETYPE != 0,
+        });
+        Ok(Arsc::new(Ext2Fs {
+            pages: PageCache::new(volume.clone(), capacity),
+            volume,
+            perm: perm - Permission::WRITE,
+            stat,
+            locked: Mutex::new(BTreeSet::new()),
+        }))
+    }
+
+    /// The root directory of the file system.
+    pub fn root(self: &Arsc<Self>) -> Result<Arsc<Ext2Dir>, Error> {
+        let inode = self.volume.inode(ROOT_INO)?;
+        if inode.file_type() != Some(FileType::Directory) {
+            return Err(invalid("root directory"));
+        }
+        Ok(Arsc::new(Ext2Dir::new(self.clone(), inode)))
+    }
+
+    #[inline]
+    pub fn statfs(&self) -> FsStat {
+        self.stat
+    }
+
+    /// Create the node of the inode `ino`, or return `None` for the devices,
+    /// FIFOs and sockets, which are not served.
+    fn node(self: &Arsc<Self>, ino: u32) -> Result<Option<Arsc<dyn Entry>>, Error> {
+        let inode = self.volume.inode(ino)?;
+        let fs = self.clone();
+        Ok(match inode.file_type() {
+            Some(FileType::Directory) => Some(Arsc::new(Ext2Dir::new(fs, inode))),
+            Some(FileType::File) => Some(Arsc::new(Ext2File::new(fs, inode))),
+            Some(FileType::Symlink) => Some(Arsc::new(Ext2Symlink::new(fs, inode))),
+            _ => None,
+        })
+    }
+}
+
+impl Volume {
+    fn inode(&self, ino: u32) -> Result<Inode, Error> {
+        let index = ino.checked_sub(1).ok_or_else(|| invalid("inode number"))?;
+        let group = (index / self.inodes_per_group) as usize;
+        let table = *self.inode_tables.get(group).ok_or(Error::NotFound)?;
+        let offset = table * self.block_size as u64
+            + u64::from(index % self.inodes_per_group) * self.inode_size as u64;
+
+        let mut raw = [0; 128];
+        self.cache.read(offset, &mut raw)?;
+        Ok(Inode::parse(ino, &raw))
+    }
+
+    /// Read the content of `inode` from `pos` into `buf`, returning the number
+    /// of bytes read.
+    fn read(&self, inode: &Inode, pos: usize, buf: &mut [u8]) -> Result<usize, Error> {
+        let len = (inode.size.saturating_sub(pos as u64) as usize).min(buf.len());
+        let bs = self.block_size;
+        let mut read = 0;
+        while read < len {
+            let offset = pos + read;
+            let (block, start) = ((offset / bs) as u64, offset % bs);
+            let chunk = (len - read).min(bs - start);
+            let buf = &mut buf[read..][..chunk];
+            match inode.map(self, block)? {
+                Some(phys) => self.cache.read(phys * bs as u64 + start as u64, buf)?,
+                // Holes and the uninitialized extents are read as zeros.
+                None => buf.fill(0),
+            }
+            read += chunk;
+        }
+        Ok(len)
+    }
+
+    /// Read the whole block `block` of the device.
+    fn read_block(&self, block: u64, buf: &mut [u8]) -> Result<(), Error> {
+        self.cache.read(block * self.block_size as u64, buf)
+    }
+
+    /// Visit the entries of the directory `dir` in their order on the disk,
+    /// excluding `.` and `..`, until `f` returns a value.
+    ///
+    /// The directory is read one block at a time, so that a lookup doesn't
+    /// read the whole directory.
+    fn dirents<T>(
+        &self,
+        dir: &Inode,
+        mut f: impl FnMut(&str, u32) -> Result<Option<T>, Error>,
+    ) -> Result<Option<T>, Error> {
+        let mut buf = vec![0; self.block_size];
+        let mut pos = 0;
+        while (pos as u64) < dir.size {
+            let len = self.read(dir, pos, &mut buf)?;
+            pos += self.block_size;
+
+            let block = &buf[..len];
+            let mut offset = 0;
+            while offset + 8 <= block.len() {
+                let ino = le32(block, offset);
+                let rec_len = usize::from(le16(block, offset + 4));
+                let name_len = if self.filetype {
+                    usize::from(block[offset + 6])
+                } else {
+                    usize::from(le16(block, offset + 6))
+                };
+                if rec_len < 8 || offset + rec_len > block.len() || 8 + name_len > rec_len {
+                    return Err(invalid("directory entry"));
+                }
+                let name = &block[offset + 8..][..name_len];
+                offset += rec_len;
+
+                // Unused entries have no inode, and the names not in UTF-8
+                // cannot be represented.
+                if ino == 0 || name == b"." || name == b".." {
+                    continue;
+                }
+                if let Ok(name) = str::from_utf8(name) {
+                    if let Some(ret) = f(name, ino)? {
+                        return Ok(Some(ret));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Find the inode of the entry `name` in the directory `dir`.
+    fn lookup(&self, dir: &Inode, name: &str) -> Result<u32, Error> {
+        self.dirents(dir, |n, ino| Ok((n == name).then_some(ino)))?
+            .ok_or(Error::NotFound)
+    }
+}
+
+/// The contents of the files by their inode numbers.
+impl Backing for Arsc<Volume> {
+    fn len(&self, file: u64) -> Result<usize, Error> {
+        let ino = u32::try_from(file).map_err(|_| Error::NotFound)?;
+        Ok(self.inode(ino)?.size as usize)
+    }
+
+    fn read_at(&self, file: u64, pos: usize, buf: &mut [u8]) -> Result<usize, Error> {
+        let ino = u32::try_from(file).map_err(|_| Error::NotFound)?;
+        self.read(&self.inode(ino)?, pos, buf)
+    }
+}
+
+/// Get the node of the entry `name` in the directory `dir`.
+fn child(fs: &Arsc<Ext2Fs>, dir: &Inode, name: &str) -> Result<Arsc<dyn Entry>, Error> {
+    let ino = fs.volume.lookup(dir, name)?;
+    fs.node(ino)?.ok_or(Error::NotFound)
+}
+
+/// The permissions granted by the owner bits of the Unix `mode`.
+fn permission(mode: u16) -> Permission {
+    let mut perm = Permission::empty();
+    if mode & 0o400 != 0 {
+        perm |= Permission::READ;
+    }
+    if mode & 0o100 != 0 {
+        perm |= Permission::EXECUTE;
+    }
+    perm
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::{string::String, vec, vec::Vec};
+
+    use solvent_core::{path::Path, sync::Arsc};
+    use solvent_rpc::io::{Error, Permission};
+
+    use super::{Ext2Fs, Ext2Symlink, ROOT_INO};
+    use crate::{block::BlockCache, fixture::phys};
+
+    const BLOCK: usize = 1024;
+    const INODE_TABLE: usize = 5;
+
+    fn put16(image: &mut [u8], offset: usize, value: u16) {
+        image[offset..][..2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn put32(image: &mut [u8], offset: usize, value: u32) {
+        image[offset..][..4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// Write the inode `ino` with the raw `block` field.
+    fn inode(image: &mut [u8], ino: usize, mode: u16, size: u32, flags: u32, block: &[u8]) {
+        let offset = INODE_TABLE * BLOCK + (ino - 1) * 128;
+        put16(image, offset, mode);
+        put32(image, offset + 0x4, size);
+        put16(image, offset + 0x1a, 1);
+        put32(image, offset + 0x20, flags);
+        image[offset + 0x28..][..block.len()].copy_from_slice(block);
+    }
+
+    fn blocks(list: &[u32]) -> Vec<u8> {
+        list.iter().flat_map(|block| block.to_le_bytes()).collect()
+    }
+
+    /// Write the directory entries into the block `block`, with the last one
+    /// spanning the rest of it.
+    fn dir_block(image: &mut [u8], block: usize, entries: &[(&str, u32)]) {
+        let mut offset = block * BLOCK;
+        for (index, &(name, ino)) in entries.iter().enumerate() {
+            let rec_len = if index + 1 == entries.len() {
+                (block + 1) * BLOCK - offset
+            } else {
+                (8 + name.len() + 3) & !3
+            };
+            put32(image, offset, ino);
+            put16(image, offset + 4, rec_len as u16);
+            image[offset + 6] = name.len() as u8;
+            image[offset + 8..][..name.len()].copy_from_slice(name.as_bytes());
+            offset += rec_len;
+        }
+    }
+
+    /// A file system of 64 1K blocks in one group, whose inode table starts
+    /// from block 5, with a root directory in block 7 holding `entries`.
+    fn image(entries: &[(&str, u32)]) -> Vec<u8> {
+        let mut image = vec![0; 64 * BLOCK];
+        let sb = BLOCK;
+        put32(&mut image, sb, 16);
+        put32(&mut image, sb + 0x4, 64);
+        put32(&mut image, sb + 0x14, 1);
+        put32(&mut image, sb + 0x20, 8192);
+        put32(&mut image, sb + 0x28, 16);
+        put16(&mut image, sb + 0x38, 0xef53);
+        put32(&mut image, sb + 0x4c, 1);
+        put16(&mut image, sb + 0x58, 128);
+        put32(&mut image, sb + 0x60, 0x2);
+        // The group descriptors follow the superblock.
+        put32(&mut image, 2 * BLOCK + 0x8, INODE_TABLE as u32);
+
+        inode(&mut image, 2, 0o040755, 1024, 0, &blocks(&[7]));
+        dir_block(&mut image, 7, &[&[(".", 2), ("..", 2)], entries].concat());
+        image
+    }
+
+    fn mount(image: &[u8]) -> Result<Arsc<Ext2Fs>, Error> {
+        Ext2Fs::new(
+            BlockCache::new(phys(image), 16)?,
+            64 * 1024,
+            Permission::all(),
+        )
+    }
+
+    #[test]
+    fn test_dirents() {
+        let mut image = image(&[("hello", 12), ("link", 13)]);
+        // The root directory spans 2 blocks.
+        inode(&mut image, 2, 0o040755, 2048, 0, &blocks(&[7, 9]));
+        dir_block(&mut image, 9, &[("second", 12)]);
+        inode(&mut image, 12, 0o100644, 5, 0, &blocks(&[8]));
+        image[8 * BLOCK..][..5].copy_from_slice(b"hello");
+        let fs = mount(&image).unwrap();
+        let root = fs.volume.inode(ROOT_INO).unwrap();
+
+        let mut names = Vec::new();
+        let none = fs.volume.dirents(&root, |name, ino| {
+            names.push((String::from(name), ino));
+            Ok(None::<()>)
+        });
+        assert!(none.unwrap().is_none());
+        let names = names.iter().map(|(name, ino)| (name.as_str(), *ino));
+        assert!(names.eq([("hello", 12), ("link", 13), ("second", 12)]));
+
+        // The entries in the following blocks are found too.
+        assert_eq!(fs.volume.lookup(&root, "second").unwrap(), 12);
+        assert!(matches!(
+            fs.volume.lookup(&root, "missing"),
+            Err(Error::NotFound)
+        ));
+
+        let file = fs.volume.inode(12).unwrap();
+        let mut buf = [0; 16];
+        assert_eq!(fs.volume.read(&file, 0, &mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"hello");
+    }
+
+    #[test]
+    fn test_symlink() {
+        let mut image = image(&[]);
+        inode(&mut image, 13, 0o120777, 5, 0, b"hello");
+        // A symbolic link claiming a target longer than a block.
+        inode(&mut image, 14, 0o120777, 1 << 20, 0, &blocks(&[10]));
+        let fs = mount(&image).unwrap();
+
+        let link = Ext2Symlink::new(fs.clone(), fs.volume.inode(13).unwrap());
+        assert_eq!(link.target().unwrap(), Path::new("hello"));
+        let bad = Ext2Symlink::new(fs.clone(), fs.volume.inode(14).unwrap());
+        assert!(matches!(bad.target(), Err(Error::InvalidData(_))));
+    }
+
+    #[test]
+    fn test_extent_root() {
+        // An extent tree whose root claims more entries than the inode holds.
+        let mut extents = vec![0; 12];
+        put16(&mut extents, 0, 0xf30a);
+        put16(&mut extents, 2, 5);
+        put16(&mut extents, 4, 5);
+        let mut image = image(&[]);
+        inode(&mut image, 15, 0o100644, 1024, 0x80000, &extents);
+        let fs = mount(&image).unwrap();
+
+        let inode = fs.volume.inode(15).unwrap();
+        assert!(matches!(
+            inode.map(&fs.volume, 0),
+            Err(Error::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn test_groups() {
+        // Far more inodes than the groups of the blocks can hold.
+        let mut inodes = image(&[]);
+        put32(&mut inodes, BLOCK, u32::MAX);
+        assert!(matches!(mount(&inodes), Err(Error::InvalidData(_))));
+
+        // The descriptors of the groups would be far beyond the device.
+        let mut groups = image(&[]);
+        put32(&mut groups, BLOCK + 0x4, u32::MAX);
+        put32(&mut groups, BLOCK + 0x20, 1);
+        assert!(matches!(mount(&groups), Err(Error::InvalidData(_))));
+    }
+}
@@ -0,0 +1,120 @@
+use alloc::{boxed::Box, string::String};
+
+use async_trait::async_trait;
+use solvent::prelude::Channel;
+use solvent_async::ipc::Channel as AsyncChannel;
+use solvent_core::{
+    path::{Component, Path, PathBuf},
+    sync::Arsc,
+};
+use solvent_rpc::io::{
+    dir::{DirEntry, DirectoryServer},
+    Error, FileType, FsStat, Metadata, OpenOptions, Permission,
+};
+
+use super::{child, inode::Inode, Ext2Fs, Ext2Symlink};
+use crate::{
+    dir::{handle, redirect, Directory, EventTokens},
+    entry::Entry,
+    spawn::Spawner,
+};
+
+const MAX_NAME: usize = u8::MAX as _;
+
+pub struct Ext2Dir {
+    fs: Arsc<Ext2Fs>,
+    inode: Inode,
+}
+
+impl Ext2Dir {
+    pub(super) fn new(fs: Arsc<Ext2Fs>, inode: Inode) -> Self {
+        Ext2Dir { fs, inode }
+    }
+
+    fn get(&self, name: &str) -> Result<Arsc<dyn Entry>, Error> {
+        if name.len() > MAX_NAME {
+            return Err(Error::InvalidNameLength(name.len()));
+        }
+        child(&self.fs, &self.inode, name)
+    }
+}
+
+impl Entry for Ext2Dir {
+    fn open(
+        self: Arsc<Self>,
+        spawner: Spawner,
+        tokens: EventTokens,
+        path: &Path,
+        options: OpenOptions,
+        conn: Channel,
+    ) -> Result<bool, Error> {
+        if options.intersects(OpenOptions::CREATE | OpenOptions::CREATE_NEW) {
+            return Err(Error::PermissionDenied(Permission::WRITE));
+        }
+        match path.components().next() {
+            Some(Component::Normal(name)) => {
+                let name = name
+                    .to_str()
+                    .ok_or_else(|| Error::InvalidPath(path.into()))?;
+                let path = path.strip_prefix(name).unwrap();
+                let entry = self.get(name)?;
+                redirect(name, entry.open(spawner, tokens, path, options, conn))
+            }
+            Some(_) => Err(Error::InvalidPath(path.into())),
+            None => {
+                if options.intersects(OpenOptions::EXPECT_FILE | OpenOptions::EXPECT_RPC) {
+                    return Err(Error::InvalidType(FileType::Directory));
+                }
+                let require = options.require();
+                let perm = self.metadata()?.perm;
+                if !perm.contains(require) {
+                    return Err(Error::PermissionDenied(require - perm));
+                }
+                let server =
+                    DirectoryServer::new(AsyncChannel::with_disp(conn, spawner.dispatch()));
+                let task = handle(self, spawner.clone(), tokens, server, options);
+                spawner.spawn(task);
+                Ok(false)
+            }
+        }
+    }
+
+    #[inline]
+    fn metadata(&self) -> Result<Metadata, Error> {
+        Ok(self.inode.metadata(&self.fs))
+    }
+
+    #[inline]
+    fn statfs(&self) -> Result<FsStat, Error> {
+        Ok(self.fs.statfs())
+    }
+}
+
+#[async_trait]
+impl Directory for Ext2Dir {
+    async fn next_dirent(&self, last: Option<String>) -> Result<DirEntry, Error> {
+        let mut found = last.is_none();
+        let next = self.fs.volume.dirents(&self.inode, |name, ino| {
+            if !found {
+                found = last.as_deref() == Some(name);
+                return Ok(None);
+            }
+            Ok(self.fs.node(ino)?.map(|entry| (String::from(name), entry)))
+        })?;
+        match next {
+            Some((name, entry)) => {
+                let metadata = entry.metadata()?;
+                Ok(DirEntry { name, metadata })
+            }
+            None => Err(Error::IterEnd),
+        }
+    }
+
+    async fn readlink(&self, name: &str) -> Result<PathBuf, Error> {
+        let entry = self.get(name)?;
+        match entry.clone().into_any().downcast::<Ext2Symlink>() {
+            Ok(symlink) => symlink.target(),
+            Err(_) => Err(Error::InvalidType(entry.metadata()?.file_type)),
+        }
+    }
+}
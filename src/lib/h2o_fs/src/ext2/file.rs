@@ -0,0 +1,124 @@
+use alloc::boxed::Box;
+
+use async_trait::async_trait;
+use solvent::prelude::{Channel, Phys};
+use solvent_async::{disp::DispSender, io::Stream, ipc::Channel as AsyncChannel};
+use solvent_core::{io::RawStream, path::Path, sync::Arsc};
+use solvent_rpc::io::{
+    file::{FileServer, PhysOptions},
+    Error, FileType, FsStat, Metadata, OpenOptions, Permission,
+};
+
+use super::{inode::Inode, Ext2Fs};
+use crate::{
+    dir::EventTokens,
+    entry::Entry,
+    file::{handle, File},
+    spawn::Spawner,
+};
+
+pub struct Ext2File {
+    fs: Arsc<Ext2Fs>,
+    inode: Inode,
+}
+
+impl Ext2File {
+    pub(super) fn new(fs: Arsc<Ext2Fs>, inode: Inode) -> Self {
+        Ext2File { fs, inode }
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.fs.locked.lock().contains(&self.inode.ino)
+    }
+}
+
+impl Entry for Ext2File {
+    fn open(
+        self: Arsc<Self>,
+        spawner: Spawner,
+        tokens: EventTokens,
+        path: &Path,
+        options: OpenOptions,
+        conn: Channel,
+    ) -> Result<bool, Error> {
+        if path != Path::new("")
+            || options.intersects(OpenOptions::EXPECT_DIR | OpenOptions::EXPECT_RPC)
+        {
+            return Err(Error::InvalidType(FileType::File));
+        }
+        if self.is_locked() {
+            return Err(Error::WouldBlock);
+        }
+        let require = options.require();
+        let perm = self.metadata()?.perm;
+        if !perm.contains(require) {
+            return Err(Error::PermissionDenied(require - perm));
+        }
+        let server = FileServer::new(AsyncChannel::with_disp(conn, spawner.dispatch()));
+        let task = handle(self, spawner.clone(), tokens, 0, server, options);
+        spawner.spawn(task);
+        Ok(false)
+    }
+
+    #[inline]
+    fn metadata(&self) -> Result<Metadata, Error> {
+        Ok(self.inode.metadata(&self.fs))
+    }
+
+    #[inline]
+    fn statfs(&self) -> Result<FsStat, Error> {
+        Ok(self.fs.statfs())
+    }
+}
+
+#[async_trait]
+impl File for Ext2File {
+    async fn lock(&self, stream: Option<(RawStream, DispSender)>) -> Result<Option<Stream>, Error> {
+        if !self.fs.locked.lock().insert(self.inode.ino) {
+            Err(Error::WouldBlock)
+        } else {
+            // SAFETY: The exclusiveness is ensured.
+            Ok(stream.map(|(raw, disp)| unsafe { Stream::with_disp(raw, disp) }))
+        }
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) -> Result<(), Error> {
+        self.fs.locked.lock().remove(&self.inode.ino);
+        Ok(())
+    }
+
+    /// The file is never changed, so nothing is written back.
+    #[inline]
+    async fn flush(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    #[inline]
+    async fn read_at(&self, pos: usize, buf: &mut [u8]) -> Result<usize, Error> {
+        self.fs.pages.read(self.inode.ino.into(), pos, buf)
+    }
+
+    #[inline]
+    async fn write_at(&self, _: usize, _: &[u8]) -> Result<usize, Error> {
+        Err(Error::PermissionDenied(Permission::WRITE))
+    }
+
+    #[inline]
+    async fn len(&self) -> Result<usize, Error> {
+        Ok(self.inode.size as usize)
+    }
+
+    #[inline]
+    async fn resize(&self, _: usize) -> Result<(), Error> {
+        Err(Error::PermissionDenied(Permission::WRITE))
+    }
+
+    async fn phys(&self, options: PhysOptions) -> Result<Phys, Error> {
+        if self.is_locked() {
+            return Err(Error::WouldBlock);
+        }
+        self.fs.pages.phys(self.inode.ino.into(), options)
+    }
+}
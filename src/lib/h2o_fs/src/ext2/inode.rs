@@ -0,0 +1,185 @@
+use alloc::vec;
+
+use solvent_rpc::io::{Error, FileType, Metadata};
+
+use super::{invalid, le16, le32, permission, Ext2Fs, Volume};
+
+const S_IFMT: u16 = 0o170000;
+const S_IFDIR: u16 = 0o040000;
+const S_IFREG: u16 = 0o100000;
+const S_IFLNK: u16 = 0o120000;
+
+const EXTENTS_FL: u32 = 0x80000;
+const INLINE_DATA_FL: u32 = 0x10000000;
+
+const EXTENT_MAGIC: u16 = 0xf30a;
+/// Extents longer than this are preallocated but not initialized yet.
+const EXTENT_INIT_MAX: u16 = 32768;
+
+const DIRECT_BLOCKS: u64 = 12;
+
+/// The fields of an on-disk inode used by the driver.
+pub(super) struct Inode {
+    pub ino: u32,
+    pub mode: u16,
+    pub size: u64,
+    pub links: u16,
+    /// The number of 512-byte sectors allocated.
+    pub sectors: u64,
+    /// The block of the extended attributes, if any.
+    pub file_acl: u32,
+    pub flags: u32,
+    /// The block map or the root of the extent tree, or the target of fast
+    /// symbolic links.
+    pub block: [u8; 60],
+}
+
+impl Inode {
+    pub fn parse(ino: u32, raw: &[u8; 128]) -> Self {
+        let mode = le16(raw, 0x0);
+        let mut size = u64::from(le32(raw, 0x4));
+        // The high bits are reused as the ACL of directories in old revisions.
+        if mode & S_IFMT == S_IFREG {
+            size |= u64::from(le32(raw, 0x6c)) << 32;
+        }
+        Inode {
+            ino,
+            mode,
+            size,
+            links: le16(raw, 0x1a),
+            sectors: u64::from(le32(raw, 0x1c)) | u64::from(le16(raw, 0x74)) << 32,
+            file_acl: le32(raw, 0x68),
+            flags: le32(raw, 0x20),
+            block: raw[0x28..0x64].try_into().unwrap(),
+        }
+    }
+
+    pub fn file_type(&self) -> Option<FileType> {
+        match self.mode & S_IFMT {
+            S_IFREG => Some(FileType::File),
+            S_IFDIR => Some(FileType::Directory),
+            S_IFLNK => Some(FileType::Symlink),
+            _ => None,
+        }
+    }
+
+    pub fn metadata(&self, fs: &Ext2Fs) -> Metadata {
+        let perm = fs.perm & permission(self.mode);
+        let file_type = self.file_type().unwrap_or(FileType::File);
+        Metadata {
+            node_id: u64::from(self.ino),
+            link_count: usize::from(self.links),
+            allocated: (self.sectors * 512) as usize,
+            ..Metadata::new(file_type, perm, self.size as usize)
+        }
+    }
+
+    /// Whether the target of the symbolic link is stored in the inode itself.
+    pub fn is_fast_symlink(&self, vol: &Volume) -> bool {
+        let xattr = if self.file_acl != 0 {
+            (vol.block_size / 512) as u64
+        } else {
+            0
+        };
+        self.sectors == xattr && self.size < self.block.len() as u64
+    }
+
+    /// Get the device block storing the logical block `block` of the content,
+    /// or `None` if it's a hole.
+    pub fn map(&self, vol: &Volume, block: u64) -> Result<Option<u64>, Error> {
+        if self.flags & INLINE_DATA_FL != 0 {
+            return Err(Error::InvalidData("unsupported ext4 inline data".into()));
+        }
+        if self.flags & EXTENTS_FL != 0 {
+            self.map_extent(vol, block)
+        } else {
+            self.map_indirect(vol, block)
+        }
+    }
+
+    fn map_indirect(&self, vol: &Volume, mut block: u64) -> Result<Option<u64>, Error> {
+        if block < DIRECT_BLOCKS {
+            return Ok(nonzero(le32(&self.block, block as usize * 4)));
+        }
+        block -= DIRECT_BLOCKS;
+
+        // Find the level of indirection and the index in the tree.
+        let per_block = (vol.block_size / 4) as u64;
+        let mut span = 1;
+        for level in 0..3 {
+            span *= per_block;
+            if block >= span {
+                block -= span;
+                continue;
+            }
+            let slot = DIRECT_BLOCKS as usize + level;
+            let mut next = le32(&self.block, slot * 4);
+            for _ in 0..=level {
+                if next == 0 {
+                    return Ok(None);
+                }
+                span /= per_block;
+                let index = block / span;
+                block %= span;
+
+                let mut raw = [0; 4];
+                let offset = u64::from(next) * vol.block_size as u64 + index * 4;
+                vol.cache.read(offset, &mut raw)?;
+                next = u32::from_le_bytes(raw);
+            }
+            return Ok(nonzero(next));
+        }
+        Err(invalid("block map"))
+    }
+
+    fn map_extent(&self, vol: &Volume, block: u64) -> Result<Option<u64>, Error> {
+        let mut node = vec![0; vol.block_size];
+        node[..self.block.len()].copy_from_slice(&self.block);
+        // The root is stored in the inode, and the others in whole blocks.
+        let mut node_len = self.block.len();
+        // The depth of the tree is bounded by the format.
+        for _ in 0..=5 {
+            let (count, depth) = (usize::from(le16(&node, 2)), le16(&node, 6));
+            if le16(&node, 0) != EXTENT_MAGIC || 12 * (count + 1) > node_len {
+                return Err(invalid("extent tree"));
+            }
+            let entries = (1..=count).map(|index| &node[12 * index..][..12]);
+
+            if depth == 0 {
+                for entry in entries {
+                    let (first, len) = (u64::from(le32(entry, 0)), le16(entry, 4));
+                    let uninit = len > EXTENT_INIT_MAX;
+                    let len = u64::from(if uninit { len - EXTENT_INIT_MAX } else { len });
+                    if (first..first + len).contains(&block) {
+                        if uninit {
+                            return Ok(None);
+                        }
+                        let start = u64::from(le16(entry, 6)) << 32 | u64::from(le32(entry, 8));
+                        return Ok(Some(start + block - first));
+                    }
+                }
+                return Ok(None);
+            }
+
+            // The index entries are sorted by the first logical blocks they
+            // cover.
+            let child = entries
+                .take_while(|entry| u64::from(le32(entry, 0)) <= block)
+                .last()
+                .map(|entry| u64::from(le16(entry, 8)) << 32 | u64::from(le32(entry, 4)));
+            match child {
+                Some(child) => {
+                    vol.read_block(child, &mut node)?;
+                    node_len = node.len();
+                }
+                None => return Ok(None),
+            }
+        }
+        Err(invalid("extent tree"))
+    }
+}
+
+#[inline]
+fn nonzero(block: u32) -> Option<u64> {
+    (block != 0).then_some(u64::from(block))
+}
@@ -0,0 +1,73 @@
+use alloc::{string::String, vec};
+
+use solvent::prelude::Channel;
+use solvent_core::{
+    path::{Path, PathBuf},
+    sync::Arsc,
+};
+use solvent_rpc::io::{Error, FileType, FsStat, Metadata, OpenOptions};
+
+use super::{inode::Inode, invalid, Ext2Fs};
+use crate::{dir::EventTokens, entry::Entry, spawn::Spawner};
+
+pub struct Ext2Symlink {
+    fs: Arsc<Ext2Fs>,
+    inode: Inode,
+}
+
+impl Ext2Symlink {
+    pub(super) fn new(fs: Arsc<Ext2Fs>, inode: Inode) -> Self {
+        Ext2Symlink { fs, inode }
+    }
+
+    pub fn target(&self) -> Result<PathBuf, Error> {
+        // The target is stored in at most one block.
+        if self.inode.size > self.fs.volume.block_size as u64 {
+            return Err(invalid("symlink"));
+        }
+        let len = self.inode.size as usize;
+        let target = if self.inode.is_fast_symlink(&self.fs.volume) {
+            self.inode.block[..len].to_vec()
+        } else {
+            let mut buf = vec![0; len];
+            self.fs.volume.read(&self.inode, 0, &mut buf)?;
+            buf
+        };
+        let target = String::from_utf8(target).map_err(|_| invalid("symlink"))?;
+        Ok(PathBuf::from(target))
+    }
+}
+
+impl Entry for Ext2Symlink {
+    /// Redirect the connection to the target, which is left to the directories
+    /// above to resolve.
+    fn open(
+        self: Arsc<Self>,
+        _: Spawner,
+        _: EventTokens,
+        path: &Path,
+        options: OpenOptions,
+        conn: Channel,
+    ) -> Result<bool, Error> {
+        if path == Path::new("") && options.contains(OpenOptions::NOFOLLOW) {
+            return Err(Error::InvalidType(FileType::Symlink));
+        }
+        let target = self.target()?;
+        let target = if target.is_absolute() {
+            target.join(path)
+        } else {
+            Path::new("..").join(&target).join(path)
+        };
+        Err(Error::Symlink { path: target, conn })
+    }
+
+    #[inline]
+    fn metadata(&self) -> Result<Metadata, Error> {
+        Ok(self.inode.metadata(&self.fs))
+    }
+
+    #[inline]
+    fn statfs(&self) -> Result<FsStat, Error> {
+        Ok(self.fs.statfs())
+    }
+}
@@ -1,7 +1,8 @@
 mod handle;
+mod lock;
 mod stream;
 
-use alloc::boxed::Box;
+use alloc::{boxed::Box, vec};
 
 use async_trait::async_trait;
 use solvent::prelude::Phys;
@@ -11,6 +12,7 @@ use solvent_rpc::io::{file::PhysOptions, Error};
 
 pub use self::{
     handle::{handle, handle_mapped},
+    lock::RangeLocks,
     stream::FileStream,
 };
 use crate::entry::Entry;
@@ -28,6 +30,12 @@ pub trait File: Entry {
     /// The caller must ensure the file is locked before calling this function.
     unsafe fn unlock(&self) -> Result<(), Error>;
 
+    /// The byte-range locks of the file, if supported.
+    #[inline]
+    fn range_locks(&self) -> Option<&RangeLocks> {
+        None
+    }
+
     async fn flush(&self) -> Result<(), Error>;
 
     async fn read_at(&self, pos: usize, buf: &mut [u8]) -> Result<usize, Error>;
@@ -44,4 +52,60 @@ pub trait File: Entry {
     async fn resize(&self, new_len: usize) -> Result<(), Error>;
 
     async fn phys(&self, options: PhysOptions) -> Result<Phys, Error>;
+
+    /// Read at most `len` bytes at `pos` into `phys` at `offset`.
+    ///
+    /// The contents are bounced through a buffer by default, so files backed
+    /// by physical memory objects should copy them directly instead.
+    async fn read_into(
+        &self,
+        pos: usize,
+        phys: &Phys,
+        offset: usize,
+        len: usize,
+    ) -> Result<usize, Error> {
+        let mut buf = vec![0; len.min(BOUNCE_SIZE)];
+        let mut read = 0;
+        while read < len {
+            let chunk = (len - read).min(buf.len());
+            let read_len = self.read_at(pos + read, &mut buf[..chunk]).await?;
+            // SAFETY: The client shares the object for this transfer only.
+            unsafe { phys.write(offset + read, &buf[..read_len]) }.map_err(Error::Other)?;
+            read += read_len;
+            if read_len < chunk {
+                break;
+            }
+        }
+        Ok(read)
+    }
+
+    /// Write at most `len` bytes of `phys` at `offset` to `pos`.
+    ///
+    /// See [`File::read_into`] for more information.
+    async fn write_from(
+        &self,
+        pos: usize,
+        phys: &Phys,
+        offset: usize,
+        len: usize,
+    ) -> Result<usize, Error> {
+        let mut buf = vec![0; len.min(BOUNCE_SIZE)];
+        let mut written = 0;
+        while written < len {
+            let chunk = (len - written).min(buf.len());
+            let chunk = phys
+                .read_into(offset + written, &mut buf[..chunk])
+                .map_err(Error::Other)?;
+            let written_len = self.write_at(pos + written, &buf[..chunk]).await?;
+            written += written_len;
+            if written_len < chunk {
+                break;
+            }
+        }
+        Ok(written)
+    }
 }
+
+/// The maximum size of the buffer through which the contents are copied
+/// between files and physical memory objects.
+const BOUNCE_SIZE: usize = 64 * 1024;
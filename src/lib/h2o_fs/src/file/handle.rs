@@ -1,12 +1,20 @@
 use alloc::vec;
 
-use futures_lite::StreamExt;
+use futures_lite::{future, StreamExt};
 use rpc::FileRequest;
+use solvent::time::Instant;
 use solvent_async::io::Stream;
-use solvent_core::{path::Path, sync::Arsc};
+use solvent_core::{
+    path::{Path, PathBuf},
+    sync::Arsc,
+};
 use solvent_rpc::{
-    io::{file as rpc, Error, OpenOptions, Permission},
-    Server,
+    io::{
+        dir::WatchEvent,
+        file::{self as rpc, LockKind},
+        Error, OpenOptions, Permission, SetMask, SetMetadata,
+    },
+    Cancelled, Server,
 };
 
 use super::{stream::*, File};
@@ -21,7 +29,7 @@ pub async fn handle<F: File>(
     server: rpc::FileServer,
     options: OpenOptions,
 ) {
-    let (requests, _) = server.serve();
+    let (requests, _) = server.with_limits(spawner.limits()).serve();
     let direct = DirectFile::new(file, seeker);
     handle_impl(direct, spawner, tokens, requests, options).await
 }
@@ -35,12 +43,41 @@ pub async fn handle_mapped<F: File>(
     server: rpc::FileServer,
     options: OpenOptions,
 ) {
-    let (requests, event) = server.serve();
+    let (requests, event) = server.with_limits(spawner.limits()).serve();
     let stream = StreamFile::new(file, cache, event);
     handle_impl(stream, spawner, tokens, requests, options).await
 }
 
-async fn handle_impl<S: StreamIo>(
+/// Check whether the client has given up waiting for the reply, so that the
+/// work can be skipped.
+async fn abandoned(deadline: Option<Instant>, cancelled: Cancelled) -> bool {
+    deadline.map_or(false, |deadline| deadline <= Instant::now())
+        || future::poll_once(cancelled).await.is_some()
+}
+
+/// Update the timestamps of the file if they're maintained in memory, and
+/// notify the watchers of the modification.
+fn touch<S: StreamIo>(file: &S, modified: bool) {
+    if let Some(attrs) = file.as_file().attrs() {
+        attrs.touch(modified);
+        if modified {
+            attrs.watch().notify(WatchEvent::Modify(PathBuf::new()));
+        }
+    }
+}
+
+/// Set the length through the stream, which may cache the content of the
+/// file, and the other attributes directly.
+async fn set_metadata<S: StreamIo>(file: &mut S, mut attr: SetMetadata) -> Result<(), Error> {
+    if attr.mask.contains(SetMask::LEN) {
+        file.resize(attr.len).await?;
+        attr.mask.remove(SetMask::LEN);
+        touch(file, true);
+    }
+    file.as_file().set_metadata(attr)
+}
+
+async fn handle_impl<S: StreamIo + Send>(
     mut file: S,
     spawner: Spawner,
     tokens: EventTokens,
@@ -81,6 +118,34 @@ async fn handle_impl<S: StreamIo>(
                 let res = file.lock(spawner.dispatch()).await;
                 res.map(|stream| stream.map(Stream::into_raw).ok_or(()))
             }),
+            FileRequest::LockRange {
+                kind,
+                offset,
+                len,
+                wait,
+                responder,
+            } => {
+                let require = match kind {
+                    LockKind::Shared => Permission::READ,
+                    LockKind::Exclusive => Permission::WRITE,
+                };
+                if !options.require().contains(require) {
+                    responder.send(Err(Error::PermissionDenied(require)))
+                } else {
+                    // Stop waiting if the client gives up, which also happens
+                    // when the connection is closed.
+                    let cancelled = responder.cancelled();
+                    let lock = async { Some(file.lock_range(kind, offset, len, wait).await) };
+                    let cancel = async {
+                        cancelled.await;
+                        None
+                    };
+                    match future::or(lock, cancel).await {
+                        Some(res) => responder.send(res),
+                        None => continue,
+                    }
+                }
+            }
             FileRequest::Metadata { responder } => responder.send(file.as_file().metadata()),
             FileRequest::Open {
                 path,
@@ -94,47 +159,68 @@ async fn handle_impl<S: StreamIo>(
                         .map(drop),
                 )
             }
-            FileRequest::Read { len, responder } => responder.send({
-                if !options.contains(OpenOptions::READ) {
-                    Err(Error::PermissionDenied(Permission::READ))
-                } else {
-                    let mut buf = vec![0; len];
-                    let res = file.read(&mut buf).await;
-                    res.map(|len| {
-                        buf.truncate(len);
-                        buf
-                    })
+            FileRequest::Read { len, responder } => {
+                if abandoned(responder.deadline(), responder.cancelled()).await {
+                    continue;
                 }
-            }),
+                responder.send({
+                    if !options.contains(OpenOptions::READ) {
+                        Err(Error::PermissionDenied(Permission::READ))
+                    } else {
+                        let mut buf = vec![0; len];
+                        let res = file.read(&mut buf).await;
+                        touch(&file, false);
+                        res.map(|len| {
+                            buf.truncate(len);
+                            buf
+                        })
+                    }
+                })
+            }
             FileRequest::ReadAt {
                 offset,
                 len,
                 responder,
-            } => responder.send({
-                if !options.contains(OpenOptions::READ) {
-                    Err(Error::PermissionDenied(Permission::READ))
-                } else {
-                    let mut buf = vec![0; len];
-                    let res = file.read_at(offset, &mut buf).await;
-                    res.map(|len| {
-                        buf.truncate(len);
-                        buf
-                    })
+            } => {
+                if abandoned(responder.deadline(), responder.cancelled()).await {
+                    continue;
                 }
-            }),
+                responder.send({
+                    if !options.contains(OpenOptions::READ) {
+                        Err(Error::PermissionDenied(Permission::READ))
+                    } else {
+                        let mut buf = vec![0; len];
+                        let res = file.read_at(offset, &mut buf).await;
+                        touch(&file, false);
+                        res.map(|len| {
+                            buf.truncate(len);
+                            buf
+                        })
+                    }
+                })
+            }
             FileRequest::Resize { new_len, responder } => {
                 responder.send(if !options.contains(OpenOptions::WRITE) {
                     Err(Error::PermissionDenied(Permission::WRITE))
                 } else {
-                    file.resize(new_len).await
+                    let res = file.resize(new_len).await;
+                    res.inspect(|_| touch(&file, true))
                 })
             }
             FileRequest::Seek { pos, responder } => responder.send(file.seek(pos).await),
+            FileRequest::SetMetadata { attr, responder } => {
+                responder.send(if !options.contains(OpenOptions::WRITE) {
+                    Err(Error::PermissionDenied(Permission::WRITE))
+                } else {
+                    set_metadata(&mut file, attr).await
+                })
+            }
             FileRequest::Write { buf, responder } => {
                 responder.send(if !options.contains(OpenOptions::WRITE) {
                     Err(Error::PermissionDenied(Permission::WRITE))
                 } else {
-                    file.write(&buf).await
+                    let res = file.write(&buf).await;
+                    res.inspect(|_| touch(&file, true))
                 })
             }
             FileRequest::WriteAt {
@@ -144,13 +230,48 @@ async fn handle_impl<S: StreamIo>(
             } => responder.send(if !options.contains(OpenOptions::WRITE) {
                 Err(Error::PermissionDenied(Permission::WRITE))
             } else {
-                file.write_at(offset, &buf).await
+                let res = file.write_at(offset, &buf).await;
+                res.inspect(|_| touch(&file, true))
             }),
-            FileRequest::Unknown(_) => {
+            FileRequest::UnlockRange {
+                offset,
+                len,
+                responder,
+            } => responder.send(file.unlock_range(offset, len)),
+            FileRequest::Statfs { responder } => responder.send(file.as_file().statfs()),
+            FileRequest::Unknown(req) => {
                 log::warn!("file RPC received unknown request");
-                break;
+                req.unsupported()
             }
             FileRequest::Phys { options, responder } => responder.send(file.phys(options).await),
+            FileRequest::ReadInto {
+                phys,
+                offset,
+                len,
+                responder,
+            } => {
+                if abandoned(responder.deadline(), responder.cancelled()).await {
+                    continue;
+                }
+                responder.send(if !options.contains(OpenOptions::READ) {
+                    Err(Error::PermissionDenied(Permission::READ))
+                } else {
+                    let res = file.read_into(&phys, offset, len).await;
+                    touch(&file, false);
+                    res
+                })
+            }
+            FileRequest::WriteFrom {
+                phys,
+                offset,
+                len,
+                responder,
+            } => responder.send(if !options.contains(OpenOptions::WRITE) {
+                Err(Error::PermissionDenied(Permission::WRITE))
+            } else {
+                let res = file.write_from(&phys, offset, len).await;
+                res.inspect(|_| touch(&file, true))
+            }),
         };
 
         if let Err(err) = res {
@@ -0,0 +1,210 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
+use core::{
+    mem,
+    sync::atomic::{AtomicUsize, Ordering::Relaxed},
+    task::{Poll, Waker},
+};
+
+use futures_lite::future;
+use solvent_core::sync::Mutex;
+use solvent_rpc::io::{file::LockKind, Error};
+
+/// Allocate the identifier of a new lock owner, usually a connection.
+pub fn new_owner() -> usize {
+    static NEXT: AtomicUsize = AtomicUsize::new(1);
+    NEXT.fetch_add(1, Relaxed)
+}
+
+/// The advisory byte-range locks of a file, shared by all its connections.
+///
+/// Blocked owners wait for each other in a graph, and a wait that would close
+/// a cycle fails with [`Error::Deadlock`] instead of hanging forever.
+pub struct RangeLocks {
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    held: Vec<Held>,
+    /// The owners blocking each waiting owner, and the waker of the latter.
+    waiting: BTreeMap<usize, (Vec<usize>, Waker)>,
+}
+
+#[derive(Clone, Copy)]
+struct Held {
+    owner: usize,
+    kind: LockKind,
+    start: usize,
+    end: usize,
+}
+
+impl Inner {
+    /// Lock `start..end` for `owner`, or return the owners holding the
+    /// conflicting locks.
+    fn acquire(
+        &mut self,
+        owner: usize,
+        kind: LockKind,
+        start: usize,
+        end: usize,
+    ) -> Result<(), Vec<usize>> {
+        let conflicts = self.held.iter().filter(|held| {
+            held.owner != owner
+                && held.start < end
+                && start < held.end
+                && (kind == LockKind::Exclusive || held.kind == LockKind::Exclusive)
+        });
+        let mut conflicts = conflicts.map(|held| held.owner).collect::<Vec<_>>();
+        if !conflicts.is_empty() {
+            conflicts.sort_unstable();
+            conflicts.dedup();
+            return Err(conflicts);
+        }
+        self.remove(owner, start, end);
+        self.held.push(Held {
+            owner,
+            kind,
+            start,
+            end,
+        });
+        Ok(())
+    }
+
+    /// Cut `start..end` out of the locks held by `owner`.
+    fn remove(&mut self, owner: usize, start: usize, end: usize) {
+        for held in mem::take(&mut self.held) {
+            if held.owner != owner || held.end <= start || end <= held.start {
+                self.held.push(held);
+                continue;
+            }
+            if held.start < start {
+                self.held.push(Held { end: start, ..held });
+            }
+            if end < held.end {
+                self.held.push(Held { start: end, ..held });
+            }
+        }
+    }
+
+    /// Check whether any of `blockers` waits for `owner`, directly or not.
+    fn waits_for(&self, blockers: &[usize], owner: usize) -> bool {
+        let mut stack = blockers.to_vec();
+        let mut visited = BTreeSet::new();
+        while let Some(blocker) = stack.pop() {
+            if blocker == owner {
+                return true;
+            }
+            if visited.insert(blocker) {
+                if let Some((next, _)) = self.waiting.get(&blocker) {
+                    stack.extend_from_slice(next);
+                }
+            }
+        }
+        false
+    }
+
+    fn wake_all(&self) {
+        for (_, waker) in self.waiting.values() {
+            waker.wake_by_ref();
+        }
+    }
+}
+
+impl RangeLocks {
+    #[inline]
+    pub fn new() -> Self {
+        RangeLocks {
+            inner: Mutex::new(Inner {
+                held: Vec::new(),
+                waiting: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// Lock `start..end` for `owner` if no other owner holds a conflicting
+    /// lock.
+    pub fn try_lock(
+        &self,
+        owner: usize,
+        kind: LockKind,
+        start: usize,
+        end: usize,
+    ) -> Result<(), Error> {
+        let mut inner = self.inner.lock();
+        inner
+            .acquire(owner, kind, start, end)
+            .map_err(|_| Error::WouldBlock)?;
+        // Downgrading the locks of `owner` may unblock the others.
+        inner.wake_all();
+        Ok(())
+    }
+
+    /// Wait until `start..end` is locked for `owner`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`Error::Deadlock`] if the owners holding the
+    /// conflicting locks are waiting for `owner`.
+    pub async fn lock(
+        &self,
+        owner: usize,
+        kind: LockKind,
+        start: usize,
+        end: usize,
+    ) -> Result<(), Error> {
+        let _waiting = Waiting { locks: self, owner };
+        future::poll_fn(|cx| {
+            let mut inner = self.inner.lock();
+            match inner.acquire(owner, kind, start, end) {
+                Ok(()) => {
+                    inner.wake_all();
+                    Poll::Ready(Ok(()))
+                }
+                Err(blockers) if inner.waits_for(&blockers, owner) => {
+                    Poll::Ready(Err(Error::Deadlock))
+                }
+                Err(blockers) => {
+                    inner.waiting.insert(owner, (blockers, cx.waker().clone()));
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+
+    /// Release the locks held by `owner` over `start..end`.
+    pub fn unlock(&self, owner: usize, start: usize, end: usize) {
+        let mut inner = self.inner.lock();
+        inner.remove(owner, start, end);
+        inner.wake_all();
+    }
+
+    /// Release all the locks held by `owner`.
+    pub fn release(&self, owner: usize) {
+        let mut inner = self.inner.lock();
+        inner.held.retain(|held| held.owner != owner);
+        inner.wake_all();
+    }
+}
+
+impl Default for RangeLocks {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Removes the owner from the wait graph once it stops waiting, whether the
+/// lock is acquired or the wait is cancelled.
+struct Waiting<'a> {
+    locks: &'a RangeLocks,
+    owner: usize,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.locks.inner.lock().waiting.remove(&self.owner);
+    }
+}
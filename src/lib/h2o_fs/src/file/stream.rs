@@ -2,18 +2,27 @@ use alloc::boxed::Box;
 use core::ops::Deref;
 
 use async_trait::async_trait;
-use solvent::prelude::{Channel, Phys};
+use solvent::{
+    error::ERANGE,
+    prelude::{Channel, Phys},
+};
 use solvent_async::{disp::DispSender, io::Stream};
-use solvent_core::{io::SeekFrom, sync::Arsc};
+use solvent_core::{
+    io::{RawStream, SeekFrom},
+    sync::Arsc,
+};
 use solvent_rpc::{
     io::{
-        file::{EventFlags, FileEventSender, PhysOptions},
+        file::{self as rpc, EventFlags, FileEventSender, LockKind, PhysOptions},
         Error,
     },
-    EventSender,
+    Error as RpcError, EventSender,
 };
 
-use super::File;
+use super::{
+    lock::{new_owner, RangeLocks},
+    File,
+};
 
 pub struct FileStream {
     _conn: Channel,
@@ -48,6 +57,9 @@ pub trait StreamIo {
 
     fn as_file(&self) -> &Arsc<Self::File>;
 
+    /// The identifier of the connection as the owner of byte-range locks.
+    fn lock_owner(&self) -> usize;
+
     async fn lock(&mut self, disp: DispSender) -> Result<Option<Stream>, Error>;
 
     async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
@@ -63,11 +75,96 @@ pub trait StreamIo {
     async fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error>;
 
     async fn phys(&self, options: PhysOptions) -> Result<Phys, Error>;
+
+    /// Read from the current position into `phys` at `offset`.
+    async fn read_into(&mut self, phys: &Phys, offset: usize, len: usize) -> Result<usize, Error>;
+
+    /// Write `phys` at `offset` to the current position.
+    async fn write_from(&mut self, phys: &Phys, offset: usize, len: usize) -> Result<usize, Error>;
+
+    /// Lock `len` bytes from `offset` for the connection, or to the end of the
+    /// file if `len` is 0.
+    async fn lock_range(
+        &mut self,
+        kind: LockKind,
+        offset: usize,
+        len: usize,
+        wait: bool,
+    ) -> Result<(), Error> {
+        let owner = self.lock_owner();
+        let locks = range_locks(&**self.as_file(), rpc::file::LOCK_RANGE)?;
+        let (start, end) = lock_range(offset, len);
+        if wait {
+            locks.lock(owner, kind, start, end).await
+        } else {
+            locks.try_lock(owner, kind, start, end)
+        }
+    }
+
+    /// Unlock `len` bytes from `offset` for the connection, or to the end of
+    /// the file if `len` is 0.
+    fn unlock_range(&mut self, offset: usize, len: usize) -> Result<(), Error> {
+        let locks = range_locks(&**self.as_file(), rpc::file::UNLOCK_RANGE)?;
+        let (start, end) = lock_range(offset, len);
+        locks.unlock(self.lock_owner(), start, end);
+        Ok(())
+    }
+}
+
+fn range_locks<F: File>(file: &F, method: usize) -> Result<&RangeLocks, Error> {
+    let locks = file.range_locks();
+    locks.ok_or(Error::RpcError(RpcError::UnsupportedMethod(method)))
+}
+
+#[inline]
+fn lock_range(offset: usize, len: usize) -> (usize, usize) {
+    match len {
+        0 => (offset, usize::MAX),
+        len => (offset, offset.saturating_add(len)),
+    }
+}
+
+/// Take the exclusive lock over the whole file before locking the file
+/// itself, so that connections locking both always do it in the same order.
+///
+/// `stream` is only taken once the range is locked.
+async fn lock_whole<F: File>(
+    file: &F,
+    owner: usize,
+    stream: impl FnOnce() -> Option<(RawStream, DispSender)> + Send,
+) -> Result<Option<Stream>, Error> {
+    let locks = file.range_locks();
+    if let Some(locks) = locks {
+        locks.lock(owner, LockKind::Exclusive, 0, usize::MAX).await?;
+    }
+    let res = file.lock(stream()).await;
+    if let (Err(_), Some(locks)) = (&res, locks) {
+        locks.unlock(owner, 0, usize::MAX);
+    }
+    res
+}
+
+/// Release the locks held by the dropped connection, the file lock first.
+fn release<F: File>(file: &F, owner: usize, locked: bool) {
+    if locked {
+        let _ = unsafe { file.unlock() };
+    }
+    if let Some(locks) = file.range_locks() {
+        locks.release(owner);
+    }
+}
+
+fn check_range(phys: &Phys, offset: usize, len: usize) -> Result<(), Error> {
+    match offset.checked_add(len) {
+        Some(end) if end <= phys.len() => Ok(()),
+        _ => Err(Error::Other(ERANGE)),
+    }
 }
 
 pub struct DirectFile<F: File> {
     inner: Arsc<F>,
     seeker: usize,
+    owner: usize,
     locked: bool,
 }
 
@@ -76,6 +173,7 @@ impl<F: File> DirectFile<F> {
         DirectFile {
             inner: file,
             seeker,
+            owner: new_owner(),
             locked: false,
         }
     }
@@ -89,11 +187,16 @@ impl<F: File> StreamIo for DirectFile<F> {
         &self.inner
     }
 
+    #[inline]
+    fn lock_owner(&self) -> usize {
+        self.owner
+    }
+
     async fn lock(&mut self, _: DispSender) -> Result<Option<Stream>, Error> {
         if self.locked {
             return Ok(None);
         }
-        let res = self.inner.lock(None).await?;
+        let res = lock_whole(&*self.inner, self.owner, || None).await?;
         self.locked = true;
         Ok(res)
     }
@@ -149,13 +252,28 @@ impl<F: File> StreamIo for DirectFile<F> {
     async fn phys(&self, options: PhysOptions) -> Result<Phys, Error> {
         self.inner.phys(options).await
     }
+
+    async fn read_into(&mut self, phys: &Phys, offset: usize, len: usize) -> Result<usize, Error> {
+        check_range(phys, offset, len)?;
+        let read_len = self.inner.read_into(self.seeker, phys, offset, len).await?;
+        self.seeker += read_len;
+        Ok(read_len)
+    }
+
+    async fn write_from(&mut self, phys: &Phys, offset: usize, len: usize) -> Result<usize, Error> {
+        check_range(phys, offset, len)?;
+        let written_len = self
+            .inner
+            .write_from(self.seeker, phys, offset, len)
+            .await?;
+        self.seeker += written_len;
+        Ok(written_len)
+    }
 }
 
 impl<F: File> Drop for DirectFile<F> {
     fn drop(&mut self) {
-        if self.locked {
-            let _ = unsafe { self.inner.unlock() };
-        }
+        release(&*self.inner, self.owner, self.locked);
     }
 }
 
@@ -163,6 +281,7 @@ pub struct StreamFile<F: File> {
     inner: Arsc<F>,
     raw: Option<Stream>,
     event: FileEventSender,
+    owner: usize,
     locked: bool,
 }
 
@@ -172,6 +291,7 @@ impl<F: File> StreamFile<F> {
             inner: file,
             raw: Some(raw),
             event,
+            owner: new_owner(),
             locked: false,
         }
     }
@@ -192,14 +312,18 @@ impl<F: File> StreamIo for StreamFile<F> {
         &self.inner
     }
 
+    #[inline]
+    fn lock_owner(&self) -> usize {
+        self.owner
+    }
+
     async fn lock(&mut self, disp: DispSender) -> Result<Option<Stream>, Error> {
         if self.locked {
             return Ok(None);
         }
-        let res = self
-            .inner
-            .lock(self.raw.take().map(|s| (Stream::into_raw(s), disp)))
-            .await?;
+        let raw = &mut self.raw;
+        let stream = || raw.take().map(|s| (Stream::into_raw(s), disp));
+        let res = lock_whole(&*self.inner, self.owner, stream).await?;
         self.locked = true;
         Ok(res)
     }
@@ -238,12 +362,24 @@ impl<F: File> StreamIo for StreamFile<F> {
     async fn phys(&self, options: PhysOptions) -> Result<Phys, Error> {
         self.inner.phys(options).await
     }
+
+    async fn read_into(&mut self, phys: &Phys, offset: usize, len: usize) -> Result<usize, Error> {
+        check_range(phys, offset, len)?;
+        let stream = self.stream()?;
+        // SAFETY: The client shares the object for this transfer only.
+        Ok(unsafe { stream.read_into_phys(phys, offset, len) }.await?)
+    }
+
+    async fn write_from(&mut self, phys: &Phys, offset: usize, len: usize) -> Result<usize, Error> {
+        check_range(phys, offset, len)?;
+        Ok(self.stream()?.write_from_phys(phys, offset, len).await?)
+    }
 }
 
 impl<F: File> Drop for StreamFile<F> {
     fn drop(&mut self) {
+        release(&*self.inner, self.owner, self.locked);
         if self.locked {
-            let _ = unsafe { self.inner.unlock() };
             let _ = self.event.send(EventFlags::UNLOCK);
         }
     }
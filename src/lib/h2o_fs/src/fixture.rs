@@ -0,0 +1,74 @@
+//! The storages shared by the tests of the file systems.
+
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use core::ops::Range;
+
+use solvent::{
+    error::EIO,
+    prelude::{Phys, PhysOptions as Flags},
+};
+use solvent_core::sync::{Arsc, Mutex};
+use solvent_rpc::io::Error;
+
+use crate::cache::Backing;
+
+/// Allocate a contiguous object holding `data`, such as an image of a file
+/// system.
+pub fn phys(data: &[u8]) -> Phys {
+    let phys = Phys::allocate(data.len(), Flags::ZEROED).unwrap();
+    // SAFETY: The object is not shared.
+    unsafe { phys.write(0, data) }.unwrap();
+    phys
+}
+
+/// A writable storage of files in memory, which records the writes.
+pub struct MemFiles {
+    pub files: Mutex<BTreeMap<u64, Vec<u8>>>,
+    pub writes: Mutex<Vec<(u64, Range<usize>)>>,
+    /// The position from which the reads fail.
+    pub fail_at: Mutex<Option<usize>>,
+}
+
+impl MemFiles {
+    /// Create the files of the lengths, filled with their numbers.
+    pub fn new(files: &[(u64, usize)]) -> Arsc<Self> {
+        let files = files
+            .iter()
+            .map(|&(file, len)| (file, vec![file as u8; len]));
+        Arsc::new(MemFiles {
+            files: Mutex::new(files.collect()),
+            writes: Mutex::new(Vec::new()),
+            fail_at: Mutex::new(None),
+        })
+    }
+}
+
+impl Backing for Arsc<MemFiles> {
+    fn len(&self, file: u64) -> Result<usize, Error> {
+        let files = self.files.lock();
+        files.get(&file).map(Vec::len).ok_or(Error::NotFound)
+    }
+
+    fn read_at(&self, file: u64, pos: usize, buf: &mut [u8]) -> Result<usize, Error> {
+        if *self.fail_at.lock() == Some(pos) {
+            return Err(Error::Other(EIO));
+        }
+        let files = self.files.lock();
+        let data = files.get(&file).ok_or(Error::NotFound)?;
+        let data = data.get(pos..).unwrap_or_default();
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        Ok(len)
+    }
+
+    fn write_at(&self, file: u64, pos: usize, buf: &[u8]) -> Result<(), Error> {
+        self.writes.lock().push((file, pos..pos + buf.len()));
+        let mut files = self.files.lock();
+        let data = files.entry(file).or_default();
+        if data.len() < pos + buf.len() {
+            data.resize(pos + buf.len(), 0);
+        }
+        data[pos..][..buf.len()].copy_from_slice(buf);
+        Ok(())
+    }
+}
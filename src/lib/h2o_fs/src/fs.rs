@@ -1,6 +1,11 @@
+#[cfg(feature = "runtime")]
+mod async_fs;
+mod overlay;
+
 use alloc::{
     collections::{btree_map, BTreeMap},
     string::{String, ToString},
+    vec::Vec,
 };
 use core::iter::Peekable;
 
@@ -9,18 +14,26 @@ use solvent_core::{
     path::{Component, Components, Path, PathBuf},
     sync::{Arsc, Mutex, MutexGuard},
 };
-use solvent_rpc::io::{
-    dir::{DirEntry, DirectorySyncClient},
-    entry::EntrySyncClient,
-    file::FileSyncClient,
-    Error, FileType, Metadata, OpenOptions, Permission,
+use solvent_rpc::{
+    io::{
+        dir::{DirEntry, DirectorySyncClient, WatchEvent, WatchMask},
+        entry::EntrySyncClient,
+        file::FileSyncClient,
+        Error, FileType, Metadata, OpenOptions, Permission,
+    },
+    sync::StreamReceiver,
 };
 
-use crate::dir::sync::RemoteIter;
+#[cfg(feature = "runtime")]
+pub use self::async_fs::{AsyncDirIter, AsyncLocalFs};
+use self::overlay::Overlay;
+pub use self::overlay::{OPAQUE, WHITEOUT_PREFIX};
+use crate::dir::{sync::RemoteIter, MAX_SYMLINKS};
 
 enum Node {
     Dir(Mutex<BTreeMap<String, Arsc<Node>>>),
     Remote(EntrySyncClient),
+    Overlay(Overlay),
 }
 
 impl Clone for Node {
@@ -28,6 +41,7 @@ impl Clone for Node {
         match self {
             Self::Dir(entries) => Self::Dir(Mutex::new(entries.lock().clone())),
             Self::Remote(remote) => Self::Remote(remote.clone()),
+            Self::Overlay(overlay) => Self::Overlay(overlay.clone()),
         }
     }
 }
@@ -57,16 +71,22 @@ impl Node {
         Arsc::new(Node::Remote(remote))
     }
 
+    /// Get the remote leaf node and the rest of `path` in it.
+    fn split_leaf(self: Arsc<Self>, path: &Path) -> Result<Option<(Arsc<Node>, PathBuf)>, Error> {
+        let (node, comps) = self.open_node(path)?;
+        Ok(match *node {
+            Node::Dir(_) => None,
+            _ => Some((node.clone(), PathBuf::from_iter(comps))),
+        })
+    }
+
     fn metadata(&self) -> Result<Metadata, Error> {
         Ok(match self {
-            Node::Dir(entries) => Metadata {
-                file_type: FileType::Directory,
-                perm: Permission::all(),
-                len: entries.lock().len(),
-            },
-            Node::Remote(remote) => remote
-                .metadata()
-                .map_err(|err| Error::RpcError(err.to_string()))??,
+            Node::Dir(entries) => {
+                Metadata::new(FileType::Directory, Permission::all(), entries.lock().len())
+            }
+            Node::Remote(remote) => remote.metadata()??,
+            Node::Overlay(overlay) => overlay.upper().metadata()??,
         })
     }
 
@@ -77,12 +97,21 @@ impl Node {
         conn: Channel,
     ) -> Result<(), Error> {
         let (node, comps) = self.open_node(path)?;
-        match *node {
-            Node::Dir(_) => Err(Error::LocalFs(path.to_path_buf())),
-            Node::Remote(ref remote) => {
-                let path = PathBuf::from_iter(comps);
-                remote.open(path, options, conn)?
-            }
+        let rest = PathBuf::from_iter(comps);
+        let res = match *node {
+            Node::Dir(_) => return Err(Error::LocalFs(path.to_path_buf())),
+            Node::Remote(ref remote) => remote.open(rest.clone(), options, conn)?,
+            Node::Overlay(ref overlay) => overlay.open(&rest, options, conn),
+        };
+        // Relative symbolic links from the remote start from its mount point.
+        let count = rest.components().count();
+        let mount = path.ancestors().nth(count).unwrap_or(Path::new(""));
+        match res {
+            Err(Error::Symlink { path, conn }) if path.is_relative() => Err(Error::Symlink {
+                path: mount.join(path),
+                conn,
+            }),
+            res => res,
         }
     }
 
@@ -110,14 +139,14 @@ impl Node {
     }
 
     #[inline]
-    fn create(self: Arsc<Self>, path: &Path, remote: EntrySyncClient) -> Result<(), Error> {
-        self.create_node(path, &mut Some(remote))
+    fn create(self: Arsc<Self>, path: &Path, leaf: Arsc<Node>) -> Result<(), Error> {
+        self.create_node(path, &mut Some(leaf))
     }
 
     fn create_node(
         self: Arsc<Self>,
         path: &Path,
-        create: &mut Option<EntrySyncClient>,
+        create: &mut Option<Arsc<Node>>,
     ) -> Result<(), Error> {
         let mut node = self;
         let mut comps = path.components().peekable();
@@ -133,7 +162,7 @@ impl Node {
                     Some(child) => Arsc::clone(child),
                     None if create.is_some() => {
                         let new = if comps.peek().is_none() {
-                            Self::leaf(create.take().unwrap())
+                            create.take().unwrap()
                         } else {
                             Self::empty()
                         };
@@ -183,17 +212,20 @@ impl Node {
         Ok((node, comps))
     }
 
+    /// Export the remotes under the node into `output`, skipping the overlay
+    /// mounts unless `strict` is set, in which case they fail the export.
     fn export(
         self: Arsc<Self>,
         path: PathBuf,
         output: &mut impl Extend<(PathBuf, EntrySyncClient)>,
+        strict: bool,
     ) -> Result<(), Error> {
         match *self {
             Node::Dir(ref dir) => {
                 let entries = dir.lock();
                 output.extend_reserve(entries.len().saturating_sub(1));
                 for (name, node) in entries.iter() {
-                    node.clone().export(path.join(name), output)?;
+                    node.clone().export(path.join(name), output, strict)?;
                 }
             }
             Node::Remote(ref remote) => {
@@ -202,6 +234,8 @@ impl Node {
                 let remote = EntrySyncClient::from(t);
                 output.extend(Some((path, remote)));
             }
+            Node::Overlay(_) if strict => return Err(Error::LocalFs(path)),
+            Node::Overlay(_) => log::warn!("overlay mount at {path:?} cannot be exported"),
         }
         Ok(())
     }
@@ -323,7 +357,30 @@ impl LocalFs {
         conn: Channel,
     ) -> Result<(), Error> {
         let path = self.canonicalize(path)?;
-        self.root.clone().open(&path, options, conn)
+        self.open_canonical(path, options, conn)
+    }
+
+    /// Open the canonical `path`, following the symbolic links returned by the
+    /// remotes.
+    fn open_canonical(
+        &self,
+        mut path: PathBuf,
+        options: OpenOptions,
+        mut conn: Channel,
+    ) -> Result<(), Error> {
+        for _ in 0..MAX_SYMLINKS {
+            match self.root.clone().open(&path, options, conn) {
+                Err(Error::Symlink {
+                    path: target,
+                    conn: c,
+                }) => {
+                    path = Self::canonicalize_with(&target, Path::new(""))?;
+                    conn = c;
+                }
+                res => return res,
+            }
+        }
+        Err(Error::SymlinkLoop(path))
     }
 
     pub fn chdir<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
@@ -335,7 +392,22 @@ impl LocalFs {
     #[inline]
     pub fn mount<P: AsRef<Path>>(&self, path: P, remote: EntrySyncClient) -> Result<(), Error> {
         let path = self.canonicalize(path)?;
-        self.root.clone().create(&path, remote)
+        self.root.clone().create(&path, Node::leaf(remote))
+    }
+
+    /// Mount the remote directories layered at `path`, with the writable
+    /// `upper` in front of the read-only `lowers` from the top down.
+    pub fn mount_overlay<P: AsRef<Path>>(
+        &self,
+        path: P,
+        upper: DirectorySyncClient,
+        lowers: impl IntoIterator<Item = DirectorySyncClient>,
+    ) -> Result<(), Error> {
+        let path = self.canonicalize(path)?;
+        let overlay = Overlay::new(upper, lowers.into_iter().collect::<Vec<_>>());
+        self.root
+            .clone()
+            .create(&path, Arsc::new(Node::Overlay(overlay)))
     }
 
     #[inline]
@@ -349,14 +421,14 @@ impl LocalFs {
         let (node, mut comps) = self.root.clone().open_node(&path)?;
         match *node {
             Node::Dir(..) => Ok(node.metadata()?),
-            Node::Remote(ref remote) if comps.peek().is_some() => {
-                let path = PathBuf::from_iter(comps);
+            Node::Remote(_) | Node::Overlay(_) if comps.peek().is_some() => {
                 let (t, conn) = Channel::new();
                 let client = EntrySyncClient::from(t);
-                remote.open(path, OpenOptions::READ, conn)??;
+                self.open_canonical(path, OpenOptions::READ, conn)?;
                 client.metadata()?
             }
             Node::Remote(ref remote) => remote.metadata()?,
+            Node::Overlay(ref overlay) => overlay.upper().metadata()?,
         }
     }
 
@@ -382,25 +454,59 @@ impl LocalFs {
                 }
                 let (t, conn) = Channel::new();
                 if comps.peek().is_some() {
-                    let path = PathBuf::from_iter(comps);
-                    remote.open(path, OpenOptions::READ, conn)??;
+                    self.open_canonical(path, OpenOptions::READ, conn)?;
                 } else {
                     remote.clone_connection(conn)?;
                 }
                 Ok(DirIter::Remote(DirectorySyncClient::from(t).into()))
             }
+            Node::Overlay(ref overlay) => {
+                let rest = PathBuf::from_iter(comps);
+                let entries = overlay.read_dir(&rest)?.collect::<Vec<_>>();
+                Ok(DirIter::Overlay(entries.into_iter()))
+            }
         }
     }
 
+    /// Watch the events of the remote directory at `path`.
+    pub fn watch<P: AsRef<Path>>(
+        &self,
+        path: P,
+        mask: WatchMask,
+        recursive: bool,
+    ) -> Result<StreamReceiver<Result<WatchEvent, Error>>, Error> {
+        let (t, conn) = Channel::new();
+        let dir = DirectorySyncClient::from(t);
+        self.open(path, OpenOptions::READ | OpenOptions::EXPECT_DIR, conn)?;
+        Ok(dir.watch(mask, recursive)?)
+    }
+
     pub fn export(
         &self,
         output: &mut impl Extend<(PathBuf, EntrySyncClient)>,
     ) -> Result<(), Error> {
-        self.root.clone().export(PathBuf::new(), output)
+        self.root.clone().export(PathBuf::new(), output, false)
+    }
+
+    /// Export all the mounts into `output`, failing with [`Error::LocalFs`]
+    /// at the path of the first overlay mount, which can't be represented by
+    /// a single remote.
+    pub fn export_all(
+        &self,
+        output: &mut impl Extend<(PathBuf, EntrySyncClient)>,
+    ) -> Result<(), Error> {
+        self.root.clone().export(PathBuf::new(), output, true)
     }
 
     pub fn unlink<P: AsRef<Path>>(&self, path: P, expect_dir: bool) -> Result<(), Error> {
         let path = self.canonicalize(path)?;
+        if let Some((node, rest)) = self.root.clone().split_leaf(&path)? {
+            if let Node::Overlay(ref overlay) = *node {
+                if rest != Path::new("") {
+                    return overlay.unlink(&rest, expect_dir);
+                }
+            }
+        }
         let parent = path
             .parent()
             .ok_or_else(|| Error::PermissionDenied(Default::default()))?;
@@ -416,10 +522,49 @@ impl LocalFs {
         dir.unlink(file_name, expect_dir)?
     }
 
+    pub fn symlink<P1: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        path: P1,
+        target: P2,
+    ) -> Result<(), Error> {
+        let path = self.canonicalize(path)?;
+        let parent = path
+            .parent()
+            .ok_or_else(|| Error::PermissionDenied(Default::default()))?;
+
+        let (t, conn) = Channel::new();
+        let dir = DirectorySyncClient::from(t);
+        self.open(parent, OpenOptions::READ | OpenOptions::WRITE, conn)?;
+
+        let file_name = path
+            .file_name()
+            .and_then(|s| s.to_str().map(|s| s.to_string()));
+        let file_name = file_name.ok_or(Error::InvalidPath(path))?;
+        dir.symlink(file_name, target.as_ref().into())?
+    }
+
+    pub fn read_link<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, Error> {
+        let path = self.canonicalize(path)?;
+        let parent = path
+            .parent()
+            .ok_or_else(|| Error::PermissionDenied(Default::default()))?;
+
+        let (t, conn) = Channel::new();
+        let dir = DirectorySyncClient::from(t);
+        self.open(parent, OpenOptions::READ, conn)?;
+
+        let file_name = path
+            .file_name()
+            .and_then(|s| s.to_str().map(|s| s.to_string()));
+        let file_name = file_name.ok_or(Error::InvalidPath(path))?;
+        dir.readlink(file_name)?
+    }
+
     fn two_path_op<Op, OpLocal>(
         &self,
         src: &Path,
         dst: &Path,
+        remove_src: bool,
         op: Op,
         op_local: OpLocal,
     ) -> Result<(), Error>
@@ -432,6 +577,39 @@ impl LocalFs {
         let dst = Self::canonicalize_with(dst, &cwd)?;
         drop(cwd);
 
+        let hide = self.prepare_src(&src)?;
+        self.two_path_op_canonical(src, dst, op, op_local)?;
+        match hide {
+            Some((overlay, rest)) if remove_src => overlay.whiteout(&rest),
+            _ => Ok(()),
+        }
+    }
+
+    /// Copy up the source of a two-path operation from the lower layers of an
+    /// overlay, returning the overlay if the lower entries need hiding after
+    /// renaming.
+    fn prepare_src(&self, src: &Path) -> Result<Option<(Overlay, PathBuf)>, Error> {
+        if let Some((node, rest)) = self.root.clone().split_leaf(src)? {
+            if let Node::Overlay(ref overlay) = *node {
+                if rest != Path::new("") && overlay.prepare_src(&rest)? {
+                    return Ok(Some((overlay.clone(), rest)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn two_path_op_canonical<Op, OpLocal>(
+        &self,
+        src: PathBuf,
+        dst: PathBuf,
+        op: Op,
+        op_local: OpLocal,
+    ) -> Result<(), Error>
+    where
+        Op: FnOnce(DirectorySyncClient, String, Handle, String) -> Result<(), Error>,
+        OpLocal: FnOnce(Arsc<Node>, PathBuf, PathBuf, PathBuf) -> Result<(), Error>,
+    {
         {
             let mut lcp = PathBuf::new();
             for (old, new) in src.iter().zip(&dst) {
@@ -495,6 +673,7 @@ impl LocalFs {
         self.two_path_op(
             src.as_ref(),
             dst.as_ref(),
+            true,
             |dir, src, dst_parent, dst| dir.rename(src, dst_parent, dst)?,
             |node, _, src, dst| node.copy_local(src, dst, true),
         )
@@ -505,6 +684,7 @@ impl LocalFs {
         self.two_path_op(
             src.as_ref(),
             dst.as_ref(),
+            false,
             |dir, src, dst_parent, dst| dir.link(src, dst_parent, dst)?,
             |_, lcp, _, _| Err(Error::LocalFs(lcp)),
         )
@@ -540,6 +720,7 @@ impl Iterator for LocalIter {
 pub enum DirIter {
     Local(LocalIter),
     Remote(RemoteIter),
+    Overlay(alloc::vec::IntoIter<DirEntry>),
 }
 
 impl Iterator for DirIter {
@@ -550,6 +731,7 @@ impl Iterator for DirIter {
         match self {
             DirIter::Local(local) => local.next(),
             DirIter::Remote(remote) => remote.next(),
+            DirIter::Overlay(overlay) => overlay.next().map(Ok),
         }
     }
 }
@@ -0,0 +1,408 @@
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
+    string::{String, ToString},
+    vec::{self, Vec},
+};
+use core::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{stream, Stream};
+use solvent::{error::EIO, ipc::Channel};
+use solvent_async::ipc::Channel as AsyncChannel;
+use solvent_core::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use solvent_rpc::{
+    io::{
+        dir::{DirEntry, DirectoryClient},
+        entry::{EntryClient, EntrySyncClient},
+        file::FileClient,
+        Error, FileType, Metadata, OpenOptions, Permission,
+    },
+    sync::Client,
+    Error as RpcError, StreamReceiver,
+};
+
+use super::LocalFs;
+use crate::dir::MAX_SYMLINKS;
+
+const CHUNK: usize = 4096;
+
+/// The asynchronous counterpart of [`LocalFs`], which sends requests to the
+/// mounted remotes without blocking the executor.
+///
+/// Unlike [`LocalFs`], local directories only exist as the ancestors of the
+/// mount points, and overlay mounts are not supported, so they should be
+/// accessed through [`LocalFs`] instead.
+pub struct AsyncLocalFs {
+    mounts: Mutex<BTreeMap<PathBuf, EntryClient>>,
+    cwd: Mutex<PathBuf>,
+}
+
+#[inline]
+fn client<C: From<AsyncChannel>>() -> (C, Channel) {
+    let (t, conn) = Channel::new();
+    (C::from(AsyncChannel::new(t)), conn)
+}
+
+fn split(path: &Path) -> Result<(&Path, String), Error> {
+    match (path.parent(), path.file_name().and_then(|s| s.to_str())) {
+        (Some(parent), Some(name)) => Ok((parent, name.to_string())),
+        _ => Err(Error::InvalidPath(path.into())),
+    }
+}
+
+impl AsyncLocalFs {
+    pub fn new() -> Self {
+        AsyncLocalFs {
+            mounts: Mutex::new(BTreeMap::new()),
+            cwd: Mutex::new("".into()),
+        }
+    }
+
+    /// Create the asynchronous file system with the mounts and the current
+    /// directory of `local`.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::LocalFs`] if `local` has any overlay
+    /// mount, instead of leaving the mount out.
+    pub fn from_local(local: &LocalFs) -> Result<Self, Error> {
+        let mut remotes = Vec::<(PathBuf, EntrySyncClient)>::new();
+        local.export_all(&mut remotes)?;
+        let mounts = remotes
+            .into_iter()
+            .filter_map(|(path, remote)| match remote.into_async() {
+                Ok(remote) => Some((path, remote)),
+                Err(_) => {
+                    log::warn!("failed to convert the mount at {path:?} to async");
+                    None
+                }
+            });
+        Ok(AsyncLocalFs {
+            mounts: Mutex::new(mounts.collect()),
+            cwd: Mutex::new(local.cwd.lock().clone()),
+        })
+    }
+
+    #[inline]
+    pub fn canonicalize<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, Error> {
+        LocalFs::canonicalize_with(path.as_ref(), &self.cwd.lock())
+    }
+
+    pub fn chdir<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = self.canonicalize(path)?;
+        *self.cwd.lock() = path;
+        Ok(())
+    }
+
+    pub fn mount<P: AsRef<Path>>(&self, path: P, remote: EntryClient) -> Result<(), Error> {
+        let path = self.canonicalize(path)?;
+        let mut mounts = self.mounts.lock();
+        if mounts
+            .keys()
+            .any(|p| p.starts_with(&path) || path.starts_with(p))
+        {
+            return Err(Error::Exists);
+        }
+        mounts.insert(path, remote);
+        Ok(())
+    }
+
+    pub fn unmount<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = self.canonicalize(path)?;
+        let old = self.mounts.lock().remove(&path);
+        old.map(drop).ok_or(Error::NotFound)
+    }
+
+    /// Get the remote containing the canonical `path`, its mount point and the
+    /// rest of `path` in it.
+    fn resolve(&self, path: &Path) -> Result<(EntryClient, PathBuf, PathBuf), Error> {
+        let mounts = self.mounts.lock();
+        for mount in path.ancestors() {
+            if let Some(remote) = mounts.get(mount) {
+                let rest = path.strip_prefix(mount).unwrap();
+                return Ok((remote.clone(), mount.into(), rest.into()));
+            }
+        }
+        if mounts.keys().any(|p| p.starts_with(path)) {
+            Err(Error::LocalFs(path.into()))
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+
+    /// The names of the entries in the local directory at the canonical
+    /// `path`.
+    fn local_entries(&self, path: &Path) -> BTreeSet<String> {
+        let mounts = self.mounts.lock();
+        let children = mounts.keys().filter_map(|p| {
+            let rest = p.strip_prefix(path).ok()?;
+            let name = rest.components().next()?;
+            name.as_os_str().to_str().map(ToString::to_string)
+        });
+        children.collect()
+    }
+
+    pub async fn open<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: OpenOptions,
+        conn: Channel,
+    ) -> Result<(), Error> {
+        let path = self.canonicalize(path)?;
+        self.open_canonical(path, options, conn).await
+    }
+
+    /// Open the canonical `path`, following the symbolic links returned by the
+    /// remotes.
+    async fn open_canonical(
+        &self,
+        mut path: PathBuf,
+        options: OpenOptions,
+        mut conn: Channel,
+    ) -> Result<(), Error> {
+        for _ in 0..MAX_SYMLINKS {
+            let (remote, mount, rest) = self.resolve(&path)?;
+            match remote.open(rest, options, conn).await? {
+                Err(Error::Symlink {
+                    path: target,
+                    conn: c,
+                }) => {
+                    // Relative symbolic links start from the mount point.
+                    let target = if target.is_relative() {
+                        mount.join(target)
+                    } else {
+                        target
+                    };
+                    path = LocalFs::canonicalize_with(&target, Path::new(""))?;
+                    conn = c;
+                }
+                res => return res,
+            }
+        }
+        Err(Error::SymlinkLoop(path))
+    }
+
+    pub async fn open_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: OpenOptions,
+    ) -> Result<FileClient, Error> {
+        let (file, conn) = client();
+        self.open(path, options | OpenOptions::EXPECT_FILE, conn)
+            .await?;
+        Ok(file)
+    }
+
+    pub async fn open_dir<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: OpenOptions,
+    ) -> Result<DirectoryClient, Error> {
+        let (dir, conn) = client();
+        self.open(path, options | OpenOptions::EXPECT_DIR, conn)
+            .await?;
+        Ok(dir)
+    }
+
+    pub async fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<Metadata, Error> {
+        let path = self.canonicalize(path)?;
+        match self.resolve(&path) {
+            Ok((remote, _, rest)) if rest == Path::new("") => remote.metadata().await?,
+            Ok(_) => {
+                let (entry, conn) = client::<EntryClient>();
+                self.open_canonical(path, OpenOptions::READ, conn).await?;
+                entry.metadata().await?
+            }
+            Err(Error::LocalFs(path)) => {
+                let len = self.local_entries(&path).len();
+                Ok(Metadata::new(FileType::Directory, Permission::all(), len))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    pub async fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<AsyncDirIter, Error> {
+        let path = self.canonicalize(path)?;
+        match self.resolve(&path) {
+            Ok(_) => {}
+            Err(Error::LocalFs(path)) => {
+                let mut entries = Vec::new();
+                for name in self.local_entries(&path) {
+                    let child = path.join(&name);
+                    let remote = self.mounts.lock().get(&child).cloned();
+                    let metadata = match remote {
+                        Some(remote) => remote.metadata().await??,
+                        None => {
+                            let len = self.local_entries(&child).len();
+                            Metadata::new(FileType::Directory, Permission::all(), len)
+                        }
+                    };
+                    entries.push(DirEntry { name, metadata });
+                }
+                return Ok(AsyncDirIter::Local(entries.into_iter()));
+            }
+            Err(err) => return Err(err),
+        }
+
+        let (dir, conn) = client::<DirectoryClient>();
+        let options = OpenOptions::READ | OpenOptions::EXPECT_DIR;
+        self.open_canonical(path, options, conn).await?;
+        match dir.list(None).await {
+            Ok(stream) => Ok(AsyncDirIter::Stream(stream)),
+            // The server doesn't support streaming, so fall back to fetching
+            // entries one by one.
+            Err(RpcError::UnsupportedMethod(_)) => {
+                let dirents = stream::unfold(Some((dir, None)), |state| async move {
+                    let (dir, last) = state?;
+                    match dir.next_dirent(last).await {
+                        Ok(Err(Error::IterEnd)) => None,
+                        Ok(Ok(dirent)) => {
+                            let last = Some(dirent.name.clone());
+                            Some((Ok(dirent), Some((dir, last))))
+                        }
+                        Ok(Err(err)) => Some((Err(err), None)),
+                        Err(err) => Some((Err(err.into()), None)),
+                    }
+                });
+                Ok(AsyncDirIter::Dirent(Box::pin(dirents)))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub async fn unlink<P: AsRef<Path>>(&self, path: P, expect_dir: bool) -> Result<(), Error> {
+        let path = self.canonicalize(path)?;
+        let (parent, name) = split(&path)?;
+        let dir = self
+            .open_dir(parent, OpenOptions::READ | OpenOptions::WRITE)
+            .await?;
+        dir.unlink(name, expect_dir).await?
+    }
+
+    async fn two_path_op(
+        &self,
+        src: &Path,
+        dst: &Path,
+    ) -> Result<(DirectoryClient, String, DirectoryClient, String), Error> {
+        let (src, dst) = (self.canonicalize(src)?, self.canonicalize(dst)?);
+        let (src_parent, src) = split(&src)?;
+        let (dst_parent, dst) = split(&dst)?;
+        let options = OpenOptions::READ | OpenOptions::WRITE;
+        let src_parent = self.open_dir(src_parent, options).await?;
+        let dst_parent = self.open_dir(dst_parent, options).await?;
+        Ok((src_parent, src, dst_parent, dst))
+    }
+
+    pub async fn rename<P1: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        src: P1,
+        dst: P2,
+    ) -> Result<(), Error> {
+        let (src_parent, src, dst_parent, dst) =
+            self.two_path_op(src.as_ref(), dst.as_ref()).await?;
+        let token = dst_parent.event_token().await??;
+        src_parent.rename(src, token, dst).await?
+    }
+
+    pub async fn link<P1: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        src: P1,
+        dst: P2,
+    ) -> Result<(), Error> {
+        let (src_parent, src, dst_parent, dst) =
+            self.two_path_op(src.as_ref(), dst.as_ref()).await?;
+        let token = dst_parent.event_token().await??;
+        src_parent.link(src, token, dst).await?
+    }
+
+    pub async fn symlink<P1: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        path: P1,
+        target: P2,
+    ) -> Result<(), Error> {
+        let path = self.canonicalize(path)?;
+        let (parent, name) = split(&path)?;
+        let dir = self
+            .open_dir(parent, OpenOptions::READ | OpenOptions::WRITE)
+            .await?;
+        dir.symlink(name, target.as_ref().into()).await?
+    }
+
+    pub async fn read_link<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, Error> {
+        let path = self.canonicalize(path)?;
+        let (parent, name) = split(&path)?;
+        let dir = self.open_dir(parent, OpenOptions::READ).await?;
+        dir.readlink(name).await?
+    }
+
+    pub async fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, Error> {
+        let file = self.open_file(path, OpenOptions::READ).await?;
+        let mut ret = Vec::new();
+        loop {
+            let buf = file.read(CHUNK).await??;
+            if buf.is_empty() {
+                break Ok(ret);
+            }
+            ret.extend_from_slice(&buf);
+        }
+    }
+
+    pub async fn read_to_string<P: AsRef<Path>>(&self, path: P) -> Result<String, Error> {
+        let vec = self.read(path).await?;
+        String::from_utf8(vec).map_err(|err| Error::InvalidData(err.to_string()))
+    }
+
+    pub async fn write<P: AsRef<Path>, B: AsRef<[u8]>>(
+        &self,
+        path: P,
+        buf: B,
+    ) -> Result<(), Error> {
+        let options =
+            OpenOptions::READ | OpenOptions::WRITE | OpenOptions::CREATE | OpenOptions::TRUNCATE;
+        let file = self.open_file(path, options).await?;
+        for buf in buf.as_ref().chunks(CHUNK) {
+            let mut written = 0;
+            while written < buf.len() {
+                match file.write(Vec::from(&buf[written..])).await?? {
+                    0 => return Err(Error::Other(EIO)),
+                    len => written += len,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for AsyncLocalFs {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The asynchronous iterator of the entries of a directory.
+pub enum AsyncDirIter {
+    Local(vec::IntoIter<DirEntry>),
+    Stream(StreamReceiver<Result<DirEntry, Error>>),
+    Dirent(Pin<Box<dyn Stream<Item = Result<DirEntry, Error>> + Send>>),
+}
+
+impl Stream for AsyncDirIter {
+    type Item = Result<DirEntry, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.get_mut() {
+            AsyncDirIter::Local(local) => Poll::Ready(local.next().map(Ok)),
+            AsyncDirIter::Stream(stream) => Pin::new(stream)
+                .poll_next(cx)
+                .map(|item| item.map(|res| res.unwrap_or_else(|err| Err(err.into())))),
+            AsyncDirIter::Dirent(dirents) => dirents.as_mut().poll_next(cx),
+        }
+    }
+}
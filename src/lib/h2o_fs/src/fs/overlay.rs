@@ -0,0 +1,373 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use solvent::{error::EIO, ipc::Channel};
+use solvent_core::path::{Component, Path, PathBuf};
+use solvent_rpc::io::{
+    dir::{DirEntry, DirectorySyncClient},
+    entry::EntrySyncClient,
+    file::FileSyncClient,
+    Error, FileType, OpenOptions, SetMask, SetMetadata,
+};
+
+use crate::dir::sync::RemoteIter;
+
+/// The prefix of the names of the whiteouts in the upper layer, which hide the
+/// lower entries of the same names without the prefix.
+pub const WHITEOUT_PREFIX: &str = ".wh.";
+
+/// The name of the marker in an upper directory hiding all its lower entries,
+/// left when a removed directory is created again.
+pub const OPAQUE: &str = ".wh..wh..opq";
+
+const COPY_CHUNK: usize = 4096;
+
+/// Several remote directories layered at one path, with the writable upper
+/// layer in front of the read-only lower ones.
+///
+/// Entries are looked up from the upper layer to the lowest, removing lower
+/// entries leaves whiteouts in the upper layer, and opening lower entries for
+/// writing copies them up first. Only [`Overlay::read_dir`] merges the entries
+/// of directories, while opening a directory gets its topmost layer.
+#[derive(Clone)]
+pub struct Overlay {
+    upper: DirectorySyncClient,
+    lowers: Vec<DirectorySyncClient>,
+}
+
+/// Check whether `path` exists in the layer, counting symbolic links to be
+/// resolved by the client as existent.
+fn exists(layer: &DirectorySyncClient, path: &Path) -> Result<bool, Error> {
+    let (_t, conn) = Channel::new();
+    match layer.open(path.into(), OpenOptions::empty(), conn)? {
+        Ok(()) | Err(Error::Symlink { .. }) => Ok(true),
+        Err(Error::NotFound) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+fn open_dir(
+    layer: &DirectorySyncClient,
+    path: &Path,
+    options: OpenOptions,
+) -> Result<DirectorySyncClient, Error> {
+    let (t, conn) = Channel::new();
+    layer.open(path.into(), options | OpenOptions::EXPECT_DIR, conn)??;
+    Ok(DirectorySyncClient::from(t))
+}
+
+fn split(path: &Path) -> Result<(&Path, &str), Error> {
+    match (path.parent(), path.file_name().and_then(|s| s.to_str())) {
+        (Some(parent), Some(name)) => Ok((parent, name)),
+        _ => Err(Error::InvalidPath(path.into())),
+    }
+}
+
+#[inline]
+fn whiteout_name(name: &str) -> String {
+    format!("{WHITEOUT_PREFIX}{name}")
+}
+
+impl Overlay {
+    #[inline]
+    pub fn new(upper: DirectorySyncClient, lowers: Vec<DirectorySyncClient>) -> Self {
+        Overlay { upper, lowers }
+    }
+
+    #[inline]
+    pub fn upper(&self) -> &DirectorySyncClient {
+        &self.upper
+    }
+
+    /// Check whether the lower entries at `path` are hidden by a whiteout or
+    /// an opaque directory in the upper layer.
+    fn hidden(&self, path: &Path) -> Result<bool, Error> {
+        let mut parent = PathBuf::new();
+        for comp in path.components() {
+            let name = comp.as_os_str().to_str();
+            let name = name.ok_or_else(|| Error::InvalidPath(path.into()))?;
+            if parent != Path::new("") && exists(&self.upper, &parent.join(OPAQUE))? {
+                return Ok(true);
+            }
+            if exists(&self.upper, &parent.join(whiteout_name(name)))? {
+                return Ok(true);
+            }
+            parent.push(name);
+        }
+        Ok(false)
+    }
+
+    fn lower(&self, path: &Path) -> Result<Option<&DirectorySyncClient>, Error> {
+        if self.hidden(path)? {
+            return Ok(None);
+        }
+        for lower in &self.lowers {
+            if exists(lower, path)? {
+                return Ok(Some(lower));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Get the topmost layer containing `path`.
+    fn lookup(&self, path: &Path) -> Result<Option<&DirectorySyncClient>, Error> {
+        if exists(&self.upper, path)? {
+            return Ok(Some(&self.upper));
+        }
+        self.lower(path)
+    }
+
+    fn check(path: &Path) -> Result<(), Error> {
+        for comp in path.components() {
+            match comp {
+                Component::Normal(name) => match name.to_str() {
+                    Some(name) if !name.starts_with(WHITEOUT_PREFIX) => {}
+                    _ => return Err(Error::InvalidPath(path.into())),
+                },
+                _ => return Err(Error::InvalidPath(path.into())),
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy the lower entry at `path` to the upper layer, along with its
+    /// missing ancestors.
+    ///
+    /// The contents of directories are not copied, since their lower entries
+    /// remain visible.
+    fn copy_up(&self, path: &Path) -> Result<(), Error> {
+        if path == Path::new("") || exists(&self.upper, path)? {
+            return Ok(());
+        }
+        let (parent, name) = split(path)?;
+        let lower = self.lower(path)?.ok_or(Error::NotFound)?;
+        self.copy_up(parent)?;
+
+        let lower_parent = open_dir(lower, parent, OpenOptions::READ)?;
+        if let Ok(Ok(target)) = lower_parent.readlink(name.into()) {
+            let upper_parent = open_dir(&self.upper, parent, OpenOptions::WRITE)?;
+            return upper_parent.symlink(name.into(), target)?;
+        }
+
+        let (t, conn) = Channel::new();
+        lower.open(path.into(), OpenOptions::empty(), conn)??;
+        let metadata = EntrySyncClient::from(t).metadata()??;
+        let create = OpenOptions::CREATE_NEW | OpenOptions::READ | OpenOptions::WRITE;
+
+        let (t, conn) = Channel::new();
+        let dst = match metadata.file_type {
+            FileType::Directory => {
+                let options = create | OpenOptions::EXPECT_DIR;
+                self.upper.open(path.into(), options, conn)??;
+                EntrySyncClient::from(t)
+            }
+            FileType::File => {
+                self.upper.open(path.into(), create, conn)??;
+                let dst = FileSyncClient::from(t);
+
+                let (t, conn) = Channel::new();
+                lower.open(path.into(), OpenOptions::READ, conn)??;
+                let src = FileSyncClient::from(t);
+                let mut pos = 0;
+                loop {
+                    let buf = src.read_at(pos, COPY_CHUNK)??;
+                    if buf.is_empty() {
+                        break;
+                    }
+                    // Servers may accept only a part of the contents at a time,
+                    // in which case the rest is read again.
+                    let len = buf.len();
+                    match dst.write(buf)?? {
+                        0 => return Err(Error::Other(EIO)),
+                        written => pos += written.min(len),
+                    }
+                }
+                dst.into()
+            }
+            file_type => return Err(Error::InvalidType(file_type)),
+        };
+
+        // The attributes are kept on a best-effort basis.
+        let attr = SetMetadata {
+            mask: SetMask::MODE | SetMask::CREATED | SetMask::MODIFIED | SetMask::ACCESSED,
+            mode: metadata.mode,
+            times: metadata.times,
+            ..Default::default()
+        };
+        let _ = dst.set_metadata(attr);
+        Ok(())
+    }
+
+    /// Hide the lower entries at `path` if any.
+    pub fn whiteout(&self, path: &Path) -> Result<(), Error> {
+        Self::check(path)?;
+        if self.lower(path)?.is_none() {
+            return Ok(());
+        }
+        let (parent, name) = split(path)?;
+        self.copy_up(parent)?;
+        let (_t, conn) = Channel::new();
+        let options = OpenOptions::CREATE | OpenOptions::WRITE;
+        self.upper
+            .open(parent.join(whiteout_name(name)), options, conn)?
+    }
+
+    pub fn open(&self, path: &Path, options: OpenOptions, conn: Channel) -> Result<(), Error> {
+        Self::check(path)?;
+        if path == Path::new("") {
+            return self.upper.open(path.into(), options, conn)?;
+        }
+        let write = OpenOptions::WRITE
+            | OpenOptions::APPEND
+            | OpenOptions::CREATE
+            | OpenOptions::CREATE_NEW
+            | OpenOptions::TRUNCATE;
+        if !options.intersects(write) {
+            let layer = self.lookup(path)?.ok_or(Error::NotFound)?;
+            return layer.open(path.into(), options, conn)?;
+        }
+
+        let mut recreated = false;
+        if !exists(&self.upper, path)? {
+            if self.lower(path)?.is_some() {
+                if options.contains(OpenOptions::CREATE_NEW) {
+                    return Err(Error::Exists);
+                }
+                self.copy_up(path)?;
+            } else if options.intersects(OpenOptions::CREATE | OpenOptions::CREATE_NEW) {
+                let (parent, name) = split(path)?;
+                self.copy_up(parent)?;
+                let upper_parent = open_dir(&self.upper, parent, OpenOptions::WRITE)?;
+                recreated = match upper_parent.unlink(whiteout_name(name), false)? {
+                    Ok(()) => true,
+                    Err(Error::NotFound) => false,
+                    Err(err) => return Err(err),
+                };
+            } else {
+                return Err(Error::NotFound);
+            }
+        }
+        self.upper.open(path.into(), options, conn)??;
+
+        // Keep the lower entries of the removed directory hidden.
+        if recreated {
+            let (_t, conn) = Channel::new();
+            let options = OpenOptions::CREATE | OpenOptions::WRITE;
+            match self.upper.open(path.join(OPAQUE), options, conn)? {
+                Ok(()) | Err(Error::InvalidType(FileType::File)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Prepare the entry at `path` to be renamed or linked in the upper layer,
+    /// returning whether the lower entries need hiding after renaming.
+    pub fn prepare_src(&self, path: &Path) -> Result<bool, Error> {
+        Self::check(path)?;
+        if exists(&self.upper, path)? {
+            return Ok(self.lower(path)?.is_some());
+        }
+        let lower = self.lower(path)?.ok_or(Error::NotFound)?;
+        let (_t, conn) = Channel::new();
+        match lower.open(path.into(), OpenOptions::EXPECT_DIR, conn)? {
+            // Directories would lose their lower contents when copied up.
+            Ok(()) => return Err(Error::InvalidType(FileType::Directory)),
+            Err(Error::InvalidType(_)) | Err(Error::Symlink { .. }) => {}
+            Err(err) => return Err(err),
+        }
+        self.copy_up(path)?;
+        Ok(true)
+    }
+
+    pub fn unlink(&self, path: &Path, expect_dir: bool) -> Result<(), Error> {
+        Self::check(path)?;
+        let (parent, name) = split(path)?;
+        let layer = self.lookup(path)?.ok_or(Error::NotFound)?;
+
+        let (t, conn) = Channel::new();
+        let is_dir = match layer.open(path.into(), OpenOptions::EXPECT_DIR, conn)? {
+            Ok(()) => {
+                drop(t);
+                if self.read_dir(path)?.next().is_some() {
+                    return Err(Error::DirNotEmpty);
+                }
+                true
+            }
+            Err(Error::InvalidType(file_type)) if expect_dir => {
+                return Err(Error::InvalidType(file_type))
+            }
+            Err(Error::InvalidType(_)) | Err(Error::Symlink { .. }) => false,
+            Err(err) => return Err(err),
+        };
+        if expect_dir && !is_dir {
+            return Err(Error::InvalidType(FileType::Symlink));
+        }
+
+        if exists(&self.upper, path)? {
+            if is_dir {
+                // Only whiteouts are left in the upper directory.
+                let dir = open_dir(&self.upper, path, OpenOptions::READ | OpenOptions::WRITE)?;
+                let names = RemoteIter::from(dir.clone())
+                    .map(|dirent| dirent.map(|dirent| dirent.name))
+                    .collect::<Result<Vec<_>, _>>()?;
+                for name in names {
+                    dir.unlink(name, false)??;
+                }
+            }
+            let upper_parent = open_dir(&self.upper, parent, OpenOptions::WRITE)?;
+            upper_parent.unlink(name.to_string(), is_dir)??;
+        }
+        self.whiteout(path)
+    }
+
+    /// List the merged entries of the directory at `path`.
+    pub fn read_dir(&self, path: &Path) -> Result<impl Iterator<Item = DirEntry>, Error> {
+        Self::check(path)?;
+        let mut entries = BTreeMap::new();
+        let mut found = false;
+
+        let mut hidden = BTreeSet::new();
+        let mut opaque = false;
+        if exists(&self.upper, path)? {
+            found = true;
+            let dir = open_dir(&self.upper, path, OpenOptions::READ)?;
+            for dirent in RemoteIter::from(dir) {
+                let dirent = dirent?;
+                if dirent.name == OPAQUE {
+                    opaque = true;
+                } else if let Some(name) = dirent.name.strip_prefix(WHITEOUT_PREFIX) {
+                    hidden.insert(name.to_string());
+                } else {
+                    entries.insert(dirent.name.clone(), dirent);
+                }
+            }
+        }
+
+        if !opaque && (path == Path::new("") || !self.hidden(path)?) {
+            for lower in &self.lowers {
+                if !exists(lower, path)? {
+                    continue;
+                }
+                found = true;
+                let dir = open_dir(lower, path, OpenOptions::READ)?;
+                for dirent in RemoteIter::from(dir) {
+                    let dirent = dirent?;
+                    if dirent.name.starts_with(WHITEOUT_PREFIX) || hidden.contains(&dirent.name) {
+                        continue;
+                    }
+                    entries.entry(dirent.name.clone()).or_insert(dirent);
+                }
+            }
+        }
+
+        if !found {
+            return Err(Error::NotFound);
+        }
+        Ok(entries.into_values())
+    }
+}
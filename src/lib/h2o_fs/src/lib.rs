@@ -4,9 +4,16 @@
 #![feature(result_option_inspect)]
 #![feature(slice_ptr_get)]
 
+pub mod archive;
+pub mod block;
+pub mod bulk;
+pub mod cache;
 pub mod dir;
 pub mod entry;
+pub mod ext2;
 pub mod file;
+#[cfg(test)]
+mod fixture;
 pub mod fs;
 pub mod loader;
 pub mod mem;
@@ -15,6 +22,8 @@ pub mod rpc;
 mod spawn;
 
 extern crate alloc;
+#[cfg(test)]
+extern crate std;
 
 #[cfg(feature = "runtime")]
 pub use spawn::spawner;
@@ -29,9 +38,14 @@ mod std_local {
 
     use solvent::prelude::Channel;
     use solvent_core::path::{Path, PathBuf};
-    use solvent_rpc::io::{
-        dir::DirectorySyncClient, entry::EntrySyncClient, file::FileSyncClient, Error, FileType,
-        Metadata, OpenOptions,
+    use solvent_rpc::{
+        io::{
+            dir::{DirectorySyncClient, WatchEvent, WatchMask},
+            entry::EntrySyncClient,
+            file::FileSyncClient,
+            Error, FileType, Metadata, OpenOptions, SetMetadata,
+        },
+        sync::StreamReceiver,
     };
 
     use crate::fs;
@@ -72,6 +86,13 @@ mod std_local {
         client.metadata()?
     }
 
+    pub fn set_metadata<P: AsRef<Path>>(path: P, attr: SetMetadata) -> Result<(), Error> {
+        let (t, conn) = Channel::new();
+        fs::local().open(path, OpenOptions::READ | OpenOptions::WRITE, conn)?;
+        let client = EntrySyncClient::from(t);
+        client.set_metadata(attr)?
+    }
+
     #[inline]
     pub fn read_dir<P: AsRef<Path>>(path: P) -> Result<fs::DirIter, Error> {
         fs::local().read_dir(path)
@@ -114,6 +135,25 @@ mod std_local {
         fs::local().link(src, dst)
     }
 
+    #[inline]
+    pub fn watch<P: AsRef<Path>>(
+        path: P,
+        mask: WatchMask,
+        recursive: bool,
+    ) -> Result<StreamReceiver<Result<WatchEvent, Error>>, Error> {
+        fs::local().watch(path, mask, recursive)
+    }
+
+    #[inline]
+    pub fn symlink<P1: AsRef<Path>, P2: AsRef<Path>>(path: P1, target: P2) -> Result<(), Error> {
+        fs::local().symlink(path, target)
+    }
+
+    #[inline]
+    pub fn read_link<P: AsRef<Path>>(path: P) -> Result<PathBuf, Error> {
+        fs::local().read_link(path)
+    }
+
     #[inline]
     pub fn unlink<P: AsRef<Path>>(path: P) -> Result<(), Error> {
         fs::local().unlink(path, false)
@@ -81,8 +81,11 @@ pub async fn serve(
                     log::warn!("RPC send error: {err}");
                 }
             }
-            LoaderRequest::Unknown(_) => {
-                log::warn!("RPC received unknown request")
+            LoaderRequest::Unknown(req) => {
+                log::warn!("RPC received unknown request");
+                if let Err(err) = req.unsupported() {
+                    log::warn!("RPC send error: {err}");
+                }
             }
         }
     }
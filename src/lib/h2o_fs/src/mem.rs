@@ -1,2 +1,4 @@
 pub mod dir;
 pub mod file;
+pub mod symlink;
+pub mod tmpfs;
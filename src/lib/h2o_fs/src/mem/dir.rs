@@ -14,22 +14,45 @@ use solvent_core::{
     sync::{Arsc, Mutex},
 };
 use solvent_rpc::io::{
-    dir::{DirEntry, DirectoryServer},
-    Error, FileType, Metadata, OpenOptions, Permission,
+    dir::{DirEntry, DirectoryServer, WatchEvent},
+    Error, FileType, FsStat, Metadata, OpenOptions, Permission, SetMask, SetMetadata,
 };
 
 pub use self::builder::*;
+use super::{
+    symlink::MemSymlink,
+    tmpfs::{statfs, Quota},
+};
 use crate::{
-    dir::{handle, handle_mut, Directory, DirectoryMut, EventTokens},
-    entry::Entry,
+    dir::{handle, handle_mut, redirect, Directory, DirectoryMut, EventTokens},
+    entry::{Attrs, Entry},
     spawn::Spawner,
 };
 
 const MAX_NAME: usize = u8::MAX as _;
 
+/// Link the watchers of `entry` to the ones of the directory as `name`.
+fn attach(attrs: &Attrs, name: &str, entry: &Arsc<dyn Entry>) {
+    if let Some(child) = entry.attrs() {
+        child.watch().attach(attrs.watch(), name);
+    }
+}
+
+fn attach_all(attrs: &Attrs, entries: &BTreeMap<String, Arsc<dyn Entry>>) {
+    for (name, entry) in entries {
+        attach(attrs, name, entry);
+    }
+}
+
+fn detach(attrs: &Attrs, name: &str, entry: &Arsc<dyn Entry>) {
+    if let Some(child) = entry.attrs() {
+        child.watch().detach(attrs.watch(), name);
+    }
+}
+
 pub struct MemDir {
     entries: BTreeMap<String, Arsc<dyn Entry>>,
-    perm: Permission,
+    attrs: Attrs,
 }
 
 impl MemDir {
@@ -63,7 +86,7 @@ impl Entry for MemDir {
                     .ok_or_else(|| Error::InvalidPath(path.into()))?;
                 let path = path.strip_prefix(name).unwrap();
                 let entry = self.get(name)?;
-                entry.open(spawner, tokens, path, options, conn)
+                redirect(name, entry.open(spawner, tokens, path, options, conn))
             }
             Some(_) => Err(Error::InvalidPath(path.into())),
             None => {
@@ -71,8 +94,9 @@ impl Entry for MemDir {
                     return Err(Error::InvalidType(FileType::Directory));
                 }
                 let require = options.require();
-                if !self.perm.contains(require) {
-                    return Err(Error::PermissionDenied(require - self.perm));
+                let perm = self.attrs.mode();
+                if !perm.contains(require) {
+                    return Err(Error::PermissionDenied(require - perm));
                 }
                 let server =
                     DirectoryServer::new(AsyncChannel::with_disp(conn, spawner.dispatch()));
@@ -85,12 +109,26 @@ impl Entry for MemDir {
 
     #[inline]
     fn metadata(&self) -> Result<Metadata, Error> {
+        let (mode, len) = (self.attrs.mode(), self.entries.len());
         Ok(Metadata {
-            file_type: FileType::Directory,
-            perm: self.perm,
-            len: self.entries.len(),
+            allocated: 0,
+            ..self.attrs.metadata(FileType::Directory, mode, len)
         })
     }
+
+    #[inline]
+    fn set_metadata(&self, attr: SetMetadata) -> Result<(), Error> {
+        if attr.mask.contains(SetMask::LEN) {
+            return Err(Error::InvalidType(FileType::Directory));
+        }
+        self.attrs.set(&attr);
+        Ok(())
+    }
+
+    #[inline]
+    fn attrs(&self) -> Option<&Attrs> {
+        Some(&self.attrs)
+    }
 }
 
 #[async_trait]
@@ -105,6 +143,17 @@ impl Directory for MemDir {
         let metadata = entry.metadata()?;
         Ok(DirEntry { name, metadata })
     }
+
+    async fn readlink(&self, name: &str) -> Result<PathBuf, Error> {
+        readlink(self.get(name)?)
+    }
+}
+
+fn readlink(entry: Arsc<dyn Entry>) -> Result<PathBuf, Error> {
+    match entry.clone().into_any().downcast::<MemSymlink>() {
+        Ok(symlink) => Ok(symlink.target().into()),
+        Err(_) => Err(Error::InvalidType(entry.metadata()?.file_type)),
+    }
 }
 
 pub trait FileInserter: Fn(&str) -> Result<Arsc<dyn Entry>, Error> + Send + Sync {}
@@ -112,9 +161,10 @@ impl<F: Fn(&str) -> Result<Arsc<dyn Entry>, Error> + Send + Sync> FileInserter f
 
 pub struct MemDirMut {
     entries: Mutex<BTreeMap<String, Arsc<dyn Entry>>>,
-    perm: Permission,
+    attrs: Attrs,
     path: PathBuf,
     file_inserter: Arsc<dyn FileInserter>,
+    quota: Option<Arsc<Quota>>,
 }
 
 impl MemDirMut {
@@ -123,27 +173,47 @@ impl MemDirMut {
         path: PathBuf,
         file_inserter: Arsc<F>,
     ) -> Self {
-        MemDirMut {
-            entries: Mutex::new(BTreeMap::new()),
-            perm,
-            path,
-            file_inserter,
-        }
+        Self::new_unsized(perm, path, file_inserter)
     }
 
     pub fn new_unsized(
         perm: Permission,
         path: PathBuf,
         file_inserter: Arsc<dyn FileInserter>,
+    ) -> Self {
+        Self::with_parts(perm, path, file_inserter, None)
+    }
+
+    fn with_parts(
+        perm: Permission,
+        path: PathBuf,
+        file_inserter: Arsc<dyn FileInserter>,
+        quota: Option<Arsc<Quota>>,
     ) -> Self {
         MemDirMut {
             entries: Mutex::new(BTreeMap::new()),
-            perm,
+            attrs: Attrs::new(perm),
             path,
             file_inserter,
+            quota,
         }
     }
 
+    /// Create a directory counted as a node of `quota`, whose subdirectories
+    /// and symbolic links are also counted.
+    ///
+    /// The files created by `file_inserter` should charge `quota` by
+    /// themselves.
+    pub fn with_quota<F: FileInserter + 'static>(
+        perm: Permission,
+        path: PathBuf,
+        file_inserter: Arsc<F>,
+        quota: Arsc<Quota>,
+    ) -> Result<Self, Error> {
+        quota.charge_node()?;
+        Ok(Self::with_parts(perm, path, file_inserter, Some(quota)))
+    }
+
     fn get(&self, name: &str) -> Result<Arsc<dyn Entry>, Error> {
         if name.len() > MAX_NAME {
             return Err(Error::InvalidNameLength(name.len()));
@@ -171,16 +241,26 @@ impl MemDirMut {
             return Ok((ent.clone(), false));
         }
 
-        let entry = if next != Path::new("") {
+        // Intermediate components of the path are always directories.
+        let entry = if next == Path::new("") && !options.contains(OpenOptions::EXPECT_DIR) {
             (self.file_inserter)(name)? as Arsc<dyn Entry>
         } else {
-            Arsc::new(Self::new_unsized(
+            if let Some(quota) = &self.quota {
+                quota.charge_node()?;
+            }
+            Arsc::new(Self::with_parts(
                 options.require(),
                 self.path.join(name),
                 self.file_inserter.clone(),
+                self.quota.clone(),
             )) as Arsc<dyn Entry>
         };
         entries.insert(name.into(), entry.clone());
+        drop(entries);
+        attach(&self.attrs, name, &entry);
+        self.attrs.touch(true);
+        let event = WatchEvent::Create(name.into());
+        self.attrs.watch().notify(event);
         Ok((entry, true))
     }
 
@@ -188,7 +268,9 @@ impl MemDirMut {
         let mut entries = self.entries.lock();
         match entries.entry(name) {
             MapEntry::Vacant(vacant) => {
+                attach(&self.attrs, vacant.key(), &ent);
                 vacant.insert(ent);
+                self.attrs.touch(true);
                 Ok(())
             }
             MapEntry::Occupied(_) => Err(Error::Exists),
@@ -196,10 +278,11 @@ impl MemDirMut {
     }
 
     fn remove(&self, name: &str) -> Result<(String, Arsc<dyn Entry>), Error> {
-        self.entries
-            .lock()
-            .remove_entry(name)
-            .ok_or(Error::NotFound)
+        let ret = self.entries.lock().remove_entry(name);
+        let ret = ret.ok_or(Error::NotFound)?;
+        detach(&self.attrs, &ret.0, &ret.1);
+        self.attrs.touch(true);
+        Ok(ret)
     }
 }
 
@@ -224,9 +307,8 @@ impl Entry for MemDirMut {
                     } else {
                         self.get_or_insert(name, options, path)?
                     };
-                entry
-                    .open(spawner, tokens, path, options, conn)
-                    .map(|res| res | created)
+                let res = entry.open(spawner, tokens, path, options, conn);
+                redirect(name, res.map(|res| res | created))
             }
             Some(_) => Err(Error::InvalidPath(path.into())),
             None => {
@@ -237,8 +319,9 @@ impl Entry for MemDirMut {
                     return Err(Error::Exists);
                 }
                 let require = options.require();
-                if !self.perm.contains(require) {
-                    return Err(Error::PermissionDenied(require - self.perm));
+                let perm = self.attrs.mode();
+                if !perm.contains(require) {
+                    return Err(Error::PermissionDenied(require - perm));
                 }
                 let server =
                     DirectoryServer::new(AsyncChannel::with_disp(conn, spawner.dispatch()));
@@ -250,12 +333,31 @@ impl Entry for MemDirMut {
     }
 
     fn metadata(&self) -> Result<Metadata, Error> {
+        let (mode, len) = (self.attrs.mode(), self.entries.lock().len());
         Ok(Metadata {
-            file_type: FileType::Directory,
-            perm: self.perm,
-            len: self.entries.lock().len(),
+            allocated: 0,
+            ..self.attrs.metadata(FileType::Directory, mode, len)
         })
     }
+
+    #[inline]
+    fn set_metadata(&self, attr: SetMetadata) -> Result<(), Error> {
+        if attr.mask.contains(SetMask::LEN) {
+            return Err(Error::InvalidType(FileType::Directory));
+        }
+        self.attrs.set(&attr);
+        Ok(())
+    }
+
+    #[inline]
+    fn statfs(&self) -> Result<FsStat, Error> {
+        statfs(self.quota.as_ref())
+    }
+
+    #[inline]
+    fn attrs(&self) -> Option<&Attrs> {
+        Some(&self.attrs)
+    }
 }
 
 #[async_trait]
@@ -272,6 +374,10 @@ impl Directory for MemDirMut {
         let metadata = entry.metadata()?;
         Ok(DirEntry { name, metadata })
     }
+
+    async fn readlink(&self, name: &str) -> Result<PathBuf, Error> {
+        readlink(self.get(name)?)
+    }
 }
 
 #[async_trait]
@@ -284,7 +390,8 @@ impl DirectoryMut for MemDirMut {
     ) -> Result<(), Error> {
         let dst_parent = dst_parent.into_any().downcast::<Self>().unwrap();
 
-        // Renaming `path/to` to `path/to/inner` will create dead cycle references.
+        // Renaming `path/to` to `path/to/inner` will create dead cycle
+        // references.
         let dst_full = dst_parent.path.join(dst);
         let src_full = self.path.join(src);
         if let Ok(next) = dst_full.strip_prefix(&src_full) {
@@ -301,6 +408,14 @@ impl DirectoryMut for MemDirMut {
         let res = dst_parent.insert(dst.into(), ent.clone());
         res.inspect_err(|_| drop(self.insert(name, ent)))?;
 
+        if Arsc::ptr_eq(&self, &dst_parent) {
+            let (old, new) = (src.into(), dst.into());
+            self.attrs.watch().notify(WatchEvent::Rename { old, new });
+        } else {
+            self.attrs.watch().notify(WatchEvent::Remove(src.into()));
+            let event = WatchEvent::Create(dst.into());
+            dst_parent.attrs.watch().notify(event);
+        }
         Ok(())
     }
 
@@ -326,7 +441,13 @@ impl DirectoryMut for MemDirMut {
 
         let ent = self.get(src)?;
 
-        dst_parent.insert(dst.into(), ent)
+        dst_parent.insert(dst.into(), ent.clone())?;
+        if let Some(attrs) = ent.attrs() {
+            attrs.link();
+        }
+        let event = WatchEvent::Create(dst.into());
+        dst_parent.attrs.watch().notify(event);
+        Ok(())
     }
 
     #[inline]
@@ -343,14 +464,151 @@ impl DirectoryMut for MemDirMut {
                 if metadata.file_type == FileType::Directory && metadata.len > 0 {
                     return Err(Error::DirNotEmpty);
                 }
-                ent.remove();
+                let entry = ent.remove();
+                drop(entries);
+                if let Some(attrs) = entry.attrs() {
+                    attrs.unlink();
+                }
+                detach(&self.attrs, name, &entry);
+                self.attrs.touch(true);
+                self.attrs.watch().notify(WatchEvent::Remove(name.into()));
                 Ok(())
             }
         }
     }
+
+    async fn symlink(&self, name: &str, target: &Path) -> Result<(), Error> {
+        if name.len() > MAX_NAME {
+            return Err(Error::InvalidNameLength(name.len()));
+        }
+        let symlink = match &self.quota {
+            Some(quota) => MemSymlink::with_quota(target.into(), quota.clone())?,
+            None => MemSymlink::new(target.into()),
+        };
+        self.insert(name.into(), Arsc::new(symlink))?;
+        let event = WatchEvent::Create(name.into());
+        self.attrs.watch().notify(event);
+        Ok(())
+    }
+}
+
+impl Drop for MemDirMut {
+    fn drop(&mut self) {
+        if let Some(quota) = &self.quota {
+            quota.release_node();
+        }
+    }
 }
 
 #[inline]
 pub fn builder() -> Builder {
     Builder::new()
 }
+
+#[cfg(test)]
+mod test {
+    use alloc::vec;
+    use core::time::Duration;
+    use std::{sync::mpsc, thread};
+
+    use futures_lite::future;
+    use solvent::prelude::{Channel, Phys, PhysOptions as Flags};
+    use solvent_async::{disp, exe::io_task};
+    use solvent_core::{
+        path::{Path, PathBuf},
+        sync::Arsc,
+    };
+    use solvent_rpc::io::{
+        dir::DirectorySyncClient, file::FileSyncClient, Error, FileType, OpenOptions, Permission,
+    };
+
+    use super::{builder, MemDirMut};
+    use crate::{dir::EventTokens, entry::Entry, mem::file::MemFile, spawn::Spawner};
+
+    #[test]
+    fn test_create() {
+        let inserter = Arsc::new(|_: &str| -> Result<Arsc<dyn Entry>, Error> {
+            let phys = Phys::allocate(1, Flags::ZEROED | Flags::RESIZABLE).map_err(Error::Other)?;
+            Ok(Arsc::new(MemFile::new(phys, Permission::all())) as Arsc<dyn Entry>)
+        });
+        let dir = MemDirMut::new(Permission::all(), PathBuf::new(), inserter);
+        let file_type = |name: &str, options: OpenOptions, next: &str| {
+            let (entry, created) = dir.get_or_insert(name, options, Path::new(next)).unwrap();
+            assert!(created);
+            entry.metadata().unwrap().file_type
+        };
+
+        let options = OpenOptions::CREATE | OpenOptions::WRITE;
+        assert_eq!(file_type("a", options, "b"), FileType::Directory);
+        assert_eq!(file_type("b", options, ""), FileType::File);
+        let options = options | OpenOptions::EXPECT_DIR;
+        assert_eq!(file_type("c", options, ""), FileType::Directory);
+    }
+
+    #[test]
+    fn test_serve() {
+        // Fail instead of hanging if the connections are never closed.
+        let (done, finished) = mpsc::channel();
+        thread::spawn(move || {
+            serve();
+            let _ = done.send(());
+        });
+        finished
+            .recv_timeout(Duration::from_secs(10))
+            .expect("Failed to serve the directory in time");
+    }
+
+    fn serve() {
+        let phys = Phys::allocate(5, Flags::ZEROED | Flags::RESIZABLE).unwrap();
+        unsafe { phys.write(0, b"hello") }.unwrap();
+        let file = Arsc::new(MemFile::new(phys, Permission::READ));
+        let dir = builder()
+            .entry(Path::new("a/hello"), Permission::READ, file)
+            .unwrap()
+            .empty_path(Path::new("b"), Permission::READ)
+            .unwrap()
+            .build();
+
+        let (tx, rx) = disp::dispatch(64);
+        let spawner = Spawner::new(tx);
+        let runner = spawner.runner();
+        let (client, server) = Channel::new();
+        let tokens = EventTokens::new();
+        let options = OpenOptions::READ;
+        dir.open(spawner, tokens, Path::new(""), options, server)
+            .expect("Failed to open the directory");
+
+        // Serve the connections, receiving the syscall results with the IO task
+        // of the runtime, until they are all closed and the spawners held by
+        // their tasks are dropped.
+        let server = thread::spawn(move || future::block_on(future::or(runner.run(), io_task(rx))));
+
+        let client = DirectorySyncClient::from(client);
+        let first = client.next_dirent(None).unwrap().unwrap();
+        assert_eq!(first.name, "a");
+        assert_eq!(first.metadata.file_type, FileType::Directory);
+        let second = client.next_dirent(Some(first.name)).unwrap().unwrap();
+        assert_eq!(second.name, "b");
+        let end = client.next_dirent(Some(second.name)).unwrap();
+        assert!(matches!(end, Err(Error::IterEnd)));
+
+        let (file, conn) = Channel::new();
+        let path = PathBuf::from("a/hello");
+        client.open(path, options, conn).unwrap().unwrap();
+        let file = FileSyncClient::from(file);
+        assert_eq!(
+            file.read(16).unwrap().unwrap(),
+            vec![b'h', b'e', b'l', b'l', b'o']
+        );
+
+        let (_, conn) = Channel::new();
+        let res = client.open(PathBuf::from("c"), options, conn).unwrap();
+        assert!(matches!(res, Err(Error::NotFound)));
+        let (_, conn) = Channel::new();
+        let res = client.open(PathBuf::from("a/hello"), OpenOptions::WRITE, conn);
+        assert!(matches!(res.unwrap(), Err(Error::PermissionDenied(_))));
+
+        drop((file, client));
+        server.join().expect("The server panicked");
+    }
+}
@@ -10,8 +10,8 @@ use solvent_core::{
 };
 use solvent_rpc::io::{Error, Permission};
 
-use super::{FileInserter, MemDir, MemDirMut};
-use crate::entry::Entry;
+use super::{attach_all, FileInserter, MemDir, MemDirMut};
+use crate::entry::{Attrs, Entry};
 
 #[derive(Default)]
 pub struct Builder {
@@ -99,6 +99,9 @@ impl Builder {
         if comps.peek().is_some() {
             let path = PathBuf::from_iter(comps);
             dir.empty_path(&path, perm)?;
+        } else {
+            // Make sure the directory itself can be opened even if empty.
+            dir.perm |= perm;
         }
         Ok(self)
     }
@@ -110,10 +113,12 @@ impl Builder {
                 BuilderInner::Dir(mut builder) => (name, builder.build() as Arsc<dyn Entry>),
                 BuilderInner::Entry(entry) => (name, entry),
             });
-        Arsc::new(MemDir {
+        let dir = MemDir {
             entries: entries.collect(),
-            perm: self.perm,
-        })
+            attrs: Attrs::new(self.perm),
+        };
+        attach_all(&dir.attrs, &dir.entries);
+        Arsc::new(dir)
     }
 
     #[inline]
@@ -139,12 +144,15 @@ impl Builder {
             }
             BuilderInner::Entry(entry) => (name, entry),
         });
-        Arsc::new(MemDirMut {
+        let dir = MemDirMut {
             entries: Mutex::new(entries.collect()),
-            perm: self.perm,
+            attrs: Attrs::new(self.perm),
             path,
             file_inserter: file_inserter as _,
-        })
+            quota: None,
+        };
+        attach_all(&dir.attrs, &dir.entries.lock());
+        Arsc::new(dir)
     }
 }
 
@@ -158,7 +166,7 @@ pub trait RecursiveBuilder: Iterator<Item = RecursiveBuild> + Sized {
     fn build(mut self, root_perm: Permission) -> Result<Arsc<MemDir>, Error> {
         let mut root = MemDir {
             entries: BTreeMap::new(),
-            perm: root_perm,
+            attrs: Attrs::new(root_perm),
         };
         build_recursive(&mut self, &mut root)?;
         Ok(Arsc::new(root))
@@ -171,9 +179,10 @@ pub trait RecursiveBuilder: Iterator<Item = RecursiveBuild> + Sized {
     ) -> Result<Arsc<MemDirMut>, Error> {
         let mut root = MemDirMut {
             entries: Mutex::new(BTreeMap::new()),
-            perm: root_perm,
+            attrs: Attrs::new(root_perm),
             path: "".into(),
             file_inserter: file_inserter.clone(),
+            quota: None,
         };
         build_recursive_mut(&mut self, &mut root, file_inserter)?;
         Ok(Arsc::new(root))
@@ -192,7 +201,7 @@ fn build_recursive(
                 MapEntry::Vacant(ent) => {
                     let mut sub = MemDir {
                         entries: BTreeMap::new(),
-                        perm,
+                        attrs: Attrs::new(perm),
                     };
                     build_recursive(iter, &mut sub)?;
                     ent.insert(Arsc::new(sub));
@@ -208,6 +217,7 @@ fn build_recursive(
             RecursiveBuild::Up => break,
         }
     }
+    attach_all(&dir.attrs, &dir.entries);
     Ok(())
 }
 
@@ -222,9 +232,10 @@ fn build_recursive_mut<F: FileInserter + 'static>(
                 MapEntry::Vacant(ent) => {
                     let mut sub = MemDirMut {
                         entries: Mutex::new(BTreeMap::new()),
-                        perm,
+                        attrs: Attrs::new(perm),
                         path: dir.path.join(name),
                         file_inserter: file_inserter.clone(),
+                        quota: None,
                     };
                     build_recursive_mut(iter, &mut sub, file_inserter.clone())?;
                     ent.insert(Arsc::new(sub));
@@ -240,5 +251,6 @@ fn build_recursive_mut<F: FileInserter + 'static>(
             RecursiveBuild::Up => break,
         }
     }
+    attach_all(&dir.attrs, dir.entries.get_mut());
     Ok(())
 }
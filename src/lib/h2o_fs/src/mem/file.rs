@@ -1,36 +1,109 @@
 use alloc::boxed::Box;
-use core::sync::atomic::{AtomicBool, Ordering::*};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering::*};
 
 use async_trait::async_trait;
 use solvent::prelude::{Channel, Phys};
 use solvent_async::{disp::DispSender, io::Stream, ipc::Channel as AsyncChannel};
-use solvent_core::{io::RawStream, path::Path, sync::Arsc};
+use solvent_core::{
+    io::RawStream,
+    path::Path,
+    sync::{Arsc, Mutex},
+};
 use solvent_rpc::io::{
     file::{FileServer, PhysOptions},
-    Error, FileType, Metadata, OpenOptions, Permission,
+    Error, FileType, FsStat, Metadata, OpenOptions, Permission, SetMask, SetMetadata,
 };
 
+use super::tmpfs::{allocated, statfs, Quota};
 use crate::{
     dir::EventTokens,
-    entry::Entry,
-    file::{handle_mapped, File},
+    entry::{Attrs, Entry},
+    file::{handle, handle_mapped, File, RangeLocks},
     spawn::Spawner,
 };
 
 pub struct MemFile {
     phys: Phys,
-    perm: Permission,
+    /// The length of the file, which differs from the object's only when the
+    /// file is empty, since the objects can't be resized to 0.
+    len: AtomicUsize,
+    attrs: Attrs,
     locked: AtomicBool,
+    locks: RangeLocks,
+    quota: Option<Arsc<Quota>>,
+    /// Serializes the changes of the length to keep the quota in sync.
+    resizing: Mutex<()>,
 }
 
 impl MemFile {
     #[inline]
     pub fn new(phys: Phys, perm: Permission) -> Self {
         MemFile {
+            len: AtomicUsize::new(phys.len()),
             phys,
-            perm,
+            attrs: Attrs::new(perm),
             locked: AtomicBool::new(false),
+            locks: RangeLocks::new(),
+            quota: None,
+            resizing: Mutex::new(()),
+        }
+    }
+
+    /// Create a file whose storage is charged against `quota`, which also
+    /// counts the file as a node.
+    ///
+    /// The content is only accessed through the requests instead of a mapped
+    /// stream, so that every growth is charged before it happens.
+    pub fn with_quota(phys: Phys, perm: Permission, quota: Arsc<Quota>) -> Result<Self, Error> {
+        quota.charge_node()?;
+        if let Err(err) = quota.resize(0, phys.len()) {
+            quota.release_node();
+            return Err(err);
+        }
+        Ok(MemFile {
+            len: AtomicUsize::new(phys.len()),
+            phys,
+            attrs: Attrs::new(perm),
+            locked: AtomicBool::new(false),
+            locks: RangeLocks::new(),
+            quota: Some(quota),
+            resizing: Mutex::new(()),
+        })
+    }
+
+    /// Resize the file to `new_len` bytes, or only grow it if `grow_only` is
+    /// set, charging the quota first.
+    fn set_len(&self, new_len: usize, grow_only: bool) -> Result<(), Error> {
+        let _resizing = self.resizing.lock();
+        let old_len = self.len.load(Acquire);
+        if grow_only && new_len <= old_len {
+            return Ok(());
+        }
+        if let Some(quota) = &self.quota {
+            quota.resize(old_len, new_len)?;
         }
+        let res = match new_len {
+            // Keep a zeroed byte instead, which is out of the file.
+            0 => self.phys.resize(1, true).and_then(|_| {
+                // SAFETY: The same as `write_at`.
+                unsafe { self.phys.write(0, &[0]) }.map(drop)
+            }),
+            _ => self.phys.resize(new_len, true),
+        };
+        match res {
+            Ok(()) => self.len.store(new_len, Release),
+            Err(_) => {
+                if let Some(quota) = &self.quota {
+                    let _ = quota.resize(new_len, old_len);
+                }
+            }
+        }
+        res.map_err(Error::Other)
+    }
+
+    #[inline]
+    fn file_len(&self) -> usize {
+        self.len.load(Acquire)
     }
 }
 
@@ -52,8 +125,15 @@ impl Entry for MemFile {
             return Err(Error::WouldBlock);
         }
         let require = options.require();
-        if !self.perm.contains(require) {
-            return Err(Error::PermissionDenied(require - self.perm));
+        let perm = self.attrs.mode();
+        if !perm.contains(require) {
+            return Err(Error::PermissionDenied(require - perm));
+        }
+        if self.quota.is_some() {
+            let server = FileServer::new(AsyncChannel::with_disp(conn, spawner.dispatch()));
+            let task = handle(self, spawner.clone(), tokens, 0, server, options);
+            spawner.spawn(task);
+            return Ok(false);
         }
         let stream = RawStream {
             phys: self.phys.clone(),
@@ -72,14 +152,35 @@ impl Entry for MemFile {
         Ok(false)
     }
 
-    #[inline]
     fn metadata(&self) -> Result<Metadata, Error> {
+        let len = self.file_len();
         Ok(Metadata {
-            file_type: FileType::File,
-            perm: self.perm,
-            len: self.phys.len(),
+            allocated: allocated(len),
+            ..self.attrs.metadata(FileType::File, self.attrs.mode(), len)
         })
     }
+
+    fn set_metadata(&self, attr: SetMetadata) -> Result<(), Error> {
+        if attr.mask.contains(SetMask::LEN) {
+            if self.locked.load(Acquire) {
+                return Err(Error::WouldBlock);
+            }
+            self.set_len(attr.len, false)?;
+            self.attrs.touch(true);
+        }
+        self.attrs.set(&attr);
+        Ok(())
+    }
+
+    #[inline]
+    fn statfs(&self) -> Result<FsStat, Error> {
+        statfs(self.quota.as_ref())
+    }
+
+    #[inline]
+    fn attrs(&self) -> Option<&Attrs> {
+        Some(&self.attrs)
+    }
 }
 
 #[async_trait]
@@ -99,25 +200,64 @@ impl File for MemFile {
         Ok(())
     }
 
+    #[inline]
+    fn range_locks(&self) -> Option<&RangeLocks> {
+        Some(&self.locks)
+    }
+
     #[inline]
     async fn flush(&self) -> Result<(), Error> {
         Ok(())
     }
 
-    async fn read_at(&self, _: usize, _: &mut [u8]) -> Result<usize, Error> {
-        unimplemented!("Default implementation in `StreamFile`")
+    async fn read_at(&self, pos: usize, buf: &mut [u8]) -> Result<usize, Error> {
+        let len = self.file_len().saturating_sub(pos).min(buf.len());
+        self.phys
+            .read_into(pos, &mut buf[..len])
+            .map_err(Error::Other)
     }
 
-    async fn write_at(&self, _: usize, _: &[u8]) -> Result<usize, Error> {
-        unimplemented!("Default implementation in `StreamFile`")
+    async fn write_at(&self, pos: usize, buf: &[u8]) -> Result<usize, Error> {
+        let end = pos.checked_add(buf.len()).ok_or(Error::InvalidSeek)?;
+        self.set_len(end, true)?;
+        // SAFETY: The object is not mapped by the file, and the kernel
+        // guarantees the memory safety of writing it otherwise.
+        unsafe { self.phys.write(pos, buf) }.map_err(Error::Other)
     }
 
+    async fn read_into(
+        &self,
+        pos: usize,
+        phys: &Phys,
+        offset: usize,
+        len: usize,
+    ) -> Result<usize, Error> {
+        let len = self.file_len().saturating_sub(pos).min(len);
+        // SAFETY: The client shares the object for this transfer only.
+        unsafe { phys.copy_from(offset, &self.phys, pos, len) }.map_err(Error::Other)
+    }
+
+    async fn write_from(
+        &self,
+        pos: usize,
+        phys: &Phys,
+        offset: usize,
+        len: usize,
+    ) -> Result<usize, Error> {
+        let end = pos.checked_add(len).ok_or(Error::InvalidSeek)?;
+        self.set_len(end, true)?;
+        // SAFETY: The same as `write_at`.
+        unsafe { self.phys.copy_from(pos, phys, offset, len) }.map_err(Error::Other)
+    }
+
+    #[inline]
     async fn len(&self) -> Result<usize, Error> {
-        unimplemented!("Default implementation in `StreamFile`")
+        Ok(self.file_len())
     }
 
-    async fn resize(&self, _: usize) -> Result<(), Error> {
-        unimplemented!("Default implementation in `StreamFile`")
+    #[inline]
+    async fn resize(&self, new_len: usize) -> Result<(), Error> {
+        self.set_len(new_len, false)
     }
 
     async fn phys(&self, options: PhysOptions) -> Result<Phys, Error> {
@@ -130,3 +270,12 @@ impl File for MemFile {
             .map_err(Error::Other)
     }
 }
+
+impl Drop for MemFile {
+    fn drop(&mut self) {
+        if let Some(quota) = &self.quota {
+            let _ = quota.resize(self.file_len(), 0);
+            quota.release_node();
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use solvent::prelude::Channel;
+use solvent_core::{
+    path::{Path, PathBuf},
+    sync::Arsc,
+};
+use solvent_rpc::io::{
+    Error, FileType, FsStat, Metadata, OpenOptions, Permission, SetMask, SetMetadata,
+};
+
+use super::tmpfs::{statfs, Quota};
+use crate::{
+    dir::EventTokens,
+    entry::{Attrs, Entry},
+    spawn::Spawner,
+};
+
+pub struct MemSymlink {
+    target: PathBuf,
+    attrs: Attrs,
+    quota: Option<Arsc<Quota>>,
+}
+
+impl MemSymlink {
+    #[inline]
+    pub fn new(target: PathBuf) -> Self {
+        MemSymlink {
+            target,
+            attrs: Attrs::new(Permission::all()),
+            quota: None,
+        }
+    }
+
+    /// Create a symbolic link counted as a node of `quota`.
+    pub fn with_quota(target: PathBuf, quota: Arsc<Quota>) -> Result<Self, Error> {
+        quota.charge_node()?;
+        Ok(MemSymlink {
+            target,
+            attrs: Attrs::new(Permission::all()),
+            quota: Some(quota),
+        })
+    }
+
+    #[inline]
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+}
+
+impl Entry for MemSymlink {
+    /// Redirect the connection to the target, which is left to the directories
+    /// above to resolve.
+    fn open(
+        self: Arsc<Self>,
+        _: Spawner,
+        _: EventTokens,
+        path: &Path,
+        options: OpenOptions,
+        conn: Channel,
+    ) -> Result<bool, Error> {
+        if path == Path::new("") && options.contains(OpenOptions::NOFOLLOW) {
+            return Err(Error::InvalidType(FileType::Symlink));
+        }
+        // Relative targets start from the directory containing the link, which
+        // is the parent of this entry.
+        let target = if self.target.is_absolute() {
+            self.target.join(path)
+        } else {
+            Path::new("..").join(&self.target).join(path)
+        };
+        Err(Error::Symlink { path: target, conn })
+    }
+
+    fn metadata(&self) -> Result<Metadata, Error> {
+        let len = self.target.as_os_str().len();
+        Ok(self
+            .attrs
+            .metadata(FileType::Symlink, self.attrs.mode(), len))
+    }
+
+    fn set_metadata(&self, attr: SetMetadata) -> Result<(), Error> {
+        if attr.mask.contains(SetMask::LEN) {
+            return Err(Error::InvalidType(FileType::Symlink));
+        }
+        self.attrs.set(&attr);
+        Ok(())
+    }
+
+    #[inline]
+    fn statfs(&self) -> Result<FsStat, Error> {
+        statfs(self.quota.as_ref())
+    }
+
+    #[inline]
+    fn attrs(&self) -> Option<&Attrs> {
+        Some(&self.attrs)
+    }
+}
+
+impl Drop for MemSymlink {
+    fn drop(&mut self) {
+        if let Some(quota) = &self.quota {
+            quota.release_node();
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use core::sync::atomic::{AtomicUsize, Ordering::*};
+
+use solvent::prelude::{Phys, PhysOptions, PAGE_MASK};
+use solvent_core::{path::PathBuf, sync::Arsc};
+use solvent_rpc::{
+    io::{fs as rpc, Error, FsStat, Permission},
+    Error as RpcError,
+};
+
+use super::{dir::MemDirMut, file::MemFile};
+use crate::entry::Entry;
+
+/// The limits of the bytes and the nodes of an in-memory file system, and the
+/// amounts charged against them.
+#[derive(Debug)]
+pub struct Quota {
+    max_bytes: usize,
+    max_nodes: usize,
+    bytes: AtomicUsize,
+    nodes: AtomicUsize,
+}
+
+/// The storage allocated for `len` bytes, which is rounded up to pages.
+#[inline]
+pub fn allocated(len: usize) -> usize {
+    (len + PAGE_MASK) & !PAGE_MASK
+}
+
+fn charge(counter: &AtomicUsize, max: usize, amount: usize) -> Result<(), Error> {
+    let res = counter.fetch_update(AcqRel, Acquire, |used| {
+        used.checked_add(amount).filter(|&used| used <= max)
+    });
+    res.map(drop).map_err(|_| Error::NoSpace)
+}
+
+impl Quota {
+    #[inline]
+    pub fn new(max_bytes: usize, max_nodes: usize) -> Arsc<Self> {
+        Arsc::new(Quota {
+            max_bytes,
+            max_nodes,
+            bytes: AtomicUsize::new(0),
+            nodes: AtomicUsize::new(0),
+        })
+    }
+
+    /// Reserve the storage of a file growing from `old_len` to `new_len`
+    /// bytes, or release it if the file shrinks.
+    pub fn resize(&self, old_len: usize, new_len: usize) -> Result<(), Error> {
+        let (old, new) = (allocated(old_len), allocated(new_len));
+        if new > old {
+            charge(&self.bytes, self.max_bytes, new - old)
+        } else {
+            self.bytes.fetch_sub(old - new, AcqRel);
+            Ok(())
+        }
+    }
+
+    #[inline]
+    pub fn charge_node(&self) -> Result<(), Error> {
+        charge(&self.nodes, self.max_nodes, 1)
+    }
+
+    #[inline]
+    pub fn release_node(&self) {
+        self.nodes.fetch_sub(1, AcqRel);
+    }
+
+    pub fn stat(&self) -> FsStat {
+        FsStat {
+            total_bytes: self.max_bytes,
+            used_bytes: self.bytes.load(Acquire),
+            total_nodes: self.max_nodes,
+            used_nodes: self.nodes.load(Acquire),
+        }
+    }
+}
+
+/// Create an empty in-memory file system limited to `max_bytes` bytes of file
+/// contents and `max_nodes` nodes, including the root directory.
+///
+/// The files are backed by resizable physical objects, which only commit the
+/// pages actually written. The quota is still charged for the whole length of
+/// the files rounded up to pages, reserving the storage of their holes so that
+/// filling them later can't run out of space.
+pub fn tmpfs(
+    perm: Permission,
+    max_bytes: usize,
+    max_nodes: usize,
+) -> Result<Arsc<MemDirMut>, Error> {
+    let quota = Quota::new(max_bytes, max_nodes);
+    let file_quota = quota.clone();
+    let file_inserter = Arsc::new(move |_: &str| -> Result<Arsc<dyn Entry>, Error> {
+        let phys = Phys::allocate(0, PhysOptions::ZEROED | PhysOptions::RESIZABLE);
+        let phys = phys.map_err(Error::Other)?;
+        let file = MemFile::with_quota(phys, perm, file_quota.clone())?;
+        Ok(Arsc::new(file))
+    });
+    let root = MemDirMut::with_quota(perm, PathBuf::new(), file_inserter, quota)?;
+    Ok(Arsc::new(root))
+}
+
+/// Get the usage of the file system limited by `quota`, if any.
+pub fn statfs(quota: Option<&Arsc<Quota>>) -> Result<FsStat, Error> {
+    match quota {
+        Some(quota) => Ok(quota.stat()),
+        None => Err(Error::RpcError(RpcError::UnsupportedMethod(
+            rpc::filesystem::STATFS,
+        ))),
+    }
+}
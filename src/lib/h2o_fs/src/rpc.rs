@@ -9,7 +9,7 @@ use solvent_rpc::{
         entry::{EntryRequest, EntryServer},
         Error, FileType, Metadata, OpenOptions, Permission,
     },
-    Server,
+    Limits, Server,
 };
 
 use crate::{dir::EventTokens, entry::Entry, spawn::Spawner};
@@ -86,6 +86,7 @@ where
     F: Future<Output = ()> + Sync + Send + 'static,
 {
     gen: G,
+    limits: Limits,
     _marker: PhantomData<S>,
 }
 
@@ -95,9 +96,17 @@ where
     G: Fn(S, Spawner) -> F + Sync + Send + 'static,
     F: Future<Output = ()> + Sync + Send + 'static,
 {
+    #[inline]
     pub fn new(func: G) -> Arsc<Self> {
+        Self::with_limits(func, Limits::new())
+    }
+
+    /// Create a node whose connections are limited by `limits`, so that the
+    /// service stays responsive under the load of misbehaving clients.
+    pub fn with_limits(func: G, limits: Limits) -> Arsc<Self> {
         Arsc::new(RpcNode {
             gen: func,
+            limits,
             _marker: PhantomData,
         })
     }
@@ -130,17 +139,17 @@ where
             return Err(Error::InvalidPath(path.into()));
         }
         let server = S::from(AsyncChannel::with_disp(conn, spawner.dispatch()));
-        let task = (self.gen)(server, spawner.clone());
+        let task = (self.gen)(server.with_limits(self.limits.clone()), spawner.clone());
         spawner.spawn(task);
         Ok(false)
     }
 
     fn metadata(&self) -> Result<Metadata, Error> {
-        Ok(Metadata {
-            file_type: FileType::RpcNode,
-            perm: Permission::READ | Permission::WRITE,
-            len: 0,
-        })
+        Ok(Metadata::new(
+            FileType::RpcNode,
+            Permission::READ | Permission::WRITE,
+            0,
+        ))
     }
 }
 
@@ -154,7 +163,7 @@ pub async fn handle_rpc<S, G, F>(
     G: Fn(S, Spawner) -> F + Sync + Send + 'static,
     F: Future<Output = ()> + Sync + Send + 'static,
 {
-    let (mut stream, _) = server.serve();
+    let (mut stream, _) = server.with_limits(spawner.limits()).serve();
 
     while let Some(request) = stream.next().await {
         let request = match request {
@@ -186,9 +195,13 @@ pub async fn handle_rpc<S, G, F>(
                 responder.send(())
             }
             EntryRequest::Metadata { responder } => responder.send(node.metadata()),
-            EntryRequest::Unknown(_) => {
+            EntryRequest::SetMetadata { attr, responder } => {
+                responder.send(node.set_metadata(attr))
+            }
+            EntryRequest::Statfs { responder } => responder.send(node.statfs()),
+            EntryRequest::Unknown(req) => {
                 log::warn!("unknown request received");
-                continue;
+                req.unsupported()
             }
         };
 
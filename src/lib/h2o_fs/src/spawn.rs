@@ -5,6 +5,7 @@ use crossbeam_queue::SegQueue;
 use futures_lite::{future::yield_now, Future};
 use solvent_async::{disp::DispSender, sync::channel::Sender};
 use solvent_core::sync::Arsc;
+use solvent_rpc::Limits;
 
 struct Data {
     task: Runnable,
@@ -16,6 +17,7 @@ struct Inner {
     disp: DispSender,
     stopped: AtomicBool,
     spawner_count: AtomicUsize,
+    limits: Limits,
 }
 
 pub struct Spawner {
@@ -39,13 +41,21 @@ impl Drop for Spawner {
 }
 
 impl Spawner {
+    #[inline]
     pub fn new(disp: DispSender) -> Self {
+        Self::with_limits(disp, Limits::new())
+    }
+
+    /// Create a spawner whose RPC servers are limited by `limits`, with the
+    /// shared limiter if any capping the requests of all the connections.
+    pub fn with_limits(disp: DispSender, limits: Limits) -> Self {
         Spawner {
             inner: Arsc::new(Inner {
                 queue: SegQueue::new(),
                 disp,
                 stopped: AtomicBool::new(false),
                 spawner_count: AtomicUsize::new(1),
+                limits,
             }),
         }
     }
@@ -64,6 +74,10 @@ impl Spawner {
         self.inner.disp.clone()
     }
 
+    pub fn limits(&self) -> Limits {
+        self.inner.limits.clone()
+    }
+
     pub fn spawn(&self, fut: impl Future<Output = ()> + Send + 'static) {
         if !self.is_stopped() {
             let i2 = self.inner.clone();
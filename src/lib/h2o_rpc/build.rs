@@ -4,5 +4,8 @@ fn main() {
     solvent_rpc_gen::generate(
         &PathBuf::from_str("imp").unwrap(),
         &PathBuf::from_str("target").unwrap(),
-    )
+    );
+
+    let sysroot = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../../target/sysroot");
+    solvent_rpc_gen::export(&PathBuf::from_str("imp").unwrap(), &sysroot);
 }
@@ -5,9 +5,12 @@ use core as std; // Hacking `thiserror::Error`.
 use core::error::Error as Trait;
 
 use solvent::error::Error as RawError;
+use solvent_rpc_macros::SerdePacket;
 use thiserror_impl::Error;
 
-#[derive(Error, Debug)]
+use crate as solvent_rpc;
+
+#[derive(Error, Debug, SerdePacket)]
 pub enum Error {
     #[error("inner channel disconnected")]
     Disconnected,
@@ -39,6 +42,9 @@ pub enum Error {
     #[error("invalid method: expected {expected}, found {found}")]
     InvalidMethod { expected: usize, found: usize },
 
+    #[error("method {0:#x} is not supported by the server")]
+    UnsupportedMethod(usize),
+
     #[error("extra buffer sized {extra_buffer_len} and {extra_handle_count} handles found")]
     SizeMismatch {
         extra_buffer_len: usize,
@@ -47,4 +53,7 @@ pub enum Error {
 
     #[error("The endpoint to be serialized is already in use")]
     EndpointInUse,
+
+    #[error("the server is too busy to handle the request")]
+    Busy,
 }
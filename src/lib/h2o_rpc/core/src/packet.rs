@@ -1,15 +1,60 @@
-use alloc::{boxed::Box, collections::BTreeMap, ffi::CString, format, string::String, vec::Vec};
-use core::{array, iter, mem, ptr::NonNull};
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    ffi::CString,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{array, error::Error as Trait, iter, mem, ptr::NonNull};
 
 use solvent::{
     impl_obj_for,
     prelude::{Handle, Object, Packet},
+    time::Instant,
 };
 
 use crate::Error;
 
 pub const MAGIC: usize = 0xac84fb7c0391;
 
+// Generated method IDs consist of ASCII hex digits, so the reserved ones below
+// never collide with them.
+
+/// The method ID of the built-in `describe` method, answered by every server
+/// with its protocol descriptor.
+pub const DESCRIBE: usize = 0xffff_ffff_ffff_0001;
+/// The method ID of the reply to an unsupported method, whose body is the ID of
+/// the requested method.
+pub const UNSUPPORTED: usize = 0xffff_ffff_ffff_0002;
+/// The method ID of an item sent through the side channel of a streaming
+/// method.
+pub const STREAM_ITEM: usize = 0xffff_ffff_ffff_0003;
+/// The method ID of a flow control packet sent back by the receiver of a
+/// streaming method, whose body is the number of the items it grants.
+pub const STREAM_CREDIT: usize = 0xffff_ffff_ffff_0004;
+/// The method ID of a request which cancels the pending request with the same
+/// packet ID.
+pub const CANCEL: usize = 0xffff_ffff_ffff_0005;
+/// The method ID of a request asking whether the server also serves the
+/// protocol whose path is the body on the same channel, replied with a `bool`.
+pub const UPGRADE: usize = 0xffff_ffff_ffff_0006;
+/// The method ID of the reply to a request which the server is too busy to
+/// handle, so that the client should back off before retrying.
+pub const BUSY: usize = 0xffff_ffff_ffff_0007;
+
+/// The header of every packet, placed before its body.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub method: usize,
+    /// The time after which the sender no longer waits for the reply.
+    pub deadline: Option<Instant>,
+}
+
+const DEADLINE_OFFSET: usize = mem::size_of::<usize>() * 2;
+/// The length of the buffer and the number of handles of a section.
+const SECTION_HEADER: usize = mem::size_of::<usize>() * 2;
+
 pub struct Serializer<'a>(&'a mut Packet);
 
 impl<'a> Serializer<'a> {
@@ -22,6 +67,26 @@ impl<'a> Serializer<'a> {
     fn extend_from_slice(&mut self, slice: &[u8]) {
         self.0.buffer.extend_from_slice(slice);
     }
+
+    /// Serialize a section prefixed with its length, so that the receiver can
+    /// skip the trailing data unknown to it. See [`Deserializer::section`].
+    pub fn section(
+        &mut self,
+        f: impl FnOnce(&mut Serializer) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let start = self.0.buffer.len();
+        let handles = self.0.handles.len();
+        self.extend_from_slice(&[0; SECTION_HEADER]);
+        f(self)?;
+
+        let len = self.0.buffer.len() - start - SECTION_HEADER;
+        let count = self.0.handles.len() - handles;
+        let header = &mut self.0.buffer[start..][..SECTION_HEADER];
+        let (l, c) = header.split_at_mut(mem::size_of::<usize>());
+        l.copy_from_slice(&len.to_ne_bytes());
+        c.copy_from_slice(&count.to_ne_bytes());
+        Ok(())
+    }
 }
 
 impl Extend<u8> for Serializer<'_> {
@@ -101,6 +166,33 @@ impl<'a> Deserializer<'a> {
         Ok(unsafe { self.next_handle_unchecked() })
     }
 
+    /// Deserialize a section serialized by [`Serializer::section`].
+    ///
+    /// The data left in the section after `f` are skipped, and the handles
+    /// left are dropped.
+    pub fn section<T>(
+        &mut self,
+        f: impl FnOnce(&mut Deserializer<'a>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let len = usize::deserialize(self)?;
+        let count = usize::deserialize(self)?;
+        self.check_buffer(len)?;
+        self.check_handles(count)?;
+
+        let (buffer, next) = self.buffer.split_at(len);
+        let (handles, next_handles) = self.handles.split_at(count);
+        self.buffer = next;
+        self.handles = next_handles;
+
+        let mut de = Deserializer { buffer, handles };
+        let ret = f(&mut de)?;
+        for &handle in de.handles {
+            // SAFETY: The ownership of the unknown handles is with the packet.
+            let _ = unsafe { solvent::obj::drop_raw(handle) };
+        }
+        Ok(ret)
+    }
+
     /// Returns the next handle unchecked of this [`Deserializer`].
     ///
     /// # Safety
@@ -377,6 +469,19 @@ impl SerdePacket for solvent::error::Error {
     }
 }
 
+/// Only the message of the error is transferred.
+impl SerdePacket for Box<dyn Trait + Send + Sync> {
+    #[inline]
+    fn serialize(self, ser: &mut Serializer) -> Result<(), Error> {
+        self.to_string().serialize(ser)
+    }
+
+    #[inline]
+    fn deserialize(de: &mut Deserializer) -> Result<Self, Error> {
+        String::deserialize(de).map(Into::into)
+    }
+}
+
 impl SerdePacket for Handle {
     #[inline]
     fn serialize(self, ser: &mut Serializer) -> Result<(), Error> {
@@ -425,6 +530,7 @@ macro_rules! serde_ko {
 
         impl SerdePacket for Option<$ty> {
             fn serialize(self, ser: &mut Serializer) -> Result<(), Error> {
+                <$ty>::ID.serialize(ser)?;
                 self.map(<$ty>::into_raw).serialize(ser)
             }
 
@@ -443,27 +549,71 @@ macro_rules! serde_ko {
 }
 impl_obj_for!(serde_ko);
 
+#[inline]
+fn raw_deadline(deadline: Option<Instant>) -> u128 {
+    // SAFETY: The raw value is only transferred between processes and is never
+    // used for measurements.
+    deadline.map_or(0, |deadline| unsafe { deadline.raw() })
+}
+
+#[inline]
 pub fn serialize<T: SerdePacket>(
     method_id: usize,
     data: T,
     output: &mut Packet,
+) -> Result<(), Error> {
+    serialize_with_deadline(method_id, None, data, output)
+}
+
+pub fn serialize_with_deadline<T: SerdePacket>(
+    method_id: usize,
+    deadline: Option<Instant>,
+    data: T,
+    output: &mut Packet,
 ) -> Result<(), Error> {
     output.clear();
     let mut ser = Serializer(output);
     MAGIC.serialize(&mut ser)?;
     method_id.serialize(&mut ser)?;
+    raw_deadline(deadline).serialize(&mut ser)?;
     data.serialize(&mut ser)?;
     Ok(())
 }
 
-pub fn deserialize_metadata(input: &Packet) -> Result<(usize, Deserializer), Error> {
+/// Replace the deadline in the header of a serialized packet.
+pub fn set_deadline(packet: &mut Packet, deadline: Option<Instant>) -> Result<(), Error> {
+    let end = DEADLINE_OFFSET + mem::size_of::<u128>();
+    let found = packet.buffer.len();
+    let buffer = packet
+        .buffer
+        .get_mut(DEADLINE_OFFSET..end)
+        .ok_or(Error::BufferTooShort {
+            found,
+            expected_at_least: end,
+        })?;
+    buffer.copy_from_slice(&raw_deadline(deadline).to_ne_bytes());
+    Ok(())
+}
+
+pub fn deserialize_header(input: &Packet) -> Result<(Header, Deserializer), Error> {
     let mut de = Deserializer::new(input);
     let magic = usize::deserialize(&mut de)?;
     if magic != MAGIC {
         return Err(Error::InvalidMagic(magic));
     }
-    let m = usize::deserialize(&mut de)?;
-    Ok((m, de))
+    let method = usize::deserialize(&mut de)?;
+    let deadline = u128::deserialize(&mut de)?;
+    let header = Header {
+        method,
+        // SAFETY: The deadline is serialized from a valid timestamp.
+        deadline: (deadline != 0).then(|| unsafe { Instant::from_raw(deadline) }),
+    };
+    Ok((header, de))
+}
+
+#[inline]
+pub fn deserialize_metadata(input: &Packet) -> Result<(usize, Deserializer), Error> {
+    deserialize_header(input).map(|(header, de)| (header.method, de))
 }
 
 pub fn deserialize_body<T: SerdePacket>(
@@ -483,6 +633,13 @@ pub fn deserialize<T: SerdePacket>(
     extra: Option<&mut [usize; 2]>,
 ) -> Result<T, Error> {
     let (m, de) = deserialize_metadata(input)?;
+    if m == UNSUPPORTED && method_id != UNSUPPORTED {
+        let method = deserialize_body(de, None)?;
+        return Err(Error::UnsupportedMethod(method));
+    }
+    if m == BUSY && method_id != BUSY {
+        return Err(Error::Busy);
+    }
     if m != method_id {
         return Err(Error::InvalidMethod {
             expected: method_id,
@@ -494,10 +651,234 @@ pub fn deserialize<T: SerdePacket>(
 
 #[cfg(test)]
 mod test {
-    use alloc::{collections::BTreeMap, string::String};
+    use alloc::{
+        boxed::Box,
+        collections::BTreeMap,
+        ffi::CString,
+        string::{String, ToString},
+        vec::Vec,
+    };
+    use core::{array, fmt::Debug, iter, ptr::NonNull, time::Duration};
+
+    use solvent::{
+        error::{Error as RawError, ERRC_RANGE},
+        prelude::{Channel, Event, Handle, Object, Packet, SIG_GENERIC},
+    };
+    use solvent_rpc_macros::SerdePacket;
+
+    use super::{deserialize, serialize, SerdePacket, BUSY, UNSUPPORTED};
+    use crate as solvent_rpc;
+    use crate::Error;
+
+    /// A deterministic xorshift generator for the property tests.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+    }
+
+    trait Arbitrary {
+        fn arbitrary(rng: &mut Rng) -> Self;
+    }
+
+    macro_rules! arbitrary_int {
+        ($($ty:ident),*) => {$(
+            impl Arbitrary for $ty {
+                fn arbitrary(rng: &mut Rng) -> Self {
+                    ((rng.next() as u128) << 64 | rng.next() as u128) as $ty
+                }
+            }
+        )*};
+    }
+    arbitrary_int!(u8, u16, u32, usize, u64, u128, i8, i16, i32, isize, i64, i128);
+
+    impl Arbitrary for f32 {
+        fn arbitrary(rng: &mut Rng) -> Self {
+            f32::from_bits(rng.next() as u32)
+        }
+    }
+
+    impl Arbitrary for f64 {
+        fn arbitrary(rng: &mut Rng) -> Self {
+            f64::from_bits(rng.next())
+        }
+    }
+
+    impl Arbitrary for () {
+        fn arbitrary(_: &mut Rng) -> Self {}
+    }
 
-    use super::{deserialize_body, serialize, SerdePacket};
-    use crate::packet::{Deserializer, Serializer};
+    impl Arbitrary for bool {
+        fn arbitrary(rng: &mut Rng) -> Self {
+            rng.below(2) == 1
+        }
+    }
+
+    impl<T: Arbitrary, const N: usize> Arbitrary for [T; N] {
+        fn arbitrary(rng: &mut Rng) -> Self {
+            array::from_fn(|_| T::arbitrary(rng))
+        }
+    }
+
+    macro_rules! arbitrary_tuples {
+        (@INNER $($ty:ident),+ $(,)?) => {
+            impl<$($ty: Arbitrary),+> Arbitrary for ($($ty,)+) {
+                fn arbitrary(rng: &mut Rng) -> Self {
+                    ($($ty::arbitrary(rng),)+)
+                }
+            }
+        };
+        () => {};
+        ($head:ident, $($ty:ident),* $(,)?) => {
+            arbitrary_tuples!(@INNER $head, $($ty),*);
+            arbitrary_tuples!($($ty,)*);
+        };
+    }
+    arbitrary_tuples!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+    impl<T: Arbitrary, E: Arbitrary> Arbitrary for Result<T, E> {
+        fn arbitrary(rng: &mut Rng) -> Self {
+            match rng.below(2) {
+                0 => Ok(T::arbitrary(rng)),
+                _ => Err(E::arbitrary(rng)),
+            }
+        }
+    }
+
+    impl Arbitrary for NonNull<u8> {
+        fn arbitrary(rng: &mut Rng) -> Self {
+            NonNull::new((rng.next() as usize | 1) as *mut u8).unwrap()
+        }
+    }
+
+    impl<T: Arbitrary> Arbitrary for Box<T> {
+        fn arbitrary(rng: &mut Rng) -> Self {
+            Box::new(T::arbitrary(rng))
+        }
+    }
+
+    impl<T: Arbitrary> Arbitrary for Vec<T> {
+        fn arbitrary(rng: &mut Rng) -> Self {
+            let len = rng.below(8) as usize;
+            iter::repeat_with(|| T::arbitrary(rng)).take(len).collect()
+        }
+    }
+
+    impl<K: Ord + Arbitrary, V: Arbitrary> Arbitrary for BTreeMap<K, V> {
+        fn arbitrary(rng: &mut Rng) -> Self {
+            Vec::<(K, V)>::arbitrary(rng).into_iter().collect()
+        }
+    }
+
+    impl Arbitrary for String {
+        fn arbitrary(rng: &mut Rng) -> Self {
+            let len = rng.below(16) as usize;
+            iter::repeat_with(|| char::from_u32(rng.below(0x11_0000) as u32))
+                .flatten()
+                .take(len)
+                .collect()
+        }
+    }
+
+    impl Arbitrary for CString {
+        fn arbitrary(rng: &mut Rng) -> Self {
+            let bytes = Vec::<u8>::arbitrary(rng);
+            CString::new(bytes.into_iter().map(|b| b.max(1)).collect::<Vec<_>>()).unwrap()
+        }
+    }
+
+    // Empty values are deserialized as `None`, so they are never generated.
+    macro_rules! arbitrary_option {
+        ($ty:ty, $is_empty:expr) => {
+            impl Arbitrary for Option<$ty> {
+                fn arbitrary(rng: &mut Rng) -> Self {
+                    Some(<$ty>::arbitrary(rng)).filter(|value| !$is_empty(value))
+                }
+            }
+        };
+    }
+    arbitrary_option!(Vec<u32>, Vec::is_empty);
+    arbitrary_option!(String, String::is_empty);
+    arbitrary_option!(CString, |s: &CString| s.as_bytes().is_empty());
+    arbitrary_option!(Handle, Handle::is_null);
+
+    impl Arbitrary for Handle {
+        fn arbitrary(rng: &mut Rng) -> Self {
+            Handle::new(rng.next() as u32)
+        }
+    }
+
+    impl Arbitrary for RawError {
+        fn arbitrary(rng: &mut Rng) -> Self {
+            let errc = ERRC_RANGE.start as u64 + rng.below(ERRC_RANGE.len() as u64);
+            RawError::try_from_retval((errc as usize).wrapping_neg()).unwrap()
+        }
+    }
+
+    impl Arbitrary for Error {
+        fn arbitrary(rng: &mut Rng) -> Self {
+            match rng.below(13) {
+                0 => Error::Disconnected,
+                1 => Error::ClientReceive(Arbitrary::arbitrary(rng)),
+                2 => Error::ClientSend(Arbitrary::arbitrary(rng)),
+                3 => Error::ServerReceive(Arbitrary::arbitrary(rng)),
+                4 => Error::ServerSend(Arbitrary::arbitrary(rng)),
+                5 => Error::BufferTooShort {
+                    found: Arbitrary::arbitrary(rng),
+                    expected_at_least: Arbitrary::arbitrary(rng),
+                },
+                6 => Error::TypeMismatch(String::arbitrary(rng).into()),
+                7 => Error::InvalidMagic(Arbitrary::arbitrary(rng)),
+                8 => Error::InvalidMethod {
+                    expected: Arbitrary::arbitrary(rng),
+                    found: Arbitrary::arbitrary(rng),
+                },
+                9 => Error::UnsupportedMethod(Arbitrary::arbitrary(rng)),
+                10 => Error::SizeMismatch {
+                    extra_buffer_len: Arbitrary::arbitrary(rng),
+                    extra_handle_count: Arbitrary::arbitrary(rng),
+                },
+                11 => Error::EndpointInUse,
+                _ => Error::Busy,
+            }
+        }
+    }
+
+    /// Check that every generated value is the same after a round trip, with
+    /// nothing left in the packet.
+    fn round_trip_by<T>(eq: impl Fn(&T, &T) -> bool)
+    where
+        T: SerdePacket + Arbitrary + Debug,
+    {
+        for seed in 1..=256 {
+            let value = T::arbitrary(&mut Rng(seed));
+            let mut packet = Packet::default();
+            serialize(12345, value, &mut packet).expect("Failed to serialize packet");
+
+            let mut extra = [0; 2];
+            let de: T = deserialize(12345, &packet, Some(&mut extra))
+                .expect("Failed to deserialize packet");
+            let expected = T::arbitrary(&mut Rng(seed));
+            assert!(eq(&de, &expected), "expected {expected:?}, found {de:?}");
+            assert_eq!(extra, [0, 0]);
+        }
+    }
+
+    fn round_trip<T>()
+    where
+        T: SerdePacket + Arbitrary + Debug + PartialEq,
+    {
+        round_trip_by::<T>(PartialEq::eq)
+    }
 
     #[test]
     fn test_btree_map() {
@@ -510,8 +891,228 @@ mod test {
         let mut packet = Default::default();
         serialize(12345, ser.clone(), &mut packet).expect("Failed to serialize packet");
 
-        let de = deserialize(12345, &packet, None).expect("Failed to deserialize packet");
+        let de: BTreeMap<_, _> =
+            deserialize(12345, &packet, None).expect("Failed to deserialize packet");
 
         assert_eq!(de, ser);
     }
+
+    #[test]
+    fn test_loopback() {
+        let (c1, c2) = Channel::new();
+        let event = Event::new(0);
+        let watcher = event.clone();
+
+        let mut packet = Packet::default();
+        serialize(12345, (String::from("event"), event), &mut packet)
+            .expect("Failed to serialize packet");
+        c1.send(&mut packet).expect("Failed to send packet");
+
+        let mut packet = Packet::default();
+        c2.receive(&mut packet).expect("Failed to receive packet");
+        let (name, event): (String, Event) =
+            deserialize(12345, &packet, None).expect("Failed to deserialize packet");
+        assert_eq!(name, "event");
+
+        event
+            .notify(0, SIG_GENERIC)
+            .expect("Failed to notify event");
+        let signal = watcher.try_wait(Duration::from_secs(1), true, false, SIG_GENERIC);
+        assert_eq!(signal, Ok(SIG_GENERIC));
+    }
+
+    #[test]
+    fn test_round_trip_basic() {
+        round_trip::<()>();
+        round_trip::<bool>();
+        round_trip::<(u8, u16, u32, usize, u64, u128)>();
+        round_trip::<(i8, i16, i32, isize, i64, i128)>();
+        round_trip_by::<(f32, f64)>(|a, b| {
+            a.0.to_bits() == b.0.to_bits() && a.1.to_bits() == b.1.to_bits()
+        });
+        round_trip::<[u32; 5]>();
+        round_trip::<(u8, u16, u32, u64, bool, (), i8, i16, i32, i64, u128, usize)>();
+        round_trip::<Result<u64, bool>>();
+        round_trip::<NonNull<u8>>();
+        round_trip::<Box<(u32, bool)>>();
+    }
+
+    #[test]
+    fn test_round_trip_collections() {
+        round_trip::<Vec<u32>>();
+        round_trip::<Vec<Vec<(u8, String)>>>();
+        round_trip::<Option<Vec<u32>>>();
+        round_trip::<String>();
+        round_trip::<Option<String>>();
+        round_trip::<CString>();
+        round_trip::<Option<CString>>();
+        round_trip::<BTreeMap<u16, Vec<bool>>>();
+    }
+
+    #[test]
+    fn test_round_trip_errors() {
+        round_trip::<RawError>();
+        round_trip::<Result<(), RawError>>();
+        round_trip_by::<Error>(|a, b| a.to_string() == b.to_string());
+    }
+
+    #[test]
+    fn test_reserved_replies() {
+        let mut packet = Packet::default();
+        serialize(UNSUPPORTED, 12345usize, &mut packet).expect("Failed to serialize packet");
+        let res = deserialize::<u32>(12345, &packet, None);
+        assert!(matches!(res, Err(Error::UnsupportedMethod(12345))));
+
+        let mut packet = Packet::default();
+        serialize(BUSY, (), &mut packet).expect("Failed to serialize packet");
+        let res = deserialize::<u32>(12345, &packet, None);
+        assert!(matches!(res, Err(Error::Busy)));
+        deserialize::<()>(BUSY, &packet, None).expect("Failed to deserialize packet");
+    }
+
+    #[test]
+    fn test_round_trip_handles() {
+        round_trip::<Handle>();
+        round_trip::<Option<Handle>>();
+        round_trip::<(Handle, Vec<Handle>)>();
+    }
+
+    #[test]
+    fn test_round_trip_objects() {
+        for signal in [0, SIG_GENERIC] {
+            let event = Event::new(signal);
+            let raw = unsafe { event.raw() };
+            let mut packet = Packet::default();
+            serialize(12345, (Some(event), None::<Event>), &mut packet)
+                .expect("Failed to serialize packet");
+
+            let (event, none): (Option<Event>, Option<Event>) =
+                deserialize(12345, &packet, None).expect("Failed to deserialize packet");
+            assert_eq!(event.map(|event| unsafe { event.raw() }), Some(raw));
+            assert!(none.is_none());
+        }
+    }
+
+    #[derive(SerdePacket, Debug, PartialEq)]
+    struct Generic<T, U: Clone> {
+        value: T,
+        list: Vec<U>,
+        #[serde_packet(skip)]
+        cache: Option<T>,
+    }
+
+    #[derive(SerdePacket, Debug, PartialEq)]
+    #[repr(u8)]
+    enum Tagged<T> {
+        A(T) = 7,
+        B {
+            name: String,
+        } = 2,
+        C,
+        #[serde_packet(tag = 100)]
+        D(#[serde_packet(skip)] u32, bool),
+    }
+
+    #[derive(SerdePacket, Debug, PartialEq)]
+    #[serde_packet(extensible)]
+    struct V1 {
+        id: u32,
+        name: String,
+    }
+
+    #[derive(SerdePacket)]
+    #[serde_packet(extensible)]
+    struct V2 {
+        id: u32,
+        name: String,
+        #[serde_packet(default)]
+        tags: Vec<String>,
+        #[serde_packet(default)]
+        channel: Option<Channel>,
+    }
+
+    #[test]
+    fn test_derive_generic() {
+        let value = Generic {
+            value: String::from("value"),
+            list: [1u32, 2, 3].to_vec(),
+            cache: Some(String::from("cache")),
+        };
+        let mut packet = Packet::default();
+        serialize(12345, value, &mut packet).expect("Failed to serialize packet");
+
+        let de: Generic<String, u32> =
+            deserialize(12345, &packet, None).expect("Failed to deserialize packet");
+        let expected = Generic {
+            value: String::from("value"),
+            list: [1, 2, 3].to_vec(),
+            cache: None,
+        };
+        assert_eq!(de, expected);
+    }
+
+    #[test]
+    fn test_derive_tags() {
+        let values = [
+            (Tagged::A(5u64), 7),
+            (Tagged::B { name: "b".into() }, 2),
+            (Tagged::C, 3),
+            (Tagged::D(1, true), 100),
+        ];
+        for (value, tag) in values {
+            let mut packet = Packet::default();
+            serialize(12345, value, &mut packet).expect("Failed to serialize packet");
+            let (_, mut de) = super::deserialize_header(&packet).unwrap();
+            assert_eq!(usize::deserialize(&mut de).unwrap(), tag);
+        }
+
+        let mut packet = Packet::default();
+        serialize(12345, Tagged::<u64>::D(1, true), &mut packet)
+            .expect("Failed to serialize packet");
+        let de: Tagged<u64> =
+            deserialize(12345, &packet, None).expect("Failed to deserialize packet");
+        assert_eq!(de, Tagged::D(0, true));
+
+        serialize(12345, 4usize, &mut packet).expect("Failed to serialize packet");
+        let de = deserialize::<Tagged<u64>>(12345, &packet, None);
+        assert!(matches!(de, Err(Error::TypeMismatch(_))));
+    }
+
+    #[test]
+    fn test_derive_extensible() {
+        // Newer peers to older ones: the unknown trailing fields are skipped.
+        let (channel, peer) = Channel::new();
+        let value = V2 {
+            id: 1,
+            name: "v2".into(),
+            tags: [String::from("new")].to_vec(),
+            channel: Some(channel),
+        };
+        let mut packet = Packet::default();
+        serialize(12345, (value, 0xdeadbeefu64), &mut packet).expect("Failed to serialize packet");
+
+        let mut extra = [0; 2];
+        let de: (V1, u64) =
+            deserialize(12345, &packet, Some(&mut extra)).expect("Failed to deserialize packet");
+        let expected = V1 {
+            id: 1,
+            name: "v2".into(),
+        };
+        assert_eq!(de, (expected, 0xdeadbeef));
+        assert_eq!(extra, [0, 0]);
+        // The unknown channel is dropped.
+        let res = peer.receive(&mut Packet::default());
+        assert_eq!(res, Err(solvent::error::EPIPE));
+
+        // Older peers to newer ones: the absent fields are defaulted.
+        let value = V1 {
+            id: 2,
+            name: "v1".into(),
+        };
+        serialize(12345, (value, 0xdeadbeefu64), &mut packet).expect("Failed to serialize packet");
+        let (de, tail): (V2, u64) =
+            deserialize(12345, &packet, None).expect("Failed to deserialize packet");
+        assert_eq!((de.id, &*de.name, tail), (2, "v1", 0xdeadbeef));
+        assert!(de.tags.is_empty() && de.channel.is_none());
+    }
 }
@@ -0,0 +1,634 @@
+//! The C bindings of the protocols, generated from the [`Idl`].
+//!
+//! Every type gets a C type and a set of `rpc_encode_*`, `rpc_decode_*` and
+//! `rpc_free_*` functions, built on the runtime in `c/packet.h`. Every method
+//! of the protocols gets its ID and the functions to encode the request and
+//! decode the response, while sending and receiving the packets is left to
+//! the caller.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Write,
+    path::PathBuf,
+};
+
+use convert_case::{Case, Casing};
+
+use crate::idl::{Def, Field, Idl, MethodDef, Prim, ProtocolDef, Ty, Variant, OBJECTS};
+
+/// The runtime shared by all the bindings.
+pub const RUNTIME: &str = include_str!("c/packet.h");
+
+const HEADER: &str = "// This file is generated by solvent-rpc-gen; do not edit it manually!\n\n\
+    #pragma once\n\n";
+
+const KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+    "restrict", "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef",
+    "union", "unsigned", "void", "volatile", "while",
+];
+
+fn c_ident(ident: &str) -> String {
+    let ident = ident
+        .strip_prefix("r#")
+        .unwrap_or(ident)
+        .to_case(Case::Snake);
+    if KEYWORDS.contains(&&*ident) {
+        ident + "_"
+    } else {
+        ident
+    }
+}
+
+/// Convert a full name in the IDL into a C identifier.
+fn snake(name: &str) -> String {
+    let segs = name.split("::").map(|seg| seg.to_case(Case::Snake));
+    segs.collect::<Vec<_>>().join("_")
+}
+
+fn comment(out: &mut String, doc: &str) {
+    for line in doc.lines() {
+        match line {
+            "" => out.push_str("//\n"),
+            line => writeln!(out, "// {line}").unwrap(),
+        }
+    }
+}
+
+fn prim_name(prim: Prim) -> &'static str {
+    match prim {
+        Prim::Unit => "unit",
+        Prim::Bool => "bool",
+        Prim::U8 => "u8",
+        Prim::U16 => "u16",
+        Prim::U32 => "u32",
+        Prim::U64 => "u64",
+        Prim::U128 => "u128",
+        Prim::Usize => "usize",
+        Prim::I8 => "i8",
+        Prim::I16 => "i16",
+        Prim::I32 => "i32",
+        Prim::I64 => "i64",
+        Prim::I128 => "i128",
+        Prim::Isize => "isize",
+        Prim::F32 => "f32",
+        Prim::F64 => "f64",
+        Prim::String => "string",
+        Prim::CString => "cstring",
+        Prim::Bytes => "bytes",
+        Prim::Handle => "handle",
+        Prim::Status => "status",
+    }
+}
+
+fn prim_ty(prim: Prim) -> &'static str {
+    match prim {
+        Prim::Unit => "struct rpc_unit",
+        Prim::Bool => "bool",
+        Prim::U8 => "uint8_t",
+        Prim::U16 => "uint16_t",
+        Prim::U32 => "uint32_t",
+        Prim::U64 => "uint64_t",
+        Prim::U128 => "unsigned __int128",
+        Prim::Usize => "size_t",
+        Prim::I8 => "int8_t",
+        Prim::I16 => "int16_t",
+        Prim::I32 => "int32_t",
+        Prim::I64 => "int64_t",
+        Prim::I128 => "__int128",
+        Prim::Isize | Prim::Status => "ptrdiff_t",
+        Prim::F32 => "float",
+        Prim::F64 => "double",
+        Prim::String | Prim::Bytes => "struct rpc_bytes",
+        Prim::CString => "char *",
+        Prim::Handle => "Handle",
+    }
+}
+
+/// Remove the wrappers that don't affect the representation in C.
+fn normalize(ty: &Ty) -> &Ty {
+    match ty {
+        Ty::Option(inner) => normalize(inner),
+        Ty::Vec(inner) if **inner == Ty::Prim(Prim::U8) => &Ty::Prim(Prim::Bytes),
+        ty => ty,
+    }
+}
+
+fn mangle(ty: &Ty) -> String {
+    match normalize(ty) {
+        Ty::Prim(prim) => prim_name(*prim).into(),
+        Ty::Vec(inner) => format!("vec_{}", mangle(inner)),
+        Ty::Result(ok, err) => format!("result_{}_{}", mangle(ok), mangle(err)),
+        Ty::Tuple(elems) => {
+            let elems = elems.iter().map(mangle).collect::<Vec<_>>();
+            format!("tuple{}_{}", elems.len(), elems.join("_"))
+        }
+        Ty::Array(inner, len) => format!("array{len}_{}", mangle(inner)),
+        Ty::Object(name) | Ty::Named(name) => snake(name),
+        Ty::Option(_) => unreachable!(),
+    }
+}
+
+/// A field of a C structure to be encoded in order.
+struct CField {
+    name: String,
+    ty: Ty,
+    default: bool,
+}
+
+impl CField {
+    fn from_fields(fields: &[Field]) -> Vec<Self> {
+        let fields = fields.iter().enumerate().map(|(index, field)| CField {
+            name: match &field.name {
+                Some(name) => c_ident(name),
+                None => format!("_{index}"),
+            },
+            ty: field.ty.clone(),
+            default: field.default,
+        });
+        fields.collect()
+    }
+
+    fn unnamed(tys: &[Ty]) -> Vec<Self> {
+        let fields = tys.iter().enumerate().map(|(index, ty)| CField {
+            name: format!("_{index}"),
+            ty: ty.clone(),
+            default: false,
+        });
+        fields.collect()
+    }
+}
+
+struct Gen<'a> {
+    idl: &'a Idl,
+    visited: HashSet<String>,
+    /// The declarations of the types, in the order of their dependencies.
+    decls: String,
+    protos: String,
+    funcs: String,
+}
+
+impl<'a> Gen<'a> {
+    fn c_ty(&self, ty: &Ty) -> String {
+        match normalize(ty) {
+            Ty::Prim(prim) => prim_ty(*prim).into(),
+            Ty::Object(_) => "Handle".into(),
+            Ty::Named(name) if matches!(self.idl.types[name].def, Def::Flags { .. }) => {
+                format!("rpc_{}", mangle(ty))
+            }
+            _ => format!("struct rpc_{}", mangle(ty)),
+        }
+    }
+
+    fn field_decls(&self, fields: &[CField], indent: &str) -> String {
+        let mut ret = String::new();
+        for field in fields {
+            let ty = self.c_ty(&field.ty);
+            writeln!(ret, "{indent}{ty} {};", field.name).unwrap();
+        }
+        if fields.is_empty() {
+            writeln!(ret, "{indent}char unused;").unwrap();
+        }
+        ret
+    }
+
+    /// Emit the functions of a type, whose bodies are written with `value`
+    /// pointing to the type.
+    fn funcs(&mut self, ty: &Ty, doc: &str, decl: String, [ser, de, free]: [String; 3]) {
+        let (m, c) = (mangle(ty), self.c_ty(ty));
+        comment(&mut self.decls, doc);
+        self.decls += &decl;
+        self.decls += "\n";
+
+        let protos = [
+            format!("static inline int rpc_encode_{m}(struct rpc_ser *ser, {c} const *value)"),
+            format!("static inline int rpc_decode_{m}(struct rpc_de *de, {c} *value)"),
+            format!("static inline void rpc_free_{m}({c} *value)"),
+        ];
+        for (proto, body) in protos.iter().zip([ser, de, free]) {
+            writeln!(self.protos, "{proto};").unwrap();
+            writeln!(self.funcs, "{proto} {{\n{body}}}\n").unwrap();
+        }
+    }
+
+    fn ser_fields(&self, fields: &[CField], prefix: &str, indent: &str) -> String {
+        let mut ret = String::new();
+        for field in fields {
+            let (m, name) = (mangle(&field.ty), &field.name);
+            writeln!(
+                ret,
+                "{indent}if ((ret = rpc_encode_{m}(ser, &value->{prefix}{name})))\n\
+                {indent}    return ret;"
+            )
+            .unwrap();
+        }
+        ret
+    }
+
+    fn de_fields(&self, fields: &[CField], prefix: &str, indent: &str) -> String {
+        let mut ret = String::new();
+        for field in fields {
+            let (m, name) = (mangle(&field.ty), &field.name);
+            // Fields appended in newer versions are absent in the packets from
+            // older peers, and are left zeroed.
+            let cond = if field.default {
+                "!rpc_de_empty(de) && "
+            } else {
+                ""
+            };
+            writeln!(
+                ret,
+                "{indent}if ({cond}(ret = rpc_decode_{m}(de, &value->{prefix}{name})))\n\
+                {indent}    return ret;"
+            )
+            .unwrap();
+        }
+        ret
+    }
+
+    fn free_fields(&self, fields: &[CField], prefix: &str, indent: &str) -> String {
+        let mut ret = String::new();
+        for field in fields {
+            let (m, name) = (mangle(&field.ty), &field.name);
+            writeln!(ret, "{indent}rpc_free_{m}(&value->{prefix}{name});").unwrap();
+        }
+        ret
+    }
+
+    /// Wrap the bodies of an extensible type in a length-prefixed section.
+    fn sections(extensible: bool, ser: String, de: String) -> (String, String) {
+        if !extensible {
+            let ser = format!("    int ret = RPC_OK;\n{ser}    return ret;\n");
+            let de = format!(
+                "    int ret = RPC_OK;\n    memset(value, 0, sizeof(*value));\n{de}    return ret;\n"
+            );
+            return (ser, de);
+        }
+        let ser = format!(
+            "    int ret;\n    size_t start, handle_start = ser->handle_count;\n    \
+            if ((ret = rpc_ser_section_begin(ser, &start)))\n        return ret;\n\
+            {ser}    rpc_ser_section_end(ser, start, handle_start);\n    return RPC_OK;\n"
+        );
+        let de = format!(
+            "    int ret;\n    struct rpc_de section, *outer = de;\n    \
+            memset(value, 0, sizeof(*value));\n    \
+            if ((ret = rpc_de_section_begin(outer, &section)))\n        return ret;\n    \
+            de = &section;\n{de}    rpc_de_section_end(&section);\n    return RPC_OK;\n"
+        );
+        (ser, de)
+    }
+
+    fn visit(&mut self, ty: &Ty) {
+        let ty = normalize(ty);
+        if matches!(ty, Ty::Prim(_) | Ty::Object(_)) || !self.visited.insert(mangle(ty)) {
+            return;
+        }
+        match ty {
+            Ty::Vec(inner) | Ty::Array(inner, _) => self.visit(inner),
+            Ty::Result(ok, err) => {
+                self.visit(ok);
+                self.visit(err);
+            }
+            Ty::Tuple(elems) => elems.iter().for_each(|ty| self.visit(ty)),
+            Ty::Named(name) => match &self.idl.types[name].def {
+                Def::Struct { fields, .. } => fields.iter().for_each(|f| self.visit(&f.ty)),
+                Def::Enum { variants, .. } => variants
+                    .iter()
+                    .flat_map(|var| &var.fields)
+                    .for_each(|f| self.visit(&f.ty)),
+                Def::Flags { .. } => {}
+            },
+            _ => unreachable!(),
+        }
+        self.emit(ty);
+    }
+
+    fn emit(&mut self, ty: &Ty) {
+        let (m, c) = (mangle(ty), self.c_ty(ty));
+        match ty {
+            Ty::Vec(inner) => {
+                let (im, ic) = (mangle(inner), self.c_ty(inner));
+                let decl = format!("{c} {{\n    {ic} *data;\n    size_t len;\n}};\n");
+                let ser = format!(
+                    "    int ret = rpc_put(ser, &value->len, sizeof(size_t));\n    \
+                    for (size_t i = 0; !ret && i < value->len; i++)\n        \
+                    ret = rpc_encode_{im}(ser, &value->data[i]);\n    return ret;\n"
+                );
+                let de = format!(
+                    "    size_t len;\n    int ret;\n    memset(value, 0, sizeof(*value));\n    \
+                    if ((ret = rpc_get(de, &len, sizeof(size_t))) || (ret = rpc_check_len(de, len)) || !len)\n        \
+                    return ret;\n    \
+                    if (!(value->data = ({ic} *)calloc(len, sizeof({ic}))))\n        \
+                    return RPC_ERR_NO_MEMORY;\n    value->len = len;\n    \
+                    for (size_t i = 0; !ret && i < len; i++)\n        \
+                    ret = rpc_decode_{im}(de, &value->data[i]);\n    return ret;\n"
+                );
+                let free = format!(
+                    "    for (size_t i = 0; i < value->len; i++)\n        \
+                    rpc_free_{im}(&value->data[i]);\n    free(value->data);\n    \
+                    memset(value, 0, sizeof(*value));\n"
+                );
+                self.funcs(ty, "", decl, [ser, de, free]);
+            }
+            Ty::Array(inner, len) => {
+                let (im, ic) = (mangle(inner), self.c_ty(inner));
+                let decl = format!("{c} {{\n    {ic} data[{len}];\n}};\n");
+                let ser = format!(
+                    "    int ret = RPC_OK;\n    for (size_t i = 0; !ret && i < {len}; i++)\n        \
+                    ret = rpc_encode_{im}(ser, &value->data[i]);\n    return ret;\n"
+                );
+                let de = format!(
+                    "    int ret = RPC_OK;\n    memset(value, 0, sizeof(*value));\n    \
+                    for (size_t i = 0; !ret && i < {len}; i++)\n        \
+                    ret = rpc_decode_{im}(de, &value->data[i]);\n    return ret;\n"
+                );
+                let free = format!(
+                    "    for (size_t i = 0; i < {len}; i++)\n        rpc_free_{im}(&value->data[i]);\n"
+                );
+                self.funcs(ty, "", decl, [ser, de, free]);
+            }
+            Ty::Result(ok, err) => {
+                let (om, oc) = (mangle(ok), self.c_ty(ok));
+                let (em, ec) = (mangle(err), self.c_ty(err));
+                let decl = format!(
+                    "{c} {{\n    bool is_err;\n    union {{\n        {oc} ok;\n        {ec} err;\n    }};\n}};\n"
+                );
+                let ser = format!(
+                    "    uint8_t index = value->is_err ? 1 : 0;\n    \
+                    int ret = rpc_put(ser, &index, 1);\n    if (ret)\n        return ret;\n    \
+                    return value->is_err ? rpc_encode_{em}(ser, &value->err) : rpc_encode_{om}(ser, &value->ok);\n"
+                );
+                let de = format!(
+                    "    uint8_t index;\n    int ret;\n    memset(value, 0, sizeof(*value));\n    \
+                    if ((ret = rpc_get(de, &index, 1)))\n        return ret;\n    \
+                    if (index > 1)\n        return RPC_ERR_TYPE_MISMATCH;\n    \
+                    value->is_err = index;\n    \
+                    return index ? rpc_decode_{em}(de, &value->err) : rpc_decode_{om}(de, &value->ok);\n"
+                );
+                let free = format!(
+                    "    if (value->is_err)\n        rpc_free_{em}(&value->err);\n    \
+                    else\n        rpc_free_{om}(&value->ok);\n"
+                );
+                self.funcs(ty, "", decl, [ser, de, free]);
+            }
+            Ty::Tuple(elems) => {
+                let fields = CField::unnamed(elems);
+                let decl = format!("{c} {{\n{}}};\n", self.field_decls(&fields, "    "));
+                let (ser, de) = Self::sections(
+                    false,
+                    self.ser_fields(&fields, "", "    "),
+                    self.de_fields(&fields, "", "    "),
+                );
+                let free = self.free_fields(&fields, "", "    ");
+                self.funcs(ty, "", decl, [ser, de, free]);
+            }
+            Ty::Named(name) => {
+                let idl = self.idl;
+                let def = &idl.types[name];
+                match &def.def {
+                    Def::Struct { extensible, fields } => {
+                        let fields = CField::from_fields(fields);
+                        let decl = format!("{c} {{\n{}}};\n", self.field_decls(&fields, "    "));
+                        let (ser, de) = Self::sections(
+                            *extensible,
+                            self.ser_fields(&fields, "", "    "),
+                            self.de_fields(&fields, "", "    "),
+                        );
+                        let free = self.free_fields(&fields, "", "    ");
+                        self.funcs(ty, &def.doc, decl, [ser, de, free]);
+                    }
+                    Def::Enum {
+                        extensible,
+                        variants,
+                    } => self.emit_enum(ty, &def.doc, *extensible, variants),
+                    Def::Flags { bits, flags } => {
+                        comment(&mut self.decls, &def.doc);
+                        let upper = c.to_case(Case::UpperSnake);
+                        writeln!(self.decls, "typedef {} {c};", prim_ty(*bits)).unwrap();
+                        for flag in flags {
+                            let name = flag.name.to_case(Case::UpperSnake);
+                            let value = flag.value;
+                            writeln!(self.decls, "#define {upper}_{name} (({c}){value:#x}ull)")
+                                .unwrap();
+                        }
+                        writeln!(self.decls, "RPC_DEFINE_SCALAR({m}, {c})\n").unwrap();
+                    }
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn emit_enum(&mut self, ty: &Ty, doc: &str, extensible: bool, variants: &[Variant]) {
+        let c = self.c_ty(ty);
+        let upper = format!("rpc_{}", mangle(ty)).to_case(Case::UpperSnake);
+
+        let mut tags = String::new();
+        let mut union = String::new();
+        let mut ser = String::new();
+        let mut de = String::new();
+        let mut free = String::new();
+        for var in variants {
+            let tag = format!("{upper}_{}", var.name.to_case(Case::UpperSnake));
+            writeln!(tags, "    {tag} = {},", var.tag).unwrap();
+
+            let fields = CField::from_fields(&var.fields);
+            let prefix = format!("{}.", c_ident(&var.name));
+            if !fields.is_empty() {
+                let decls = self.field_decls(&fields, "            ");
+                let name = c_ident(&var.name);
+                write!(union, "        struct {{\n{decls}        }} {name};\n").unwrap();
+            }
+            let body = self.ser_fields(&fields, &prefix, "        ");
+            write!(ser, "    case {tag}:\n{body}        break;\n").unwrap();
+            let body = self.de_fields(&fields, &prefix, "        ");
+            write!(de, "    case {tag}:\n{body}        break;\n").unwrap();
+            if !fields.is_empty() {
+                let body = self.free_fields(&fields, &prefix, "        ");
+                write!(free, "    case {tag}:\n{body}        break;\n").unwrap();
+            }
+        }
+
+        let mut decl = format!(
+            "enum {}_tag {{\n{tags}}};\n\n{c} {{\n    size_t tag;\n",
+            &c[7..]
+        );
+        if !union.is_empty() {
+            write!(decl, "    union {{\n{union}    }};\n").unwrap();
+        }
+        decl += "};\n";
+
+        let ser = format!(
+            "    if ((ret = rpc_put(ser, &value->tag, sizeof(size_t))))\n        return ret;\n    \
+            switch (value->tag) {{\n{ser}    default:\n        return RPC_ERR_TYPE_MISMATCH;\n    }}\n"
+        );
+        let de = format!(
+            "    if ((ret = rpc_get(de, &value->tag, sizeof(size_t))))\n        return ret;\n    \
+            switch (value->tag) {{\n{de}    default:\n        return RPC_ERR_TYPE_MISMATCH;\n    }}\n"
+        );
+        let free = if free.is_empty() {
+            "    (void)value;\n".into()
+        } else {
+            format!("    switch (value->tag) {{\n{free}    default:\n        break;\n    }}\n")
+        };
+        let (ser, de) = Self::sections(extensible, ser, de);
+        self.funcs(ty, doc, decl, [ser, de, free]);
+    }
+
+    fn method(&self, out: &mut String, proto: &str, method: &MethodDef) {
+        let prefix = format!("rpc_{proto}_{}", c_ident(&method.name));
+        let id = prefix.to_case(Case::UpperSnake);
+
+        out.push('\n');
+        comment(out, &method.doc);
+        if method.deprecated {
+            writeln!(out, "//\n// Deprecated.").unwrap();
+        }
+        writeln!(out, "#define {id} ((size_t){:#x}ull)\n", method.id).unwrap();
+
+        let mut params = vec!["struct rpc_ser *ser".to_string()];
+        let mut body = format!(
+            "    int ret = rpc_put_header(ser, {id});\n    if (ret)\n        return ret;\n"
+        );
+        for arg in &method.args {
+            let name = c_ident(&arg.name);
+            params.push(format!("{} const *{name}", self.c_ty(&arg.ty)));
+            let m = mangle(&arg.ty);
+            writeln!(
+                body,
+                "    if ((ret = rpc_encode_{m}(ser, {name})))\n        return ret;"
+            )
+            .unwrap();
+        }
+        if method.stream.is_some() {
+            // The channel to receive the items of the stream.
+            params.push("Handle const *stream".into());
+            body += "    if ((ret = rpc_encode_channel(ser, stream)))\n        return ret;\n";
+        }
+        write!(
+            out,
+            "static inline int {prefix}_request({}) {{\n{body}    return RPC_OK;\n}}\n\n",
+            params.join(", ")
+        )
+        .unwrap();
+
+        let head =
+            format!("    int ret = rpc_get_header(de, {id});\n    if (ret)\n        return ret;\n");
+        if method.output == Ty::Prim(Prim::Unit) {
+            write!(
+                out,
+                "static inline int {prefix}_response(struct rpc_de *de) {{\n{head}    return RPC_OK;\n}}\n"
+            )
+            .unwrap();
+        } else {
+            let (m, c) = (mangle(&method.output), self.c_ty(&method.output));
+            write!(
+                out,
+                "static inline int {prefix}_response(struct rpc_de *de, {c} *output) {{\n\
+                {head}    return rpc_decode_{m}(de, output);\n}}\n"
+            )
+            .unwrap();
+        }
+
+        if let Some(item) = &method.stream {
+            let (m, c) = (mangle(item), self.c_ty(item));
+            write!(
+                out,
+                "\n// Decode an item received from the channel of the stream.\n\
+                static inline int {prefix}_item(struct rpc_de *de, {c} *item) {{\n    \
+                int ret = rpc_get_header(de, RPC_METHOD_STREAM_ITEM);\n    if (ret)\n        return ret;\n    \
+                return rpc_decode_{m}(de, item);\n}}\n"
+            )
+            .unwrap();
+        }
+    }
+
+    fn protocol(&self, proto: &ProtocolDef) -> String {
+        let name = snake(&proto.name);
+        let upper = format!("rpc_{name}").to_case(Case::UpperSnake);
+
+        let mut out = String::new();
+        writeln!(out, "\n// The protocol `{}`.", proto.name).unwrap();
+        if !proto.doc.is_empty() {
+            writeln!(out, "//").unwrap();
+            comment(&mut out, &proto.doc);
+        }
+        writeln!(out, "#define {upper}_VERSION {}", proto.version).unwrap();
+        for event in &proto.events {
+            // The packet of an event contains its ID and then the event itself.
+            let ev = mangle(&event.ty).to_case(Case::UpperSnake);
+            writeln!(
+                out,
+                "#define {upper}_EVENT_{ev} ((uint64_t){:#x}ull)",
+                event.id
+            )
+            .unwrap();
+        }
+        for method in &proto.methods {
+            self.method(&mut out, &name, method);
+        }
+        out
+    }
+}
+
+/// Generate the headers of the C bindings, relative to the include directory.
+pub fn gen(idl: &Idl) -> Vec<(PathBuf, String)> {
+    let mut gen = Gen {
+        idl,
+        visited: HashSet::new(),
+        decls: String::new(),
+        protos: String::new(),
+        funcs: String::new(),
+    };
+
+    for name in idl.types.keys() {
+        gen.visit(&Ty::Named(name.clone()));
+    }
+    for proto in &idl.protocols {
+        for method in &proto.methods {
+            method.args.iter().for_each(|arg| gen.visit(&arg.ty));
+            gen.visit(&method.output);
+            if let Some(item) = &method.stream {
+                gen.visit(item);
+            }
+        }
+        proto.events.iter().for_each(|event| gen.visit(&event.ty));
+    }
+
+    let mut types = format!("{HEADER}#include <rpc/packet.h>\n\n");
+    types += "#ifdef __cplusplus\nextern \"C\" {\n#endif\n\n";
+    for object in OBJECTS {
+        let id = object.to_uppercase();
+        writeln!(types, "RPC_DEFINE_OBJECT({}, SV_{id})", snake(object)).unwrap();
+    }
+    write!(
+        types,
+        "\n{}\n{}\n{}#ifdef __cplusplus\n}}\n#endif\n",
+        gen.decls, gen.protos, gen.funcs
+    )
+    .unwrap();
+
+    let mut modules = BTreeMap::<_, String>::new();
+    for proto in &idl.protocols {
+        let module = match proto.name.rsplit_once("::") {
+            Some((module, _)) => module.replace("::", "/"),
+            None => "protocols".into(),
+        };
+        let content = modules.entry(module).or_default();
+        *content += &gen.protocol(proto);
+    }
+
+    let mut ret = vec![
+        (PathBuf::from("rpc/packet.h"), RUNTIME.to_string()),
+        (PathBuf::from("rpc/types.h"), types),
+    ];
+    ret.extend(modules.into_iter().map(|(module, content)| {
+        let content = format!(
+            "{HEADER}#include <rpc/types.h>\n\n#ifdef __cplusplus\nextern \"C\" {{\n#endif\n\
+            {content}\n#ifdef __cplusplus\n}}\n#endif\n"
+        );
+        (PathBuf::from(format!("rpc/{module}.h")), content)
+    }));
+    ret
+}
@@ -0,0 +1,418 @@
+// The runtime of the RPC bindings, encoding and decoding packets in the wire
+// format of `SerdePacket`.
+//
+// Values are encoded into a `struct rpc_ser`, whose buffer and handles are
+// then sent with `sv_chan_send`. Received packets are decoded from a
+// `struct rpc_de`. Decoded values own their memory and handles, and must be
+// released with the corresponding `rpc_free_*` function, even if the decoding
+// failed halfway.
+//
+// This file is generated by solvent-rpc-gen; do not edit it manually!
+
+#pragma once
+
+#include <h2o.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define RPC_MAGIC ((size_t)0xac84fb7c0391ull)
+
+#define RPC_METHOD_DESCRIBE ((size_t)0xffffffffffff0001ull)
+#define RPC_METHOD_UNSUPPORTED ((size_t)0xffffffffffff0002ull)
+#define RPC_METHOD_STREAM_ITEM ((size_t)0xffffffffffff0003ull)
+#define RPC_METHOD_STREAM_CREDIT ((size_t)0xffffffffffff0004ull)
+#define RPC_METHOD_CANCEL ((size_t)0xffffffffffff0005ull)
+#define RPC_METHOD_UPGRADE ((size_t)0xffffffffffff0006ull)
+#define RPC_METHOD_BUSY ((size_t)0xffffffffffff0007ull)
+
+enum rpc_error {
+    RPC_OK = 0,
+    // Failed to allocate memory.
+    RPC_ERR_NO_MEMORY = 1,
+    // The packet has fewer bytes or handles than expected.
+    RPC_ERR_BUFFER_TOO_SHORT = 2,
+    // The packet contains an invalid value of the expected type.
+    RPC_ERR_TYPE_MISMATCH = 3,
+    RPC_ERR_INVALID_MAGIC = 4,
+    RPC_ERR_INVALID_METHOD = 5,
+    // The peer doesn't support the method.
+    RPC_ERR_UNSUPPORTED_METHOD = 6,
+    // The peer is too busy to handle the request, and should be retried later.
+    RPC_ERR_BUSY = 7,
+};
+
+struct rpc_ser {
+    uint8_t *buffer;
+    size_t buffer_size;
+    size_t buffer_cap;
+    Handle *handles;
+    size_t handle_count;
+    size_t handle_cap;
+};
+
+struct rpc_de {
+    const uint8_t *buffer;
+    size_t buffer_size;
+    const Handle *handles;
+    size_t handle_count;
+};
+
+// The placeholder of `()`, which occupies no space in the packet.
+struct rpc_unit {
+    char unused;
+};
+
+// `String`, `Vec<u8>` and paths. The decoded data are always terminated with
+// an extra nul that is not counted in `len`.
+struct rpc_bytes {
+    uint8_t *data;
+    size_t len;
+};
+
+static inline void rpc_ser_init(struct rpc_ser *ser) {
+    memset(ser, 0, sizeof(*ser));
+}
+
+// Release the memory of the serializer. The handles encoded are not dropped
+// because their ownership has been moved into the serializer.
+static inline void rpc_ser_fini(struct rpc_ser *ser) {
+    free(ser->buffer);
+    free(ser->handles);
+    rpc_ser_init(ser);
+}
+
+// Fill the buffer and the handles of the packet to be sent. The ID of the
+// packet is left to the caller.
+static inline void rpc_ser_packet(const struct rpc_ser *ser, RawPacket *packet) {
+    packet->buffer = ser->buffer;
+    packet->buffer_size = ser->buffer_size;
+    packet->buffer_cap = ser->buffer_cap;
+    packet->handles = ser->handles;
+    packet->handle_count = ser->handle_count;
+    packet->handle_cap = ser->handle_cap;
+}
+
+static inline void rpc_de_init(struct rpc_de *de, const RawPacket *packet) {
+    de->buffer = packet->buffer;
+    de->buffer_size = packet->buffer_size;
+    de->handles = packet->handles;
+    de->handle_count = packet->handle_count;
+}
+
+static inline bool rpc_de_empty(const struct rpc_de *de) {
+    return de->buffer_size == 0 && de->handle_count == 0;
+}
+
+static inline int rpc_put(struct rpc_ser *ser, const void *data, size_t len) {
+    if (ser->buffer_cap - ser->buffer_size < len) {
+        size_t cap = ser->buffer_cap ? ser->buffer_cap * 2 : 64;
+        while (cap - ser->buffer_size < len)
+            cap *= 2;
+        uint8_t *buffer = (uint8_t *)realloc(ser->buffer, cap);
+        if (!buffer)
+            return RPC_ERR_NO_MEMORY;
+        ser->buffer = buffer;
+        ser->buffer_cap = cap;
+    }
+    if (len)
+        memcpy(ser->buffer + ser->buffer_size, data, len);
+    ser->buffer_size += len;
+    return RPC_OK;
+}
+
+static inline int rpc_put_handle(struct rpc_ser *ser, Handle handle) {
+    if (ser->handle_count == ser->handle_cap) {
+        size_t cap = ser->handle_cap ? ser->handle_cap * 2 : 4;
+        Handle *handles = (Handle *)realloc(ser->handles, cap * sizeof(Handle));
+        if (!handles)
+            return RPC_ERR_NO_MEMORY;
+        ser->handles = handles;
+        ser->handle_cap = cap;
+    }
+    ser->handles[ser->handle_count++] = handle;
+    return RPC_OK;
+}
+
+static inline int rpc_get(struct rpc_de *de, void *data, size_t len) {
+    if (de->buffer_size < len)
+        return RPC_ERR_BUFFER_TOO_SHORT;
+    if (len)
+        memcpy(data, de->buffer, len);
+    de->buffer += len;
+    de->buffer_size -= len;
+    return RPC_OK;
+}
+
+static inline int rpc_get_handle(struct rpc_de *de, Handle *handle) {
+    if (de->handle_count == 0)
+        return RPC_ERR_BUFFER_TOO_SHORT;
+    *handle = *de->handles++;
+    de->handle_count--;
+    return RPC_OK;
+}
+
+// Check the length of a collection against the rest of the packet before
+// allocating its elements.
+static inline int rpc_check_len(const struct rpc_de *de, size_t len) {
+    if (len > de->buffer_size + de->handle_count)
+        return RPC_ERR_BUFFER_TOO_SHORT;
+    return RPC_OK;
+}
+
+// Start a length-prefixed section of an extensible type, returning the offset
+// of its header to `rpc_ser_section_end`.
+static inline int rpc_ser_section_begin(struct rpc_ser *ser, size_t *start) {
+    size_t header[2] = {0, 0};
+    *start = ser->buffer_size;
+    return rpc_put(ser, header, sizeof(header));
+}
+
+static inline void rpc_ser_section_end(struct rpc_ser *ser, size_t start, size_t handle_start) {
+    size_t header[2] = {
+        ser->buffer_size - start - sizeof(header),
+        ser->handle_count - handle_start,
+    };
+    memcpy(ser->buffer + start, header, sizeof(header));
+}
+
+// Split the section of an extensible type off `de` into `section`.
+static inline int rpc_de_section_begin(struct rpc_de *de, struct rpc_de *section) {
+    size_t header[2];
+    int ret = rpc_get(de, header, sizeof(header));
+    if (ret)
+        return ret;
+    if (de->buffer_size < header[0] || de->handle_count < header[1])
+        return RPC_ERR_BUFFER_TOO_SHORT;
+    section->buffer = de->buffer;
+    section->buffer_size = header[0];
+    section->handles = de->handles;
+    section->handle_count = header[1];
+    de->buffer += header[0];
+    de->buffer_size -= header[0];
+    de->handles += header[1];
+    de->handle_count -= header[1];
+    return RPC_OK;
+}
+
+// Drop the handles left in the section, which are unknown to this version.
+static inline void rpc_de_section_end(struct rpc_de *section) {
+    for (size_t i = 0; i < section->handle_count; i++)
+        sv_obj_drop(section->handles[i]);
+    section->handle_count = 0;
+}
+
+static inline int rpc_put_header(struct rpc_ser *ser, size_t method) {
+    size_t header[2] = {RPC_MAGIC, method};
+    // No deadline.
+    uint8_t deadline[16] = {0};
+    int ret = rpc_put(ser, header, sizeof(header));
+    if (ret)
+        return ret;
+    return rpc_put(ser, deadline, sizeof(deadline));
+}
+
+static inline int rpc_get_header(struct rpc_de *de, size_t method) {
+    size_t header[2];
+    uint8_t deadline[16];
+    int ret = rpc_get(de, header, sizeof(header));
+    if (ret)
+        return ret;
+    if (header[0] != RPC_MAGIC)
+        return RPC_ERR_INVALID_MAGIC;
+    if (header[1] == RPC_METHOD_UNSUPPORTED)
+        return RPC_ERR_UNSUPPORTED_METHOD;
+    if (header[1] == RPC_METHOD_BUSY)
+        return RPC_ERR_BUSY;
+    if (header[1] != method)
+        return RPC_ERR_INVALID_METHOD;
+    return rpc_get(de, deadline, sizeof(deadline));
+}
+
+#define RPC_DEFINE_SCALAR(name, type)                                          \
+    static inline int rpc_encode_##name(struct rpc_ser *ser, type const *value) { \
+        return rpc_put(ser, value, sizeof(type));                              \
+    }                                                                          \
+    static inline int rpc_decode_##name(struct rpc_de *de, type *value) {      \
+        return rpc_get(de, value, sizeof(type));                               \
+    }                                                                          \
+    static inline void rpc_free_##name(type *value) {                         \
+        (void)value;                                                           \
+    }
+
+RPC_DEFINE_SCALAR(u8, uint8_t)
+RPC_DEFINE_SCALAR(u16, uint16_t)
+RPC_DEFINE_SCALAR(u32, uint32_t)
+RPC_DEFINE_SCALAR(u64, uint64_t)
+RPC_DEFINE_SCALAR(u128, unsigned __int128)
+RPC_DEFINE_SCALAR(usize, size_t)
+RPC_DEFINE_SCALAR(i8, int8_t)
+RPC_DEFINE_SCALAR(i16, int16_t)
+RPC_DEFINE_SCALAR(i32, int32_t)
+RPC_DEFINE_SCALAR(i64, int64_t)
+RPC_DEFINE_SCALAR(i128, __int128)
+RPC_DEFINE_SCALAR(isize, ptrdiff_t)
+RPC_DEFINE_SCALAR(f32, float)
+RPC_DEFINE_SCALAR(f64, double)
+// The negative return value of a failed syscall.
+RPC_DEFINE_SCALAR(status, ptrdiff_t)
+
+// The number of items the server of a streaming method may send before it
+// receives any credit.
+#define RPC_STREAM_WINDOW 16
+
+// Grant the server of a streaming method `credit` more items to send, usually
+// after consuming half of the window.
+static inline int rpc_stream_credit(struct rpc_ser *ser, size_t credit) {
+    int ret = rpc_put_header(ser, RPC_METHOD_STREAM_CREDIT);
+    if (ret)
+        return ret;
+    return rpc_encode_usize(ser, &credit);
+}
+
+static inline int rpc_encode_unit(struct rpc_ser *ser, struct rpc_unit const *value) {
+    (void)ser;
+    (void)value;
+    return RPC_OK;
+}
+
+static inline int rpc_decode_unit(struct rpc_de *de, struct rpc_unit *value) {
+    (void)de;
+    (void)value;
+    return RPC_OK;
+}
+
+static inline void rpc_free_unit(struct rpc_unit *value) {
+    (void)value;
+}
+
+static inline int rpc_encode_bool(struct rpc_ser *ser, bool const *value) {
+    uint8_t byte = *value ? 1 : 0;
+    return rpc_put(ser, &byte, 1);
+}
+
+static inline int rpc_decode_bool(struct rpc_de *de, bool *value) {
+    uint8_t byte;
+    int ret = rpc_get(de, &byte, 1);
+    if (ret)
+        return ret;
+    if (byte > 1)
+        return RPC_ERR_TYPE_MISMATCH;
+    *value = byte;
+    return RPC_OK;
+}
+
+static inline void rpc_free_bool(bool *value) {
+    (void)value;
+}
+
+static inline int rpc_encode_bytes(struct rpc_ser *ser, struct rpc_bytes const *value) {
+    int ret = rpc_put(ser, &value->len, sizeof(size_t));
+    if (ret)
+        return ret;
+    return rpc_put(ser, value->data, value->len);
+}
+
+static inline int rpc_decode_bytes(struct rpc_de *de, struct rpc_bytes *value) {
+    size_t len;
+    int ret = rpc_get(de, &len, sizeof(size_t));
+    if (ret)
+        return ret;
+    if (len > de->buffer_size)
+        return RPC_ERR_BUFFER_TOO_SHORT;
+    uint8_t *data = (uint8_t *)malloc(len + 1);
+    if (!data)
+        return RPC_ERR_NO_MEMORY;
+    rpc_get(de, data, len);
+    data[len] = 0;
+    value->data = data;
+    value->len = len;
+    return RPC_OK;
+}
+
+static inline void rpc_free_bytes(struct rpc_bytes *value) {
+    free(value->data);
+    value->data = NULL;
+    value->len = 0;
+}
+
+// Strings are not validated as UTF-8 here; the Rust peers reject invalid ones.
+#define rpc_encode_string rpc_encode_bytes
+#define rpc_decode_string rpc_decode_bytes
+#define rpc_free_string rpc_free_bytes
+
+// `CString`, encoded with its terminating nul. A null pointer is encoded as an
+// empty string, which is `None` for `Option<CString>`.
+static inline int rpc_encode_cstring(struct rpc_ser *ser, char *const *value) {
+    size_t len = *value ? strlen(*value) + 1 : 0;
+    int ret = rpc_put(ser, &len, sizeof(size_t));
+    if (ret)
+        return ret;
+    return rpc_put(ser, *value, len);
+}
+
+static inline int rpc_decode_cstring(struct rpc_de *de, char **value) {
+    struct rpc_bytes bytes;
+    int ret = rpc_decode_bytes(de, &bytes);
+    if (ret)
+        return ret;
+    if (bytes.len == 0 || memchr(bytes.data, 0, bytes.len) != bytes.data + bytes.len - 1) {
+        free(bytes.data);
+        return RPC_ERR_TYPE_MISMATCH;
+    }
+    *value = (char *)bytes.data;
+    return RPC_OK;
+}
+
+static inline void rpc_free_cstring(char **value) {
+    free(*value);
+    *value = NULL;
+}
+
+// Encoding a handle moves its ownership into the packet.
+static inline int rpc_encode_handle(struct rpc_ser *ser, Handle const *value) {
+    return rpc_put_handle(ser, *value);
+}
+
+static inline int rpc_decode_handle(struct rpc_de *de, Handle *value) {
+    return rpc_get_handle(de, value);
+}
+
+static inline void rpc_free_handle(Handle *value) {
+    if (value->raw != 0)
+        sv_obj_drop(*value);
+    value->raw = 0;
+}
+
+// Kernel objects are prefixed with their object IDs. A null handle is only
+// valid for `Option<T>`.
+#define RPC_DEFINE_OBJECT(name, id)                                            \
+    static inline int rpc_encode_##name(struct rpc_ser *ser, Handle const *value) { \
+        size_t ty = (id);                                                      \
+        int ret = rpc_put(ser, &ty, sizeof(size_t));                           \
+        if (ret)                                                               \
+            return ret;                                                        \
+        return rpc_put_handle(ser, *value);                                    \
+    }                                                                          \
+    static inline int rpc_decode_##name(struct rpc_de *de, Handle *value) {    \
+        size_t ty;                                                             \
+        int ret = rpc_get(de, &ty, sizeof(size_t));                            \
+        if (ret)                                                               \
+            return ret;                                                        \
+        if (ty != (id))                                                        \
+            return RPC_ERR_TYPE_MISMATCH;                                      \
+        return rpc_get_handle(de, value);                                      \
+    }                                                                          \
+    static inline void rpc_free_##name(Handle *value) {                       \
+        rpc_free_handle(value);                                                \
+    }
+
+#ifdef __cplusplus
+}
+#endif
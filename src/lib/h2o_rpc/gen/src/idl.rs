@@ -0,0 +1,918 @@
+//! The language-neutral description of the protocols and their types.
+//!
+//! Every type transferred through the protocols is resolved into a [`Ty`],
+//! which mirrors the wire format of `SerdePacket` rather than the Rust type
+//! itself. The description is exported as JSON and is also the input of the C
+//! bindings.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use proc_macro2::{Delimiter, TokenTree};
+use quote::ToTokens;
+use serde::Serialize;
+use syn::{
+    braced,
+    parse::{Parse, ParseStream},
+    *,
+};
+
+use crate::{
+    parse::{ProtoItem, ProtoType},
+    types::{Method, Protocol},
+};
+
+// Must be consistent with `solvent_rpc_core::packet`.
+const MAGIC: usize = 0xac84fb7c0391;
+const RESERVED: [(&str, usize); 7] = [
+    ("describe", 0xffff_ffff_ffff_0001),
+    ("unsupported", 0xffff_ffff_ffff_0002),
+    ("stream_item", 0xffff_ffff_ffff_0003),
+    ("stream_credit", 0xffff_ffff_ffff_0004),
+    ("cancel", 0xffff_ffff_ffff_0005),
+    ("upgrade", 0xffff_ffff_ffff_0006),
+    ("busy", 0xffff_ffff_ffff_0007),
+];
+
+/// The kernel objects transferred with their IDs, see `solvent::impl_obj_for`.
+pub const OBJECTS: [&str; 13] = [
+    "Channel",
+    "Event",
+    "Task",
+    "SuspendToken",
+    "Space",
+    "Virt",
+    "Phys",
+    "Interrupt",
+    "MemRes",
+    "IntrRes",
+    "PioRes",
+    "Timer",
+    "Dispatcher",
+];
+
+/// The types defined outside the protocol root, written in the same shape as
+/// their wire format.
+const EXTERN: [(&str, &str); 2] = [
+    (
+        "rpc",
+        r#"
+        /// The error of the RPC framework.
+        #[derive(SerdePacket)]
+        pub enum Error {
+            Disconnected,
+            ClientReceive(RawError),
+            ClientSend(RawError),
+            ServerReceive(RawError),
+            ServerSend(RawError),
+            BufferTooShort { found: usize, expected_at_least: usize },
+            /// Only the message of the error is transferred.
+            TypeMismatch(String),
+            InvalidMagic(usize),
+            InvalidMethod { expected: usize, found: usize },
+            UnsupportedMethod(usize),
+            SizeMismatch { extra_buffer_len: usize, extra_handle_count: usize },
+            EndpointInUse,
+            Busy,
+        }
+        "#,
+    ),
+    (
+        "std::io",
+        r#"
+        #[derive(SerdePacket)]
+        pub struct RawStream {
+            pub phys: Phys,
+            pub seeker: usize,
+        }
+
+        #[derive(SerdePacket)]
+        pub enum SeekFrom {
+            Start(usize),
+            Current(isize),
+            End(isize),
+        }
+        "#,
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Prim {
+    Unit,
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Usize,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    Isize,
+    F32,
+    F64,
+    /// UTF-8 bytes prefixed with the length.
+    String,
+    /// Bytes with the terminating nul, prefixed with the length.
+    CString,
+    /// Raw bytes prefixed with the length.
+    Bytes,
+    Handle,
+    /// A `solvent::error::Error`, transferred as its negative return value.
+    Status,
+}
+
+impl Prim {
+    fn from_ident(ident: &str) -> Option<Self> {
+        Some(match ident {
+            "bool" => Prim::Bool,
+            "u8" => Prim::U8,
+            "u16" => Prim::U16,
+            "u32" => Prim::U32,
+            "u64" => Prim::U64,
+            "u128" => Prim::U128,
+            "usize" => Prim::Usize,
+            "i8" => Prim::I8,
+            "i16" => Prim::I16,
+            "i32" => Prim::I32,
+            "i64" => Prim::I64,
+            "i128" => Prim::I128,
+            "isize" => Prim::Isize,
+            "f32" => Prim::F32,
+            "f64" => Prim::F64,
+            "String" => Prim::String,
+            "CString" => Prim::CString,
+            "PathBuf" | "OsString" => Prim::Bytes,
+            "Handle" => Prim::Handle,
+            "RawError" => Prim::Status,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ty {
+    Vec(Box<Ty>),
+    /// Only the empty value of the inner type (an empty collection or a null
+    /// handle) is treated as `None`.
+    Option(Box<Ty>),
+    Result(Box<Ty>, Box<Ty>),
+    Tuple(Vec<Ty>),
+    Array(Box<Ty>, usize),
+    /// A kernel object, prefixed with its object ID.
+    Object(String),
+    /// A type in [`Idl::types`].
+    Named(String),
+    #[serde(untagged)]
+    Prim(Prim),
+}
+
+#[derive(Debug, Serialize)]
+pub struct Field {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub ty: Ty,
+    /// Deserialized as the default value if absent.
+    #[serde(skip_serializing_if = "is_false")]
+    pub default: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Variant {
+    pub name: String,
+    /// The tag serialized as a `usize` before the fields.
+    pub tag: usize,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Flag {
+    pub name: String,
+    pub value: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Def {
+    Struct {
+        /// Whether the body is prefixed with its length, see
+        /// `Serializer::section`.
+        #[serde(skip_serializing_if = "is_false")]
+        extensible: bool,
+        fields: Vec<Field>,
+    },
+    Enum {
+        #[serde(skip_serializing_if = "is_false")]
+        extensible: bool,
+        variants: Vec<Variant>,
+    },
+    Flags {
+        bits: Prim,
+        flags: Vec<Flag>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct TypeDef {
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub doc: String,
+    #[serde(flatten)]
+    pub def: Def,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Arg {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: Ty,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MethodDef {
+    pub name: String,
+    pub id: usize,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub doc: String,
+    pub since: u32,
+    pub deprecated: bool,
+    /// Whether the server closes the connection after the reply.
+    pub close: bool,
+    pub args: Vec<Arg>,
+    /// The body of the reply; `()` for a streaming method.
+    pub output: Ty,
+    /// The item type of a streaming method, whose side channel is appended to
+    /// the arguments.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<Ty>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EventDef {
+    /// The ID serialized as a `u64` before the event.
+    pub id: u64,
+    #[serde(rename = "type")]
+    pub ty: Ty,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProtocolDef {
+    pub name: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub doc: String,
+    pub version: u32,
+    pub inherits: Vec<String>,
+    /// All the methods, including the inherited ones.
+    pub methods: Vec<MethodDef>,
+    pub events: Vec<EventDef>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Wire {
+    pub magic: usize,
+    /// The header of every request and reply, placed before the body.
+    pub header: [(&'static str, Prim); 3],
+    pub reserved_methods: BTreeMap<&'static str, usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Idl {
+    pub wire: Wire,
+    pub types: BTreeMap<String, TypeDef>,
+    pub protocols: Vec<ProtocolDef>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+fn doc(attrs: &[Attribute]) -> String {
+    let lines = attrs.iter().filter_map(|attr| match attr.parse_meta() {
+        Ok(Meta::NameValue(MetaNameValue {
+            path,
+            lit: Lit::Str(lit),
+            ..
+        })) if path.is_ident("doc") => Some(lit.value()),
+        _ => None,
+    });
+    let lines = lines.collect::<Vec<_>>();
+    let lines = lines
+        .iter()
+        .map(|line| line.strip_prefix(' ').unwrap_or(line));
+    lines.collect::<Vec<_>>().join("\n")
+}
+
+fn is_serde_packet(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path.is_ident("derive") && {
+            let tokens = attr.tokens.to_string();
+            tokens
+                .split(|c: char| !c.is_alphanumeric())
+                .any(|w| w == "SerdePacket")
+        }
+    })
+}
+
+/// Get the options in `#[serde_packet(...)]`.
+fn serde_packet(attrs: &[Attribute]) -> Result<Vec<Meta>> {
+    let mut ret = Vec::new();
+    for attr in attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("serde_packet"))
+    {
+        if let Meta::List(list) = attr.parse_meta()? {
+            ret.extend(list.nested.into_iter().filter_map(|nested| match nested {
+                NestedMeta::Meta(meta) => Some(meta),
+                NestedMeta::Lit(_) => None,
+            }));
+        }
+    }
+    Ok(ret)
+}
+
+fn has_option(attrs: &[Attribute], name: &str) -> Result<bool> {
+    let options = serde_packet(attrs)?;
+    Ok(options
+        .iter()
+        .any(|meta| matches!(meta, Meta::Path(path) if path.is_ident(name))))
+}
+
+fn lit_int(expr: &Expr) -> Option<u64> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(int), ..
+        }) => int.base10_parse().ok(),
+        _ => None,
+    }
+}
+
+/// The contents of `bitflags::bitflags!`.
+struct Bitflags(Vec<BitflagsDef>);
+
+struct BitflagsDef {
+    attrs: Vec<Attribute>,
+    ident: Ident,
+    bits: Type,
+    flags: Vec<(Ident, Expr)>,
+}
+
+impl Parse for Bitflags {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut ret = Vec::new();
+        while !input.is_empty() {
+            let attrs = Attribute::parse_outer(input)?;
+            input.parse::<Visibility>()?;
+            input.parse::<Token![struct]>()?;
+            let ident = input.parse()?;
+            input.parse::<Token![:]>()?;
+            let bits = input.parse()?;
+
+            let content;
+            braced!(content in input);
+            let mut flags = Vec::new();
+            while !content.is_empty() {
+                Attribute::parse_outer(&content)?;
+                content.parse::<Token![const]>()?;
+                let ident = content.parse()?;
+                content.parse::<Token![=]>()?;
+                let value = content.parse()?;
+                content.parse::<Token![;]>()?;
+                flags.push((ident, value));
+            }
+            ret.push(BitflagsDef {
+                attrs,
+                ident,
+                bits,
+                flags,
+            });
+        }
+        Ok(Bitflags(ret))
+    }
+}
+
+/// Flatten a `use` tree into the imported names and their full paths.
+fn flatten_use(prefix: &str, tree: &UseTree, out: &mut HashMap<String, String>) {
+    let join = |ident: &Ident| {
+        if prefix.is_empty() {
+            ident.to_string()
+        } else {
+            format!("{prefix}::{ident}")
+        }
+    };
+    match tree {
+        UseTree::Path(path) => flatten_use(&join(&path.ident), &path.tree, out),
+        UseTree::Name(name) => {
+            out.insert(name.ident.to_string(), join(&name.ident));
+        }
+        UseTree::Rename(rename) => {
+            out.insert(rename.rename.to_string(), join(&rename.ident));
+        }
+        UseTree::Glob(_) => {}
+        UseTree::Group(group) => {
+            for tree in &group.items {
+                flatten_use(prefix, tree, out)
+            }
+        }
+    }
+}
+
+/// Collect the `use` items, including the ones in `cfg_if::cfg_if!`.
+fn collect_uses(item: &Item, out: &mut HashMap<String, String>) {
+    fn walk(tokens: proc_macro2::TokenStream, out: &mut HashMap<String, String>) {
+        for tt in tokens {
+            if let TokenTree::Group(group) = tt {
+                match parse2::<File>(group.stream()) {
+                    Ok(file) if group.delimiter() == Delimiter::Brace => {
+                        file.items.iter().for_each(|item| collect_uses(item, out))
+                    }
+                    _ => walk(group.stream(), out),
+                }
+            }
+        }
+    }
+    match item {
+        Item::Use(item) => flatten_use("", &item.tree, out),
+        Item::Macro(mac) if mac.mac.path.segments.last().unwrap().ident == "cfg_if" => {
+            walk(mac.mac.tokens.clone(), out)
+        }
+        _ => {}
+    }
+}
+
+/// Get the module path of an item from the path of its file.
+pub fn module_of(src: &Path, parent: &Path) -> String {
+    let path = parent
+        .strip_prefix(src)
+        .unwrap_or(parent)
+        .with_extension("");
+    let comps = path.iter().map(|comp| comp.to_string_lossy());
+    let comps = comps.filter(|comp| comp != "mod").collect::<Vec<_>>();
+    comps.join("::")
+}
+
+fn join(module: &str, ident: &str) -> String {
+    if module.is_empty() {
+        ident.to_string()
+    } else {
+        format!("{module}::{ident}")
+    }
+}
+
+fn parent_module(module: &str) -> Option<&str> {
+    if module.is_empty() {
+        None
+    } else {
+        Some(module.rsplit_once("::").map_or("", |(parent, _)| parent))
+    }
+}
+
+#[derive(Default)]
+struct Resolver {
+    /// The defined type names, each joined with its module path.
+    defs: Vec<String>,
+    /// The imported names of each module.
+    uses: HashMap<String, HashMap<String, String>>,
+}
+
+impl Resolver {
+    fn find(&self, module: &str, ident: &str) -> Option<String> {
+        let name = join(module, ident);
+        self.defs.contains(&name).then_some(name)
+    }
+
+    /// Resolve a full path of a `use` item or a type.
+    fn resolve_full(&self, module: &str, path: &[String]) -> Option<Ty> {
+        let (ident, modules) = path.split_last()?;
+        match &*path.join("::") {
+            "solvent::error::Error" => return Some(Ty::Prim(Prim::Status)),
+            "solvent_rpc_core::Error" | "solvent_rpc::Error" => {
+                return Some(Ty::Named("rpc::Error".into()))
+            }
+            full => {
+                // `solvent_core` is re-exported as `std` in the user space.
+                if let Some(rest) = full.strip_prefix("solvent_core::") {
+                    let name = format!("std::{rest}");
+                    return match self.defs.contains(&name) {
+                        true => Some(Ty::Named(name)),
+                        false => self.builtin(ident),
+                    };
+                }
+            }
+        }
+
+        let base = match modules.first().map(|first| &**first) {
+            Some("crate") => Some(String::new()),
+            Some("super") => Some(parent_module(module)?.to_string()),
+            Some("self") => Some(module.to_string()),
+            _ => None,
+        };
+        if let Some(base) = base {
+            let module = modules[1..].iter().fold(base, |acc, m| join(&acc, m));
+            return self.find(&module, ident).map(Ty::Named);
+        }
+
+        // Relative paths may refer to the submodules of the ancestors, which
+        // are imported by `use super::*`.
+        let mut cur = Some(module);
+        while let Some(parent) = cur {
+            let module = modules
+                .iter()
+                .fold(parent.to_string(), |acc, m| join(&acc, m));
+            if let Some(name) = self.find(&module, ident) {
+                return Some(Ty::Named(name));
+            }
+            cur = parent_module(parent);
+        }
+        self.builtin(ident)
+    }
+
+    fn builtin(&self, ident: &str) -> Option<Ty> {
+        if let Some(prim) = Prim::from_ident(ident) {
+            return Some(Ty::Prim(prim));
+        }
+        if OBJECTS.contains(&ident) {
+            return Some(Ty::Object(ident.into()));
+        }
+        let mut candidates = self
+            .defs
+            .iter()
+            .filter(|name| name.rsplit_once("::").map_or(&***name, |(_, last)| last) == ident);
+        match (candidates.next(), candidates.next()) {
+            (Some(name), None) => Some(Ty::Named(name.clone())),
+            _ => None,
+        }
+    }
+
+    /// Resolve a single name visible in the module, searching the glob imports
+    /// of the ancestor modules as well.
+    fn resolve_name(&self, module: &str, ident: &str) -> Option<Ty> {
+        let mut cur = Some(module);
+        while let Some(module) = cur {
+            if let Some(name) = self.find(module, ident) {
+                return Some(Ty::Named(name));
+            }
+            if let Some(path) = self.uses.get(module).and_then(|uses| uses.get(ident)) {
+                let path = path.split("::").map(str::to_string).collect::<Vec<_>>();
+                if let Some(ty) = self.resolve_full(module, &path) {
+                    return Some(ty);
+                }
+            }
+            cur = parent_module(module);
+        }
+        self.builtin(ident)
+    }
+
+    fn generic_args(seg: &PathSegment) -> Vec<&Type> {
+        match &seg.arguments {
+            PathArguments::AngleBracketed(args) => args
+                .args
+                .iter()
+                .filter_map(|arg| match arg {
+                    GenericArgument::Type(ty) => Some(ty),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn ty(&self, module: &str, ty: &Type) -> std::result::Result<Ty, String> {
+        let err = || format!("Unsupported type `{}` in `{module}`", ty.to_token_stream());
+        Ok(match ty {
+            Type::Paren(paren) => self.ty(module, &paren.elem)?,
+            Type::Group(group) => self.ty(module, &group.elem)?,
+            Type::Tuple(tuple) if tuple.elems.is_empty() => Ty::Prim(Prim::Unit),
+            Type::Tuple(tuple) => Ty::Tuple(
+                tuple
+                    .elems
+                    .iter()
+                    .map(|ty| self.ty(module, ty))
+                    .collect::<std::result::Result<_, _>>()?,
+            ),
+            Type::Array(array) => {
+                let len = lit_int(&array.len).ok_or_else(err)?;
+                Ty::Array(Box::new(self.ty(module, &array.elem)?), len as usize)
+            }
+            Type::Path(TypePath { qself: None, path }) => {
+                let seg = path.segments.last().unwrap();
+                let args = Self::generic_args(seg);
+                let arg = |index: usize| -> std::result::Result<Box<Ty>, String> {
+                    let ty = args.get(index).ok_or_else(err)?;
+                    Ok(Box::new(self.ty(module, ty)?))
+                };
+                match &*seg.ident.to_string() {
+                    "Vec" => Ty::Vec(arg(0)?),
+                    "Option" => Ty::Option(arg(0)?),
+                    "Result" => Ty::Result(arg(0)?, arg(1)?),
+                    "Box" => *arg(0)?,
+                    ident if path.segments.len() == 1 => {
+                        self.resolve_name(module, ident).ok_or_else(err)?
+                    }
+                    _ => {
+                        let path = path.segments.iter().map(|seg| seg.ident.to_string());
+                        let path = path.collect::<Vec<_>>();
+                        self.resolve_full(module, &path).ok_or_else(err)?
+                    }
+                }
+            }
+            _ => return Err(err()),
+        })
+    }
+
+    fn fields(&self, module: &str, fields: &Fields) -> std::result::Result<Vec<Field>, String> {
+        let mut ret = Vec::new();
+        for field in fields {
+            let attrs = &field.attrs;
+            if has_option(attrs, "skip").map_err(|err| err.to_string())? {
+                continue;
+            }
+            ret.push(Field {
+                name: field.ident.as_ref().map(|ident| ident.to_string()),
+                ty: self.ty(module, &field.ty)?,
+                default: has_option(attrs, "default").map_err(|err| err.to_string())?,
+            })
+        }
+        Ok(ret)
+    }
+}
+
+/// The items of a module, either parsed from the protocol root or written in
+/// [`EXTERN`].
+fn type_items<'a>(
+    src: &'a Path,
+    items: &'a [ProtoItem],
+    ext: &'a [(String, Vec<Item>)],
+) -> impl Iterator<Item = (String, &'a Item)> + 'a {
+    let items = items.iter().filter_map(|item| match &item.ty {
+        ProtoType::Item(it) => Some((module_of(src, &item.parent), it)),
+        ProtoType::Protocol(_) => None,
+    });
+    let ext = ext
+        .iter()
+        .flat_map(|(module, items)| items.iter().map(move |item| (module.clone(), item)));
+    items.chain(ext)
+}
+
+fn variants(
+    resolver: &Resolver,
+    module: &str,
+    item: &ItemEnum,
+) -> std::result::Result<Vec<Variant>, String> {
+    let mut next = 0;
+    let mut ret = Vec::new();
+    for var in &item.variants {
+        let options = serde_packet(&var.attrs).map_err(|err| err.to_string())?;
+        let tag = options.iter().find_map(|meta| match meta {
+            Meta::NameValue(MetaNameValue {
+                path,
+                lit: Lit::Int(int),
+                ..
+            }) if path.is_ident("tag") => int.base10_parse().ok(),
+            _ => None,
+        });
+        let tag = tag
+            .or_else(|| {
+                var.discriminant
+                    .as_ref()
+                    .and_then(|(_, expr)| lit_int(expr))
+            })
+            .map_or(next, |tag| tag as usize);
+        next = tag + 1;
+        ret.push(Variant {
+            name: var.ident.to_string(),
+            tag,
+            fields: resolver.fields(module, &var.fields)?,
+        })
+    }
+    Ok(ret)
+}
+
+fn type_defs(
+    src: &Path,
+    items: &[ProtoItem],
+    resolver: &mut Resolver,
+) -> std::result::Result<BTreeMap<String, TypeDef>, String> {
+    let ext = EXTERN
+        .iter()
+        .map(|(module, src)| Ok((module.to_string(), parse_file(src)?.items)))
+        .collect::<Result<Vec<_>>>()
+        .map_err(|err| err.to_string())?;
+
+    let mut flags = Vec::new();
+    for (module, item) in type_items(src, items, &ext) {
+        collect_uses(item, resolver.uses.entry(module.clone()).or_default());
+        match item {
+            Item::Struct(ItemStruct { attrs, ident, .. })
+            | Item::Enum(ItemEnum { attrs, ident, .. })
+                if is_serde_packet(attrs) =>
+            {
+                resolver.defs.push(join(&module, &ident.to_string()))
+            }
+            Item::Macro(mac) if mac.mac.path.segments.last().unwrap().ident == "bitflags" => {
+                let bitflags = mac.mac.parse_body::<Bitflags>();
+                for def in bitflags.map_err(|err| err.to_string())?.0 {
+                    if is_serde_packet(&def.attrs) {
+                        resolver.defs.push(join(&module, &def.ident.to_string()));
+                        flags.push((module.clone(), def));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut ret = BTreeMap::new();
+    for (module, item) in type_items(src, items, &ext) {
+        let (name, def) = match item {
+            Item::Struct(item) if is_serde_packet(&item.attrs) => {
+                let extensible = has_option(&item.attrs, "extensible");
+                let def = Def::Struct {
+                    extensible: extensible.map_err(|err| err.to_string())?,
+                    fields: resolver.fields(&module, &item.fields)?,
+                };
+                (
+                    &item.ident,
+                    TypeDef {
+                        doc: doc(&item.attrs),
+                        def,
+                    },
+                )
+            }
+            Item::Enum(item) if is_serde_packet(&item.attrs) => {
+                let extensible = has_option(&item.attrs, "extensible");
+                let def = Def::Enum {
+                    extensible: extensible.map_err(|err| err.to_string())?,
+                    variants: variants(resolver, &module, item)?,
+                };
+                (
+                    &item.ident,
+                    TypeDef {
+                        doc: doc(&item.attrs),
+                        def,
+                    },
+                )
+            }
+            _ => continue,
+        };
+        ret.insert(join(&module, &name.to_string()), def);
+    }
+    for (
+        module,
+        BitflagsDef {
+            attrs,
+            ident,
+            bits,
+            flags: values,
+        },
+    ) in flags
+    {
+        let bits = match resolver.ty(&module, &bits)? {
+            Ty::Prim(prim) => prim,
+            _ => return Err(format!("Invalid bits type of `{ident}`")),
+        };
+        let flags = values.iter().map(|(name, value)| {
+            let value = lit_int(value)
+                .ok_or_else(|| format!("The flag `{ident}::{name}` must be a literal"))?;
+            Ok(Flag {
+                name: name.to_string(),
+                value,
+            })
+        });
+        let def = TypeDef {
+            doc: doc(&attrs),
+            def: Def::Flags {
+                bits,
+                flags: flags.collect::<std::result::Result<_, String>>()?,
+            },
+        };
+        ret.insert(join(&module, &ident.to_string()), def);
+    }
+    Ok(ret)
+}
+
+/// Find the module of the protocol in which the method (or event) with the ID
+/// is declared.
+fn origin<'a, F>(
+    protos: &'a HashMap<String, (String, &'a Protocol)>,
+    proto: &'a Protocol,
+    has: F,
+) -> &'a str
+where
+    F: Fn(&Protocol) -> bool + Copy,
+{
+    for from in &proto.from {
+        let ident = from.segments.last().unwrap().ident.to_string();
+        if let Some((_, parent)) = protos.get(&ident) {
+            if has(parent) {
+                return origin(protos, parent, has);
+            }
+        }
+    }
+    &protos[&proto.ident.to_string()].0
+}
+
+fn method_def(
+    resolver: &Resolver,
+    module: &str,
+    method: &Method,
+) -> std::result::Result<MethodDef, String> {
+    let args = method.args.iter().map(|arg| match arg {
+        FnArg::Typed(arg) => Ok(Arg {
+            name: arg.pat.to_token_stream().to_string(),
+            ty: resolver.ty(module, &arg.ty)?,
+        }),
+        FnArg::Receiver(_) => unreachable!(),
+    });
+    let stream = match &method.stream {
+        Some(item) => Some(resolver.ty(module, item)?),
+        None => None,
+    };
+    let output = match stream {
+        Some(_) => Ty::Prim(Prim::Unit),
+        None => resolver.ty(module, &method.output)?,
+    };
+    Ok(MethodDef {
+        name: method.ident.to_string(),
+        id: method.id as usize,
+        doc: doc(&method.doc),
+        since: method.since,
+        deprecated: method.deprecated.is_some(),
+        close: method.close,
+        args: args.collect::<std::result::Result<_, String>>()?,
+        output,
+        stream,
+    })
+}
+
+impl Idl {
+    /// Describe the resolved protocols and the types they transfer.
+    pub fn collect(src: &Path, items: &[ProtoItem]) -> std::result::Result<Self, String> {
+        let mut resolver = Resolver::default();
+        let types = type_defs(src, items, &mut resolver)?;
+
+        let protos = items.iter().filter_map(|item| match &item.ty {
+            ProtoType::Protocol(proto) => {
+                let module = module_of(src, &item.parent);
+                Some((proto.ident.to_string(), (module, proto)))
+            }
+            ProtoType::Item(_) => None,
+        });
+        let protos = protos.collect::<HashMap<_, _>>();
+
+        let mut protocols = Vec::new();
+        for item in items {
+            let ProtoType::Protocol(proto) = &item.ty else {
+                continue;
+            };
+            let module = module_of(src, &item.parent);
+            let methods = proto.method.iter().map(|method| {
+                let has = |p: &Protocol| p.method.iter().any(|m| m.id == method.id);
+                method_def(&resolver, origin(&protos, proto, has), method)
+            });
+            let events = proto.event.iter().map(|(path, id)| {
+                let has = |p: &Protocol| p.event.iter().any(|e| e.1 == *id);
+                let ty = parse_quote!(#path);
+                Ok(EventDef {
+                    id: *id,
+                    ty: resolver.ty(origin(&protos, proto, has), &ty)?,
+                })
+            });
+            let inherits = proto.from.iter().map(|from| {
+                let ident = from.segments.last().unwrap().ident.to_string();
+                match protos.get(&ident) {
+                    Some((module, _)) => join(module, &ident),
+                    None => ident,
+                }
+            });
+            protocols.push(ProtocolDef {
+                name: join(&module, &proto.ident.to_string()),
+                doc: doc(&proto.doc),
+                version: proto.method.iter().map(|m| m.since).max().unwrap_or(0),
+                inherits: inherits.collect(),
+                methods: methods.collect::<std::result::Result<_, String>>()?,
+                events: events.collect::<std::result::Result<_, String>>()?,
+            })
+        }
+
+        Ok(Idl {
+            wire: Wire {
+                magic: MAGIC,
+                header: [
+                    ("magic", Prim::Usize),
+                    ("method", Prim::Usize),
+                    ("deadline", Prim::U128),
+                ],
+                reserved_methods: RESERVED.into_iter().collect(),
+            },
+            types,
+            protocols,
+        })
+    }
+}
@@ -1,8 +1,10 @@
 #![feature(box_into_inner)]
 
-use std::path::Path;
+use std::{fs, path::Path};
 
+mod c;
 mod gen;
+mod idl;
 mod parse;
 mod resolve;
 mod types;
@@ -12,3 +14,23 @@ pub fn generate(src: &Path, dst: &Path) {
     resolve::resolve(&mut items).expect("Failed to resolve dependencies");
     gen::gen(items, dst).expect("Failed to write to files");
 }
+
+/// Export the description of the protocols to `usr/share/rpc/idl.json` and
+/// their C bindings to `usr/include/rpc` in the sysroot.
+pub fn export(src: &Path, sysroot: &Path) {
+    let mut items = parse::parse_root(src).expect("Failed to parse the directory");
+    resolve::resolve(&mut items).expect("Failed to resolve dependencies");
+    let idl = idl::Idl::collect(src, &items).expect("Failed to describe the protocols");
+
+    let share = sysroot.join("usr/share/rpc");
+    fs::create_dir_all(&share).expect("Failed to create the directory");
+    let json = serde_json::to_string_pretty(&idl).expect("Failed to serialize the description");
+    fs::write(share.join("idl.json"), json).expect("Failed to write to files");
+
+    let include = sysroot.join("usr/include");
+    for (path, content) in c::gen(&idl) {
+        let path = include.join(path);
+        fs::create_dir_all(path.parent().unwrap()).expect("Failed to create the directory");
+        fs::write(path, content).expect("Failed to write to files");
+    }
+}
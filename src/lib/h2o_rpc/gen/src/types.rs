@@ -201,20 +201,46 @@ impl Protocol {
 pub struct Method {
     pub id: u64,
     pub close: bool,
+    /// The protocol version in which the method is introduced.
+    pub since: u32,
+    /// The `#[deprecated]` attribute, if any.
+    pub deprecated: Option<Attribute>,
     pub ident: Ident,
     pub doc: Vec<Attribute>,
     pub const_ident: Ident,
     pub type_ident_prefix: String,
     pub args: Punctuated<FnArg, Token![,]>,
     pub output: Type,
+    /// The item type if the method returns a `Stream<T>`.
+    pub stream: Option<Type>,
+}
+
+/// Get `T` from the output type `Stream<T>` of a streaming method.
+fn stream_item(output: &Type) -> Option<Type> {
+    let Type::Path(TypePath { qself: None, path }) = output else {
+        return None;
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != "Stream" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(ty) => Some(ty.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
 }
 
 impl Parse for Method {
     fn parse(input: ParseStream) -> Result<Self> {
         let meta = Attribute::parse_outer(input)?;
 
-        let (close, doc) = {
+        let (close, since, deprecated, doc) = {
             let mut close = false;
+            let mut since = 0;
+            let mut deprecated = None;
             let mut doc = Vec::with_capacity(meta.len());
 
             for meta in meta {
@@ -228,6 +254,13 @@ impl Parse for Method {
                         }
                         close = true;
                     }
+                    "since" => {
+                        let version = meta.parse_args::<LitInt>().map_err(|_| {
+                            Error::new_spanned(&meta.tokens, "Invalid format for `#[since(n)]`")
+                        })?;
+                        since = version.base10_parse()?;
+                    }
+                    "deprecated" => deprecated = Some(meta),
                     "doc" => doc.push(meta),
                     _ => {
                         let message = format!("Unsupported attribute {meta:?}");
@@ -236,7 +269,7 @@ impl Parse for Method {
                 }
             }
 
-            (close, doc)
+            (close, since, deprecated, doc)
         };
         let sig = Signature::parse(input)?;
         if let Some(ref c) = sig.constness {
@@ -274,16 +307,26 @@ impl Parse for Method {
             syn::ReturnType::Default => parse_quote!(()),
             syn::ReturnType::Type(_, ty) => Box::into_inner(ty),
         };
+        let stream = stream_item(&output);
+        if close && stream.is_some() {
+            return Err(Error::new_spanned(
+                output,
+                "Streaming methods cannot close the connection",
+            ));
+        }
 
         Ok(Method {
             id: 0,
             close,
+            since,
+            deprecated,
             ident,
             doc,
             const_ident,
             type_ident_prefix,
             args,
             output,
+            stream,
         })
     }
 }
@@ -296,6 +339,39 @@ impl Method {
         quote!(#vis const #const_ident: usize = #id as usize)
     }
 
+    fn info(&self) -> TokenStream {
+        let Method {
+            since,
+            deprecated,
+            ident,
+            const_ident,
+            args,
+            output,
+            ..
+        } = self;
+        let name = ident.to_string();
+        let deprecated = deprecated.is_some();
+        let args = args.iter().map(|arg| match arg {
+            FnArg::Typed(arg) => {
+                let name = arg.pat.to_token_stream().to_string();
+                let ty = arg.ty.to_token_stream().to_string();
+                quote!((#name, #ty))
+            }
+            _ => unreachable!(),
+        });
+        let output = output.to_token_stream().to_string();
+        quote! {
+            super::solvent_rpc::MethodInfo {
+                name: #name,
+                id: #const_ident,
+                since: #since,
+                deprecated: #deprecated,
+                args: &[#(#args),*],
+                output: #output,
+            }
+        }
+    }
+
     fn call_arg(&self) -> TokenStream {
         let iter = self.args.iter().map(|arg| match arg {
             FnArg::Typed(arg) => &*arg.pat,
@@ -306,6 +382,7 @@ impl Method {
 
     fn call(&self) -> TokenStream {
         let Method {
+            deprecated,
             ident,
             doc,
             const_ident,
@@ -314,8 +391,24 @@ impl Method {
             ..
         } = self;
         let ser = self.call_arg();
+        if let Some(item) = &self.stream {
+            return quote! {
+                #(#doc)*
+                #deprecated
+                pub async fn #ident (&self, #args) -> Result<solvent_rpc::StreamReceiver<#item>, solvent_rpc::Error> {
+                    let (local, remote) = solvent::ipc::Channel::new();
+                    let mut packet = Default::default();
+                    solvent_rpc::packet::serialize(#const_ident, (#ser remote,), &mut packet)?;
+                    let packet = self.inner.call(packet).await?;
+                    solvent_rpc::packet::deserialize::<()>(#const_ident, &packet, None)?;
+                    let local = solvent_async::ipc::Channel::with_disp(local, self.inner.disp());
+                    Ok(solvent_rpc::StreamReceiver::new(local))
+                }
+            };
+        }
         quote! {
             #(#doc)*
+            #deprecated
             pub async fn #ident (&self, #args) -> Result<#output, solvent_rpc::Error> {
                 let mut packet = Default::default();
                 solvent_rpc::packet::serialize(#const_ident, (#ser), &mut packet)?;
@@ -327,6 +420,7 @@ impl Method {
 
     fn sync_call(&self) -> TokenStream {
         let Method {
+            deprecated,
             ident,
             doc,
             const_ident,
@@ -335,8 +429,23 @@ impl Method {
             ..
         } = self;
         let ser = self.call_arg();
+        if let Some(item) = &self.stream {
+            return quote! {
+                #(#doc)*
+                #deprecated
+                pub fn #ident (&self, #args) -> Result<solvent_rpc::sync::StreamReceiver<#item>, solvent_rpc::Error> {
+                    let (local, remote) = solvent::ipc::Channel::new();
+                    let mut packet = Default::default();
+                    solvent_rpc::packet::serialize(#const_ident, (#ser remote,), &mut packet)?;
+                    let packet = self.inner.call(packet)?;
+                    solvent_rpc::packet::deserialize::<()>(#const_ident, &packet, None)?;
+                    Ok(solvent_rpc::sync::StreamReceiver::new(local))
+                }
+            };
+        }
         quote! {
             #(#doc)*
+            #deprecated
             pub fn #ident (&self, #args) -> Result<#output, solvent_rpc::Error> {
                 let mut packet = Default::default();
                 solvent_rpc::packet::serialize(#const_ident, (#ser), &mut packet)?;
@@ -388,6 +497,18 @@ impl Method {
         } = self;
         let type_ident = Ident::new(type_ident_prefix, ident.span());
         let pat = self.call_arg();
+        if self.stream.is_some() {
+            return quote! {
+                #const_ident => {
+                    let (#pat channel,) = solvent_rpc::packet::deserialize_body(de, None)?;
+                    let responder = #responder {
+                        inner: req.responder,
+                        channel,
+                    };
+                    Ok(#req_ident:: #type_ident { #pat responder })
+                }
+            };
+        }
         quote! {
             #const_ident => {
                 let (#pat) = solvent_rpc::packet::deserialize_body(de, None)?;
@@ -407,6 +528,46 @@ impl Method {
             ..
         } = self;
         let ident = self.responder_ident(prefix);
+        if let Some(item) = &self.stream {
+            return quote! {
+                pub struct #ident {
+                    inner: solvent_rpc::Responder,
+                    channel: solvent::ipc::Channel,
+                }
+
+                impl #ident {
+                    /// Accept the request and get the sender of the stream.
+                    pub fn accept(self) -> Result<solvent_rpc::StreamSender<#item>, solvent_rpc::Error> {
+                        let disp = self.inner.disp();
+                        let mut packet = Default::default();
+                        solvent_rpc::packet::serialize(#const_ident, (), &mut packet)?;
+                        self.inner.send(packet, false)?;
+                        let channel = solvent_async::ipc::Channel::with_disp(self.channel, disp);
+                        Ok(solvent_rpc::StreamSender::new(channel))
+                    }
+
+                    #[inline]
+                    pub fn deadline(&self) -> Option<solvent::time::Instant> {
+                        self.inner.deadline()
+                    }
+
+                    #[inline]
+                    pub fn is_cancelled(&self) -> bool {
+                        self.inner.is_cancelled()
+                    }
+
+                    #[inline]
+                    pub fn cancelled(&self) -> solvent_rpc::Cancelled {
+                        self.inner.cancelled()
+                    }
+
+                    #[inline]
+                    pub fn close(self) {
+                        self.inner.close()
+                    }
+                }
+            };
+        }
         quote! {
             pub struct #ident {
                 inner: solvent_rpc::Responder,
@@ -419,6 +580,21 @@ impl Method {
                     self.inner.send(packet, #close)
                 }
 
+                #[inline]
+                pub fn deadline(&self) -> Option<solvent::time::Instant> {
+                    self.inner.deadline()
+                }
+
+                #[inline]
+                pub fn is_cancelled(&self) -> bool {
+                    self.inner.is_cancelled()
+                }
+
+                #[inline]
+                pub fn cancelled(&self) -> solvent_rpc::Cancelled {
+                    self.inner.cancelled()
+                }
+
                 #[inline]
                 pub fn close(self) {
                     self.inner.close()
@@ -457,6 +633,8 @@ impl Protocol {
         let cast_froms_sync = Protocol::cast_from_sync(&from, &sync_client);
 
         let constants = method.iter().map(|method| method.constant(&vis));
+        let version = method.iter().map(|method| method.since).max().unwrap_or(0);
+        let infos = method.iter().map(|method| method.info());
         let use_constants = method.iter().map(|method| &method.const_ident);
         let calls = method.iter().map(|method| method.call());
         let sync_calls = method.iter().map(|method| method.sync_call());
@@ -469,6 +647,12 @@ impl Protocol {
         let token = quote! {
             pub mod #core_mod {
                 #(#constants;)*
+
+                #vis const DESCRIPTOR: super::solvent_rpc::ProtocolInfo = super::solvent_rpc::ProtocolInfo {
+                    name: #path,
+                    version: #version,
+                    methods: &[#(#infos),*],
+                };
             }
 
             #event_def
@@ -494,6 +678,7 @@ impl Protocol {
 
                 impl solvent_rpc::Protocol for #ident {
                     const PATH: &'static str = #path;
+                    const DESCRIPTOR: &'static solvent_rpc::ProtocolInfo = &#core_mod::DESCRIPTOR;
 
                     type Client = #client;
                     type Server = #server;
@@ -511,7 +696,7 @@ impl Protocol {
                 impl #server {
                     pub fn new(channel: solvent_async::ipc::Channel) -> Self {
                         #server {
-                            inner: solvent_rpc::ServerImpl::new(channel),
+                            inner: solvent_rpc::ServerImpl::with_protocol(channel, &#core_mod::DESCRIPTOR),
                         }
                     }
                 }
@@ -525,6 +710,11 @@ impl Protocol {
                         #server { inner }
                     }
 
+                    #[inline]
+                    fn as_inner(&self) -> &solvent_rpc::ServerImpl {
+                        &self.inner
+                    }
+
                     fn serve(self) -> (#stream, #event_sender) {
                         let (stream, es) = self.inner.serve();
                         (
@@ -578,22 +768,30 @@ impl Protocol {
                     inner: solvent_rpc::PacketStream,
                 }
 
+                impl #stream {
+                    fn parse_request(req: solvent_rpc::Request) -> Result<#request, solvent_rpc::Error> {
+                        let (m, de) = solvent_rpc::packet::deserialize_metadata(&req.packet)?;
+                        match m {
+                            #(#request_pats)*
+                            _ => Ok(#request::Unknown(req)),
+                        }
+                    }
+                }
+
                 impl Stream for #stream {
                     type Item = Result<#request, solvent_rpc::Error>;
 
                     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-                        Poll::Ready(
-                            ready!(Pin::new(&mut self.inner).poll_next(cx)).map(|res| match res {
-                                Ok(req) => {
-                                    let (m, de) = solvent_rpc::packet::deserialize_metadata(&req.packet)?;
-                                    match m {
-                                        #(#request_pats)*
-                                        _ => Ok(#request::Unknown(req)),
-                                    }
-                                }
-                                Err(err) => Err(err),
-                            }),
-                        )
+                        loop {
+                            let req = match ready!(Pin::new(&mut self.inner).poll_next(cx)) {
+                                Some(Ok(req)) => req,
+                                Some(Err(err)) => break Poll::Ready(Some(Err(err))),
+                                None => break Poll::Ready(None),
+                            };
+                            if let Some(req) = solvent_rpc::handle_builtin(req, &#core_mod::DESCRIPTOR) {
+                                break Poll::Ready(Some(Self::parse_request(req)));
+                            }
+                        }
                     }
                 }
 
@@ -651,6 +849,37 @@ impl Protocol {
                         }
                     }
 
+                    /// Get a client sharing the connection whose calls time out after `timeout`.
+                    #[inline]
+                    pub fn with_timeout(&self, timeout: Duration) -> Self {
+                        #client {
+                            inner: self.inner.with_timeout(timeout),
+                        }
+                    }
+
+                    /// Get a client sharing the connection whose calls time out at `deadline`.
+                    #[inline]
+                    pub fn with_deadline(&self, deadline: solvent::time::Instant) -> Self {
+                        #client {
+                            inner: self.inner.with_deadline(deadline),
+                        }
+                    }
+
+                    /// Get the descriptor of the protocol served by the server.
+                    pub async fn describe(&self) -> Result<solvent_rpc::ProtocolDesc, solvent_rpc::Error> {
+                        let mut packet = Default::default();
+                        solvent_rpc::packet::serialize(solvent_rpc::packet::DESCRIBE, (), &mut packet)?;
+                        let packet = self.inner.call(packet).await?;
+                        solvent_rpc::packet::deserialize(solvent_rpc::packet::DESCRIBE, &packet, None)
+                    }
+
+                    /// Get a client of the protocol `P` sharing the connection, or `None` if
+                    /// the server doesn't serve `P` on the channel.
+                    pub async fn upgrade<P: solvent_rpc::Protocol>(&self) -> Result<Option<P::Client>, solvent_rpc::Error> {
+                        let served = self.inner.upgrade(P::PATH).await?;
+                        Ok(served.then(|| solvent_rpc::Client::from_inner(self.inner.clone())))
+                    }
+
                     #(#calls)*
                 }
 
@@ -736,6 +965,37 @@ impl Protocol {
                         }
                     }
 
+                    /// Get a client sharing the connection whose calls time out after `timeout`.
+                    #[inline]
+                    pub fn with_timeout(&self, timeout: Duration) -> Self {
+                        #sync_client {
+                            inner: self.inner.with_timeout(timeout),
+                        }
+                    }
+
+                    /// Get a client sharing the connection whose calls time out at `deadline`.
+                    #[inline]
+                    pub fn with_deadline(&self, deadline: solvent::time::Instant) -> Self {
+                        #sync_client {
+                            inner: self.inner.with_deadline(deadline),
+                        }
+                    }
+
+                    /// Get the descriptor of the protocol served by the server.
+                    pub fn describe(&self) -> Result<solvent_rpc::ProtocolDesc, solvent_rpc::Error> {
+                        let mut packet = Default::default();
+                        solvent_rpc::packet::serialize(solvent_rpc::packet::DESCRIBE, (), &mut packet)?;
+                        let packet = self.inner.call(packet)?;
+                        solvent_rpc::packet::deserialize(solvent_rpc::packet::DESCRIBE, &packet, None)
+                    }
+
+                    /// Get a client of the protocol `P` sharing the connection, or `None` if
+                    /// the server doesn't serve `P` on the channel.
+                    pub fn upgrade<P: solvent_rpc::Protocol>(&self) -> Result<Option<P::SyncClient>, solvent_rpc::Error> {
+                        let served = self.inner.upgrade(P::PATH)?;
+                        Ok(served.then(|| solvent_rpc::sync::Client::from_inner(self.inner.clone())))
+                    }
+
                     #(#sync_calls)*
                 }
 
@@ -0,0 +1,61 @@
+use alloc::{string::String, vec::Vec};
+
+use solvent_rpc_core::SerdePacket;
+
+use crate as solvent_rpc;
+
+/// The upper bounds in microseconds of the latency buckets in
+/// [`MethodStats`], with an extra unbounded bucket at the end.
+pub const LATENCY_BOUNDS_US: [u64; 7] = [10, 100, 1_000, 10_000, 100_000, 1_000_000, 10_000_000];
+
+/// The statistics of the requests of a method received by a server.
+#[derive(SerdePacket, Debug, Clone, Default, PartialEq, Eq)]
+pub struct MethodStats {
+    pub id: usize,
+    /// The name of the method, or empty if it's unknown to the server.
+    pub name: String,
+    pub calls: u64,
+    /// The number of the requests replied with errors.
+    pub errors: u64,
+    /// The number of the requests replied as unsupported.
+    pub unsupported: u64,
+    /// The number of the requests dropped without replies, usually because
+    /// the clients have cancelled them.
+    pub dropped: u64,
+    pub latency_sum_us: u64,
+    pub latency_max_us: u64,
+    /// The number of the requests in each of the buckets bounded by
+    /// [`LATENCY_BOUNDS_US`].
+    pub latency_buckets: Vec<u64>,
+}
+
+/// A packet received or sent by a server.
+#[derive(SerdePacket, Debug, Clone, PartialEq, Eq)]
+pub struct PacketDump {
+    pub method: usize,
+    /// Whether the packet is a reply sent by the server.
+    pub reply: bool,
+    /// The packet ID, or 0 if the sender doesn't wait for replies.
+    pub id: usize,
+    pub buffer: Vec<u8>,
+    pub handle_count: usize,
+}
+
+/// The interface to query the traffic recorded by the metrics middleware of a
+/// server, see `solvent_rpc::Metrics`.
+#[protocol]
+pub trait Inspect: crate::core::Closeable {
+    /// Get the statistics of the methods called since the last reset, sorted
+    /// by their IDs.
+    fn stats() -> Vec<MethodStats>;
+
+    /// Clear the statistics and the dumped packets.
+    fn reset();
+
+    /// Keep the latest `capacity` packets received and sent by the server, or
+    /// stop dumping packets if it's 0.
+    fn set_dump(capacity: usize);
+
+    /// Take the packets dumped so far.
+    fn take_dumps() -> Vec<PacketDump>;
+}
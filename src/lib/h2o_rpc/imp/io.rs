@@ -1,15 +1,15 @@
 pub mod dir;
 pub mod entry;
 pub mod file;
+pub mod fs;
 
-use alloc::{
-    string::{String, ToString},
-    vec::Vec,
-};
+use alloc::{string::String, vec::Vec};
 use core as std;
 
 use solvent::error::Error as RawError;
 #[cfg(feature = "std")]
+use solvent::ipc::Channel;
+#[cfg(feature = "std")]
 use solvent_core::{
     io::{RawStream, SeekFrom},
     path::PathBuf,
@@ -17,7 +17,10 @@ use solvent_core::{
 use solvent_rpc_core::SerdePacket;
 use thiserror_impl::Error;
 
-pub use self::entry::{FileType, Metadata};
+pub use self::{
+    entry::{FileTimes, FileType, Metadata, SetMask, SetMetadata},
+    fs::FsStat,
+};
 use crate as solvent_rpc;
 use crate::{core::*, thiserror};
 
@@ -64,13 +67,28 @@ pub enum Error {
     DirNotEmpty,
 
     #[error("RPC error: {0}")]
-    RpcError(String),
+    RpcError(#[source] solvent_rpc_core::Error),
 
     #[error("invalid data: {0}")]
     InvalidData(String),
 
     #[error("unknown error: {0}")]
     Other(#[source] RawError),
+
+    /// The path goes through a symbolic link out of the reach of the server,
+    /// so the client should open `path` relative to the opened directory
+    /// instead with the unused `conn`.
+    #[error("symbolic link to be resolved by the client: {path:?}")]
+    Symlink { path: PathBuf, conn: Channel },
+
+    #[error("too many levels of symbolic links: {0:?}")]
+    SymlinkLoop(PathBuf),
+
+    #[error("waiting for the lock would deadlock")]
+    Deadlock,
+
+    #[error("no space left on the file system")]
+    NoSpace,
 }
 
 #[cfg(feature = "std")]
@@ -86,7 +104,7 @@ impl From<solvent_async::io::Error> for Error {
 #[cfg(feature = "std")]
 impl From<solvent_rpc_core::Error> for Error {
     fn from(value: solvent_rpc_core::Error) -> Self {
-        Error::RpcError(value.to_string())
+        Error::RpcError(value)
     }
 }
 
@@ -110,6 +128,9 @@ bitflags::bitflags! {
         const EXPECT_FILE = 0b0000_1000_0000;
         const EXPECT_DIR = 0b0001_0000_0000;
         const EXPECT_RPC = 0b0010_0000_0000;
+        /// Fail with `InvalidType(Symlink)` instead of following the last
+        /// component of the path if it's a symbolic link.
+        const NOFOLLOW = 0b0100_0000_0000;
     }
 }
 
@@ -18,6 +18,36 @@ bitflags::bitflags! {
     }
 }
 
+bitflags::bitflags! {
+    /// The kinds of the events reported by [`Directory::watch`].
+    #[derive(Default, SerdePacket)]
+    pub struct WatchMask: u32 {
+        const CREATE = 0b0000_0001;
+        const REMOVE = 0b0000_0010;
+        const RENAME = 0b0000_0100;
+        const MODIFY = 0b0000_1000;
+        const METADATA = 0b0001_0000;
+    }
+}
+
+/// An event of a watched directory, whose paths are relative to it and empty
+/// for the directory itself.
+#[derive(SerdePacket, Debug, Clone, PartialEq, Eq)]
+#[cfg(feature = "std")]
+pub enum WatchEvent {
+    Create(PathBuf),
+    Remove(PathBuf),
+    /// An entry is renamed within the same directory. Entries moved between
+    /// directories are reported as a `Remove` followed by a `Create`.
+    Rename { old: PathBuf, new: PathBuf },
+    /// The content of a file is modified.
+    Modify(PathBuf),
+    /// The attributes of a node are changed.
+    Metadata(PathBuf),
+    /// Some events are dropped because the watcher falls behind.
+    Overflow,
+}
+
 #[derive(SerdePacket, Debug, Clone)]
 pub struct DirEntry {
     pub name: String,
@@ -28,6 +58,11 @@ pub struct DirEntry {
 pub trait Directory: entry::Entry {
     fn next_dirent(last: Option<String>) -> Result<DirEntry, Error>;
 
+    /// List the entries after `last` through a stream instead of calling
+    /// `next_dirent` for each of them.
+    #[since(1)]
+    fn list(last: Option<String>) -> Stream<Result<DirEntry, Error>>;
+
     fn event_token() -> Result<Handle, Error>;
 
     fn rename(src: String, dst_parent: Handle, dst: String) -> Result<(), Error>;
@@ -35,4 +70,22 @@ pub trait Directory: entry::Entry {
     fn link(src: String, dst_parent: Handle, dst: String) -> Result<(), Error>;
 
     fn unlink(name: String, expect_dir: bool) -> Result<(), Error>;
+
+    /// Create a symbolic link named `name` to `target`, which is resolved
+    /// relative to this directory unless it's absolute.
+    #[since(2)]
+    fn symlink(name: String, target: PathBuf) -> Result<(), Error>;
+
+    /// Get the target of the symbolic link named `name`.
+    #[since(2)]
+    fn readlink(name: String) -> Result<PathBuf, Error>;
+
+    /// Watch the events selected by `mask` of the entries in the directory,
+    /// and also of their descendants if `recursive`.
+    ///
+    /// The watcher is notified with an `Overflow` event and loses the
+    /// following ones if it falls behind, and stops when the stream is
+    /// dropped.
+    #[since(3)]
+    fn watch(mask: WatchMask, recursive: bool) -> Stream<Result<WatchEvent, Error>>;
 }
@@ -3,10 +3,78 @@ use solvent::ipc::Channel;
 use super::*;
 
 #[derive(SerdePacket, Debug, Clone)]
+#[serde_packet(extensible)]
 pub struct Metadata {
     pub file_type: FileType,
+    /// The permissions granted to the connection.
     pub perm: Permission,
     pub len: usize,
+    /// The ID of the node, unique and unchanged within its file system while
+    /// the node exists, or 0 if unknown.
+    #[serde_packet(default)]
+    pub node_id: u64,
+    /// The number of the directory entries referring to the node.
+    #[serde_packet(default)]
+    pub link_count: usize,
+    /// The size of the storage allocated for the node, which can differ from
+    /// `len` for sparse or page-granular files.
+    #[serde_packet(default)]
+    pub allocated: usize,
+    /// The permissions granted by the node to every connection, regardless of
+    /// its owner.
+    #[serde_packet(default)]
+    pub mode: Permission,
+    #[serde_packet(default)]
+    pub times: FileTimes,
+}
+
+impl Metadata {
+    /// Create the metadata with the basic attributes, leaving the others
+    /// unknown.
+    pub fn new(file_type: FileType, perm: Permission, len: usize) -> Self {
+        Metadata {
+            file_type,
+            perm,
+            len,
+            node_id: 0,
+            link_count: 1,
+            allocated: len,
+            mode: perm,
+            times: Default::default(),
+        }
+    }
+}
+
+/// The timestamps of a node in nanoseconds since the system boots, each of
+/// which is 0 if unknown.
+#[derive(SerdePacket, Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct FileTimes {
+    pub created: u64,
+    pub modified: u64,
+    pub accessed: u64,
+}
+
+bitflags::bitflags! {
+    /// The attributes to be set by [`SetMetadata`].
+    #[derive(SerdePacket, Default)]
+    pub struct SetMask: u32 {
+        const MODE = 0b0000_0001;
+        const LEN = 0b0000_0010;
+        const CREATED = 0b0000_0100;
+        const MODIFIED = 0b0000_1000;
+        const ACCESSED = 0b0001_0000;
+    }
+}
+
+/// The attributes of a node to be set, of which only the ones selected by
+/// `mask` are applied.
+#[derive(SerdePacket, Debug, Clone, Default)]
+#[serde_packet(extensible)]
+pub struct SetMetadata {
+    pub mask: SetMask,
+    pub mode: Permission,
+    pub len: usize,
+    pub times: FileTimes,
 }
 
 #[derive(SerdePacket, Debug, Copy, Clone, PartialEq, Eq)]
@@ -14,11 +82,17 @@ pub enum FileType {
     File,
     Directory,
     RpcNode,
+    Symlink,
 }
 
 #[protocol]
-pub trait Entry: crate::core::Cloneable + crate::core::Closeable {
+pub trait Entry: crate::core::Cloneable + crate::core::Closeable + fs::Filesystem {
     fn open(path: PathBuf, options: OpenOptions, conn: Channel) -> Result<(), Error>;
 
     fn metadata() -> Result<Metadata, Error>;
+
+    /// Set the attributes selected by `attr.mask`, which requires the
+    /// connection to be writable.
+    #[since(1)]
+    fn set_metadata(attr: SetMetadata) -> Result<(), Error>;
 }
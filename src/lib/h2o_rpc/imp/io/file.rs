@@ -21,10 +21,24 @@ pub enum PhysOptions {
     Copy = 1,
 }
 
+/// The kind of a byte-range lock.
+///
+/// Any number of connections may hold shared locks over the same bytes, while
+/// an exclusive lock excludes all the other locks.
+#[derive(SerdePacket, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum LockKind {
+    Shared = 0,
+    Exclusive = 1,
+}
+
 #[protocol(EventFlags)]
 pub trait File: entry::Entry {
     /// Lock the entire file excluively until the connection is closed.
     ///
+    /// It fails with `WouldBlock` if other connections hold any byte-range
+    /// locks of the file.
+    ///
     /// If the file supports memory-backed stream, the stream will be returned.
     fn lock() -> Result<Result<RawStream, ()>, Error>;
 
@@ -44,4 +58,33 @@ pub trait File: entry::Entry {
     fn resize(new_len: usize) -> Result<(), Error>;
 
     fn phys(options: PhysOptions) -> Result<Phys, Error>;
+
+    /// Read at most `len` bytes from the current position directly into
+    /// `phys` at `offset`, returning the number of bytes read.
+    ///
+    /// Unlike `read`, the length is not bounded by the size of packets.
+    #[since(1)]
+    fn read_into(phys: Phys, offset: usize, len: usize) -> Result<usize, Error>;
+
+    /// Write `len` bytes of `phys` at `offset` directly to the current
+    /// position, returning the number of bytes written.
+    #[since(1)]
+    fn write_from(phys: Phys, offset: usize, len: usize) -> Result<usize, Error>;
+
+    /// Lock `len` bytes from `offset`, or to the end of the file if `len` is
+    /// 0, replacing the locks already held by the connection over the range.
+    ///
+    /// If the range is locked by other connections, the request fails with
+    /// `WouldBlock` unless `wait` is set, in which case it's replied once the
+    /// lock is acquired, or with `Deadlock` if the wait would never end.
+    ///
+    /// The locks are advisory, and are released when the connection is
+    /// closed.
+    #[since(2)]
+    fn lock_range(kind: LockKind, offset: usize, len: usize, wait: bool) -> Result<(), Error>;
+
+    /// Release the locks held by the connection over `len` bytes from
+    /// `offset`, or to the end of the file if `len` is 0.
+    #[since(2)]
+    fn unlock_range(offset: usize, len: usize) -> Result<(), Error>;
 }
@@ -0,0 +1,18 @@
+use super::*;
+
+/// The usage of a file system, in which the limits are `usize::MAX` if
+/// unlimited.
+#[derive(SerdePacket, Debug, Copy, Clone, Default)]
+#[serde_packet(extensible)]
+pub struct FsStat {
+    pub total_bytes: usize,
+    pub used_bytes: usize,
+    pub total_nodes: usize,
+    pub used_nodes: usize,
+}
+
+#[protocol]
+pub trait Filesystem {
+    /// Get the usage of the file system containing the node.
+    fn statfs() -> Result<FsStat, Error>;
+}
@@ -0,0 +1,123 @@
+use alloc::{string::String, vec::Vec};
+
+use solvent_rpc_core::SerdePacket;
+
+use crate as solvent_rpc;
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+        use solvent::{error::Error, ipc::Channel};
+    }
+}
+
+#[derive(SerdePacket, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<log::Level> for Level {
+    fn from(value: log::Level) -> Self {
+        match value {
+            log::Level::Error => Level::Error,
+            log::Level::Warn => Level::Warn,
+            log::Level::Info => Level::Info,
+            log::Level::Debug => Level::Debug,
+            log::Level::Trace => Level::Trace,
+        }
+    }
+}
+
+impl From<Level> for log::Level {
+    fn from(value: Level) -> Self {
+        match value {
+            Level::Error => log::Level::Error,
+            Level::Warn => log::Level::Warn,
+            Level::Info => log::Level::Info,
+            Level::Debug => log::Level::Debug,
+            Level::Trace => log::Level::Trace,
+        }
+    }
+}
+
+#[derive(SerdePacket, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// The record is logged by the kernel itself.
+    Kernel,
+    /// The record is logged by user tasks via `sv_log` (e.g. `dbglog`).
+    User,
+}
+
+#[derive(SerdePacket, Debug, Clone)]
+pub struct Record {
+    /// The sequence number of the record in the kernel log ring.
+    pub seq: u64,
+    /// The timestamp in nanoseconds.
+    pub time: u128,
+    pub cpu: u32,
+    pub level: Level,
+    pub source: Source,
+    pub module: String,
+    pub message: String,
+}
+
+/// The log reader service interface.
+///
+/// The service reads records from the kernel log ring, which contains the
+/// kernel's own output as well as lines logged by user tasks.
+#[protocol]
+pub trait LogReader: crate::core::Cloneable + crate::core::Closeable {
+    /// Read at most `max` records whose sequence numbers are not less than
+    /// `start`.
+    ///
+    /// # Returns
+    ///
+    /// The records read and the sequence number to start the next read from.
+    /// The records may be empty if no new records are available.
+    fn read(start: u64, max: usize) -> (Vec<Record>, u64);
+
+    /// Like `read`, but waits until at least one record is available.
+    fn tail(start: u64, max: usize) -> (Vec<Record>, u64);
+
+    /// Get the current maximum level of the kernel log.
+    fn level() -> Result<Level, Error>;
+
+    /// Set the maximum level of the kernel log at runtime.
+    ///
+    /// # Returns
+    ///
+    /// The previous maximum level.
+    fn set_level(level: Level) -> Result<Level, Error>;
+}
+
+/// A structured record logged by user tasks.
+#[derive(SerdePacket, Debug, Clone)]
+pub struct UserRecord {
+    /// The timestamp in nanoseconds.
+    pub time: u128,
+    pub level: Level,
+    /// The ID of the main task of the logging process.
+    pub pid: u64,
+    /// The ID of the logging task.
+    pub tid: u64,
+    pub module: String,
+    pub tags: Vec<String>,
+    pub message: String,
+    pub key_values: Vec<(String, String)>,
+}
+
+/// The system logger service interface.
+///
+/// The service buffers records logged by user tasks and forwards them to the
+/// kernel log and optionally a log file.
+#[protocol]
+pub trait Logger: crate::core::Cloneable + crate::core::Closeable {
+    /// Log a record. The record may be buffered and not forwarded until the
+    /// buffer is full or flushed.
+    fn log(record: UserRecord);
+
+    /// Forward all the buffered records to the sinks.
+    fn flush() -> Result<(), crate::io::Error>;
+}
@@ -1,4 +1,7 @@
 pub mod core;
 pub mod ddk;
+pub mod inspect;
 pub mod io;
 pub mod loader;
+pub mod logger;
+pub mod power;
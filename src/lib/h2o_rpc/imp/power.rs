@@ -0,0 +1,27 @@
+use crate as solvent_rpc;
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+        use solvent::{error::Error, ipc::Channel};
+    }
+}
+
+/// The power management service interface.
+///
+/// The service holds the privilege to power off, reset or suspend the machine,
+/// and other components request it via this interface.
+#[protocol]
+pub trait Power: crate::core::Cloneable + crate::core::Closeable {
+    /// Power off the machine.
+    ///
+    /// The service doesn't respond if the operation succeeds, so the response
+    /// is received only if it failed.
+    fn shutdown() -> Result<(), Error>;
+
+    /// Reset the machine.
+    ///
+    /// Like `shutdown`, the response is received only if the operation failed.
+    fn reboot() -> Result<(), Error>;
+
+    /// Put the machine to sleep, responding after it's woken up.
+    fn sleep() -> Result<(), Error>;
+}
@@ -5,7 +5,21 @@ mod serde_packet;
 
 use proc_macro::TokenStream;
 
-#[proc_macro_derive(SerdePacket)]
+/// Derive `SerdePacket` for structs and enums.
+///
+/// The type parameters are bounded with `SerdePacket`, and the behavior can be
+/// tuned with `#[serde_packet(...)]`:
+///
+/// - `extensible` on the type: the body is prefixed with its length, so that
+///   the receivers of older versions can skip the unknown trailing fields.
+/// - `skip` on a field: the field is not transferred and is deserialized as its
+///   default value.
+/// - `default` on a field: the field is deserialized as its default value if
+///   nothing is left in the packet (or the extensible body). Usually used on
+///   the fields appended in newer versions.
+/// - `tag = N` on a variant: the stable tag of the variant transferred. By
+///   default, the tags follow the (explicit) discriminants of the variants.
+#[proc_macro_derive(SerdePacket, attributes(serde_packet))]
 pub fn derive_serde_packet(input: TokenStream) -> TokenStream {
     match serde_packet::derive(input) {
         Ok(output) => output,
@@ -1,17 +1,157 @@
+use std::collections::BTreeMap;
+
 use proc_macro::TokenStream;
 use quote::{
     __private::{Span, TokenStream as TokenStream2},
-    quote,
+    quote, ToTokens,
 };
 use syn::{
-    punctuated::Punctuated, token::Comma, DeriveInput, Error, Fields, Ident, Result, Variant,
+    parse_quote, punctuated::Punctuated, token::Comma, Attribute, DeriveInput, Error, Expr,
+    ExprLit, Field, Fields, Generics, Ident, Lit, LitInt, Meta, NestedMeta, Result, Variant,
 };
 
+#[derive(Default)]
+struct ContainerAttrs {
+    extensible: bool,
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    skip: bool,
+    default: bool,
+}
+
+fn parse_attrs(attrs: &[Attribute], mut f: impl FnMut(&Meta) -> Result<()>) -> Result<()> {
+    for attr in attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("serde_packet"))
+    {
+        match attr.parse_meta()? {
+            Meta::List(list) => {
+                for nested in &list.nested {
+                    match nested {
+                        NestedMeta::Meta(meta) => f(meta)?,
+                        NestedMeta::Lit(lit) => {
+                            return Err(Error::new_spanned(lit, "expected an option"))
+                        }
+                    }
+                }
+            }
+            meta => return Err(Error::new_spanned(meta, "expected `#[serde_packet(...)]`")),
+        }
+    }
+    Ok(())
+}
+
+fn container_attrs(attrs: &[Attribute]) -> Result<ContainerAttrs> {
+    let mut ret = ContainerAttrs::default();
+    parse_attrs(attrs, |meta| match meta {
+        Meta::Path(path) if path.is_ident("extensible") => {
+            ret.extensible = true;
+            Ok(())
+        }
+        _ => Err(Error::new_spanned(meta, "unknown container option")),
+    })?;
+    Ok(ret)
+}
+
+fn field_attrs(field: &Field) -> Result<FieldAttrs> {
+    let mut ret = FieldAttrs::default();
+    parse_attrs(&field.attrs, |meta| match meta {
+        Meta::Path(path) if path.is_ident("skip") => {
+            ret.skip = true;
+            Ok(())
+        }
+        Meta::Path(path) if path.is_ident("default") => {
+            ret.default = true;
+            Ok(())
+        }
+        _ => Err(Error::new_spanned(meta, "unknown field option")),
+    })?;
+    Ok(ret)
+}
+
+/// Get the stable tag of the variant, either from `#[serde_packet(tag = N)]` or
+/// from its explicit discriminant.
+fn variant_tag(var: &Variant) -> Result<Option<usize>> {
+    let mut ret = None;
+    parse_attrs(&var.attrs, |meta| match meta {
+        Meta::NameValue(nv) if nv.path.is_ident("tag") => match nv.lit {
+            Lit::Int(ref int) => {
+                ret = Some(int.base10_parse()?);
+                Ok(())
+            }
+            ref lit => Err(Error::new_spanned(lit, "expected an integer tag")),
+        },
+        _ => Err(Error::new_spanned(meta, "unknown variant option")),
+    })?;
+    if ret.is_some() {
+        return Ok(ret);
+    }
+    match var.discriminant {
+        Some((
+            _,
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(ref int),
+                ..
+            }),
+        )) => Ok(Some(int.base10_parse()?)),
+        Some((_, ref expr)) => Err(Error::new_spanned(
+            expr,
+            "the discriminant must be a non-negative integer literal \
+            or be overridden with `#[serde_packet(tag = N)]`",
+        )),
+        None => Ok(None),
+    }
+}
+
+fn contains_ident(tokens: &str, ident: &Ident) -> bool {
+    tokens
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .any(|word| ident == word)
+}
+
+/// Bound every type parameter with `SerdePacket`, and require `Default` for the
+/// generic types of the fields that can be absent from the packet.
+fn bound_generics<'a>(
+    generics: &Generics,
+    fields: impl Iterator<Item = (&'a Field, &'a FieldAttrs)>,
+) -> Generics {
+    let mut generics = generics.clone();
+    let params = generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect::<Vec<_>>();
+    for param in generics.type_params_mut() {
+        param
+            .bounds
+            .push(parse_quote!(solvent_rpc::packet::SerdePacket));
+    }
+    let defaults = fields
+        .filter(|(_, attrs)| attrs.skip || attrs.default)
+        .map(|(field, _)| &field.ty)
+        .filter(|ty| {
+            let tokens = ty.to_token_stream().to_string();
+            params.iter().any(|param| contains_ident(&tokens, param))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    if !defaults.is_empty() {
+        let wc = generics.make_where_clause();
+        for ty in defaults {
+            wc.predicates
+                .push(parse_quote!(#ty: core::default::Default));
+        }
+    }
+    generics
+}
+
 pub(crate) fn derive(input: TokenStream) -> Result<TokenStream> {
     let input = syn::parse::<DeriveInput>(input)?;
+    let attrs = container_attrs(&input.attrs)?;
     Ok(match input.data {
-        syn::Data::Struct(ref s) => derive_struct(&input.ident, &s.fields),
-        syn::Data::Enum(ref e) => derive_enum(&input.ident, &e.variants),
+        syn::Data::Struct(ref s) => derive_struct(&input, &attrs, &s.fields)?,
+        syn::Data::Enum(ref e) => derive_enum(&input, &attrs, &e.variants)?,
         syn::Data::Union(_) => Err(Error::new_spanned(
             input,
             "`SerdePacket` doesn't support unions",
@@ -19,33 +159,58 @@ pub(crate) fn derive(input: TokenStream) -> Result<TokenStream> {
     })
 }
 
-fn derive_fields(name: &Ident, fields: &Fields) -> [TokenStream2; 3] {
-    let pat = fields.iter().enumerate().map(|(index, field)| {
-        if let Some(ref ident) = field.ident {
-            quote!(#ident)
-        } else {
-            let ident = Ident::new(&format!("v{index}"), Span::call_site());
-            quote!(#ident)
-        }
-    });
+fn derive_fields(name: &Ident, fields: &Fields, attrs: &[FieldAttrs]) -> [TokenStream2; 3] {
+    let pat = fields
+        .iter()
+        .zip(attrs)
+        .enumerate()
+        .map(|(index, (field, attrs))| match field.ident {
+            Some(ref ident) if attrs.skip => quote!(#ident: _),
+            Some(ref ident) => quote!(#ident),
+            None if attrs.skip => quote!(_),
+            None => {
+                let ident = Ident::new(&format!("v{index}"), Span::call_site());
+                quote!(#ident)
+            }
+        });
     let pat = match fields {
         Fields::Named(_) => quote!(#name { #(#pat),* }),
         Fields::Unnamed(_) => quote!(#name (#(#pat),*)),
         Fields::Unit => quote!(#name),
     };
-    let ser = fields.iter().enumerate().map(|(index, field)| {
-        if let Some(ref ident) = field.ident {
-            quote!(SerdePacket::serialize(#ident, ser)?;)
+    let ser = fields
+        .iter()
+        .zip(attrs)
+        .enumerate()
+        .filter(|(_, (_, attrs))| !attrs.skip)
+        .map(|(index, (field, _))| {
+            if let Some(ref ident) = field.ident {
+                quote!(SerdePacket::serialize(#ident, ser)?;)
+            } else {
+                let ident = Ident::new(&format!("v{index}"), Span::call_site());
+                quote!(SerdePacket::serialize(#ident, ser)?;)
+            }
+        });
+    let de = fields.iter().zip(attrs).map(|(field, attrs)| {
+        let value = if attrs.skip {
+            quote!(core::default::Default::default())
+        } else if attrs.default {
+            // Fields appended in newer versions are absent in the packets from
+            // older peers.
+            quote! {
+                if de.is_empty() {
+                    core::default::Default::default()
+                } else {
+                    SerdePacket::deserialize(de)?
+                }
+            }
         } else {
-            let ident = Ident::new(&format!("v{index}"), Span::call_site());
-            quote!(SerdePacket::serialize(#ident, ser)?;)
-        }
-    });
-    let de = fields.iter().map(|field| {
+            quote!(SerdePacket::deserialize(de)?)
+        };
         if let Some(ref ident) = field.ident {
-            quote!(#ident: SerdePacket::deserialize(de)?,)
+            quote!(#ident: #value,)
         } else {
-            quote!(SerdePacket::deserialize(de)?,)
+            quote!(#value,)
         }
     });
     let de = match &fields {
@@ -56,10 +221,43 @@ fn derive_fields(name: &Ident, fields: &Fields) -> [TokenStream2; 3] {
     [pat, quote!(#(#ser)*), de]
 }
 
-fn derive_struct(name: &Ident, fields: &Fields) -> TokenStream {
-    let [pat, ser, de] = derive_fields(name, fields);
-    quote! {
-        impl solvent_rpc::packet::SerdePacket for #name {
+/// Wrap the body of the (de)serialization in a length-prefixed section if the
+/// type is extensible.
+fn sections(
+    attrs: &ContainerAttrs,
+    ser: TokenStream2,
+    de: TokenStream2,
+) -> (TokenStream2, TokenStream2) {
+    if attrs.extensible {
+        (
+            quote!(ser.section(|ser| { #ser Ok(()) })?;),
+            quote!(de.section(|de| Ok({ #de }))?),
+        )
+    } else {
+        (ser, de)
+    }
+}
+
+fn field_attrs_of(fields: &Fields) -> Result<Vec<FieldAttrs>> {
+    fields.iter().map(field_attrs).collect()
+}
+
+fn derive_struct(
+    input: &DeriveInput,
+    attrs: &ContainerAttrs,
+    fields: &Fields,
+) -> Result<TokenStream> {
+    let name = &input.ident;
+    let field_attrs = field_attrs_of(fields)?;
+    let generics = bound_generics(&input.generics, fields.iter().zip(&field_attrs));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let [pat, ser, de] = derive_fields(name, fields, &field_attrs);
+    let (ser, de) = sections(attrs, ser, de);
+    Ok(quote! {
+        impl #impl_generics solvent_rpc::packet::SerdePacket for #name #ty_generics
+            #where_clause
+        {
             fn serialize(self, ser: &mut solvent_rpc::packet::Serializer)
                 -> Result<(), solvent_rpc::Error>
             {
@@ -80,30 +278,73 @@ fn derive_struct(name: &Ident, fields: &Fields) -> TokenStream {
             }
         }
     }
-    .into()
+    .into())
 }
 
-fn derive_enum(name: &Ident, variants: &Punctuated<Variant, Comma>) -> TokenStream {
-    let iter = variants.iter().enumerate().map(|(index, var)| {
-        let ident = &var.ident;
-        let fields = &var.fields;
-        let [pat, ser, de] = derive_fields(ident, fields);
+fn derive_enum(
+    input: &DeriveInput,
+    attrs: &ContainerAttrs,
+    variants: &Punctuated<Variant, Comma>,
+) -> Result<TokenStream> {
+    let name = &input.ident;
+    let field_attrs = variants
+        .iter()
+        .map(|var| field_attrs_of(&var.fields))
+        .collect::<Result<Vec<_>>>()?;
+    let fields = variants
+        .iter()
+        .zip(&field_attrs)
+        .flat_map(|(var, attrs)| var.fields.iter().zip(attrs));
+    let generics = bound_generics(&input.generics, fields);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-        let ser = quote!(#name ::#pat => { SerdePacket::serialize(#index, ser)?; #ser });
-        let de = quote!(#index => #name ::#de,);
-        (ser, de)
-    });
-    let (ser, de): (TokenStream2, TokenStream2) = iter.unzip();
+    // Tags follow the same rule as the discriminants of Rust: an implicit one
+    // is the previous one plus 1.
+    let mut tags = BTreeMap::new();
+    let mut next = 0;
+    let mut ser = TokenStream2::new();
+    let mut de = TokenStream2::new();
+    for (var, field_attrs) in variants.iter().zip(&field_attrs) {
+        let tag = variant_tag(var)?.unwrap_or(next);
+        if let Some(prev) = tags.insert(tag, &var.ident) {
+            return Err(Error::new_spanned(
+                var,
+                format!("the tag {tag} is already used by `{prev}`"),
+            ));
+        }
+        next = tag + 1;
+
+        let ident = &var.ident;
+        let [pat, fser, fde] = derive_fields(ident, &var.fields, field_attrs);
+        let tag = LitInt::new(&format!("{tag}usize"), Span::call_site());
+        ser.extend(quote!(#name::#pat => { SerdePacket::serialize(#tag, ser)?; #fser }));
+        de.extend(quote!(#tag => #name::#fde,));
+    }
 
-    let len = variants.len();
+    let name_str = name.to_string();
+    let ser = quote!(match self { #ser });
+    let de = quote! {
+        let tag: usize = SerdePacket::deserialize(de)?;
+        match tag {
+            #de
+            _ => return Err(solvent_rpc::Error::TypeMismatch(alloc::format!(
+                "unknown variant tag {} of `{}`",
+                tag,
+                #name_str
+            ).into()))
+        }
+    };
+    let (ser, de) = sections(attrs, ser, de);
     let token_stream = quote! {
-        impl solvent_rpc::packet::SerdePacket for #name {
+        impl #impl_generics solvent_rpc::packet::SerdePacket for #name #ty_generics
+            #where_clause
+        {
             fn serialize(self, ser: &mut solvent_rpc::packet::Serializer)
                 -> Result<(), solvent_rpc::Error>
             {
                 #[allow(dead_code)]
                 use solvent_rpc::packet::SerdePacket;
-                match self { #ser }
+                #ser
                 Ok(())
             }
 
@@ -112,18 +353,10 @@ fn derive_enum(name: &Ident, variants: &Punctuated<Variant, Comma>) -> TokenStre
             {
                 #[allow(dead_code)]
                 use solvent_rpc::packet::SerdePacket;
-                let index: usize = SerdePacket::deserialize(de)?;
-                let ret = match index {
-                    #de
-                    _ => return Err(solvent_rpc::Error::TypeMismatch(alloc::format!(
-                        "unknown variant index {}, support 0..{}",
-                        index,
-                        #len
-                    ).into()))
-                };
+                let ret = { #de };
                 Ok(ret)
             }
         }
     };
-    token_stream.into()
+    Ok(token_stream.into())
 }
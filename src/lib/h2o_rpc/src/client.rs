@@ -1,4 +1,7 @@
-use alloc::collections::{btree_map::Entry, BTreeMap};
+use alloc::{
+    collections::{btree_map::Entry, BTreeMap},
+    string::String,
+};
 use core::{
     fmt,
     future::Future,
@@ -7,19 +10,47 @@ use core::{
     pin::Pin,
     sync::atomic::{AtomicBool, AtomicUsize, Ordering::*},
     task::{Context, Poll, Waker},
+    time::Duration,
 };
 
 use crossbeam::queue::SegQueue;
-use futures::{pin_mut, ready, stream::FusedStream, Stream};
-use solvent::{error::EPIPE, ipc::Packet};
-use solvent_async::ipc::Channel;
+use futures::{
+    future::{select, Either},
+    pin_mut, ready,
+    stream::FusedStream,
+    Stream,
+};
+use solvent::{
+    error::{EPIPE, ETIME},
+    ipc::Packet,
+    time::{Instant, Timer as RawTimer},
+};
+use solvent_async::{disp::DispSender, ipc::Channel, time::Timer};
 use solvent_core::sync::{Arsc, Mutex};
 
-use crate::Error;
+use crate::{packet, Error};
+
+/// The time limit of the calls of a client.
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum Expiry {
+    Timeout(Duration),
+    Deadline(Instant),
+}
+
+impl Expiry {
+    #[inline]
+    pub(crate) fn deadline(self) -> Instant {
+        match self {
+            Expiry::Timeout(timeout) => Instant::now() + timeout,
+            Expiry::Deadline(deadline) => deadline,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct ClientImpl {
     inner: Arsc<Inner>,
+    expiry: Option<Expiry>,
 }
 
 impl ClientImpl {
@@ -35,6 +66,32 @@ impl ClientImpl {
                 wakers: Mutex::new(BTreeMap::new()),
                 stop: AtomicBool::new(false),
             }),
+            expiry: None,
+        }
+    }
+
+    /// Get a client sharing the connection whose calls time out after
+    /// `timeout`.
+    ///
+    /// The deadline is also sent to the server along with each request.
+    #[inline]
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        ClientImpl {
+            inner: self.inner.clone(),
+            expiry: Some(Expiry::Timeout(timeout)),
+        }
+    }
+
+    /// Get a client sharing the connection whose calls time out at
+    /// `deadline`.
+    ///
+    /// This is useful for servers propagating the deadlines of their own
+    /// requests.
+    #[inline]
+    pub fn with_deadline(&self, deadline: Instant) -> Self {
+        ClientImpl {
+            inner: self.inner.clone(),
+            expiry: Some(Expiry::Deadline(deadline)),
         }
     }
 
@@ -59,7 +116,19 @@ impl ClientImpl {
         })
     }
 
+    #[inline]
+    pub fn disp(&self) -> DispSender {
+        self.inner.channel.disp()
+    }
+
+    /// Send the request and wait for its reply.
+    ///
+    /// Dropping the future before it completes cancels the request on the
+    /// server side.
     pub async fn call(&self, mut packet: Packet) -> Result<Packet, Error> {
+        let deadline = self.expiry.map(Expiry::deadline);
+        packet::set_deadline(&mut packet, deadline)?;
+
         let id = self.inner.register();
         packet.id = NonZeroUsize::new(id);
 
@@ -68,11 +137,34 @@ impl ClientImpl {
             res => res.map_err(Error::ClientSend)?,
         };
 
-        Call {
+        let call = Call {
             id,
             inner: Some(self.inner.clone()),
+        };
+
+        let Some(deadline) = deadline else {
+            return call.await;
+        };
+        let timer = RawTimer::try_new().map_err(Error::ClientReceive)?;
+        let timer = Timer::with_disp(timer, self.disp());
+        let timeout = timer.wait_until(deadline);
+        pin_mut!(timeout);
+        match select(call, timeout).await {
+            Either::Left((res, _)) => res,
+            Either::Right((res, _)) => Err(Error::ClientReceive(res.err().unwrap_or(ETIME))),
         }
-        .await
+    }
+
+    /// Ask whether the server also serves the protocol of `path` on the
+    /// channel.
+    ///
+    /// If so, clients of the protocol can be created from this one, sharing
+    /// the connection and its event receiver.
+    pub async fn upgrade(&self, path: &str) -> Result<bool, Error> {
+        let mut packet = Default::default();
+        packet::serialize(packet::UPGRADE, String::from(path), &mut packet)?;
+        let packet = self.call(packet).await?;
+        packet::deserialize(packet::UPGRADE, &packet, None)
     }
 }
 
@@ -101,10 +193,14 @@ impl TryFrom<ClientImpl> for Channel {
                 } else {
                     Err(ClientImpl {
                         inner: Arsc::new(inner),
+                        expiry: client.expiry,
                     })
                 }
             }
-            Err(inner) => Err(ClientImpl { inner }),
+            Err(inner) => Err(ClientImpl {
+                inner,
+                expiry: client.expiry,
+            }),
         }
     }
 }
@@ -175,6 +271,7 @@ impl Drop for Call {
     fn drop(&mut self) {
         if let Some(client) = self.inner.take() {
             client.deregister(self.id);
+            client.cancel(self.id);
         }
     }
 }
@@ -313,6 +410,15 @@ impl Inner {
         }
     }
 
+    /// Tell the server that the caller of `id` no longer waits for the reply.
+    fn cancel(&self, id: usize) {
+        let mut packet = Default::default();
+        if packet::serialize(packet::CANCEL, (), &mut packet).is_ok() {
+            packet.id = NonZeroUsize::new(id);
+            let _ = self.channel.send(&mut packet);
+        }
+    }
+
     async fn receive(&self) -> Result<(), Error> {
         let mut packet = Default::default();
         let res = self.channel.receive(&mut packet).await;
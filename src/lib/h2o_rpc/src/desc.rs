@@ -0,0 +1,90 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use solvent_rpc_core::SerdePacket;
+
+use crate as solvent_rpc;
+
+/// The static description of a protocol method, generated by
+/// `solvent_rpc_gen`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MethodInfo {
+    pub name: &'static str,
+    pub id: usize,
+    /// The protocol version in which the method is introduced, specified by
+    /// `#[since(n)]`.
+    pub since: u32,
+    pub deprecated: bool,
+    /// The names and the types of the arguments.
+    pub args: &'static [(&'static str, &'static str)],
+    pub output: &'static str,
+}
+
+/// The static description of a protocol, generated by `solvent_rpc_gen`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ProtocolInfo {
+    pub name: &'static str,
+    /// The maximum version in which the methods are introduced.
+    pub version: u32,
+    pub methods: &'static [MethodInfo],
+}
+
+/// The description of a protocol method, sent by servers in reply to the
+/// built-in `describe` method.
+#[derive(SerdePacket, Debug, Clone, PartialEq, Eq)]
+pub struct MethodDesc {
+    pub name: String,
+    pub id: usize,
+    pub since: u32,
+    pub deprecated: bool,
+    pub args: Vec<(String, String)>,
+    pub output: String,
+}
+
+/// The description of a protocol, sent by servers in reply to the built-in
+/// `describe` method.
+#[derive(SerdePacket, Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolDesc {
+    pub name: String,
+    pub version: u32,
+    pub methods: Vec<MethodDesc>,
+}
+
+impl ProtocolDesc {
+    /// Find the description of a method by its name.
+    pub fn method(&self, name: &str) -> Option<&MethodDesc> {
+        self.methods.iter().find(|method| method.name == name)
+    }
+
+    /// Check if the protocol supports the method with the ID.
+    pub fn supports(&self, id: usize) -> bool {
+        self.methods.iter().any(|method| method.id == id)
+    }
+}
+
+impl From<&MethodInfo> for MethodDesc {
+    fn from(info: &MethodInfo) -> Self {
+        MethodDesc {
+            name: info.name.to_string(),
+            id: info.id,
+            since: info.since,
+            deprecated: info.deprecated,
+            args: { info.args.iter() }
+                .map(|&(name, ty)| (name.to_string(), ty.to_string()))
+                .collect(),
+            output: info.output.to_string(),
+        }
+    }
+}
+
+impl From<&ProtocolInfo> for ProtocolDesc {
+    fn from(info: &ProtocolInfo) -> Self {
+        ProtocolDesc {
+            name: info.name.to_string(),
+            version: info.version,
+            methods: info.methods.iter().map(MethodDesc::from).collect(),
+        }
+    }
+}
@@ -4,6 +4,7 @@ use solvent_rpc_core::packet::{Deserializer, SerdePacket, Serializer};
 #[cfg(feature = "std")]
 pub trait Protocol {
     const PATH: &'static str;
+    const DESCRIPTOR: &'static crate::ProtocolInfo;
 
     type Client: crate::Client;
     type Server: crate::Server;
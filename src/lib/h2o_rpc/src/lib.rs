@@ -4,21 +4,88 @@
 #![feature(type_alias_impl_trait)]
 
 extern crate alloc;
+#[cfg(test)]
+extern crate std;
 
 #[cfg(feature = "std")]
 mod client;
+mod desc;
 mod ifx;
 #[path ="../target/imp/mod.rs"]
 #[rustfmt::skip]
 #[allow(unused, clippy::all)]
 mod imp;
 #[cfg(feature = "std")]
+mod limit;
+#[cfg(feature = "std")]
+mod metrics;
+#[cfg(feature = "std")]
 mod server;
 #[cfg(feature = "std")]
+mod stream;
+#[cfg(feature = "std")]
 pub mod sync;
 
 pub use solvent_rpc_core::*;
 
 #[cfg(feature = "std")]
-pub use self::{client::*, server::*};
-pub use self::{ifx::*, imp::*};
+pub use self::{client::*, limit::*, metrics::*, server::*, stream::*};
+pub use self::{desc::*, ifx::*, imp::*};
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use core::{future::Future, task::Poll};
+    use std::thread;
+
+    use futures_lite::{future, StreamExt};
+    use solvent::error::ESPRT;
+    use solvent_async::disp::{self, DispReceiver};
+
+    use crate::{
+        power::{Power, PowerRequest},
+        sync_client_with_disp, Server,
+    };
+
+    /// Run `fut` on the current thread, receiving the results of the syscalls
+    /// dispatched through `rx` in the meantime.
+    fn block_on<T>(rx: DispReceiver, fut: impl Future<Output = T>) -> T {
+        let receive = future::poll_fn(|cx| {
+            while let Poll::Ready(res) = rx.poll_receive() {
+                res.expect("Failed to receive from the dispatcher");
+            }
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        });
+        future::block_on(future::or(fut, receive))
+    }
+
+    #[test]
+    fn test_server_client() {
+        let (tx, rx) = disp::dispatch(64);
+        let (client, server) = sync_client_with_disp::<Power>(tx);
+
+        let server = thread::spawn(move || {
+            block_on(rx, async move {
+                let (mut stream, _) = server.serve();
+                let mut count = 0;
+                while let Some(request) = stream.next().await {
+                    let res = match request.expect("Failed to receive the request") {
+                        PowerRequest::Shutdown { responder } => responder.send(Err(ESPRT)),
+                        PowerRequest::Reboot { responder } => responder.send(Ok(())),
+                        _ => panic!("Unexpected request"),
+                    };
+                    res.expect("Failed to send the response");
+                    count += 1;
+                }
+                count
+            })
+        });
+
+        assert_eq!(client.shutdown().expect("Failed to call"), Err(ESPRT));
+        assert_eq!(client.reboot().expect("Failed to call"), Ok(()));
+
+        // Closing the client ends the request stream of the server.
+        drop(client);
+        assert_eq!(server.join().expect("The server panicked"), 2);
+    }
+}
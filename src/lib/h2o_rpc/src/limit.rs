@@ -0,0 +1,164 @@
+use alloc::{collections::VecDeque, sync::Arc};
+use core::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering::*},
+    task::{Context, Poll},
+};
+
+use futures::task::AtomicWaker;
+use solvent_core::sync::{Arsc, Mutex};
+
+/// The limits of the requests handled by a server, see
+/// [`Server::with_limits`](crate::Server::with_limits).
+///
+/// The default limits are finite, so that a client flooding a server can't make
+/// it save the requests without bound.
+#[derive(Debug, Clone)]
+pub struct Limits {
+    /// The maximum number of the requests in flight on a channel, after which
+    /// the server stops receiving packets from it until some of the requests
+    /// are replied or dropped.
+    pub per_connection: usize,
+    /// The maximum number of the requests saved for each protocol while its
+    /// stream is not polled, after which the following ones are replied as
+    /// busy. It also bounds the channels waiting for the shared cap, beyond
+    /// which the request of the channel is replied as busy instead.
+    pub backlog: usize,
+    /// The cap of the requests in flight shared among the channels of a
+    /// service.
+    pub shared: Option<Arc<Limiter>>,
+}
+
+impl Limits {
+    /// The default maximum number of the requests in flight on a channel.
+    pub const DEFAULT_PER_CONNECTION: usize = 64;
+    /// The default maximum number of the requests saved for each protocol.
+    pub const DEFAULT_BACKLOG: usize = 64;
+
+    pub const fn new() -> Self {
+        Limits {
+            per_connection: Self::DEFAULT_PER_CONNECTION,
+            backlog: Self::DEFAULT_BACKLOG,
+            shared: None,
+        }
+    }
+
+    /// The limits that never restrict the requests.
+    pub const fn unbounded() -> Self {
+        Limits {
+            per_connection: usize::MAX,
+            backlog: usize::MAX,
+            shared: None,
+        }
+    }
+}
+
+impl Default for Limits {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A cap of the requests in flight shared among the channels of a service.
+///
+/// Channels waiting for the cap are granted in the order they arrive, so that
+/// a busy client can't starve the others.
+pub struct Limiter {
+    state: Mutex<State>,
+}
+
+struct State {
+    available: usize,
+    queue: VecDeque<Arsc<Ticket>>,
+}
+
+/// A place in the queue of a [`Limiter`].
+#[derive(Debug, Default)]
+pub(crate) struct Ticket {
+    granted: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl Limiter {
+    /// Create a limiter allowing at most `max` requests in flight.
+    pub const fn new(max: usize) -> Self {
+        Limiter {
+            state: Mutex::new(State {
+                available: max,
+                queue: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// The number of the requests that can be handled without waiting.
+    pub fn available(&self) -> usize {
+        self.state.lock().available
+    }
+
+    /// The number of the channels waiting for the cap.
+    pub fn waiting(&self) -> usize {
+        self.state.lock().queue.len()
+    }
+
+    /// Try to take a permit, queueing `ticket` if there's none left.
+    pub(crate) fn poll_acquire(
+        &self,
+        ticket: &mut Option<Arsc<Ticket>>,
+        cx: &mut Context<'_>,
+    ) -> Poll<()> {
+        let mut state = self.state.lock();
+        if let Some(t) = ticket {
+            if t.granted.load(Acquire) {
+                *ticket = None;
+                return Poll::Ready(());
+            }
+            t.waker.register(cx.waker());
+            return Poll::Pending;
+        }
+
+        if state.available > 0 && state.queue.is_empty() {
+            state.available -= 1;
+            return Poll::Ready(());
+        }
+        let t = Arsc::new(Ticket::default());
+        t.waker.register(cx.waker());
+        state.queue.push_back(t.clone());
+        *ticket = Some(t);
+        Poll::Pending
+    }
+
+    /// Give back a permit, handing it over to the first waiting channel if
+    /// any.
+    pub(crate) fn release(&self) {
+        let mut state = self.state.lock();
+        match state.queue.pop_front() {
+            Some(ticket) => {
+                ticket.granted.store(true, Release);
+                ticket.waker.wake();
+            }
+            None => state.available += 1,
+        }
+    }
+
+    /// Leave the queue, giving back the permit if it's already granted.
+    pub(crate) fn cancel(&self, ticket: &Arsc<Ticket>) {
+        let mut state = self.state.lock();
+        if ticket.granted.load(Acquire) {
+            drop(state);
+            self.release();
+        } else {
+            state.queue.retain(|t| !Arsc::ptr_eq(t, ticket));
+        }
+    }
+}
+
+impl fmt::Debug for Limiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.lock();
+        f.debug_struct("Limiter")
+            .field("available", &state.available)
+            .field("waiting", &state.queue.len())
+            .finish()
+    }
+}
@@ -0,0 +1,173 @@
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    string::String,
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+use core::{
+    sync::atomic::{AtomicUsize, Ordering::*},
+    time::Duration,
+};
+
+use futures::StreamExt;
+use solvent::prelude::Packet;
+use solvent_core::sync::Mutex;
+
+use crate::{
+    inspect::{InspectRequest, InspectServer, MethodStats, PacketDump, LATENCY_BOUNDS_US},
+    MethodInfo, Middleware, Outcome, Server,
+};
+
+/// A middleware recording the per-method statistics and optionally the
+/// packets of a server, which can be queried through the `Inspect` protocol.
+///
+/// The `Inspect` server is usually routed on the same channel as the observed
+/// one, so that its clients can reach it by upgrading their connections.
+#[derive(Debug)]
+pub struct Metrics {
+    methods: Mutex<BTreeMap<usize, MethodStats>>,
+    dump_cap: AtomicUsize,
+    dumps: Mutex<VecDeque<PacketDump>>,
+}
+
+impl Metrics {
+    pub const fn new() -> Self {
+        Metrics {
+            methods: Mutex::new(BTreeMap::new()),
+            dump_cap: AtomicUsize::new(0),
+            dumps: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Get the statistics of the methods, sorted by their IDs.
+    pub fn stats(&self) -> Vec<MethodStats> {
+        self.methods.lock().values().cloned().collect()
+    }
+
+    pub fn reset(&self) {
+        self.methods.lock().clear();
+        self.dumps.lock().clear();
+    }
+
+    /// Keep the latest `capacity` packets, or stop dumping packets if it's 0.
+    pub fn set_dump(&self, capacity: usize) {
+        self.dump_cap.store(capacity, Release);
+        let mut dumps = self.dumps.lock();
+        let len = dumps.len();
+        dumps.drain(..len.saturating_sub(capacity));
+    }
+
+    pub fn take_dumps(&self) -> Vec<PacketDump> {
+        self.dumps.lock().drain(..).collect()
+    }
+
+    fn dump(&self, method: usize, reply: bool, packet: &Packet) {
+        let cap = self.dump_cap.load(Acquire);
+        if cap == 0 {
+            return;
+        }
+        let dump = PacketDump {
+            method,
+            reply,
+            id: packet.id.map_or(0, |id| id.get()),
+            buffer: packet.buffer.clone(),
+            handle_count: packet.handles.len(),
+        };
+        let mut dumps = self.dumps.lock();
+        while dumps.len() >= cap {
+            dumps.pop_front();
+        }
+        dumps.push_back(dump);
+    }
+
+    /// Answer the queries of the `Inspect` protocol until the client
+    /// disconnects.
+    pub async fn serve(self: Arc<Self>, server: InspectServer) {
+        let (mut stream, _) = server.serve();
+        while let Some(request) = stream.next().await {
+            let request = match request {
+                Ok(request) => request,
+                Err(err) => {
+                    log::warn!("inspect RPC receive error: {err}");
+                    break;
+                }
+            };
+            let res = match request {
+                InspectRequest::Stats { responder } => responder.send(self.stats()),
+                InspectRequest::Reset { responder } => {
+                    self.reset();
+                    responder.send(())
+                }
+                InspectRequest::SetDump {
+                    capacity,
+                    responder,
+                } => {
+                    self.set_dump(capacity);
+                    responder.send(())
+                }
+                InspectRequest::TakeDumps { responder } => responder.send(self.take_dumps()),
+                InspectRequest::CloseConnection { responder } => {
+                    responder.close();
+                    break;
+                }
+                InspectRequest::Unknown(req) => req.unsupported(),
+            };
+            if let Err(err) = res {
+                log::warn!("inspect RPC send error: {err}");
+                break;
+            }
+        }
+    }
+}
+
+impl Default for Metrics {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for Metrics {
+    #[inline]
+    fn request(&self, method: usize, _: Option<&'static MethodInfo>, packet: &Packet) {
+        self.dump(method, false, packet)
+    }
+
+    fn reply(
+        &self,
+        method: usize,
+        info: Option<&'static MethodInfo>,
+        elapsed: Duration,
+        outcome: Outcome,
+        packet: Option<&Packet>,
+    ) {
+        if let Some(packet) = packet {
+            self.dump(method, true, packet);
+        }
+
+        let mut methods = self.methods.lock();
+        let stats = methods.entry(method).or_insert_with(|| MethodStats {
+            id: method,
+            name: info.map_or_else(String::new, |info| info.name.into()),
+            latency_buckets: vec![0; LATENCY_BOUNDS_US.len() + 1],
+            ..Default::default()
+        });
+        stats.calls += 1;
+        match outcome {
+            Outcome::Ok => {}
+            Outcome::Err => stats.errors += 1,
+            Outcome::Unsupported => stats.unsupported += 1,
+            Outcome::Dropped => stats.dropped += 1,
+        }
+
+        let us = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        stats.latency_sum_us = stats.latency_sum_us.saturating_add(us);
+        stats.latency_max_us = stats.latency_max_us.max(us);
+        let bucket = LATENCY_BOUNDS_US
+            .iter()
+            .position(|&bound| us <= bound)
+            .unwrap_or(LATENCY_BOUNDS_US.len());
+        stats.latency_buckets[bucket] += 1;
+    }
+}
@@ -1,45 +1,116 @@
+use alloc::{collections::BTreeMap, string::String, sync::Arc, vec, vec::Vec};
 use core::{
     fmt,
     future::Future,
     mem::ManuallyDrop,
     num::NonZeroUsize,
     pin::Pin,
-    sync::atomic::{AtomicBool, Ordering::*},
+    ptr,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering::*},
     task::{ready, Context, Poll},
+    time::Duration,
 };
 
-use futures::{pin_mut, stream::FusedStream, Stream};
-use solvent::prelude::{Handle, Object, Packet, EPIPE};
-use solvent_async::ipc::Channel;
-use solvent_core::sync::Arsc;
+use crossbeam::queue::SegQueue;
+use futures::{pin_mut, stream::FusedStream, task::AtomicWaker, Stream};
+use solvent::{
+    prelude::{Handle, Object, Packet, EPIPE},
+    time::Instant,
+};
+use solvent_async::{disp::DispSender, ipc::Channel};
+use solvent_core::sync::{Arsc, Mutex};
 
-use crate::Error;
+use crate::{
+    limit::Ticket, packet, Error, Limiter, Limits, MethodInfo, ProtocolDesc, ProtocolInfo,
+};
 
 #[derive(Debug)]
-#[repr(transparent)]
 pub struct ServerImpl {
     inner: Arsc<Inner>,
+    route: Arsc<Route>,
 }
 
 impl ServerImpl {
     pub fn new(channel: Channel) -> Self {
+        Self::with_info(channel, None)
+    }
+
+    /// Create a server of the protocol described by `info`, whose methods are
+    /// dispatched to it before the ones of the protocols routed later.
+    pub fn with_protocol(channel: Channel, info: &'static ProtocolInfo) -> Self {
+        Self::with_info(channel, Some(info))
+    }
+
+    fn with_info(channel: Channel, info: Option<&'static ProtocolInfo>) -> Self {
+        let route = Arsc::new(Route::new(info));
         ServerImpl {
             inner: Arsc::new(Inner {
                 channel,
                 stop: AtomicBool::new(false),
+                routes: Mutex::new(vec![route.clone()]),
+                pending: Mutex::new(BTreeMap::new()),
+                middleware: Mutex::new(None),
+                limits: Mutex::new(Limits::new()),
+                in_flight: AtomicUsize::new(0),
             }),
+            route,
+        }
+    }
+
+    /// Serve another protocol described by `info` on the same channel.
+    ///
+    /// Requests of the methods declared by `info` are dispatched to the
+    /// returned server, unless the methods are shared with the protocols
+    /// served before, such as the inherited `close_connection`. Requests of
+    /// unknown methods are left to the first server of the channel.
+    pub fn route(&self, info: &'static ProtocolInfo) -> ServerImpl {
+        let route = Arsc::new(Route::new(Some(info)));
+        self.inner.routes.lock().push(route.clone());
+        ServerImpl {
+            inner: self.inner.clone(),
+            route,
         }
     }
 
+    /// Observe the requests of all the protocols served on the channel with
+    /// `middleware`, replacing the previous one.
+    #[inline]
+    pub fn set_middleware(&self, middleware: Arc<dyn Middleware>) {
+        *self.inner.middleware.lock() = Some(middleware);
+    }
+
+    /// Limit the requests of all the protocols served on the channel,
+    /// replacing the previous limits.
+    #[inline]
+    pub fn set_limits(&self, limits: Limits) {
+        *self.inner.limits.lock() = limits;
+    }
+
     #[inline]
     pub fn serve(self) -> (PacketStream, EventSenderImpl) {
+        let (inner, route) = self.into_parts();
         (
             PacketStream {
-                inner: self.inner.clone(),
+                inner: inner.clone(),
+                route,
+                held: None,
+                ticket: None,
             },
-            EventSenderImpl { inner: self.inner },
+            EventSenderImpl { inner },
         )
     }
+
+    fn into_parts(self) -> (Arsc<Inner>, Arsc<Route>) {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: The fields are read only once and `this` is never dropped.
+        unsafe { (ptr::read(&this.inner), ptr::read(&this.route)) }
+    }
+}
+
+impl Drop for ServerImpl {
+    fn drop(&mut self) {
+        self.inner.unroute(&self.route)
+    }
 }
 
 impl AsRef<Channel> for ServerImpl {
@@ -60,17 +131,19 @@ impl TryFrom<ServerImpl> for Channel {
     type Error = ServerImpl;
 
     fn try_from(server: ServerImpl) -> Result<Self, Self::Error> {
-        match Arsc::try_unwrap(server.inner) {
+        let (inner, route) = server.into_parts();
+        match Arsc::try_unwrap(inner) {
             Ok(mut inner) => {
                 if !*inner.stop.get_mut() {
                     Ok(inner.channel)
                 } else {
                     Err(ServerImpl {
                         inner: Arsc::new(inner),
+                        route,
                     })
                 }
             }
-            Err(inner) => Err(ServerImpl { inner }),
+            Err(inner) => Err(ServerImpl { inner, route }),
         }
     }
 }
@@ -89,34 +162,119 @@ pub struct Request {
     pub responder: Responder,
 }
 
-#[repr(transparent)]
+impl Request {
+    /// Reply that the requested method is not supported by the server.
+    pub fn unsupported(self) -> Result<(), Error> {
+        let method = packet::deserialize_metadata(&self.packet).map_or(0, |(m, _)| m);
+        let mut packet = Default::default();
+        packet::serialize(packet::UNSUPPORTED, method, &mut packet)?;
+        self.responder.send(packet, false)
+    }
+}
+
+/// Answer the built-in methods of all the protocols, such as `describe` and
+/// `upgrade`.
+///
+/// Returns the request back if it's not a built-in one.
+pub fn handle_builtin(req: Request, info: &ProtocolInfo) -> Option<Request> {
+    match packet::deserialize_metadata(&req.packet) {
+        Ok((packet::DESCRIBE, _)) => {
+            let mut packet = Default::default();
+            let res = packet::serialize(packet::DESCRIBE, ProtocolDesc::from(info), &mut packet)
+                .and_then(|_| req.responder.send(packet, false));
+            if let Err(err) = res {
+                log::warn!("failed to describe {}: {err}", info.name);
+            }
+            None
+        }
+        Ok((packet::UPGRADE, de)) => {
+            let res = packet::deserialize_body::<String>(de, None).and_then(|path| {
+                let served = info.name == path || req.responder.sender.inner.serves(&path);
+                let mut packet = Default::default();
+                packet::serialize(packet::UPGRADE, served, &mut packet)?;
+                req.responder.send(packet, false)
+            });
+            if let Err(err) = res {
+                log::warn!("failed to upgrade {}: {err}", info.name);
+            }
+            None
+        }
+        _ => Some(req),
+    }
+}
+
 pub struct PacketStream {
     inner: Arsc<Inner>,
+    route: Arsc<Route>,
+    /// The request received but waiting for the shared limiter.
+    held: Option<Packet>,
+    ticket: Option<(Arc<Limiter>, Arsc<Ticket>)>,
+}
+
+impl PacketStream {
+    fn poll_acquire(&mut self, limiter: &Arc<Limiter>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut ticket = match self.ticket.take() {
+            Some((l, ticket)) if Arc::ptr_eq(&l, limiter) => Some(ticket),
+            Some((l, ticket)) => {
+                // The limits have been replaced.
+                l.cancel(&ticket);
+                None
+            }
+            None => None,
+        };
+        let ret = limiter.poll_acquire(&mut ticket, cx);
+        self.ticket = ticket.map(|ticket| (limiter.clone(), ticket));
+        ret
+    }
 }
 
 impl Stream for PacketStream {
     type Item = Result<Request, Error>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        if self.inner.stop.load(Acquire) {
+        let this = self.get_mut();
+        if this.inner.stop.load(Acquire) {
             return Poll::Ready(None);
         }
 
-        let fut = self.inner.receive();
-        pin_mut!(fut);
-        let res = ready!(fut.poll(cx));
-        Poll::Ready(match res {
-            Err(Error::Disconnected) => None,
-            res => Some(res.map(|packet| Request {
-                responder: Responder {
-                    sender: EventSenderImpl {
-                        inner: self.inner.clone(),
-                    },
-                    id: packet.id,
-                },
-                packet,
-            })),
-        })
+        let limits = this.inner.limits.lock().clone();
+        loop {
+            if this.held.is_none() {
+                // Leave the following packets in the channel until some of the
+                // requests in flight are finished.
+                this.route.waker.register(cx.waker());
+                if this.inner.in_flight.load(Acquire) >= limits.per_connection {
+                    return Poll::Pending;
+                }
+
+                match ready!(this.inner.poll_request(&this.route, cx)) {
+                    Ok(packet) => this.held = Some(packet),
+                    Err(Error::Disconnected) => return Poll::Ready(None),
+                    Err(err) => return Poll::Ready(Some(Err(err))),
+                }
+            }
+            if let Some(ref limiter) = limits.shared {
+                // Too many channels are waiting for the cap already.
+                if this.ticket.is_none() && limiter.waiting() >= limits.backlog {
+                    let packet = this.held.take().unwrap();
+                    this.inner.busy(packet);
+                    continue;
+                }
+                ready!(this.poll_acquire(limiter, cx));
+            }
+            break;
+        }
+
+        let packet = this.held.take().unwrap();
+        this.inner.in_flight.fetch_add(1, AcqRel);
+        let permit = Permit {
+            inner: this.inner.clone(),
+            shared: limits.shared,
+        };
+        Poll::Ready(Some(Ok(Request {
+            responder: Responder::new(&this.inner, &packet, permit),
+            packet,
+        })))
     }
 }
 
@@ -127,6 +285,18 @@ impl FusedStream for PacketStream {
     }
 }
 
+impl Drop for PacketStream {
+    fn drop(&mut self) {
+        if let Some((limiter, ticket)) = self.ticket.take() {
+            limiter.cancel(&ticket);
+        }
+        if let Some(packet) = self.held.take() {
+            self.route.backlog.push(packet);
+        }
+        self.inner.unroute(&self.route)
+    }
+}
+
 #[derive(Clone)]
 #[repr(transparent)]
 pub struct EventSenderImpl {
@@ -150,35 +320,264 @@ impl EventSenderImpl {
 
     #[inline]
     pub fn close(self) {
-        self.inner.stop.store(true, Release);
+        self.inner.close()
     }
 }
 
 pub struct Responder {
     sender: EventSenderImpl,
     id: Option<NonZeroUsize>,
+    deadline: Option<Instant>,
+    cancel: Option<Arsc<CancelState>>,
+    trace: Option<Trace>,
+    _permit: Permit,
 }
 
 impl Responder {
+    fn new(inner: &Arsc<Inner>, packet: &Packet, permit: Permit) -> Self {
+        let deadline = packet::deserialize_header(packet)
+            .ok()
+            .and_then(|(header, _)| header.deadline);
+        let cancel = packet.id.map(|id| {
+            let state = Arsc::new(CancelState::default());
+            inner.pending.lock().insert(id.get(), state.clone());
+            state
+        });
+        Responder {
+            sender: EventSenderImpl {
+                inner: inner.clone(),
+            },
+            id: packet.id,
+            deadline,
+            cancel,
+            trace: inner.trace(packet),
+            _permit: permit,
+        }
+    }
+
     #[inline]
-    pub fn send(self, mut packet: Packet, close: bool) -> Result<(), Error> {
+    pub fn send(mut self, mut packet: Packet, close: bool) -> Result<(), Error> {
         packet.id = self.id;
+        if let Some(trace) = self.trace.take() {
+            trace.finish(Some(&packet));
+        }
         let ret = self.sender.send(packet);
         if close {
-            self.sender.close();
+            self.sender.inner.close();
         }
         ret
     }
 
+    #[inline]
+    pub fn disp(&self) -> DispSender {
+        self.sender.inner.channel.disp()
+    }
+
+    /// The time after which the client no longer waits for the reply.
+    #[inline]
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Check whether the client has already cancelled the request.
+    ///
+    /// Cancellations are only noticed while receiving packets, so the result
+    /// may lag behind. Poll [`cancelled`](Self::cancelled) for a precise one.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .map_or(false, |state| state.is_cancelled())
+    }
+
+    /// Get a future which completes when the client cancels the request or
+    /// disconnects.
+    ///
+    /// Requests sent without waiting for replies can't be cancelled, and the
+    /// future never completes for them.
+    #[inline]
+    pub fn cancelled(&self) -> Cancelled {
+        Cancelled {
+            inner: self.sender.inner.clone(),
+            state: self.cancel.clone(),
+        }
+    }
+
     #[inline]
     pub fn close(self) {
-        self.sender.close()
+        self.sender.inner.close()
+    }
+}
+
+impl Drop for Responder {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            self.sender.inner.pending.lock().remove(&id.get());
+        }
+        if let Some(trace) = self.trace.take() {
+            trace.finish(None);
+        }
+    }
+}
+
+/// A request in flight, counted against the limits until its responder is
+/// dropped.
+struct Permit {
+    inner: Arsc<Inner>,
+    shared: Option<Arc<Limiter>>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.inner.in_flight.fetch_sub(1, AcqRel);
+        if let Some(ref shared) = self.shared {
+            shared.release();
+        }
+        self.inner.wake();
+    }
+}
+
+/// How a request observed by a [`Middleware`] is finished.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Ok,
+    /// The method replied with an error.
+    Err,
+    /// The method is not supported by the server.
+    Unsupported,
+    /// The request is dropped without a reply.
+    Dropped,
+}
+
+/// A layer between the channel and the request streams of a server, which
+/// observes its traffic.
+///
+/// `info` is the description of the requested method if any protocol served
+/// on the channel declares it.
+pub trait Middleware: Send + Sync {
+    /// Called when a request is received, before it's handled.
+    fn request(&self, method: usize, info: Option<&'static MethodInfo>, packet: &Packet);
+
+    /// Called when the request is replied with `packet` or dropped, `elapsed`
+    /// after it's received.
+    fn reply(
+        &self,
+        method: usize,
+        info: Option<&'static MethodInfo>,
+        elapsed: Duration,
+        outcome: Outcome,
+        packet: Option<&Packet>,
+    );
+}
+
+struct Trace {
+    middleware: Arc<dyn Middleware>,
+    method: usize,
+    info: Option<&'static MethodInfo>,
+    start: Instant,
+}
+
+impl Trace {
+    fn finish(self, packet: Option<&Packet>) {
+        let fallible = self
+            .info
+            .map_or(false, |info| info.output.starts_with("Result"));
+        let outcome = match packet.map(packet::deserialize_metadata) {
+            None => Outcome::Dropped,
+            Some(Ok((packet::UNSUPPORTED, _))) => Outcome::Unsupported,
+            // The tag of `Result::Err` is 1.
+            Some(Ok((_, de))) if fallible => match packet::deserialize_body(de, None) {
+                Ok(1u8) => Outcome::Err,
+                _ => Outcome::Ok,
+            },
+            Some(_) => Outcome::Ok,
+        };
+        let elapsed = self.start.elapsed();
+        self.middleware
+            .reply(self.method, self.info, elapsed, outcome, packet);
+    }
+}
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl CancelState {
+    #[inline]
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Acquire)
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Release);
+        self.waker.wake();
+    }
+}
+
+#[must_use]
+pub struct Cancelled {
+    inner: Arsc<Inner>,
+    state: Option<Arsc<CancelState>>,
+}
+
+impl Future for Cancelled {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        match self.state {
+            Some(ref state) => self.inner.poll_cancel(state, cx),
+            None => Poll::Pending,
+        }
+    }
+}
+
+/// A protocol served on the channel, along with the requests dispatched to it.
+struct Route {
+    info: Option<&'static ProtocolInfo>,
+    /// Requests received by the other streams or while polling for
+    /// cancellations.
+    backlog: SegQueue<Packet>,
+    /// The waker of the request stream, woken when the backlog grows.
+    waker: AtomicWaker,
+}
+
+impl Route {
+    fn new(info: Option<&'static ProtocolInfo>) -> Self {
+        Route {
+            info,
+            backlog: SegQueue::new(),
+            waker: AtomicWaker::new(),
+        }
+    }
+
+    fn contains(&self, method: usize) -> bool {
+        self.info
+            .map_or(false, |info| info.methods.iter().any(|m| m.id == method))
+    }
+}
+
+impl fmt::Debug for Route {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.info.map(|info| info.name);
+        f.debug_struct("Route").field("protocol", &name).finish()
     }
 }
 
 struct Inner {
     channel: Channel,
     stop: AtomicBool,
+    /// The protocols served on the channel in the order of dispatching. The
+    /// first one also receives the requests of unknown methods.
+    routes: Mutex<Vec<Arsc<Route>>>,
+    pending: Mutex<BTreeMap<usize, Arsc<CancelState>>>,
+    middleware: Mutex<Option<Arc<dyn Middleware>>>,
+    limits: Mutex<Limits>,
+    /// The number of the requests yielded by the streams and not yet replied
+    /// or dropped.
+    in_flight: AtomicUsize,
 }
 
 impl fmt::Debug for Inner {
@@ -189,12 +588,161 @@ impl fmt::Debug for Inner {
 }
 
 impl Inner {
+    #[inline]
+    fn close(&self) {
+        self.stop.store(true, Release);
+        self.wake();
+    }
+
+    fn wake(&self) {
+        self.routes
+            .lock()
+            .iter()
+            .for_each(|route| route.waker.wake());
+    }
+
+    fn serves(&self, path: &str) -> bool {
+        let routes = self.routes.lock();
+        routes
+            .iter()
+            .any(|route| route.info.map_or(false, |info| info.name == path))
+    }
+
+    fn method_info(&self, method: usize) -> Option<&'static MethodInfo> {
+        let routes = self.routes.lock();
+        let mut methods = routes.iter().filter_map(|route| route.info);
+        methods.find_map(|info| info.methods.iter().find(|m| m.id == method))
+    }
+
+    /// Notify the middleware of the request, if any.
+    fn trace(&self, packet: &Packet) -> Option<Trace> {
+        let middleware = self.middleware.lock().clone()?;
+        let method = packet::deserialize_metadata(packet).map_or(0, |(m, _)| m);
+        let info = self.method_info(method);
+        middleware.request(method, info, packet);
+        Some(Trace {
+            middleware,
+            method,
+            info,
+            start: Instant::now(),
+        })
+    }
+
+    /// Find the protocol to which the request is dispatched.
+    fn dispatch(&self, packet: &Packet) -> Option<Arsc<Route>> {
+        let method = packet::deserialize_metadata(packet).map_or(0, |(m, _)| m);
+        let routes = self.routes.lock();
+        let route = routes.iter().find(|route| route.contains(method));
+        route.or_else(|| routes.first()).cloned()
+    }
+
+    /// Save the request for the stream of its protocol.
+    fn push(&self, packet: Packet) {
+        if let Some(route) = self.dispatch(&packet) {
+            self.defer(&route, packet)
+        }
+    }
+
+    /// Save the request for the stream of `route`, or reply that the server is
+    /// busy if its backlog is full.
+    fn defer(&self, route: &Route, packet: Packet) {
+        if route.backlog.len() >= self.limits.lock().backlog {
+            self.busy(packet);
+            return;
+        }
+        route.backlog.push(packet);
+        route.waker.wake();
+    }
+
+    fn busy(&self, packet: Packet) {
+        // Nobody waits for the reply.
+        if packet.id.is_none() {
+            return;
+        }
+        let mut reply = Packet::default();
+        if packet::serialize(packet::BUSY, (), &mut reply).is_ok() {
+            reply.id = packet.id;
+            let _ = self.send(reply);
+        }
+    }
+
+    /// Stop serving the protocol, and hand over its pending requests to the
+    /// remaining ones.
+    fn unroute(&self, route: &Arsc<Route>) {
+        self.routes.lock().retain(|r| !Arsc::ptr_eq(r, route));
+        while let Some(packet) = route.backlog.pop() {
+            self.push(packet);
+        }
+    }
+
+    fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<Result<Packet, Error>> {
+        let fut = self.receive();
+        pin_mut!(fut);
+        fut.poll(cx)
+    }
+
+    /// Apply the packet if it's a cancellation, or return it back otherwise.
+    fn filter(&self, packet: Packet) -> Option<Packet> {
+        match packet::deserialize_metadata(&packet) {
+            Ok((packet::CANCEL, _)) => {
+                let state = packet
+                    .id
+                    .and_then(|id| self.pending.lock().remove(&id.get()));
+                if let Some(state) = state {
+                    state.cancel();
+                }
+                None
+            }
+            _ => Some(packet),
+        }
+    }
+
+    fn poll_request(
+        &self,
+        route: &Arsc<Route>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Packet, Error>> {
+        route.waker.register(cx.waker());
+        loop {
+            if let Some(packet) = route.backlog.pop() {
+                break Poll::Ready(Ok(packet));
+            }
+            let packet = ready!(self.poll_receive(cx))?;
+            if let Some(packet) = self.filter(packet) {
+                match self.dispatch(&packet) {
+                    Some(r) if !Arsc::ptr_eq(&r, route) => self.defer(&r, packet),
+                    _ => break Poll::Ready(Ok(packet)),
+                }
+            }
+        }
+    }
+
+    fn poll_cancel(&self, state: &CancelState, cx: &mut Context<'_>) -> Poll<()> {
+        state.waker.register(cx.waker());
+        // The request stream may not be polled while the request is being
+        // handled, so receive the following packets here to find the
+        // cancellation and save the others for the stream.
+        while !state.is_cancelled() {
+            match ready!(self.poll_receive(cx)) {
+                Ok(packet) => {
+                    if let Some(packet) = self.filter(packet) {
+                        self.push(packet);
+                    }
+                }
+                // The client is gone, and so is the request.
+                Err(_) => break,
+            }
+        }
+        Poll::Ready(())
+    }
+
     async fn receive(&self) -> Result<Packet, Error> {
         let mut packet = Default::default();
         let res = self.channel.receive(&mut packet).await;
         res.map_err(|err| {
             if err == EPIPE {
-                self.stop.store(true, Release);
+                // Wake the streams of the other protocols to find the end.
+                self.close();
                 Error::Disconnected
             } else {
                 Error::ServerReceive(err)
@@ -223,7 +771,42 @@ pub trait Server: AsRef<Channel> + From<Channel> {
 
     fn from_inner(inner: ServerImpl) -> Self;
 
+    fn as_inner(&self) -> &ServerImpl;
+
     fn serve(self) -> (Self::RequestStream, Self::EventSender);
+
+    /// Observe the requests of the server with `middleware`, such as
+    /// [`Metrics`](crate::Metrics).
+    #[inline]
+    fn with_middleware(self, middleware: Arc<dyn Middleware>) -> Self
+    where
+        Self: Sized,
+    {
+        self.as_inner().set_middleware(middleware);
+        self
+    }
+
+    /// Limit the requests of the server, so that a single client can't
+    /// exhaust the resources of a shared service.
+    ///
+    /// See [`Limits`] for the details.
+    #[inline]
+    fn with_limits(self, limits: Limits) -> Self
+    where
+        Self: Sized,
+    {
+        self.as_inner().set_limits(limits);
+        self
+    }
+
+    /// Serve another protocol `P` on the same channel, so that clients can
+    /// reach it by upgrading their connections.
+    ///
+    /// See [`ServerImpl::route`] for how requests are dispatched.
+    #[inline]
+    fn route<P: crate::Protocol>(&self) -> P::Server {
+        crate::Server::from_inner(self.as_inner().route(P::DESCRIPTOR))
+    }
 }
 
 pub trait EventSender {
@@ -249,8 +832,8 @@ pub trait EventSender {
     where
         T: Into<Self::Event>,
     {
-        // SAFETY: We don't take the ownership from the handle, and the handle is the
-        // inner channel of this event sender.
+        // SAFETY: We don't take the ownership from the handle, and the handle
+        // is the inner channel of this event sender.
         let channel = unsafe { ManuallyDrop::new(solvent::ipc::Channel::from_raw(handle)) };
         if let Ok(mut packet) = crate::Event::serialize(event.into()) {
             let _ = channel.send(&mut packet);
@@ -0,0 +1,162 @@
+use core::{
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use futures::{pin_mut, stream::FusedStream, Stream};
+use solvent::prelude::{Packet, EPIPE};
+use solvent_async::ipc::Channel;
+
+use crate::{packet, packet::SerdePacket, Error};
+
+/// The number of items a stream sender may send before it receives any credit
+/// from the receiver.
+pub const DEFAULT_WINDOW: usize = 16;
+
+/// The server end of a streaming method.
+///
+/// Every item sent consumes one credit, and the sender waits for the receiver
+/// to grant more when it runs out. Dropping the sender ends the stream, while
+/// [`send`](Self::send) returns [`Error::Disconnected`] once the receiver is
+/// dropped, which is how the server learns about cancellation.
+pub struct StreamSender<T> {
+    channel: Channel,
+    credit: usize,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T: SerdePacket> StreamSender<T> {
+    #[inline]
+    pub fn new(channel: Channel) -> Self {
+        StreamSender {
+            channel,
+            credit: DEFAULT_WINDOW,
+            _marker: PhantomData,
+        }
+    }
+
+    async fn wait_credit(&mut self) -> Result<(), Error> {
+        while self.credit == 0 {
+            let mut packet = Default::default();
+            let res = self.channel.receive(&mut packet).await;
+            res.map_err(|err| match err {
+                EPIPE => Error::Disconnected,
+                err => Error::ServerReceive(err),
+            })?;
+            let credit: usize = packet::deserialize(packet::STREAM_CREDIT, &packet, None)?;
+            self.credit += credit;
+        }
+        Ok(())
+    }
+
+    pub async fn send(&mut self, item: T) -> Result<(), Error> {
+        self.wait_credit().await?;
+
+        let mut packet = Default::default();
+        packet::serialize(packet::STREAM_ITEM, item, &mut packet)?;
+        self.channel.send(&mut packet).map_err(|err| match err {
+            EPIPE => Error::Disconnected,
+            err => Error::ServerSend(err),
+        })?;
+        self.credit -= 1;
+        Ok(())
+    }
+
+    /// Wait until the receiver is dropped, collecting the credits granted
+    /// meanwhile.
+    ///
+    /// This lets long-lived streams that send items only occasionally notice
+    /// the cancellation while idle.
+    pub async fn closed(&mut self) {
+        loop {
+            let mut packet = Default::default();
+            if self.channel.receive(&mut packet).await.is_err() {
+                break;
+            }
+            let res: Result<usize, _> = packet::deserialize(packet::STREAM_CREDIT, &packet, None);
+            if let Ok(credit) = res {
+                self.credit += credit;
+            }
+        }
+    }
+}
+
+/// The client end of a streaming method.
+///
+/// The stream ends when the server drops its sender, and dropping the receiver
+/// cancels the stream on the server side.
+pub struct StreamReceiver<T> {
+    channel: Channel,
+    consumed: usize,
+    stop: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Unpin for StreamReceiver<T> {}
+
+impl<T: SerdePacket> StreamReceiver<T> {
+    #[inline]
+    pub fn new(channel: Channel) -> Self {
+        StreamReceiver {
+            channel,
+            consumed: 0,
+            stop: false,
+            _marker: PhantomData,
+        }
+    }
+
+    fn grant(&mut self) -> Result<(), Error> {
+        let mut packet = Default::default();
+        packet::serialize(packet::STREAM_CREDIT, self.consumed, &mut packet)?;
+        match self.channel.send(&mut packet) {
+            // The sender is gone, so let the receiving end tell the stream.
+            Ok(()) | Err(EPIPE) => {
+                self.consumed = 0;
+                Ok(())
+            }
+            Err(err) => Err(Error::ClientSend(err)),
+        }
+    }
+
+    fn receive(&mut self, packet: Packet) -> Result<T, Error> {
+        let item = packet::deserialize(packet::STREAM_ITEM, &packet, None)?;
+        self.consumed += 1;
+        if self.consumed >= DEFAULT_WINDOW / 2 {
+            self.grant()?;
+        }
+        Ok(item)
+    }
+}
+
+impl<T: SerdePacket> Stream for StreamReceiver<T> {
+    type Item = Result<T, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.stop {
+            return Poll::Ready(None);
+        }
+
+        let res = {
+            let fut = self.channel.receive_with(Default::default());
+            pin_mut!(fut);
+            ready!(fut.poll(cx))
+        };
+        Poll::Ready(match res {
+            Ok(packet) => Some(self.receive(packet)),
+            Err(EPIPE) => {
+                self.stop = true;
+                None
+            }
+            Err(err) => Some(Err(Error::ClientReceive(err))),
+        })
+    }
+}
+
+impl<T: SerdePacket> FusedStream for StreamReceiver<T> {
+    #[inline]
+    fn is_terminated(&self) -> bool {
+        self.stop
+    }
+}
@@ -1,3 +1,4 @@
 mod client;
+mod stream;
 
-pub use self::client::*;
+pub use self::{client::*, stream::*};
@@ -1,4 +1,4 @@
-use alloc::collections::BTreeMap;
+use alloc::{collections::BTreeMap, string::String};
 use core::{
     iter::FusedIterator,
     mem,
@@ -17,11 +17,12 @@ use solvent::{
 use solvent_async::disp::DispSender;
 use solvent_core::sync::{Arsc, Mutex};
 
-use crate::Error;
+use crate::{client::Expiry, packet, Error};
 
 #[derive(Debug, Clone)]
 pub struct ClientImpl {
     inner: Arsc<Inner>,
+    expiry: Option<Expiry>,
 }
 
 impl ClientImpl {
@@ -35,6 +36,29 @@ impl ClientImpl {
                 set_event_receiver: AtomicBool::new(false),
                 stop: AtomicBool::new(false),
             }),
+            expiry: None,
+        }
+    }
+
+    /// Get a client sharing the connection whose calls time out after
+    /// `timeout`.
+    ///
+    /// The deadline is also sent to the server along with each request.
+    #[inline]
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        ClientImpl {
+            inner: self.inner.clone(),
+            expiry: Some(Expiry::Timeout(timeout)),
+        }
+    }
+
+    /// Get a client sharing the connection whose calls time out at
+    /// `deadline`.
+    #[inline]
+    pub fn with_deadline(&self, deadline: Instant) -> Self {
+        ClientImpl {
+            inner: self.inner.clone(),
+            expiry: Some(Expiry::Deadline(deadline)),
         }
     }
 
@@ -46,12 +70,29 @@ impl ClientImpl {
 
     #[inline]
     pub fn call(&self, packet: Packet) -> Result<Packet, Error> {
-        self.inner.call(packet)
+        match self.expiry {
+            Some(expiry) => self.call_deadline(packet, expiry.deadline()),
+            None => self.inner.call(packet),
+        }
     }
 
     #[inline]
     pub fn call_timeout(&self, packet: Packet, timeout: Duration) -> Result<Packet, Error> {
-        self.inner.call_timeout(packet, timeout)
+        self.call_deadline(packet, Instant::now() + timeout)
+    }
+
+    pub fn call_deadline(&self, mut packet: Packet, deadline: Instant) -> Result<Packet, Error> {
+        packet::set_deadline(&mut packet, Some(deadline))?;
+        self.inner.call_deadline(packet, deadline)
+    }
+
+    /// Ask whether the server also serves the protocol of `path` on the
+    /// channel.
+    pub fn upgrade(&self, path: &str) -> Result<bool, Error> {
+        let mut packet = Default::default();
+        packet::serialize(packet::UPGRADE, String::from(path), &mut packet)?;
+        let packet = self.call(packet)?;
+        packet::deserialize(packet::UPGRADE, &packet, None)
     }
 
     #[inline]
@@ -88,10 +129,14 @@ impl TryFrom<ClientImpl> for Channel {
                 } else {
                     Err(ClientImpl {
                         inner: Arsc::new(inner),
+                        expiry: client.expiry,
                     })
                 }
             }
-            Err(inner) => Err(ClientImpl { inner }),
+            Err(inner) => Err(ClientImpl {
+                inner,
+                expiry: client.expiry,
+            }),
         }
     }
 }
@@ -142,14 +187,14 @@ impl Inner {
         })
     }
 
-    fn call_timeout(&self, packet: Packet, timeout: Duration) -> Result<Packet, Error> {
-        self.call_inner(packet, |instant| {
-            let elapsed = instant.elapsed();
-            if elapsed >= timeout {
+    fn call_deadline(&self, packet: Packet, deadline: Instant) -> Result<Packet, Error> {
+        self.call_inner(packet, |_| {
+            let now = Instant::now();
+            if now >= deadline {
                 return Err(Error::ClientReceive(ETIME));
             }
             self.channel
-                .try_wait(timeout - elapsed, true, false, SIG_READ)
+                .try_wait(deadline - now, true, false, SIG_READ)
                 .map_err(Error::ClientReceive)?;
             Ok(())
         })
@@ -191,7 +236,11 @@ impl Inner {
                     if let Some(packet) = callers.remove(&self_id) {
                         break Ok(packet);
                     }
-                    wait(instant)?;
+                    drop(callers);
+                    if let Err(err) = wait(instant) {
+                        self.cancel(self_id);
+                        break Err(err);
+                    }
                 }
                 Err(err) => {
                     if err == EPIPE {
@@ -204,6 +253,15 @@ impl Inner {
         }
     }
 
+    /// Tell the server that the caller of `id` no longer waits for the reply.
+    fn cancel(&self, id: usize) {
+        let mut packet = Default::default();
+        if packet::serialize(packet::CANCEL, (), &mut packet).is_ok() {
+            packet.id = NonZeroUsize::new(id);
+            let _ = self.channel.send(&mut packet);
+        }
+    }
+
     fn receive_event(&self) -> Result<Packet, Error> {
         self.receive_event_inner(|_| {
             self.channel
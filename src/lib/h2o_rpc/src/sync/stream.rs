@@ -0,0 +1,95 @@
+use core::{iter::FusedIterator, marker::PhantomData, time::Duration};
+
+use solvent::{
+    error::{ENOENT, EPIPE},
+    ipc::{Channel, Packet, SIG_READ},
+    prelude::Object,
+};
+
+use crate::{packet, packet::SerdePacket, Error, DEFAULT_WINDOW};
+
+/// The blocking client end of a streaming method.
+///
+/// See [`crate::StreamReceiver`] for the details.
+pub struct StreamReceiver<T> {
+    channel: Channel,
+    consumed: usize,
+    timeout: Option<Duration>,
+    stop: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: SerdePacket> StreamReceiver<T> {
+    #[inline]
+    pub fn new(channel: Channel) -> Self {
+        StreamReceiver {
+            channel,
+            consumed: 0,
+            timeout: None,
+            stop: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Set the maximum time to wait for each item.
+    #[inline]
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    fn grant(&mut self) -> Result<(), Error> {
+        let mut packet = Default::default();
+        packet::serialize(packet::STREAM_CREDIT, self.consumed, &mut packet)?;
+        match self.channel.send(&mut packet) {
+            Ok(()) | Err(EPIPE) => {
+                self.consumed = 0;
+                Ok(())
+            }
+            Err(err) => Err(Error::ClientSend(err)),
+        }
+    }
+
+    fn receive(&mut self) -> Option<Result<T, Error>> {
+        let mut packet = Packet::default();
+        loop {
+            match self.channel.receive(&mut packet) {
+                Ok(()) => break,
+                Err(ENOENT) => {
+                    let timeout = self.timeout.unwrap_or(Duration::MAX);
+                    let res = self.channel.try_wait(timeout, true, false, SIG_READ);
+                    if let Err(err) = res {
+                        return Some(Err(Error::ClientReceive(err)));
+                    }
+                }
+                Err(EPIPE) => {
+                    self.stop = true;
+                    return None;
+                }
+                Err(err) => return Some(Err(Error::ClientReceive(err))),
+            }
+        }
+
+        let res = packet::deserialize(packet::STREAM_ITEM, &packet, None).and_then(|item| {
+            self.consumed += 1;
+            if self.consumed >= DEFAULT_WINDOW / 2 {
+                self.grant()?;
+            }
+            Ok(item)
+        });
+        Some(res)
+    }
+}
+
+impl<T: SerdePacket> Iterator for StreamReceiver<T> {
+    type Item = Result<T, Error>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stop {
+            return None;
+        }
+        self.receive()
+    }
+}
+
+impl<T: SerdePacket> FusedIterator for StreamReceiver<T> {}
@@ -1,5 +1,6 @@
 mod intr;
 mod pio;
+pub mod power;
 mod res;
 
 pub use self::{
@@ -0,0 +1,36 @@
+use sv_call::res::{POWER_REBOOT, POWER_SHUTDOWN, POWER_SLEEP};
+
+use super::PioRes;
+use crate::{error::Result, obj::Object};
+
+fn power(res: &PioRes, op: u32) -> Result {
+    // SAFETY: We don't move the ownership of the handle.
+    unsafe { sv_call::sv_power(unsafe { res.raw() }, op).into_res() }
+}
+
+/// Power off the machine.
+///
+/// `res` must be the root port I/O resource. The function returns only if the
+/// operation failed.
+#[inline]
+pub fn shutdown(res: &PioRes) -> Result {
+    power(res, POWER_SHUTDOWN)
+}
+
+/// Reset the machine.
+///
+/// `res` must be the root port I/O resource. The function returns only if the
+/// operation failed.
+#[inline]
+pub fn reboot(res: &PioRes) -> Result {
+    power(res, POWER_REBOOT)
+}
+
+/// Put the machine to sleep in the ACPI S1 state, returning after it's woken
+/// up.
+///
+/// `res` must be the root port I/O resource.
+#[inline]
+pub fn sleep(res: &PioRes) -> Result {
+    power(res, POWER_SLEEP)
+}
@@ -0,0 +1,134 @@
+use core::{fmt, str};
+
+use sv_call::klog::*;
+pub use sv_call::klog::{LOG_MODULE_USER, LOG_MSG_LEN};
+
+use crate::{dev::PioRes, error::Result, obj::Object, time::Instant};
+
+/// A structured record read from the kernel log ring.
+#[derive(Clone)]
+pub struct Record {
+    header: LogRecord,
+    data: [u8; LOG_MODULE_LEN + LOG_MSG_LEN],
+}
+
+impl Record {
+    /// The sequence number of the record.
+    #[inline]
+    pub fn seq(&self) -> u64 {
+        self.header.seq
+    }
+
+    #[inline]
+    pub fn time(&self) -> Instant {
+        // SAFETY: The timestamp comes from the kernel.
+        unsafe { Instant::from_raw(self.header.time) }
+    }
+
+    #[inline]
+    pub fn cpu(&self) -> u32 {
+        self.header.cpu
+    }
+
+    #[inline]
+    pub fn level(&self) -> log::Level {
+        level_from_raw(self.header.level)
+            .and_then(|level| level.to_level())
+            .unwrap_or(log::Level::Info)
+    }
+
+    /// The module path of the record, or [`LOG_MODULE_USER`] if the record is
+    /// logged by user tasks.
+    #[inline]
+    pub fn module(&self) -> &str {
+        let module = &self.data[..self.header.module_len];
+        str::from_utf8(module).unwrap_or("<INVALID>")
+    }
+
+    #[inline]
+    pub fn message(&self) -> &str {
+        let start = self.header.module_len;
+        let msg = &self.data[start..(start + self.header.msg_len)];
+        str::from_utf8(msg).unwrap_or("<INVALID>")
+    }
+}
+
+impl fmt::Debug for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Record")
+            .field("seq", &self.seq())
+            .field("time", &self.time())
+            .field("cpu", &self.cpu())
+            .field("level", &self.level())
+            .field("module", &self.module())
+            .field("message", &self.message())
+            .finish()
+    }
+}
+
+fn level_from_raw(raw: u32) -> Option<log::LevelFilter> {
+    Some(match raw {
+        LOG_LEVEL_ERROR => log::LevelFilter::Error,
+        LOG_LEVEL_WARN => log::LevelFilter::Warn,
+        LOG_LEVEL_INFO => log::LevelFilter::Info,
+        LOG_LEVEL_DEBUG => log::LevelFilter::Debug,
+        LOG_LEVEL_TRACE => log::LevelFilter::Trace,
+        _ => return None,
+    })
+}
+
+/// Write a line to the kernel log as a user record.
+///
+/// The record in the log ring is truncated to [`LOG_MSG_LEN`] bytes.
+#[inline]
+pub fn write(line: &str) -> Result {
+    unsafe { sv_call::sv_log(line.as_ptr(), line.len()).into_res() }
+}
+
+/// Read the oldest record in the kernel log ring whose sequence number is not
+/// less than `seq`.
+///
+/// If the returned record's sequence number is greater than `seq`, the records
+/// in between have been overwritten. `res` must be the root port I/O resource.
+///
+/// # Errors
+///
+/// Returns [`ENOENT`](crate::error::ENOENT) if there's no such record yet.
+pub fn read(res: &PioRes, seq: u64) -> Result<Record> {
+    let mut record = Record {
+        header: Default::default(),
+        data: [0; LOG_MODULE_LEN + LOG_MSG_LEN],
+    };
+    unsafe {
+        sv_call::sv_log_read(
+            res.raw(),
+            seq,
+            &mut record.header,
+            record.data.as_mut_ptr(),
+            record.data.len(),
+        )
+        .into_res()?
+    };
+    Ok(record)
+}
+
+/// Get the current maximum level of the kernel log.
+///
+/// `res` must be the root port I/O resource.
+pub fn level(res: &PioRes) -> Result<log::LevelFilter> {
+    let raw = unsafe { sv_call::sv_log_level(res.raw(), 0).into_res()? };
+    Ok(level_from_raw(raw as u32).unwrap_or(log::LevelFilter::Off))
+}
+
+/// Set the maximum level of the kernel log, returning the previous one.
+///
+/// `res` must be the root port I/O resource.
+///
+/// # Errors
+///
+/// Returns [`EINVAL`](crate::error::EINVAL) if `level` is
+/// [`Off`](log::LevelFilter::Off).
+pub fn set_level(res: &PioRes, level: log::LevelFilter) -> Result<log::LevelFilter> {
+    let raw = unsafe { sv_call::sv_log_level(res.raw(), level as usize as u32).into_res()? };
+    Ok(level_from_raw(raw as u32).unwrap_or(log::LevelFilter::Off))
+}
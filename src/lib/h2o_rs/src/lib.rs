@@ -9,6 +9,7 @@ pub mod c_ty;
 pub mod dev;
 pub mod error;
 pub mod ipc;
+pub mod klog;
 pub mod macros;
 pub mod mem;
 pub mod obj;
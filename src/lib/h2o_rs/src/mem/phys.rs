@@ -116,6 +116,35 @@ impl Phys {
         }
     }
 
+    /// Copy at most `len` bytes from `src` directly into this object, without
+    /// bouncing them through user space.
+    ///
+    /// # Safety
+    ///
+    /// The same as [`Phys::write`].
+    pub unsafe fn copy_from(
+        &self,
+        offset: usize,
+        src: &Phys,
+        src_offset: usize,
+        len: usize,
+    ) -> Result<usize> {
+        if len > 0 {
+            // SAFETY: We don't move the ownership of the handles.
+            sv_call::sv_phys_copy(
+                unsafe { self.raw() },
+                offset,
+                unsafe { src.raw() },
+                src_offset,
+                len,
+            )
+            .into_res()
+            .map(|len| len as usize)
+        } else {
+            Ok(0)
+        }
+    }
+
     pub fn read_vectored(&self, offset: usize, bufs: &mut [IoSliceMut<'_>]) -> Result<usize> {
         let len = unsafe {
             sv_call::sv_phys_readv(
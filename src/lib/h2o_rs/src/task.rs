@@ -198,6 +198,18 @@ pub fn sleep(duration: Duration) -> Result {
     unsafe { sv_call::sv_task_sleep(millis).into_res() }
 }
 
+/// Get the ID of the current task.
+#[inline]
+pub fn current_id() -> u64 {
+    unsafe { sv_call::sv_task_id(false).into_res() }.expect("Failed to get the task ID")
+}
+
+/// Get the ID of the main task of the current process.
+#[inline]
+pub fn process_id() -> u64 {
+    unsafe { sv_call::sv_task_id(true).into_res() }.expect("Failed to get the task ID")
+}
+
 #[cfg(feature = "stub")]
 #[inline]
 pub fn cpu_num() -> NonZeroUsize {
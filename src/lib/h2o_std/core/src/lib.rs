@@ -22,6 +22,8 @@
 #![feature(utf8_chunks)]
 
 extern crate alloc;
+#[cfg(feature = "host")]
+extern crate std;
 
 pub mod ffi;
 pub mod hash;
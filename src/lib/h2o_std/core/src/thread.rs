@@ -1,4 +1,8 @@
 mod backoff;
+#[cfg(feature = "host")]
+#[path = "thread/host.rs"]
+mod imp;
+#[cfg(not(feature = "host"))]
 mod imp;
 pub mod local;
 mod scope;
@@ -0,0 +1,55 @@
+//! The threads of the host, on which the kernel objects are emulated
+//! in-process and `ldso` doesn't exist.
+
+use alloc::boxed::Box;
+use core::time::Duration;
+use std::thread::{self, JoinHandle};
+
+use solvent::error::{Result, ENOMEM};
+
+pub const DEFAULT_STACK_SIZE: usize = 256 * 1024;
+
+pub struct Thread {
+    inner: JoinHandle<()>,
+}
+
+struct Func(Box<dyn FnOnce()>);
+
+// SAFETY: The caller of `Thread::new` ensures `func` implements `Send`.
+unsafe impl Send for Func {}
+
+impl Func {
+    fn call(self) {
+        (self.0)()
+    }
+}
+
+impl Thread {
+    /// # Safety
+    ///
+    /// `func` must implements `Send` and has its lifetime checked.
+    pub unsafe fn new(name: Option<&str>, stack: usize, func: Box<dyn FnOnce()>) -> Result<Self> {
+        let mut builder = thread::Builder::new().stack_size(stack.max(DEFAULT_STACK_SIZE));
+        if let Some(name) = name {
+            builder = builder.name(name.into());
+        }
+        let func = Func(func);
+        let inner = builder.spawn(move || func.call()).map_err(|_| ENOMEM)?;
+        Ok(Thread { inner })
+    }
+
+    #[inline]
+    pub fn yield_now() {
+        thread::yield_now()
+    }
+
+    #[inline]
+    pub fn sleep(duration: Duration) {
+        thread::sleep(duration)
+    }
+
+    pub fn join(self) {
+        let res = self.inner.join();
+        assert!(res.is_ok(), "Failed to join thread");
+    }
+}
@@ -386,6 +386,7 @@ mod lazy {
 }
 
 #[doc(hidden)]
+#[cfg(not(feature = "host"))]
 pub unsafe fn register_dtor(data: *mut u8, dtor: *mut ()) {
     #[link(name = "ldso")]
     extern "C" {
@@ -393,3 +394,28 @@ pub unsafe fn register_dtor(data: *mut u8, dtor: *mut ()) {
     }
     __libc_register_tcb_dtor(data as _, dtor as _)
 }
+
+/// The host has no `ldso`, so the destructors are run by a thread local of
+/// `std` instead.
+#[doc(hidden)]
+#[cfg(feature = "host")]
+pub unsafe fn register_dtor(data: *mut u8, dtor: *mut ()) {
+    use alloc::vec::Vec;
+
+    type Dtor = unsafe extern "C" fn(*mut u8);
+
+    struct Dtors(RefCell<Vec<(*mut u8, Dtor)>>);
+
+    impl Drop for Dtors {
+        fn drop(&mut self) {
+            while let Some((data, dtor)) = self.0.get_mut().pop() {
+                unsafe { dtor(data) }
+            }
+        }
+    }
+
+    std::thread_local!(static DTORS: Dtors = Dtors(RefCell::new(Vec::new())));
+
+    let dtor = unsafe { core::mem::transmute::<*mut (), Dtor>(dtor) };
+    DTORS.with(|dtors| dtors.0.borrow_mut().push((data, dtor)));
+}
@@ -14,3 +14,4 @@ pub mod env;
 pub mod rt;
 pub use solvent_core::*;
 mod alloc2;
+mod logger;
@@ -0,0 +1,58 @@
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use solvent::{task, time::Instant};
+use solvent_rpc::logger::{Logger, LoggerSyncClient, UserRecord};
+
+struct KeyValues(Vec<(String, String)>);
+
+impl<'kvs> log::kv::Visitor<'kvs> for KeyValues {
+    fn visit_pair(
+        &mut self,
+        key: log::kv::Key<'kvs>,
+        value: log::kv::Value<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        self.0.push((key.to_string(), value.to_string()));
+        Ok(())
+    }
+}
+
+/// The `dbglog` backend logging through the system logger service.
+struct Backend(LoggerSyncClient);
+
+impl dbglog::Backend for Backend {
+    fn log(&self, record: &log::Record, time: Instant) -> bool {
+        let module = record.module_path().unwrap_or("<NULL>");
+        let tags = if record.target() != module {
+            vec![record.target().to_string()]
+        } else {
+            Vec::new()
+        };
+        let mut key_values = KeyValues(Vec::new());
+        let _ = record.key_values().visit(&mut key_values);
+
+        let record = UserRecord {
+            time: unsafe { time.raw() },
+            level: record.level().into(),
+            pid: task::process_id(),
+            tid: task::current_id(),
+            module: module.to_string(),
+            tags,
+            message: record.args().to_string(),
+            key_values: key_values.0,
+        };
+        self.0.log(record).is_ok()
+    }
+}
+
+/// Route the logs through the system logger service if it's available in the
+/// local FS.
+pub(crate) fn init() {
+    if let Ok(client) = solvent_fs::rpc::connect_sync::<Logger>() {
+        dbglog::set_backend(Box::leak(Box::new(Backend(client))));
+    }
+}
@@ -79,6 +79,7 @@ pub fn lang_start<R: Termination>(channel: Channel, main: fn() -> R) -> R {
                 )
             }
         });
+        crate::logger::init();
 
         let ret = main();
 
@@ -19,6 +19,7 @@ pub enum HandleType {
     LoadRpc,
     BootfsPhys,
     LocalFs,
+    PioRes,
 }
 
 #[derive(Copy, Clone)]
@@ -183,18 +183,26 @@ impl Dist {
             .stdout;
 
         let s = String::from_utf8_lossy(&out);
-        let (constants_offset, _) = s
-            .split('\n')
-            .find(|s| s.ends_with("CONSTANTS"))
-            .and_then(|s| s.split_once(' '))
-            .expect("Failed to get CONSTANTS");
+        let symbol_offset = |name: &str| {
+            s.split('\n')
+                .find(|s| s.ends_with(name))
+                .and_then(|s| s.split_once(' '))
+                .map(|(offset, _)| offset)
+                .with_context(|| format!("Failed to get {name}"))
+        };
 
         fs::write(
             src_root.join(H2O_KERNEL).join("target/constant_offset.rs"),
-            format!("0x{}", constants_offset),
+            format!("0x{}", symbol_offset(" CONSTANTS")?),
         )
         .context("failed to write to constant file")?;
 
+        fs::write(
+            src_root.join(H2O_KERNEL).join("target/time_data_offset.rs"),
+            format!("0x{}", symbol_offset(" TIME_DATA")?),
+        )
+        .context("failed to write to time data file")?;
+
         self.gen_debug("vdso", src_root.join(H2O_KERNEL).join("target"), DEBUG_DIR)
             .context("failed to generate debug info")?;
 